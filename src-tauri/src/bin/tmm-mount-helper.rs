@@ -0,0 +1,294 @@
+//Runs as root through pkexec and only ever mounts or unmounts overlays on the game
+//directories the calling user registered with the manager, everything it gets
+//handed has to belong to that user. Usage:
+//  tmm-mount-helper mount <config dir> <appid> <upperdir> <workdir> <lowerdir>...
+//  tmm-mount-helper umount <config dir> <appid> <path>
+//The config dir is the manager's, in the caller's XDG_CONFIG_HOME. pkexec drops the
+//environment, so the manager hands it over itself
+use serde::Deserialize;
+use std::ffi::{CString, OsStr, OsString};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::raw::{c_char, c_int, c_long, c_uint};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::{Component, Path, PathBuf};
+use std::process::exit;
+use std::{env, fs, ptr};
+
+//The new mount API's flags, libc only has the syscall numbers
+const FSOPEN_CLOEXEC: c_uint = 1;
+const FSMOUNT_CLOEXEC: c_uint = 1;
+const FSCONFIG_SET_FD: c_uint = 5;
+const FSCONFIG_CMD_CREATE: c_uint = 6;
+const MOUNT_ATTR_NOSUID: c_uint = 0x2;
+const MOUNT_ATTR_NODEV: c_uint = 0x4;
+const MOVE_MOUNT_F_EMPTY_PATH: c_uint = 0x4;
+const MOVE_MOUNT_T_EMPTY_PATH: c_uint = 0x40;
+const OVERLAYFS_SUPER_MAGIC: i64 = 0x794c7630;
+const FUSE_SUPER_MAGIC: i64 = 0x65735546;
+
+//The part of the manager's game config the helper cares about
+#[derive(Deserialize)]
+struct Game {
+  install_path: PathBuf,
+  profile_path: PathBuf,
+  path_extension: PathBuf,
+}
+
+fn fail(message: &str) -> ! {
+  eprintln!("tmm-mount-helper: {}", message);
+  exit(1);
+}
+
+//pkexec tells who called it, the environment of that user is gone by now
+fn caller() -> u32 {
+  let uid: u32 = match env::var("PKEXEC_UID").ok().and_then(|uid| uid.parse().ok()) {
+    Some(uid) => uid,
+    None => fail("has to be run through pkexec"),
+  };
+  if unsafe { libc::getpwuid(uid) }.is_null() {
+    fail("the calling user doesn't exist");
+  }
+  uid
+}
+
+//The config has to be the caller's own, in a config dir of theirs
+fn registered_game(uid: u32, config_dir: &Path, appid: &str) -> Game {
+  if appid.parse::<u32>().is_err() {
+    fail("the appid has to be a number");
+  }
+  let config_dir = owned_dir(config_dir, uid);
+  let mut config = match open_at(&config_dir.file, OsStr::new(&format!("{}.json", appid)), libc::O_RDONLY) {
+    Ok(config) => config,
+    Err(_) => fail("the game isn't managed by the calling user"),
+  };
+  match config.metadata() {
+    Ok(metadata) if metadata.is_file() && metadata.uid() == uid => {},
+    _ => fail("the game isn't managed by the calling user"),
+  }
+  let mut contents = String::new();
+  if config.read_to_string(&mut contents).is_err() {
+    fail("the game's config can't be read");
+  }
+  match serde_json::from_str(&contents) {
+    Ok(game) => game,
+    Err(_) => fail("the game's config can't be read"),
+  }
+}
+
+//Mount options are text, a path that isn't UTF-8 can't go in them
+fn utf8(path: &Path) -> &str {
+  match path.to_str() {
+    Some(path) => path,
+    None => fail(&format!("{:?} isn't valid UTF-8", path)),
+  }
+}
+
+//A directory that was opened one component at a time without following any symlink. The
+//caller owns the path and could swap a part of it for a link right after it got checked, the
+//checks and the mount only ever go by the fd so they see the same directory
+struct Dir {
+  path: PathBuf,
+  file: File,
+}
+
+impl Dir {
+  fn fd(&self) -> RawFd {
+    self.file.as_raw_fd()
+  }
+}
+
+fn open_at(parent: &File, name: &OsStr, flags: c_int) -> io::Result<File> {
+  let name = CString::new(name.as_bytes()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+  let fd = unsafe { libc::openat(parent.as_raw_fd(), name.as_ptr(), flags | libc::O_NOFOLLOW | libc::O_CLOEXEC) };
+  if fd < 0 {
+    return Err(io::Error::last_os_error());
+  }
+  Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+//The path has to be canonical already, a symlink anywhere in it by now means it changed
+fn open_dir(path: &Path) -> io::Result<File> {
+  let mut dir = File::open("/")?;
+  for component in path.components() {
+    match component {
+      Component::RootDir => {},
+      Component::Normal(name) => dir = open_at(&dir, name, libc::O_PATH | libc::O_DIRECTORY)?,
+      _ => return Err(io::Error::from(io::ErrorKind::InvalidInput)),
+    }
+  }
+  Ok(dir)
+}
+
+fn owned_dir(path: &Path, uid: u32) -> Dir {
+  let path = match fs::canonicalize(path) {
+    Ok(path) => path,
+    Err(_) => fail(&format!("'{}' doesn't exist", path.display())),
+  };
+  let file = match open_dir(&path) {
+    Ok(file) => file,
+    Err(_) => fail(&format!("'{}' changed while it was checked", path.display())),
+  };
+  match file.metadata() {
+    Ok(metadata) if metadata.is_dir() && metadata.uid() == uid => Dir { path, file },
+    _ => fail(&format!("'{}' isn't a directory of the calling user", path.display())),
+  }
+}
+
+fn c_string(text: &str) -> CString {
+  match CString::new(text) {
+    Ok(text) => text,
+    Err(_) => fail(&format!("{:?} has a nul in it", text)),
+  }
+}
+
+fn check(result: c_long) -> io::Result<c_long> {
+  if result < 0 { Err(io::Error::last_os_error()) } else { Ok(result) }
+}
+
+//From 6.7 the kernel takes the layers as fds and lists their real paths in /proc/mounts,
+//which is what the manager tells its own mounts by. False when it doesn't, nothing got mounted
+fn mount_by_fds(target: &Dir, upper: &Dir, work: &Dir, lower: &[Dir]) -> io::Result<bool> {
+  let overlay = c_string("overlay");
+  let context = match check(unsafe { libc::syscall(libc::SYS_fsopen, overlay.as_ptr(), FSOPEN_CLOEXEC) }) {
+    Ok(context) => unsafe { File::from_raw_fd(context as RawFd) },
+    Err(_) => return Ok(false),
+  };
+  let layers = lower.iter().map(|dir| ("lowerdir+", dir)).chain([("upperdir", upper), ("workdir", work)]);
+  for (key, dir) in layers {
+    let key = c_string(key);
+    let set = unsafe { libc::syscall(libc::SYS_fsconfig, context.as_raw_fd(), FSCONFIG_SET_FD, key.as_ptr(), ptr::null::<c_char>(), dir.fd()) };
+    if check(set).is_err() {
+      return Ok(false);
+    }
+  }
+  check(unsafe { libc::syscall(libc::SYS_fsconfig, context.as_raw_fd(), FSCONFIG_CMD_CREATE, ptr::null::<c_char>(), ptr::null::<c_char>(), 0) })?;
+  let mount = check(unsafe { libc::syscall(libc::SYS_fsmount, context.as_raw_fd(), FSMOUNT_CLOEXEC, MOUNT_ATTR_NOSUID | MOUNT_ATTR_NODEV) })?;
+  let mount = unsafe { File::from_raw_fd(mount as RawFd) };
+  let empty = c_string("");
+  check(unsafe { libc::syscall(libc::SYS_move_mount, mount.as_raw_fd(), empty.as_ptr(), target.fd(), empty.as_ptr(), MOVE_MOUNT_F_EMPTY_PATH | MOVE_MOUNT_T_EMPTY_PATH) })?;
+  Ok(true)
+}
+
+//Older kernels get the fds' /proc paths, which is all /proc/mounts lists for those
+fn mount_by_proc(target: &Dir, upper: &Dir, work: &Dir, lower: &[Dir]) -> io::Result<()> {
+  let proc_path = |dir: &Dir| format!("/proc/self/fd/{}", dir.fd());
+  let lower: Vec<String> = lower.iter().map(proc_path).collect();
+  let options = c_string(&format!("lowerdir={},upperdir={},workdir={}", lower.join(":"), proc_path(upper), proc_path(work)));
+  let overlay = c_string("overlay");
+  let target = c_string(&proc_path(target));
+  let flags = libc::MS_NOSUID | libc::MS_NODEV;
+  check(unsafe { libc::mount(overlay.as_ptr(), target.as_ptr(), overlay.as_ptr(), flags, options.as_ptr().cast()) }.into())?;
+  Ok(())
+}
+
+//What's mounted on top at the path, by its type. Mount points in /proc/mounts have
+//spaces and such escaped as octal
+fn top_mount(path: &Path) -> Option<String> {
+  let mounts = match fs::read_to_string("/proc/mounts") {
+    Ok(mounts) => mounts,
+    Err(e) => fail(&format!("can't read the mounts: {}", e)),
+  };
+  let wanted = utf8(path).replace('\\', "\\134").replace(' ', "\\040").replace('\t', "\\011").replace('\n', "\\012");
+  mounts.lines()
+    .map(|line| line.split(' ').collect::<Vec<&str>>())
+    .rfind(|fields| fields.len() > 2 && fields[1] == wanted)
+    .map(|fields| fields[2].to_string())
+}
+
+fn canonical(path: &Path) -> PathBuf {
+  fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn mount(uid: u32, game: &Game, args: &[PathBuf]) -> ! {
+  if args.len() < 3 {
+    fail("mount needs an upperdir, a workdir and at least one lowerdir");
+  }
+  let target = owned_dir(&game.install_path.join(&game.path_extension), uid);
+  let profile = canonical(&game.profile_path);
+  let upper = owned_dir(&args[0], uid);
+  let work = owned_dir(&args[1], uid);
+  let lower: Vec<Dir> = args[2..].iter().map(|path| owned_dir(path, uid)).collect();
+
+  //deployments layer the mods, or the filtered layers made of them, over the game directory and
+  //write into the profile's overwrite, experiments layer only the game directory
+  let experiments = profile.join("experiments");
+  let under_profile = |dir: &Dir| dir.path.starts_with(profile.join("mods")) || dir.path.starts_with(profile.join("layers"));
+  let deployment = upper.path == profile.join("overwrite") && work.path == profile.join("overwrite.work")
+    && lower.last().map(|dir| &dir.path) == Some(&target.path)
+    && lower[..lower.len() - 1].iter().all(under_profile);
+  let experiment = upper.path.starts_with(&experiments) && work.path.starts_with(&experiments)
+    && lower.len() == 1 && lower[0].path == target.path;
+  if !deployment && !experiment {
+    fail("those layers aren't allowed for this game");
+  }
+  //nothing in the game directory gets to be setuid or a device, whatever the mods put there
+  let mounted = match mount_by_fds(&target, &upper, &work, &lower) {
+    Ok(true) => Ok(()),
+    Ok(false) => mount_by_proc(&target, &upper, &work, &lower),
+    Err(e) => Err(e),
+  };
+  match mounted {
+    Ok(()) => exit(0),
+    Err(e) => fail(&format!("mounting failed: {}", e)),
+  }
+}
+
+//Only the game's mount point comes off, and only with an overlay on top there, that's all
+//the manager ever mounts. Stale mounts on it get cleaned up the same way
+fn umount(uid: u32, game: &Game, path: &Path) -> ! {
+  let target = owned_dir(&game.install_path.join(&game.path_extension), uid);
+  if canonical(path) != target.path {
+    fail("that isn't where the game gets mounted");
+  }
+  let (parent, name) = match (target.path.parent(), target.path.file_name()) {
+    (Some(parent), Some(name)) => (owned_dir(parent, uid), name),
+    _ => fail("the game can't be mounted on the root"),
+  };
+  let name = match CString::new(name.as_bytes()) {
+    Ok(name) => name,
+    Err(_) => fail("the game's mount point has a nul in its name"),
+  };
+  match top_mount(&target.path) {
+    Some(fs_type) if fs_type == "overlay" || fs_type == "fuse.fuse-overlayfs" => {},
+    Some(fs_type) => fail(&format!("what's mounted there is {}, not an overlay", fs_type)),
+    None => fail("nothing is mounted there"),
+  }
+  //what the fd of the mount point sees is the top mount, the path could have been renamed since
+  let mut stats: libc::statfs = unsafe { std::mem::zeroed() };
+  if unsafe { libc::fstatfs(target.fd(), &mut stats) } != 0
+    || !matches!(stats.f_type as i64, OVERLAYFS_SUPER_MAGIC | FUSE_SUPER_MAGIC) {
+    fail("what's mounted there isn't an overlay");
+  }
+  //an fd inside the mount keeps it busy, it comes off by its name in the parent the caller
+  //can't swap out anymore. A mount point can't be renamed or removed
+  drop(target);
+  if unsafe { libc::fchdir(parent.fd()) } != 0 {
+    fail(&format!("can't get to '{}': {}", parent.path.display(), io::Error::last_os_error()));
+  }
+  if unsafe { libc::umount2(name.as_ptr(), libc::UMOUNT_NOFOLLOW) } != 0 {
+    fail(&format!("unmounting failed: {}", io::Error::last_os_error()));
+  }
+  exit(0)
+}
+
+fn main() {
+  let args: Vec<OsString> = env::args_os().skip(1).collect();
+  if args.len() < 4 {
+    fail("usage: tmm-mount-helper mount <config dir> <appid> <upperdir> <workdir> <lowerdir>... | umount <config dir> <appid> <path>");
+  }
+  let uid = caller();
+  let appid = match args[2].to_str() {
+    Some(appid) => appid,
+    None => fail("the appid has to be a number"),
+  };
+  let game = registered_game(uid, Path::new(&args[1]), appid);
+  let paths: Vec<PathBuf> = args[3..].iter().map(PathBuf::from).collect();
+  match args[0].to_str() {
+    Some("mount") => mount(uid, &game, &paths),
+    Some("umount") => umount(uid, &game, &paths[0]),
+    _ => fail("the only operations are mount and umount"),
+  }
+}
@@ -6,15 +6,156 @@
 mod mod_manager;
 mod mod_downloader;
 
+use tmm_core::{logging, status};
+
 fn main() {
+  //Steam's launch options routing the game through the manager, '<manager> launch %command%'
+  let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+  if matches!(args.get(1), Some(arg) if arg == "launch") {
+    if let Err(e) = logging::init("launch") {
+      eprintln!("Couldn't open the log file: {}", e);
+    }
+    std::process::exit(tmm_core::mod_manager::steam_wrapper::run(args[2..].to_vec()));
+  }
+  if let Err(e) = logging::init(mod_manager::LOG_NAME) {
+    eprintln!("Couldn't open the log file: {}", e);
+  }
+  if let Err(e) = status::init() {
+    tracing::warn!("Couldn't open the status stream: {}", e);
+  }
+  tmm_core::mod_manager::migrate::run_on_startup();
+  tmm_core::mod_manager::stale::report_on_startup();
   tauri::Builder::default()
+    .manage(tmm_core::mod_manager::registry::Registry::new())
+    .manage(tmm_core::tasks::TaskManager::new())
     .invoke_handler(tauri::generate_handler![
       mod_manager::uncompress, 
+      mod_manager::peek_archive,
+      mod_manager::export_mod,
+      mod_manager::install_from_folder,
       mod_manager::scan_games, 
+      mod_manager::rescan_games,
+      mod_manager::remove_game,
+      mod_manager::watch_game_updates,
+      mod_manager::watch_mod_folders,
+      mod_manager::get_steam_libraries,
+      mod_manager::get_prefix_paths,
+      mod_manager::add_custom_game,
+      mod_manager::get_game_definitions,
+      mod_manager::get_search_roots,
+      mod_manager::set_search_roots,
+      mod_manager::scan_for_other_games,
+      mod_manager::confirm_proposed_game,
       mod_manager::deploy,
+      mod_manager::deploy_preview,
+      mod_manager::verify_deployment,
+      mod_manager::purge,
+      mod_manager::repair_deployment,
+      mod_manager::get_deploy_status,
+      mod_manager::get_deploy_capabilities,
+      mod_manager::set_deploy_backend,
+      mod_manager::test_session,
+      mod_manager::bisect_start,
+      mod_manager::bisect_status,
+      mod_manager::bisect_deploy,
+      mod_manager::bisect_test,
+      mod_manager::bisect_verdict,
+      mod_manager::bisect_abort,
+      mod_manager::add_override_rule,
+      mod_manager::get_override_rules,
+      mod_manager::remove_override_rule,
+      mod_manager::start_experiment,
+      mod_manager::get_experiments,
+      mod_manager::get_experiment_files,
+      mod_manager::promote_experiment,
+      mod_manager::discard_experiment,
+      mod_manager::get_overwrite_files,
+      mod_manager::clean_overwrite,
+      mod_manager::promote_overwrite,
+      mod_manager::get_profiles,
+      mod_manager::clone_profile,
+      mod_manager::switch_profile,
+      mod_manager::delete_profile,
+      mod_manager::export_profile,
+      mod_manager::import_profile,
+      mod_manager::set_sync_path,
+      mod_manager::sync_profile,
+      mod_manager::get_stale_mounts,
+      mod_manager::cleanup_stale_mounts,
       mod_manager::get_mods,
       mod_manager::remove_mod,
+      mod_manager::get_trash,
+      mod_manager::restore_mod,
+      mod_manager::empty_trash,
+      mod_manager::set_trash_retention,
+      mod_manager::rename_mod,
+      mod_manager::annotate_mod,
+      mod_manager::set_mod_category,
+      mod_manager::set_mod_tags,
+      mod_manager::set_file_hidden,
+      mod_manager::query_mods,
+      mod_manager::get_categories,
+      mod_manager::set_mod_enabled,
+      mod_manager::enable_mods,
+      mod_manager::disable_mods,
+      mod_manager::remove_mods,
+      mod_manager::get_load_order,
+      mod_manager::set_load_order,
+      mod_manager::move_mod,
+      mod_manager::sort_load_order,
+      mod_manager::get_conflicts,
+      mod_manager::get_mod_files,
+      mod_manager::check_updates,
+      mod_manager::recheck_updates,
+      mod_manager::get_mod_versions,
+      mod_manager::switch_mod_version,
+      mod_manager::delete_mod_version,
+      mod_manager::set_update_schedule,
+      mod_manager::watch_mod_updates,
+      mod_manager::launch_game,
+      mod_manager::is_game_running,
+      mod_manager::get_hooks,
+      mod_manager::set_hooks,
+      mod_manager::get_launch_options,
+      mod_manager::set_launch_options,
+      mod_manager::get_steam_launch_options,
+      mod_manager::write_steam_launch_options,
+      mod_manager::get_runners,
+      mod_manager::set_runner,
+      mod_manager::run_executable,
+      mod_manager::add_tool,
+      mod_manager::update_tool,
+      mod_manager::remove_tool,
+      mod_manager::run_tool,
+      mod_manager::get_prefix_verbs,
+      mod_manager::run_prefix_verbs,
+      mod_manager::set_auto_purge,
+      mod_manager::set_save_isolation,
+      mod_manager::get_plugins,
+      mod_manager::set_plugins,
+      mod_manager::get_stagings,
+      mod_manager::set_active_staging,
+      mod_manager::remove_staging,
+      mod_manager::set_deploy_excludes,
+      mod_manager::dedup_mods,
+      mod_manager::collect_pool_garbage,
+      mod_manager::set_launch_through_store,
+      mod_manager::set_storage_quota,
+      mod_manager::get_storage_report,
+      mod_manager::get_mod_stats,
+      mod_manager::set_scratch_path,
+      mod_manager::get_log,
+      mod_manager::tail_log,
+      mod_manager::export_diagnostics,
+      mod_manager::get_settings,
+      mod_manager::update_settings,
+      mod_manager::list_tasks,
+      mod_manager::cancel_task,
       mod_downloader::download,
+      mod_downloader::get_download_sources,
+      mod_downloader::authenticate_source,
+      mod_downloader::resolve_mod_url,
+      mod_downloader::check_mod_update,
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
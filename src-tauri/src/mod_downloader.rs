@@ -1,36 +1,56 @@
-pub mod utils;
-pub mod download;
-pub mod core;
+use tauri::{ State, Window };
 
-use tokio::runtime::Handle;
+use tmm_core::mod_downloader::{source, utils};
+use tmm_core::mod_manager::registry::Registry;
+use tmm_core::mod_manager::storage;
+use tmm_core::tasks::{TaskKind, TaskManager};
+use tmm_core::error::{Context, Error, ErrorCode};
 
-use tauri::{ Window };
-
-use crate::mod_manager::game::Game;
+use crate::mod_manager::{registered, window_events};
 
 //if you are coming from the Vue side of this method call and are wondering at
 //what point the 'window' variable joins the mix, I don't know, but I had to dig
 //through a lot to find it, but it would probably have been easier if I knew where
 //to look in the documentation. Here is where I found it anyways:
 //https://medium.com/@marm.nakamura/trying-to-the-tauri-gui-on-rust-4-state-management-on-the-rust-side-8899bda08936 (at 22:28 on June 8th 2022)
+//Resolves with the id of the download's task right away, how it goes comes as 'task' events
 #[tauri::command]
-pub async fn download(url: String, game: Game, window: Window) {
-    let handle = Handle::current();
-    handle.spawn_blocking(move || {
-        let save_path = game.profile_path.join("downloads").clone();
-        let parsed_url;
-        match utils::parse_url(url.as_str()) {
-            Ok(url) => { parsed_url = url },
-            Err(e) => {
-                eprintln!("Something went wrong while trying to parse the url: '{}' Error message: {}", url, e);
-                return;
-            }
-        }
-        match download::http_download(parsed_url, save_path, window, false, true, "0.1.0") {
-            Ok(_) => {},
-            Err(e) => {
-                eprintln!("Something went wrong while downloading: {}", e);
-            }
-        }
+pub fn download(url: String, appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<u64, Error> {
+    let game = registered(&games, appid, Context::Download)?;
+    let events = window_events(window);
+    let task_events = events.clone();
+    let pending = tasks.spawn(TaskKind::Download, Some(appid), url.clone(), &task_events, move || {
+        let file_name = tmm_core::mod_downloader::fetch(&url, &game, &events)
+            .map_err(|e| Error::new(ErrorCode::SourceFailed, e.to_string()).context(Context::Download).param("url", &url))?;
+        storage::check_quota(&game, &events);
+        Ok(file_name)
     });
-}
\ No newline at end of file
+    Ok(pending.id)
+}
+
+#[tauri::command]
+pub fn get_download_sources() -> Vec<source::SourceInfo> {
+    source::info()
+}
+
+#[tauri::command]
+pub fn authenticate_source(source: String, token: String) -> Result<(), Error> {
+    source::by_id(&source)
+        .and_then(|download_source| download_source.authenticate(&token))
+        .map_err(|e| Error::new(ErrorCode::SourceFailed, e.to_string()).context(Context::Authenticate).param("source", &source))
+}
+
+#[tauri::command]
+pub fn resolve_mod_url(url: String) -> Result<source::ModMetadata, Error> {
+    utils::parse_url(&url)
+        .map_err(failure::Error::from)
+        .and_then(|parsed_url| source::for_url(&parsed_url).resolve(&parsed_url))
+        .map_err(|e| Error::new(ErrorCode::SourceFailed, e.to_string()).context(Context::Download).param("url", &url))
+}
+
+#[tauri::command]
+pub fn check_mod_update(metadata: source::ModMetadata) -> Result<Option<source::ModMetadata>, Error> {
+    source::by_id(&metadata.source)
+        .and_then(|download_source| download_source.check_update(&metadata))
+        .map_err(|e| Error::new(ErrorCode::SourceFailed, e.to_string()).context(Context::Download).param("source", &metadata.source))
+}
@@ -1,171 +1,1119 @@
-use std::{path::PathBuf, path::Path, collections::HashMap, fs};
+use std::{path::PathBuf, path::Path, io};
 
-use serde::{Deserialize, Serialize};
-use dirs;
-use compress_tools::*;
+use tokio::runtime::Handle;
 
+use tmm_core::error::{Context, Error, ErrorCode};
+use tmm_core::{logging, settings, tasks};
+use tmm_core::events::{BackendEvent, Emit, Events};
+use tmm_core::mod_manager::{archive, baseline, bisect, configs, conflicts, definitions, dependencies, deploy, diagnostics, excludes, experiment, export, folder, forget, game, hooks, launch, launch_options, link, load_order, localconfig, meta, mod_updates, monitor, overrides, overwrite, plugins, pool, portable, profiles, protontricks, query, reflink, registry, rename, runners, saves, scan, scratch, search, session, staging, stale, steam_wrapper, storage, sync, tools, trash, updates, verify, versions, watcher};
+use tmm_core::mod_manager::{BatchReport, Mod, check_mod_name, load_mods, resolve_mod, resolve_mods, deployable, deploy_mods, switch_for_launch, deploy_for_launch, prepare_launch, install_archive};
 
-extern crate steamlocate;
-use steamlocate::SteamDir;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{State, Window};
 
-mod ofs;
-pub mod game;
+use game::{Game, DeployMode};
+use registry::Registry;
+use tasks::{Pending, TaskKind, TaskManager};
 
-use game::{Game, Executable};
+//The Tauri commands, the frontend's way into tmm-core. What they do is in there, these
+//only move the work off the main thread and attach the context to errors
 
-// #[derive(Serialize, Deserialize)]
-// pub struct Game {
-//   appid: u32,
-//   name: String,
-//   path: PathBuf,
-//   stage_path: PathBuf,
-//   work_path: PathBuf,
-// }
+struct WindowEvents(Window);
 
-#[derive(Serialize, Deserialize)]
-pub struct Mod {
-  name: String,
+impl Emit for WindowEvents {
+  fn emit(&self, name: &str, payload: Value) -> Result<(), String> {
+    self.0.emit(name, payload).map_err(|e| e.to_string())
+  }
+}
+
+//The events core sends go to the window the command came from
+pub(crate) fn window_events(window: Window) -> Events {
+  Events::new(WindowEvents(window))
+}
+
+//Waits for a task without holding up the async runtime, its result is the command's
+pub(crate) async fn finished<T: Send + 'static>(pending: Pending<T>, context: Context) -> Result<T, Error> {
+  match Handle::current().spawn_blocking(move || pending.wait()).await {
+    Ok(result) => result,
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(context)),
+  }
+}
+
+//Commands only get the game's appid, what they work on is the config the backend has
+pub(crate) fn registered(games: &Registry, appid: u32, context: Context) -> Result<Game, Error> {
+  games.game(appid).map_err(|e| Error::io(context, e).param("appid", appid))
+}
+
+//A profile whose mods can't be read is an error, not one without mods
+pub(crate) fn installed(game: &Game, context: Context) -> Result<Vec<Mod>, Error> {
+  load_mods(game).map_err(|e| Error::io(context, e).param("appid", game.appid))
+}
+
+
+#[tauri::command]
+pub async fn deploy(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<deploy::DeployReport, Error> {
+  let game = registered(&games, appid, Context::Deploy)?;
+  let mods = resolve_mods(&game, &mods).map_err(|e| Error::io(Context::Deploy, e).param("appid", appid))?;
+  let events = window_events(window);
+  let task_events = events.clone();
+  let pending = tasks.spawn(TaskKind::Deploy, Some(appid), game.public_name.clone(), &task_events, move || {
+    let mods = deployable(&game, &mods).map_err(|e| Error::io(Context::Deploy, e).param("appid", game.appid))?;
+    let mut report = deploy_mods(&game, &mods, &events).map_err(|e| Error::io(Context::Deploy, e).param("appid", game.appid))?;
+    report.missing_dependencies = dependencies::check(&installed(&game, Context::Deploy)?);
+    Ok(report)
+  });
+  finished(pending, Context::Deploy).await
 }
 
+//Re-hashes what got deployed, e.g. after a game update or the user changing files by hand
 #[tauri::command]
-pub fn deploy(mods: Vec<Mod>, game: Game) {
-  let ofs = ofs::OFSLogic{ game, mods };
-  ofs.exec();
+pub async fn verify_deployment(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<verify::VerifyReport, Error> {
+  let game = registered(&games, appid, Context::VerifyDeployment)?;
+  let mods = resolve_mods(&game, &mods).map_err(|e| Error::io(Context::VerifyDeployment, e).param("appid", appid))?;
+  let pending = tasks.spawn(TaskKind::Hash, Some(appid), game.public_name.clone(), &window_events(window), move || {
+    verify::verify(&game, &mods).map_err(|e| Error::io(Context::VerifyDeployment, e).param("appid", appid))
+  });
+  finished(pending, Context::VerifyDeployment).await
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct SupportedGame {
-  app_id: u32,
-  public_name: String,
-  known_binaries: Vec<Executable>,
-  path_extension: PathBuf
+#[tauri::command]
+pub fn deploy_preview(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>) -> Result<conflicts::DeployPreview, Error> {
+  let game = registered(&games, appid, Context::DeployPreview)?;
+  let mods = resolve_mods(&game, &mods).map_err(|e| Error::io(Context::DeployPreview, e).param("appid", appid))?;
+  let result = deployable(&game, &mods).and_then(|mods| conflicts::preview(&game, &mods));
+  result.map_err(|e| Error::io(Context::DeployPreview, e).param("appid", game.appid))
 }
 
+//Runs the game once with `toggle` flipped compared to `mods`, without
+//changing what's enabled, resolves once the game exited again
 #[tauri::command]
-pub fn scan_games(supported_games: Vec<SupportedGame>) -> Vec<String> {
-  let mut steam_games: Vec<String> = Vec::new();
-  let steam_apps = SteamDir::locate().unwrap().apps().clone();
+pub async fn test_session(mods: Vec<Mod>, toggle: Mod, appid: u32, games: State<'_, Registry>, window: Window) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::TestSession)?;
+  let mods = resolve_mods(&game, &mods).map_err(|e| Error::io(Context::TestSession, e).param("appid", appid))?;
+  let toggle = resolve_mod(&game, &toggle).map_err(|e| Error::io(Context::TestSession, e).param("appid", appid))?;
+  let events = window_events(window);
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    session::run(&game, &mods, &toggle, &events)
+  }).await;
+  match result {
+    Ok(result) => result.map_err(|e| Error::io(Context::TestSession, e).param("appid", appid)),
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::TestSession).param("appid", appid)),
+  }
+}
 
-  // let known_path_extensions_json = dirs::config_dir().unwrap().join("tmm/known_path_extensions.json");
-  // let path_extension_contents = fs::read_to_string(known_path_extensions_json).unwrap();
-  // let known_path_extensions: Vec<(u32, PathBuf)> = serde_json::from_str(path_extension_contents.as_str()).unwrap();
+//Takes every deployed file out of the game directory again and checks
+//that it's back to how it was right before deploying
+#[tauri::command]
+pub async fn purge(appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<baseline::PurgeReport, Error> {
+  let game = registered(&games, appid, Context::Purge)?;
+  let events = window_events(window);
+  let task_events = events.clone();
+  let pending = tasks.spawn(TaskKind::Purge, Some(appid), game.public_name.clone(), &task_events, move || {
+    tmm_core::mod_manager::purge(&game, &events).map_err(|e| Error::io(Context::Purge, e).param("appid", game.appid))
+  });
+  finished(pending, Context::Purge).await
+}
 
-  // let supported_games_json = dirs::config_dir().unwrap().join("tmm/supported_games.json");
-  // let supported_games_contents = fs::read_to_string(supported_games_json).unwrap();
-  // let supported_games: Vec<u32> = serde_json::from_str(supported_games_contents.as_str()).unwrap();
+//Cleans up after a deployment that got interrupted, returns how many
+//of its file operations had to be rolled back
+#[tauri::command]
+pub fn repair_deployment(appid: u32, games: State<'_, Registry>) -> Result<usize, Error> {
+  let game = registered(&games, appid, Context::RepairDeployment)?;
+  link::repair(&game).map_err(|e| Error::io(Context::RepairDeployment, e).param("appid", game.appid))
+}
 
-  // println!("Known Path Extensions: {:?}", known_path_extensions);
-  for key in steam_apps.keys() {
-    let app = steam_apps[key].as_ref().unwrap();
+#[tauri::command]
+pub fn get_deploy_status(appid: u32, games: State<'_, Registry>) -> Result<deploy::DeployStatus, Error> {
+  let game = registered(&games, appid, Context::DeployStatus)?;
+  deploy::backend(game.deploy_mode).status(&game).map_err(|e| Error::io(Context::DeployStatus, e).param("appid", game.appid))
+}
 
-    let pathbuf_to_game_config = dirs::config_dir().unwrap().join("tmm/").join(format!("{}.json", app.appid));
-    let path_to_game_config = Path::new(&pathbuf_to_game_config);
-    let already_found = path_to_game_config.exists();
+#[tauri::command]
+pub fn get_deploy_capabilities(appid: u32, games: State<'_, Registry>) -> Result<Vec<deploy::Capabilities>, Error> {
+  let game = registered(&games, appid, Context::DeployStatus)?;
+  Ok(deploy::all_modes().iter().map(|mode| deploy::backend(*mode).capabilities(&game)).collect())
+}
+
+//Whatever the old backend deployed gets purged first,
+//the new one wouldn't know how to undo it
+#[tauri::command]
+pub fn set_deploy_backend(appid: u32, games: State<'_, Registry>, backend: DeployMode, window: Window) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Purge)?;
+  tmm_core::mod_manager::purge(&game, &window_events(window)).map_err(|e| Error::io(Context::Purge, e).param("appid", game.appid))?;
+  //configs from before reflinks were probed get probed once copies are wanted
+  let reflink = match (backend, game.reflink) {
+    (DeployMode::Copy, None) => Some(reflink::probe(&game)),
+    (_, reflink) => reflink,
+  };
+  let game = Game { deploy_mode: backend, reflink, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn scan_games(supported_games: Vec<scan::SupportedGame>, window: Window) -> Result<Vec<game::ScannedGame>, Error> {
+  let events = window_events(window);
+  let user_games = definitions::load().games;
+  let scanned = scan::scan_games(&definitions::merge(supported_games, user_games))?;
+  for change in scanned.iter().filter_map(|scanned| scanned.version_change.as_ref()) {
+    events.send(BackendEvent::GameVersionChanged(change.clone()));
+  }
+  Ok(scanned)
+}
+
+#[tauri::command]
+pub fn rescan_games(supported_games: Vec<scan::SupportedGame>, window: Window) -> Result<scan::RescanReport, Error> {
+  let events = window_events(window);
+  let user_games = definitions::load().games;
+  let report = scan::rescan(&definitions::merge(supported_games, user_games))?;
+  for change in report.scanned.iter().filter_map(|scanned| scanned.version_change.as_ref()) {
+    events.send(BackendEvent::GameVersionChanged(change.clone()));
+  }
+  Ok(report)
+}
+
+#[tauri::command]
+pub fn remove_game(appid: u32, delete_profile_data: bool, dry_run: bool, window: Window) -> Result<forget::RemoveGameReport, Error> {
+  forget::remove_game(appid, delete_profile_data, dry_run, &window_events(window)).map_err(|e| Error::io(Context::RemoveGame, e).param("appid", appid))
+}
+
+//Called once by the frontend, the watcher keeps running until the app exits
+#[tauri::command]
+pub fn watch_game_updates(window: Window) {
+  let events = window_events(window);
+  updates::watch(events);
+}
+
+//Called once by the frontend like watch_game_updates, 'mods-changed' and 'downloads-changed'
+//come whenever those folders change outside of the app
+#[tauri::command]
+pub fn watch_mod_folders(window: Window) {
+  watcher::watch(window_events(window));
+}
+
+#[tauri::command]
+pub fn get_search_roots() -> Result<Vec<PathBuf>, Error> {
+  search::roots().map_err(|e| Error::io(Context::SearchGames, e))
+}
+
+#[tauri::command]
+pub fn set_search_roots(roots: Vec<PathBuf>) -> Result<(), Error> {
+  search::set_roots(&roots).map_err(|e| Error::io(Context::SearchGames, e))
+}
+
+#[tauri::command]
+pub fn scan_for_other_games(supported_games: Vec<scan::SupportedGame>) -> Result<Vec<search::ProposedGame>, Error> {
+  let user_games = definitions::load().games;
+  search::scan_for_other_games(&definitions::merge(supported_games, user_games)).map_err(|e| Error::io(Context::SearchGames, e))
+}
+
+#[tauri::command]
+pub fn confirm_proposed_game(proposed: search::ProposedGame, supported_games: Vec<scan::SupportedGame>) -> Result<Game, Error> {
+  let user_games = definitions::load().games;
+  search::confirm(&proposed, &definitions::merge(supported_games, user_games))
+    .map_err(|e| Error::io(Context::AddGame, e).param("path", &proposed.install_path))
+}
+
+#[tauri::command]
+pub fn get_game_definitions() -> definitions::Definitions {
+  definitions::load()
+}
+
+#[tauri::command]
+pub fn add_custom_game(name: String, install_path: PathBuf, executable: PathBuf, path_extension: PathBuf) -> Result<Game, Error> {
+  scan::add_custom_game(&name, &install_path, &executable, &path_extension).map_err(|e| Error::io(Context::AddGame, e).param("path", &install_path))
+}
+
+#[tauri::command]
+pub fn get_prefix_paths(appid: u32, games: State<'_, Registry>) -> Result<Option<game::PrefixPaths>, Error> {
+  let game = registered(&games, appid, Context::Prefix)?;
+  Ok(game::PrefixPaths::for_game(&game))
+}
+
+#[tauri::command]
+pub fn get_steam_libraries() -> Vec<scan::SteamLibrary> {
+  scan::steam_libraries()
+}
+
+#[tauri::command]
+pub fn get_mods(appid: u32, games: State<'_, Registry>) -> Result<Vec<Mod>, Error> {
+  let game = registered(&games, appid, Context::ModList)?;
+  installed(&game, Context::ModList)
+}
+
+//Every path more than one enabled mod of the profile ships, with all of them in load order and the winner
+#[tauri::command]
+pub fn get_conflicts(appid: u32, games: State<'_, Registry>) -> Result<Vec<conflicts::Conflict>, Error> {
+  let game = registered(&games, appid, Context::DeployPreview)?;
+  let mods = installed(&game, Context::DeployPreview)?;
+  let result = deployable(&game, &mods).and_then(|mods| conflicts::preview(&game, &mods));
+  result.map(|preview| preview.conflicts).map_err(|e| Error::io(Context::DeployPreview, e).param("appid", game.appid))
+}
+
+//The installed mod's files as a tree, each file with its conflict status
+#[tauri::command]
+pub fn get_mod_files(mod_struct: Mod, appid: u32, games: State<'_, Registry>) -> Result<Vec<conflicts::FileNode>, Error> {
+  let game = registered(&games, appid, Context::ModFiles)?;
+  let mod_struct = resolve_mod(&game, &mod_struct).map_err(|e| Error::io(Context::ModFiles, e).param("appid", appid))?;
+  let result = if game.profile_path.join("mods").join(&mod_struct.name).is_dir() {
+    load_mods(&game).and_then(|mods| conflicts::mod_files(&game, &mods, &mod_struct.name))
+  } else {
+    Err(Error::new(ErrorCode::ModNotFound, format!("'{}' isn't installed", mod_struct.name)).param("mod_name", &mod_struct.name).into_io(io::ErrorKind::NotFound))
+  };
+  result.map_err(|e| Error::io(Context::ModFiles, e).param("appid", game.appid).param("mod_name", &mod_struct.name))
+}
+
+#[tauri::command]
+pub async fn check_updates(appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<mod_updates::UpdateReport, Error> {
+  let game = registered(&games, appid, Context::CheckUpdates)?;
+  let pending = tasks.spawn(TaskKind::UpdateCheck, Some(appid), game.public_name.clone(), &window_events(window), move || {
+    let report = mod_updates::check(&game, None);
+    tasks::checkpoint().map_err(|e| Error::io(Context::CheckUpdates, e).param("appid", appid))?;
+    Ok(report)
+  });
+  finished(pending, Context::CheckUpdates).await
+}
+
+//check_updates for only these mods
+#[tauri::command]
+pub async fn recheck_updates(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<mod_updates::UpdateReport, Error> {
+  let game = registered(&games, appid, Context::CheckUpdates)?;
+  let mods = resolve_mods(&game, &mods).map_err(|e| Error::io(Context::CheckUpdates, e).param("appid", appid))?;
+  let names: Vec<String> = mods.into_iter().map(|elem| elem.name).collect();
+  let pending = tasks.spawn(TaskKind::UpdateCheck, Some(appid), names.join(", "), &window_events(window), move || {
+    let report = mod_updates::check(&game, Some(&names));
+    tasks::checkpoint().map_err(|e| Error::io(Context::CheckUpdates, e).param("appid", appid))?;
+    Ok(report)
+  });
+  finished(pending, Context::CheckUpdates).await
+}
+
+#[tauri::command]
+pub fn get_mod_versions(mod_name: String, appid: u32, games: State<'_, Registry>) -> Result<Vec<versions::ModVersion>, Error> {
+  let game = registered(&games, appid, Context::ModVersions)?;
+  check_mod_name(&mod_name).map_err(|e| Error::io(Context::ModVersions, e).param("appid", appid))?;
+  versions::list(&game, &mod_name).map_err(|e| Error::io(Context::ModVersions, e).param("appid", game.appid).param("mod_name", &mod_name))
+}
+
+//Takes effect with the next deploy, like enabling a mod
+#[tauri::command]
+pub fn switch_mod_version(mod_name: String, version: String, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::ModVersions)?;
+  check_mod_name(&mod_name).map_err(|e| Error::io(Context::ModVersions, e).param("appid", appid))?;
+  versions::switch(&game, &mod_name, &version)
+    .map_err(|e| Error::io(Context::ModVersions, e).param("appid", game.appid).param("mod_name", &mod_name).param("version", &version))
+}
+
+#[tauri::command]
+pub fn delete_mod_version(mod_name: String, version: String, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::ModVersions)?;
+  check_mod_name(&mod_name).map_err(|e| Error::io(Context::ModVersions, e).param("appid", appid))?;
+  versions::delete(&game, &mod_name, &version)
+    .map_err(|e| Error::io(Context::ModVersions, e).param("appid", game.appid).param("mod_name", &mod_name).param("version", &version))
+}
 
-    let mut supported = HashMap::new();
-    for game in &supported_games {
-      supported.insert(
-        game.app_id,
-        game
-      );
+//None or 0 turns the background checks off
+#[tauri::command]
+pub fn set_update_schedule(appid: u32, games: State<'_, Registry>, hours: Option<u64>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  let game = Game { update_check_hours: hours, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+//Called once by the frontend, like watch_game_updates
+#[tauri::command]
+pub fn watch_mod_updates(window: Window) {
+  let events = window_events(window);
+  mod_updates::schedule(events);
+}
+
+#[tauri::command]
+pub fn get_load_order(appid: u32, games: State<'_, Registry>) -> Result<Vec<Mod>, Error> {
+  get_mods(appid, games)
+}
+
+//Moves one mod to its new place in the whole load order, the changes are which
+//files come from another mod now, of the enabled ones
+#[tauri::command]
+pub fn move_mod(mod_struct: Mod, new_index: usize, appid: u32, games: State<'_, Registry>, window: Window) -> Result<load_order::MoveReport, Error> {
+  let game = registered(&games, appid, Context::ModList)?;
+  load_order::move_mod(&game, &mod_struct, new_index, &window_events(window))
+    .map_err(|e| Error::io(Context::ModList, e).param("appid", game.appid).param("mod", &mod_struct.name))
+}
+
+//Every name has to be an installed mod, installed ones that aren't named keep their place after them
+#[tauri::command]
+pub fn set_load_order(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>) -> Result<Vec<Mod>, Error> {
+  let game = registered(&games, appid, Context::ModList)?;
+  load_order::set_order(&game, &mods).map_err(|e| Error::io(Context::ModList, e).param("appid", game.appid))
+}
+
+//Goes into the profile's trash, restore_mod brings it back
+#[tauri::command]
+pub fn remove_mod(mod_struct: Mod, appid: u32, games: State<'_, Registry>) -> Result<trash::TrashEntry, Error> {
+  let game = registered(&games, appid, Context::Trash)?;
+  let mod_struct = resolve_mod(&game, &mod_struct).map_err(|e| Error::io(Context::Trash, e).param("appid", appid))?;
+  trash::remove(&game, &mod_struct.name)
+    .map_err(|e| Error::io(Context::Trash, e).param("appid", game.appid).param("mod_name", &mod_struct.name))
+}
+
+#[tauri::command]
+pub fn get_trash(appid: u32, games: State<'_, Registry>) -> Result<Vec<trash::TrashEntry>, Error> {
+  let game = registered(&games, appid, Context::Trash)?;
+  trash::expire(&game).and_then(|()| trash::list(&game)).map_err(|e| Error::io(Context::Trash, e).param("appid", game.appid))
+}
+
+//Resolves with the name the mod got back
+#[tauri::command]
+pub fn restore_mod(id: String, new_name: Option<String>, appid: u32, games: State<'_, Registry>) -> Result<String, Error> {
+  let game = registered(&games, appid, Context::Trash)?;
+  trash::restore(&game, &id, new_name.as_deref()).map_err(|e| Error::io(Context::Trash, e).param("appid", game.appid).param("id", &id))
+}
+
+//Only the entry with the id if there is one
+#[tauri::command]
+pub fn empty_trash(id: Option<String>, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Trash)?;
+  trash::empty(&game, id.as_deref()).map_err(|e| Error::io(Context::Trash, e).param("appid", game.appid))
+}
+
+//None keeps them for 30 days, 0 until the trash gets emptied
+#[tauri::command]
+pub fn set_trash_retention(appid: u32, games: State<'_, Registry>, days: Option<u64>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  let game = Game { trash_retention_days: days, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn rename_mod(old_name: String, new_name: String, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::RenameMod)?;
+  check_mod_name(&old_name).map_err(|e| Error::io(Context::RenameMod, e).param("appid", appid))?;
+  rename::rename(&game, &old_name, &new_name)
+    .map_err(|e| Error::io(Context::RenameMod, e).param("appid", game.appid).param("mod_name", &old_name))
+}
+
+//None clears the field
+#[tauri::command]
+pub fn annotate_mod(mod_name: String, notes: Option<String>, custom_version: Option<String>, color: Option<String>, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::ModList)?;
+  meta::update(&game, &mod_name, |mod_meta| meta::ModMeta { notes, custom_version, color, ..mod_meta })
+    .map_err(|e| Error::io(Context::ModMeta, e).param("appid", game.appid).param("mod_name", &mod_name))
+}
+
+//Replaces the category a source gave the mod
+#[tauri::command]
+pub fn set_mod_category(mod_name: String, category: Option<String>, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Categories)?;
+  let category = category.map(|category| category.trim().to_string()).filter(|category| !category.is_empty());
+  meta::update(&game, &mod_name, |mod_meta| meta::ModMeta { category, ..mod_meta })
+    .map_err(|e| Error::io(Context::ModMeta, e).param("appid", game.appid).param("mod_name", &mod_name))
+}
+
+//The path is relative to the mod, hiding it keeps it out of deployments and conflicts
+#[tauri::command]
+pub fn set_file_hidden(mod_name: String, path: PathBuf, hidden: bool, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::ModMeta)?;
+  meta::set_file_hidden(&game, &mod_name, &path, hidden)
+    .map_err(|e| Error::io(Context::ModMeta, e).param("appid", game.appid).param("mod_name", &mod_name))
+}
+
+#[tauri::command]
+pub fn set_mod_tags(mod_name: String, tags: Vec<String>, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Categories)?;
+  meta::update(&game, &mod_name, |mod_meta| meta::ModMeta { tags: query::clean_tags(tags), ..mod_meta })
+    .map_err(|e| Error::io(Context::ModMeta, e).param("appid", game.appid).param("mod_name", &mod_name))
+}
+
+//One page of the mods matching the filter, in load order unless sorted otherwise
+#[tauri::command]
+pub fn query_mods(filter: query::ModFilter, sort: Option<query::ModSort>, offset: Option<usize>, limit: Option<usize>, appid: u32, games: State<'_, Registry>) -> Result<query::ModPage, Error> {
+  let game = registered(&games, appid, Context::QueryMods)?;
+  query::query(&game, installed(&game, Context::QueryMods)?, &filter, sort.unwrap_or_default(), offset.unwrap_or(0), limit)
+    .map_err(|e| Error::io(Context::QueryMods, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn get_categories(appid: u32, games: State<'_, Registry>) -> Result<Vec<query::Category>, Error> {
+  let game = registered(&games, appid, Context::Categories)?;
+  Ok(query::categories(&installed(&game, Context::Categories)?))
+}
+
+//Returns what the enabled mods are missing now, e.g. the dependencies of the one just enabled
+//or the mods that needed the one just disabled
+#[tauri::command]
+pub fn set_mod_enabled(mod_struct: Mod, enabled: bool, appid: u32, games: State<'_, Registry>) -> Result<Vec<dependencies::MissingDependency>, Error> {
+  let game = registered(&games, appid, Context::ModList)?;
+  load_order::set_enabled(&game, &mod_struct, enabled)
+    .map_err(|e| Error::io(Context::ModList, e).param("appid", game.appid).param("mod", &mod_struct.name))
+}
+
+//The mod list gets written once for all of them
+#[tauri::command]
+pub fn enable_mods(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>, window: Window) -> Result<BatchReport, Error> {
+  let game = registered(&games, appid, Context::ModList)?;
+  load_order::set_all_enabled(&game, &mods, true, &window_events(window)).map_err(|e| Error::io(Context::ModList, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn disable_mods(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>, window: Window) -> Result<BatchReport, Error> {
+  let game = registered(&games, appid, Context::ModList)?;
+  load_order::set_all_enabled(&game, &mods, false, &window_events(window)).map_err(|e| Error::io(Context::ModList, e).param("appid", game.appid))
+}
+
+//All of them go into the trash, a failure stops there and the ones before it stay removed
+#[tauri::command]
+pub fn remove_mods(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>, window: Window) -> Result<BatchReport, Error> {
+  let game = registered(&games, appid, Context::Trash)?;
+  load_order::remove_all(&game, &mods, &window_events(window)).map_err(|e| Error::io(Context::Trash, e).param("appid", game.appid))
+}
+
+//Moves dependencies in front of the mods that need them, nothing else changes place
+#[tauri::command]
+pub fn sort_load_order(appid: u32, games: State<'_, Registry>) -> Result<Vec<Mod>, Error> {
+  let game = registered(&games, appid, Context::ModList)?;
+  load_order::sort(&game).map_err(|e| Error::io(Context::ModList, e).param("appid", game.appid))
+}
+
+//Resolves with the game once Steam was asked to start it, that it's starting, running
+//and exited again gets emitted as it happens. The profile's pre-launch hooks run before it's deployed,
+//unless Steam's launch options route it through the manager which does all that itself then
+#[tauri::command]
+pub async fn launch_game(appid: u32, games: State<'_, Registry>, profile: Option<String>, window: Window) -> Result<Game, Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  let events = window_events(window);
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    let game = switch_for_launch(game, profile, &events)?;
+    let through_manager = game.store == game::Store::Steam && steam_wrapper::routed(&game);
+    if !through_manager {
+      hooks::run(&game, hooks::HookStage::PreLaunch, Some(&events)).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))?;
+      deploy_for_launch(&game, &events)?;
     }
+    launch::launch(&game).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))?;
+    launch::watch(game.clone(), profiles::active_name(&game), through_manager, events);
+    Ok(game)
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Launch).param("appid", appid))?
+}
 
-    if already_found {
-      // println!("There already exists a config for game: '{}'", app.name.as_ref().unwrap());
-      let json = fs::read_to_string(path_to_game_config).unwrap();
-      steam_games.push(json);
-    } else if !supported.contains_key(&app.appid) {
-      // println!("Game: {} not currently supported.", app.name.as_ref().unwrap());
-    } else {
-      let profile_path = dirs::config_dir().unwrap().join("tmm/profiles/").join(format!("{}", app.appid));
-      let components_count = app.path.to_path_buf().components().count();
-      let work_path = app.path.to_path_buf().components().take(components_count-4).collect::<PathBuf>().join([".tmm_work/", app.appid.to_string().as_str()].join(""));
-      // println!("Game work_directory: {}", &work_path.to_str().unwrap());
-      let path_extension = supported.get(&app.appid).unwrap().path_extension.clone();
-      // let path_extension = PathBuf::new();
-      let executables: Vec<Executable> = supported.get(&app.appid).unwrap().known_binaries.clone();
-      let game = Game {
-        public_name: app.name.as_ref().unwrap().to_owned(),
-        appid: app.appid,
-        install_path: app.path.to_path_buf(),
-        profile_path,
-        work_path,
-        path_extension,
-        executables
-      };
-
-      let json = serde_json::to_string(&game).unwrap();
-      let mut app_config_path = dirs::config_dir().unwrap().join("tmm").join(format!("{}", app.appid));
-      app_config_path.set_extension("json");
-      match fs::create_dir_all(dirs::config_dir().unwrap().join("tmm")) {
-        Ok(()) => {},
-        Err(e) => {
-          eprintln!("Couldn't create config dir while working on game '{}'/{}\nError: {}", app.name.as_ref().unwrap(), app.appid, e);
-        }
-      }
-      match fs::write(&app_config_path, &json) {
-        Ok(()) => {},
-        Err(e) => {
-          eprintln!("Couldn't write to config file for game '{}'/{}\nError: {}", app.name.as_ref().unwrap(), app.appid, e);
-        }
+#[tauri::command]
+pub fn get_launch_options(appid: u32, games: State<'_, Registry>) -> Result<launch_options::LaunchOptions, Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  launch_options::load(&game).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))
+}
+
+//Used as they are when the manager starts the game itself, going through Steam they
+//only apply once they're written into its config with write_steam_launch_options
+#[tauri::command]
+pub fn set_launch_options(appid: u32, games: State<'_, Registry>, options: launch_options::LaunchOptions) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  launch_options::save(&game, &options).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))
+}
+
+//The Proton versions and Wine builds a profile can pin for its executables and tools
+#[tauri::command]
+pub fn get_runners() -> Vec<runners::Runner> {
+  runners::installed()
+}
+
+//None goes back to the game's own, protontricks-launch or the game's Wine
+#[tauri::command]
+pub fn set_runner(appid: u32, games: State<'_, Registry>, runner: Option<runners::Runner>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  let result = runner.as_ref().map_or(Ok(()), runners::ensure_installed)
+    .and_then(|()| launch_options::load(&game))
+    .and_then(|options| launch_options::save(&game, &launch_options::LaunchOptions { runner, ..options }));
+  result.map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SteamLaunchOptions {
+  current: Option<String>,
+  //what write_steam_launch_options would replace them with
+  proposed: String,
+  //the current ones start the game through the manager
+  through_manager: bool,
+}
+
+fn steam_launch_string(game: &Game, through_manager: bool) -> io::Result<String> {
+  if through_manager {
+    steam_wrapper::steam_string(game)
+  } else {
+    launch_options::load(game).map(|options| launch_options::steam_string(&options))
+  }
+}
+
+//For the frontend to show before the user agrees to have them replaced. Through the manager
+//the profile gets deployed even when the game is started from Steam directly
+#[tauri::command]
+pub fn get_steam_launch_options(appid: u32, games: State<'_, Registry>, through_manager: bool) -> Result<SteamLaunchOptions, Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  let result = localconfig::launch_options(game.appid).and_then(|current| {
+    Ok(SteamLaunchOptions { current, proposed: steam_launch_string(&game, through_manager)?, through_manager: steam_wrapper::routed(&game) })
+  });
+  result.map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))
+}
+
+//For the Steam account that logged in last, Steam has to be closed
+#[tauri::command]
+pub fn write_steam_launch_options(appid: u32, games: State<'_, Registry>, through_manager: bool) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  let result = steam_launch_string(&game, through_manager).and_then(|options| localconfig::set_launch_options(game.appid, &options));
+  result.map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))
+}
+
+//Whether the game is running now, started by the manager or not. Deploying, purging and
+//removing mods are refused until it exited
+#[tauri::command]
+pub fn is_game_running(appid: u32, games: State<'_, Registry>) -> Result<bool, Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  Ok(launch::is_running(&game))
+}
+
+#[tauri::command]
+pub fn get_hooks(appid: u32, games: State<'_, Registry>) -> Result<hooks::Hooks, Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  hooks::load(&game).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn set_hooks(appid: u32, games: State<'_, Registry>, hooks: hooks::Hooks) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  hooks::save(&game, &hooks).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))
+}
+
+//One of the game's executables, e.g. a patcher or the launcher, in the same prefix and with the
+//same mods the game gets. Resolves with its exit code once it exited, None if it was killed
+#[tauri::command]
+pub async fn run_executable(appid: u32, games: State<'_, Registry>, profile: Option<String>, executable: String, args: Vec<String>, window: Window) -> Result<Option<i32>, Error> {
+  let game = registered(&games, appid, Context::Launch)?;
+  let events = window_events(window);
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    let game = prepare_launch(game, profile, &events)?;
+    let found = match game.executables.iter().find(|known| known.name == executable) {
+      Some(found) => found,
+      None => {
+        return Err(Error::new(ErrorCode::UnknownExecutable, format!("'{}' has no executable '{}'", game.public_name, executable))
+          .param("executable", &executable)
+          .context(Context::Launch)
+          .param("appid", appid));
       }
-      make_tmm_game_directories(game);
-      steam_games.push(json);
-    }
-    // let app = steam_apps[key].as_ref().unwrap();
-    // let stage_path = dirs::home_dir().unwrap().join([".config/tmm_stage/games/", app.appid.to_string().as_str()].join(""));
-    // let components_count = app.path.to_path_buf().components().count();
-    // let work_path = app.path.to_path_buf().components().take(components_count-4).collect::<PathBuf>().join([".tmm_work/", app.appid.to_string().as_str()].join(""));
-    // let game = Game{ appid: app.appid, name: app.name.as_ref().unwrap().to_string(), path: app.path.to_path_buf(), stage_path, work_path };
-    // let json = serde_json::to_string(&game).unwrap();
-    // make_tmm_game_directories(game);
-    // steam_games.push(json);
+    };
+    let status = launch::run_executable(&game, found, &args).and_then(|child| monitor::wait(appid, child))
+      .map_err(|e| Error::io(Context::Launch, e).param("appid", appid).param("executable", &executable))?;
+    //config tools are what changes the INIs the most
+    configs::sync_back(&game).map_err(|e| Error::io(Context::Launch, e).param("appid", appid))?;
+    Ok(status.code())
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Launch).param("appid", appid))?
+}
+
+#[tauri::command]
+pub fn add_tool(appid: u32, games: State<'_, Registry>, tool: game::Tool) -> Result<Game, Error> {
+  let game = registered(&games, appid, Context::Tools)?;
+  tools::add(game, tool).map_err(|e| Error::io(Context::Tools, e).param("appid", appid))
+}
+
+#[tauri::command]
+pub fn update_tool(appid: u32, games: State<'_, Registry>, name: String, tool: game::Tool) -> Result<Game, Error> {
+  let game = registered(&games, appid, Context::Tools)?;
+  tools::update(game, &name, tool).map_err(|e| Error::io(Context::Tools, e).param("appid", appid))
+}
+
+#[tauri::command]
+pub fn remove_tool(appid: u32, games: State<'_, Registry>, name: String) -> Result<Game, Error> {
+  let game = registered(&games, appid, Context::Tools)?;
+  tools::remove(game, &name).map_err(|e| Error::io(Context::Tools, e).param("appid", appid))
+}
+
+//Like run_executable, with the game's files deployed from the profile and its INIs kept afterwards
+#[tauri::command]
+pub async fn run_tool(appid: u32, games: State<'_, Registry>, profile: Option<String>, name: String, window: Window) -> Result<Option<i32>, Error> {
+  let game = registered(&games, appid, Context::Tools)?;
+  let events = window_events(window);
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    let game = prepare_launch(game, profile, &events)?;
+    let status = tools::run(&game, &name).and_then(|child| monitor::wait(appid, child))
+      .map_err(|e| Error::io(Context::Tools, e).param("appid", appid).param("tool", &name))?;
+    configs::sync_back(&game).map_err(|e| Error::io(Context::Tools, e).param("appid", appid))?;
+    Ok(status.code())
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Tools).param("appid", appid))?
+}
+
+//The winetricks verbs that can go into the game's prefix, through protontricks for Steam games
+#[tauri::command]
+pub async fn get_prefix_verbs(appid: u32, games: State<'_, Registry>) -> Result<Vec<protontricks::Verb>, Error> {
+  let game = registered(&games, appid, Context::Prefix)?;
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    protontricks::verbs(&game).map_err(|e| Error::io(Context::Prefix, e).param("appid", appid))
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Prefix).param("appid", appid))?
+}
+
+//Installs the verbs into the prefix, what it prints comes as 'prefix-fix-output' events
+#[tauri::command]
+pub async fn run_prefix_verbs(appid: u32, games: State<'_, Registry>, verbs: Vec<String>, window: Window) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Prefix)?;
+  let events = window_events(window);
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    monitor::ensure_not_running(&game)
+      .and_then(|_| protontricks::run(&game, &verbs, &events))
+      .map_err(|e| Error::io(Context::Prefix, e).param("appid", appid))
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Prefix).param("appid", appid))?
+}
+
+//The plugins of the enabled mods in the order they'll be written to the game's load order file
+#[tauri::command]
+pub fn get_plugins(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>) -> Result<Vec<plugins::Plugin>, Error> {
+  let game = registered(&games, appid, Context::Plugins)?;
+  let mods = resolve_mods(&game, &mods).map_err(|e| Error::io(Context::Plugins, e).param("appid", appid))?;
+  deployable(&game, &mods).and_then(|mods| plugins::list(&game, &mods)).map_err(|e| Error::io(Context::Plugins, e).param("appid", game.appid))
+}
+
+//Takes effect on the next deploy
+#[tauri::command]
+pub fn set_plugins(plugins: Vec<plugins::Plugin>, appid: u32, games: State<'_, Registry>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Plugins)?;
+  plugins::set(&game, &plugins).map_err(|e| Error::io(Context::Plugins, e).param("appid", game.appid))
+}
+
+//Moves the saves there are now into the active profile, turning it off moves the active profile's back
+#[tauri::command]
+pub fn set_save_isolation(appid: u32, games: State<'_, Registry>, enabled: bool) -> Result<Game, Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  saves::set_isolation(game, enabled).map_err(|e| Error::io(Context::SaveConfig, e).param("appid", appid))
+}
+
+#[tauri::command]
+pub fn set_auto_purge(appid: u32, games: State<'_, Registry>, enabled: bool) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  let game = Game { auto_purge: enabled, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn get_stagings(appid: u32, games: State<'_, Registry>) -> Result<Vec<String>, Error> {
+  let game = registered(&games, appid, Context::Staging)?;
+  staging::list(&game).map_err(|e| Error::io(Context::Staging, e).param("appid", game.appid))
+}
+
+//Later deployments of the staging backend go into this staging, the others are left as they are
+#[tauri::command]
+pub fn set_active_staging(appid: u32, games: State<'_, Registry>, name: String) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Staging)?;
+  staging::validate_name(&name).map_err(|e| Error::io(Context::Staging, e).param("appid", game.appid))?;
+  let game = Game { staging: Some(name), ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn remove_staging(appid: u32, games: State<'_, Registry>, name: String) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Staging)?;
+  staging::remove(&game, &name).map_err(|e| Error::io(Context::Staging, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn set_deploy_excludes(appid: u32, games: State<'_, Registry>, patterns: Vec<String>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  excludes::validate(&patterns).map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))?;
+  let game = Game { deploy_excludes: patterns, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+//Shares identical files of all the game's mods through the pool, for mods installed before it existed
+#[tauri::command]
+pub async fn dedup_mods(appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<pool::DedupReport, Error> {
+  let game = registered(&games, appid, Context::Pool)?;
+  let pending = tasks.spawn(TaskKind::Hash, Some(appid), game.public_name.clone(), &window_events(window), move || {
+    pool::ingest_game(&game).map_err(|e| Error::io(Context::Pool, e).param("appid", appid))
+  });
+  finished(pending, Context::Pool).await
+}
+
+//Removes pooled files no mod of any game uses anymore
+#[tauri::command]
+pub fn collect_pool_garbage() -> Result<pool::GcReport, Error> {
+  pool::collect_garbage().map_err(|e| Error::io(Context::Pool, e))
+}
+
+//Only does anything for stores that can start games, e.g. Lutris
+#[tauri::command]
+pub fn set_launch_through_store(appid: u32, games: State<'_, Registry>, enabled: bool) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  let game = Game { launch_through_store: enabled, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn set_storage_quota(appid: u32, games: State<'_, Registry>, quota: Option<u64>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  let game = Game { storage_quota: quota, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+//None goes back to staging inside the profile
+#[tauri::command]
+pub fn set_scratch_path(appid: u32, games: State<'_, Registry>, path: Option<PathBuf>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  if let Some(path) = &path {
+    scratch::validate(path).map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))?;
+  }
+  let game = Game { scratch_path: path, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn get_storage_report(appid: u32, games: State<'_, Registry>) -> Result<storage::StorageReport, Error> {
+  let game = registered(&games, appid, Context::StorageReport)?;
+  storage::report(&game).map_err(|e| Error::io(Context::StorageReport, e).param("appid", game.appid))
+}
+
+//Size and file count of every mod, cached until a mod's directories change
+#[tauri::command]
+pub async fn get_mod_stats(appid: u32, games: State<'_, Registry>) -> Result<Vec<storage::ModStats>, Error> {
+  let game = registered(&games, appid, Context::StorageReport)?;
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || storage::mod_stats(&game)).await;
+  match result {
+    Ok(result) => result.map_err(|e| Error::io(Context::StorageReport, e).param("appid", appid)),
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::StorageReport).param("appid", appid)),
   }
-  steam_games.into()
 }
 
 #[tauri::command]
-pub fn get_mods(game: Game) -> Vec<String>{
-  let mut mods: Vec<String> = Vec::new();
-  for path in get_directories(&game.profile_path.join("mods")) {
-    let name = path.file_name().unwrap().to_str().unwrap().to_string();
-    let mod_struct: Mod = Mod { name };
-    let mod_json: String = serde_json::to_string(&mod_struct).unwrap();
-    mods.push(mod_json);
+pub fn bisect_start(mods: Vec<Mod>, appid: u32, games: State<'_, Registry>) -> Result<bisect::Bisection, Error> {
+  let game = registered(&games, appid, Context::Bisection)?;
+  let mods = resolve_mods(&game, &mods).map_err(|e| Error::io(Context::Bisection, e).param("appid", appid))?;
+  let bisection = bisect::Bisection::start(mods).map_err(|e| Error::io(Context::Bisection, e))?;
+  bisection.save(&game).map_err(|e| Error::io(Context::Bisection, e).param("appid", game.appid))?;
+  Ok(bisection)
+}
+
+#[tauri::command]
+pub fn bisect_status(appid: u32, games: State<'_, Registry>) -> Result<Option<bisect::Bisection>, Error> {
+  let game = registered(&games, appid, Context::Bisection)?;
+  bisect::Bisection::load(&game).map_err(|e| Error::io(Context::Bisection, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn bisect_deploy(appid: u32, games: State<'_, Registry>, window: Window) -> Result<deploy::DeployReport, Error> {
+  let game = registered(&games, appid, Context::Deploy)?;
+  bisect::deploy_candidate(&game, &window_events(window)).map_err(|e| Error::io(Context::Deploy, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub async fn bisect_test(appid: u32, games: State<'_, Registry>, window: Window) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::TestSession)?;
+  let events = window_events(window);
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    bisect::test_candidate(&game, &events)
+  }).await;
+  match result {
+    Ok(result) => result.map_err(|e| Error::io(Context::TestSession, e).param("appid", appid)),
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::TestSession).param("appid", appid)),
   }
-  mods.into()
 }
 
 #[tauri::command]
-pub fn remove_mod(mod_struct: Mod, game: Game) {
-  let mod_dir = game.profile_path.join("mods").join(mod_struct.name);
-  fs::remove_dir_all(mod_dir).unwrap();
+pub fn bisect_verdict(appid: u32, games: State<'_, Registry>, crashed: bool) -> Result<bisect::Bisection, Error> {
+  let game = registered(&games, appid, Context::Bisection)?;
+  let mut bisection = bisect::current(&game).map_err(|e| Error::io(Context::Bisection, e).param("appid", game.appid))?;
+  bisection.verdict(crashed).map_err(|e| Error::io(Context::Bisection, e).param("appid", game.appid))?;
+  bisection.save(&game).map_err(|e| Error::io(Context::Bisection, e).param("appid", game.appid))?;
+  Ok(bisection)
 }
 
-pub(crate) fn make_tmm_game_directories(game: Game) {
-  fs::create_dir_all(&game.profile_path).unwrap();
-  fs::create_dir_all(&game.work_path).unwrap();
-  fs::create_dir_all(&game.profile_path.join("downloads/")).unwrap();
-  fs::create_dir_all(&game.profile_path.join("mods/")).unwrap();
+//Stops bisecting and deploys the mods it started from again
+#[tauri::command]
+pub fn bisect_abort(appid: u32, games: State<'_, Registry>, window: Window) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Bisection)?;
+  let bisection = bisect::current(&game).map_err(|e| Error::io(Context::Bisection, e).param("appid", game.appid))?;
+  deploy_mods(&game, bisection.original_mods(), &window_events(window)).map_err(|e| Error::io(Context::Deploy, e).param("appid", game.appid))?;
+  bisect::Bisection::remove(&game).map_err(|e| Error::io(Context::Bisection, e).param("appid", game.appid))
 }
 
-fn get_directories(path: &PathBuf) -> Vec<PathBuf> {
-  let mut directories: Vec<PathBuf> = Vec::new();
-  if path.exists() {
-    for entry in path.read_dir().unwrap() {
-      if let Ok (entry) = entry {
-        if entry.path().is_dir() {
-          directories.push(entry.path());
-        }
-      }
-    }
+#[tauri::command]
+pub fn add_override_rule(appid: u32, games: State<'_, Registry>, path: PathBuf, mod_name: String) -> Result<Vec<overrides::OverrideRule>, Error> {
+  let game = registered(&games, appid, Context::OverrideRules)?;
+  check_mod_name(&mod_name).map_err(|e| Error::io(Context::OverrideRules, e).param("appid", appid))?;
+  overrides::add(&game, overrides::OverrideRule { path, mod_name }).map_err(|e| Error::io(Context::OverrideRules, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn get_override_rules(appid: u32, games: State<'_, Registry>) -> Result<Vec<overrides::OverrideRule>, Error> {
+  let game = registered(&games, appid, Context::OverrideRules)?;
+  overrides::load(&game).map_err(|e| Error::io(Context::OverrideRules, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn remove_override_rule(appid: u32, games: State<'_, Registry>, path: PathBuf) -> Result<Vec<overrides::OverrideRule>, Error> {
+  let game = registered(&games, appid, Context::OverrideRules)?;
+  overrides::remove(&game, &path).map_err(|e| Error::io(Context::OverrideRules, e).param("appid", game.appid))
+}
+
+//Resolves with the experiment once the tool exited
+#[tauri::command]
+pub async fn start_experiment(appid: u32, games: State<'_, Registry>, executable: String) -> Result<experiment::Experiment, Error> {
+  let game = registered(&games, appid, Context::Experiment)?;
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    experiment::run(&game, &executable)
+  }).await;
+  match result {
+    Ok(result) => result.map_err(|e| Error::io(Context::Experiment, e).param("appid", appid)),
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Experiment).param("appid", appid)),
+  }
+}
+
+#[tauri::command]
+pub fn get_profiles(appid: u32, games: State<'_, Registry>) -> Result<Vec<profiles::Profile>, Error> {
+  let game = registered(&games, appid, Context::Profiles)?;
+  profiles::list(&game).map_err(|e| Error::io(Context::Profiles, e).param("appid", game.appid))
+}
+
+//Hardlinks the mods and downloads, so cloning a big profile is cheap
+#[tauri::command]
+pub async fn clone_profile(appid: u32, games: State<'_, Registry>, source: String, new_name: String) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Profiles)?;
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    profiles::clone(&game, &source, &new_name).map_err(|e| Error::io(Context::Profiles, e).param("appid", appid).param("profile", &source))
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Profiles).param("appid", appid))?
+}
+
+//Returns the game with its new profile path, nothing is deployed from the new profile yet
+#[tauri::command]
+pub fn switch_profile(appid: u32, games: State<'_, Registry>, name: String, window: Window) -> Result<Game, Error> {
+  let game = registered(&games, appid, Context::Profiles)?;
+  profiles::switch(game, &name, &window_events(window)).map_err(|e| Error::io(Context::Profiles, e).param("appid", appid).param("profile", &name))
+}
+
+//force purges the profile if it's deployed, discard_saves is the confirmation that its own saves
+//and INIs go with it, collect_garbage frees the files only its mods used
+#[tauri::command]
+pub fn delete_profile(appid: u32, games: State<'_, Registry>, name: String, force: bool, discard_saves: bool, collect_garbage: bool, window: Window) -> Result<profiles::DeleteReport, Error> {
+  let game = registered(&games, appid, Context::Profiles)?;
+  profiles::delete(game, &name, force, discard_saves, collect_garbage, &window_events(window)).map_err(|e| Error::io(Context::Profiles, e).param("appid", appid).param("profile", &name))
+}
+
+//Mod files are only in there with include_mods, otherwise importing downloads them again
+#[tauri::command]
+pub async fn export_profile(appid: u32, games: State<'_, Registry>, profile: String, dest: PathBuf, include_mods: bool) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Profiles)?;
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    portable::export(&game, &profile, &dest, include_mods).map_err(|e| Error::io(Context::Profiles, e).param("appid", appid).param("profile", &profile))
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Profiles).param("appid", appid))?
+}
+
+#[tauri::command]
+pub async fn import_profile(appid: u32, games: State<'_, Registry>, archive: PathBuf, profile: String, window: Window) -> Result<portable::ImportReport, Error> {
+  let game = registered(&games, appid, Context::Profiles)?;
+  let events = window_events(window);
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    portable::import(&game, &archive, &profile, &events).map_err(|e| Error::io(Context::Profiles, e).param("appid", appid).param("profile", &profile))
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Profiles).param("appid", appid))?
+}
+
+//None stops syncing, what got synced stays where it is
+#[tauri::command]
+pub fn set_sync_path(appid: u32, games: State<'_, Registry>, path: Option<PathBuf>) -> Result<Game, Error> {
+  let game = registered(&games, appid, Context::SaveConfig)?;
+  let game = Game { sync_path: path, ..game };
+  game.save().map_err(|e| Error::io(Context::SaveConfig, e).param("appid", game.appid))?;
+  Ok(game)
+}
+
+//The active profile without a name. Sections changed on both machines are conflicts
+//until it's run again with the side that should win
+#[tauri::command]
+pub async fn sync_profile(appid: u32, games: State<'_, Registry>, profile: Option<String>, prefer: Option<sync::SyncSide>, window: Window) -> Result<sync::SyncReport, Error> {
+  let game = registered(&games, appid, Context::Sync)?;
+  let events = window_events(window);
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    let profile = profile.unwrap_or_else(|| profiles::active_name(&game));
+    sync::sync(&game, &profile, prefer, &events).map_err(|e| Error::io(Context::Sync, e).param("appid", appid).param("profile", &profile))
+  }).await;
+  result.map_err(|e| Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Sync).param("appid", appid))?
+}
+
+//What the game and tools wrote while running from the overlay or a staging
+#[tauri::command]
+pub fn get_overwrite_files(appid: u32, games: State<'_, Registry>) -> Result<overwrite::OverwriteFiles, Error> {
+  let game = registered(&games, appid, Context::Overwrite)?;
+  overwrite::files(&game).map_err(|e| Error::io(Context::Overwrite, e).param("appid", game.appid))
+}
+
+//Relative to the overwrite, without paths it's everything
+#[tauri::command]
+pub fn clean_overwrite(appid: u32, games: State<'_, Registry>, paths: Option<Vec<PathBuf>>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Overwrite)?;
+  overwrite::clean(&game, paths.as_deref()).map_err(|e| Error::io(Context::Overwrite, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn promote_overwrite(appid: u32, games: State<'_, Registry>, mod_name: String, paths: Option<Vec<PathBuf>>) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Overwrite)?;
+  overwrite::promote(&game, &mod_name, paths.as_deref())
+    .map_err(|e| Error::io(Context::Overwrite, e).param("appid", game.appid).param("mod_name", &mod_name))
+}
+
+#[tauri::command]
+pub fn get_experiments(appid: u32, games: State<'_, Registry>) -> Result<Vec<experiment::Experiment>, Error> {
+  let game = registered(&games, appid, Context::Experiment)?;
+  experiment::list(&game).map_err(|e| Error::io(Context::Experiment, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn get_experiment_files(appid: u32, games: State<'_, Registry>, id: String) -> Result<experiment::ExperimentFiles, Error> {
+  let game = registered(&games, appid, Context::Experiment)?;
+  experiment::files(&game, &id).map_err(|e| Error::io(Context::Experiment, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn promote_experiment(appid: u32, games: State<'_, Registry>, id: String, mod_name: String) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Experiment)?;
+  experiment::promote(&game, &id, &mod_name).map_err(|e| Error::io(Context::Experiment, e).param("appid", game.appid))
+}
+
+#[tauri::command]
+pub fn discard_experiment(appid: u32, games: State<'_, Registry>, id: String) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Experiment)?;
+  experiment::discard(&game, &id).map_err(|e| Error::io(Context::Experiment, e).param("appid", game.appid))
+}
+
+//Mounts the last run left behind, across all games
+#[tauri::command]
+pub fn get_stale_mounts() -> Result<Vec<stale::StaleMount>, Error> {
+  game::Game::all().and_then(|games| stale::find(&games)).map_err(|e| Error::io(Context::StaleMounts, e))
+}
+
+//Resolves with the mounts that were taken down
+#[tauri::command]
+pub async fn cleanup_stale_mounts() -> Result<Vec<stale::StaleMount>, Error> {
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || {
+    game::Game::all().and_then(|games| stale::cleanup(&games))
+  }).await;
+  match result {
+    Ok(result) => result.map_err(|e| Error::io(Context::StaleMounts, e)),
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::StaleMounts)),
+  }
+}
+
+//For mods built locally or cloned from git, mode defaults to copying
+#[tauri::command]
+pub async fn install_from_folder(src_path: String, mod_name: String, mode: Option<folder::FolderInstall>, appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Install)?;
+  let events = window_events(window);
+  let checked = game.clone();
+  let pending = tasks.spawn(TaskKind::Install, Some(appid), mod_name.clone(), &events, move || {
+    folder::install(Path::new(&src_path), &mod_name, mode.unwrap_or_default(), &game).map_err(|e| Error::io(Context::Install, e).param("appid", appid))
+  });
+  finished(pending, Context::Install).await?;
+  storage::check_quota(&checked, &events);
+  Ok(())
+}
+
+//Packs the installed mod into dest, a .zip or .7z
+#[tauri::command]
+pub async fn export_mod(mod_struct: Mod, appid: u32, games: State<'_, Registry>, dest: String) -> Result<(), Error> {
+  let game = registered(&games, appid, Context::Export)?;
+  let mod_struct = resolve_mod(&game, &mod_struct).map_err(|e| Error::io(Context::Export, e).param("appid", appid))?;
+  let mod_name = mod_struct.name.clone();
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || export::export(&game, &mod_struct.name, Path::new(&dest))).await;
+  match result {
+    Ok(result) => result.map_err(|e| Error::io(Context::Export, e).param("appid", appid).param("mod_name", &mod_name)),
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Export).param("appid", appid)),
+  }
+}
+
+//Lists what installing the archive would bring in, for picking a subdirectory or another name first
+#[tauri::command]
+pub async fn peek_archive(file_path: String) -> Result<archive::ArchivePeek, Error> {
+  let handle = Handle::current();
+  let path = file_path.clone();
+  let result = handle.spawn_blocking(move || archive::peek(Path::new(&path))).await;
+  match result {
+    Ok(result) => result.map_err(|e| Error::io(Context::PeekArchive, e).param("path", &file_path)),
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::PeekArchive)),
+  }
+}
+
+//file_name becomes the mod's name. Without a password an encrypted archive fails
+//with 'archive_encrypted', to be asked for one. Over a mod of the same name it only
+//goes with the 'replace' duplicate option, as a reinstall
+#[tauri::command]
+pub async fn uncompress(file_path: String, file_name: String, options: Option<archive::InstallOptions>, appid: u32, games: State<'_, Registry>, tasks: State<'_, TaskManager>, window: Window) -> Result<archive::InstallReport, Error> {
+  let game = registered(&games, appid, Context::Install)?;
+  let events = window_events(window);
+  let checked = game.clone();
+  let pending = tasks.spawn(TaskKind::Install, Some(appid), file_name.clone(), &events, move || {
+    install_archive(Path::new(&file_path), &file_name, options.unwrap_or_default(), &game)
+      .map_err(|e| Error::io(Context::Install, e).param("appid", game.appid))
+  });
+  let report = finished(pending, Context::Install).await?;
+  storage::check_quota(&checked, &events);
+  Ok(report)
+}
+
+//The app's own log in the logs dir, the CLI and Steam's launches write theirs next to it
+pub const LOG_NAME: &str = "app";
+
+#[tauri::command]
+pub fn get_log() -> Result<String, Error> {
+  logging::read(LOG_NAME).map_err(|e| Error::io(Context::Logs, e))
+}
+
+//What got logged since offset, for following along by asking again with the offset and file_id it returned
+#[tauri::command]
+pub fn tail_log(offset: u64, file_id: Option<u64>) -> Result<logging::LogTail, Error> {
+  logging::tail(LOG_NAME, offset, file_id).map_err(|e| Error::io(Context::Logs, e))
+}
+
+//A zip of the logs, the system and the state of every game, to attach to bug reports
+#[tauri::command]
+pub async fn export_diagnostics(dest: String) -> Result<(), Error> {
+  let handle = Handle::current();
+  let result = handle.spawn_blocking(move || diagnostics::export(Path::new(&dest))).await;
+  match result {
+    Ok(result) => result.map_err(|e| Error::io(Context::Diagnostics, e)),
+    Err(e) => Err(Error::new(ErrorCode::TaskCrashed, e.to_string()).context(Context::Diagnostics)),
   }
-  return directories;
 }
 
 #[tauri::command]
-pub async fn uncompress(file_path: String, file_name: String, game: Game) {
-  let mut source_file = fs::File::open(file_path).unwrap();
-  let target = game.profile_path.join("mods/").join(file_name);
-  uncompress_archive(&mut source_file,&target, Ownership::Ignore).unwrap();
-}
\ No newline at end of file
+pub fn get_settings() -> Result<settings::Settings, Error> {
+  settings::load().map_err(|e| Error::io(Context::Settings, e))
+}
+
+#[tauri::command]
+pub fn update_settings(settings: settings::Settings, window: Window) -> Result<(), Error> {
+  settings::update(&settings, &window_events(window)).map_err(|e| Error::io(Context::Settings, e))
+}
+
+//Queued and running tasks and the last ones that finished, the 'task' events keep them up to date
+#[tauri::command]
+pub fn list_tasks(tasks: State<'_, TaskManager>) -> Vec<tasks::TaskInfo> {
+  tasks.list()
+}
+
+#[tauri::command]
+pub fn cancel_task(id: u64, tasks: State<'_, TaskManager>) -> Result<(), Error> {
+  tasks.cancel(id).map_err(|e| Error::io(Context::Tasks, e).param("task", id))
+}
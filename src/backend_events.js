@@ -0,0 +1,18 @@
+import { event } from '@tauri-apps/api'
+
+//Everything the backend sends comes on 'backend-event' as { type, payload },
+//this listens once and hands each one to whoever asked for its type
+const handlers = {}
+let listening = undefined
+
+export function onBackendEvent(type, handler) {
+  if (handlers[type] == undefined) {
+    handlers[type] = []
+  }
+  handlers[type].push(handler)
+  if (listening == undefined) {
+    listening = event.listen("backend-event", event => {
+      (handlers[event.payload.type] || []).forEach(handler => handler(event.payload.payload))
+    })
+  }
+}
@@ -0,0 +1,284 @@
+//The mod manager without its window. Everything goes through tmm-core like the app's
+//commands do, so the app and the CLI can be used on the same games in turn
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::exit;
+
+use tmm_core::error::{Context, Error, ErrorCode};
+use tmm_core::mod_manager::game::{Game, Store};
+use tmm_core::mod_manager::{archive, dependencies, hooks, launch, profiles, steam_wrapper};
+use tmm_core::mod_manager::registry::Registry;
+use tmm_core::mod_manager::{deploy_for_launch, deploy_mods, deployable, install_archive, load_mods, switch_for_launch};
+use tmm_core::events::Events;
+use tmm_core::{logging, status};
+
+const USAGE: &str = "usage: tmm [--json] [--status <target>] <command>
+
+commands:
+  list-games                                  the games the manager knows
+  mods <appid> [--profile <name>]             the mods in load order, '*' for enabled ones
+  deploy <appid> [--profile <name>]           switches to the profile first if it's given
+  purge <appid>
+  install <appid> <archive> [--name <name>] [--replace]
+  launch <appid> [--profile <name>]           deploys, starts the game and waits for it
+  launch <command>...                         for Steam's launch options, 'tmm launch %command%'";
+
+struct Options {
+  json: bool,
+  profile: Option<String>,
+  name: Option<String>,
+  replace: bool,
+  //what's left once the options are taken out
+  positional: Vec<String>,
+}
+
+fn parse(args: Vec<String>) -> Result<Options, String> {
+  let mut options = Options { json: false, profile: None, name: None, replace: false, positional: Vec::new() };
+  let mut args = args.into_iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--json" => options.json = true,
+      "--replace" => options.replace = true,
+      "--profile" => options.profile = Some(args.next().ok_or("--profile needs a name")?),
+      "--name" => options.name = Some(args.next().ok_or("--name needs a name")?),
+      //status::init reads it on its own
+      "--status" => { args.next(); },
+      arg if arg.starts_with("--status=") => {},
+      arg if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+      _ => options.positional.push(arg),
+    }
+  }
+  Ok(options)
+}
+
+fn appid(appid: Option<&String>) -> Result<u32, Error> {
+  appid.and_then(|appid| appid.parse().ok()).ok_or_else(|| Error::new(ErrorCode::GameNotManaged, "an appid is needed"))
+}
+
+fn game(appid: Option<&String>) -> Result<Game, Error> {
+  let appid = self::appid(appid)?;
+  Registry::new().game(appid).map_err(|e| Error::io(Context::ModList, e).param("appid", appid))
+}
+
+fn print<S: Serialize>(options: &Options, value: &S, text: String) {
+  if options.json {
+    //everything printed is made of plain structs, it serializes
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+  } else if !text.is_empty() {
+    println!("{}", text);
+  }
+}
+
+fn list_games(options: &Options) -> Result<(), Error> {
+  let games = Game::all().map_err(|e| Error::io(Context::ModList, e))?;
+  let text = games.iter()
+    .map(|game| format!("{}\t{}\t{}", game.appid, game.public_name, profiles::active_name(game)))
+    .collect::<Vec<String>>()
+    .join("\n");
+  print(options, &games, text);
+  Ok(())
+}
+
+//Listing another profile's mods doesn't switch to it
+fn mods(options: &Options) -> Result<(), Error> {
+  let game = match &options.profile {
+    Some(profile) => {
+      let appid = appid(options.positional.get(1))?;
+      Registry::new().profile(appid, profile).map_err(|e| Error::io(Context::Profiles, e).param("appid", appid).param("profile", profile))?
+    },
+    None => game(options.positional.get(1))?,
+  };
+  let mods = load_mods(&game).map_err(|e| Error::io(Context::ModList, e).param("appid", game.appid))?;
+  let text = mods.iter()
+    .map(|elem| format!("{} {}", if elem.enabled { "*" } else { " " }, elem.name))
+    .collect::<Vec<String>>()
+    .join("\n");
+  print(options, &mods, text);
+  Ok(())
+}
+
+fn deploy(options: &Options) -> Result<(), Error> {
+  let game = switch_for_launch(game(options.positional.get(1))?, options.profile.clone(), &Events::none())?;
+  let result = load_mods(&game).and_then(|mods| deployable(&game, &mods)).and_then(|mods| deploy_mods(&game, &mods, &Events::none()));
+  let mut report = result.map_err(|e| Error::io(Context::Deploy, e).param("appid", game.appid))?;
+  report.missing_dependencies = dependencies::check(&load_mods(&game).map_err(|e| Error::io(Context::Deploy, e).param("appid", game.appid))?);
+  let text = format!(
+    "Deployed '{}' of {}, {} missing dependencies, {} case conflicts",
+    profiles::active_name(&game), game.public_name, report.missing_dependencies.len(), report.case_conflicts.len()
+  );
+  print(options, &report, text);
+  Ok(())
+}
+
+//Checked against how the game directory was before deploying, like the app's purge
+fn purge(options: &Options) -> Result<(), Error> {
+  let game = game(options.positional.get(1))?;
+  let report = tmm_core::mod_manager::purge(&game, &Events::none()).map_err(|e| Error::io(Context::Purge, e).param("appid", game.appid))?;
+  let text = if report.restored() {
+    format!("Purged {}", game.public_name)
+  } else {
+    format!("Purged {}, some of its own files aren't as they were before deploying", game.public_name)
+  };
+  print(options, &report, text);
+  Ok(())
+}
+
+//The mod is named after the archive unless a name is given
+fn install(options: &Options) -> Result<(), Error> {
+  let game = game(options.positional.get(1))?;
+  let archive_path = options.positional.get(2).map(Path::new)
+    .ok_or_else(|| Error::new(ErrorCode::InvalidArchivePath, "an archive to install is needed"))?;
+  let name = match &options.name {
+    Some(name) => name.clone(),
+    None => archive_path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+  };
+  let install_options = archive::InstallOptions {
+    duplicate: if options.replace { Some(archive::Duplicate::Replace) } else { None },
+    ..archive::InstallOptions::default()
+  };
+  let report = install_archive(archive_path, &name, install_options, &game)
+    .map_err(|e| Error::io(Context::Install, e).param("appid", game.appid))?;
+  let text = report.mods.iter().map(|name| format!("Installed '{}'", name)).collect::<Vec<String>>().join("\n");
+  print(options, &report, text);
+  Ok(())
+}
+
+#[derive(Serialize)]
+struct LaunchReport {
+  appid: u32,
+  profile: String,
+  started: bool,
+  configs_synced: bool,
+  purged: bool,
+}
+
+//Like the app's launch_game, except that it stays until the game exited
+fn launch_game(options: &Options) -> Result<(), Error> {
+  let game = switch_for_launch(game(options.positional.get(1))?, options.profile.clone(), &Events::none())?;
+  let through_manager = game.store == Store::Steam && steam_wrapper::routed(&game);
+  if !through_manager {
+    hooks::run(&game, hooks::HookStage::PreLaunch, None).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))?;
+    deploy_for_launch(&game, &Events::none())?;
+  }
+  launch::launch(&game).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))?;
+  let started = launch::wait_for_exit(&game);
+  let (configs_synced, purged) = if through_manager { (false, false) } else { launch::after_exit(&game, None) };
+  let report = LaunchReport { appid: game.appid, profile: profiles::active_name(&game), started, configs_synced, purged };
+  let text = if started { format!("{} exited", game.public_name) } else { format!("{} didn't start", game.public_name) };
+  print(options, &report, text);
+  Ok(())
+}
+
+//Steam's launch options give the game's whole command line after 'launch', it isn't ours to parse.
+//An appid or an option there is the manager's own launch
+fn steam_command(args: &[OsString]) -> Option<&[OsString]> {
+  match args {
+    [launch, first, ..] if launch == "launch" => {
+      let ours = first.to_str().map_or(false, |first| first.starts_with("--") || first.parse::<u32>().is_ok());
+      if ours { None } else { Some(&args[1..]) }
+    },
+    _ => None,
+  }
+}
+
+fn main() {
+  let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+  if let Some(command) = steam_command(&args) {
+    if let Err(e) = logging::init("launch") {
+      eprintln!("Couldn't open the log file: {}", e);
+    }
+    exit(steam_wrapper::run(command.to_vec()));
+  }
+  let args: Vec<String> = match args.into_iter().map(|arg| arg.into_string()).collect() {
+    Ok(args) => args,
+    Err(arg) => {
+      eprintln!("{:?} isn't valid UTF-8", arg);
+      exit(2);
+    }
+  };
+  let options = match parse(args) {
+    Ok(options) => options,
+    Err(e) => {
+      eprintln!("{}\n\n{}", e, USAGE);
+      exit(2);
+    }
+  };
+  if let Err(e) = logging::init("cli") {
+    eprintln!("Couldn't open the log file: {}", e);
+  }
+  if let Err(e) = status::init() {
+    eprintln!("Couldn't open the status stream: {}", e);
+  }
+  let result = match options.positional.first().map(|command| command.as_str()) {
+    Some("list-games") => list_games(&options),
+    Some("mods") => mods(&options),
+    Some("deploy") => deploy(&options),
+    Some("purge") => purge(&options),
+    Some("install") => install(&options),
+    Some("launch") => launch_game(&options),
+    _ => {
+      eprintln!("{}", USAGE);
+      exit(2);
+    }
+  };
+  if let Err(e) = result {
+    if options.json {
+      eprintln!("{}", serde_json::to_string_pretty(&e).unwrap());
+    } else {
+      eprintln!("error: {}", e);
+    }
+    exit(1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+  }
+
+  fn os_args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+  }
+
+  #[test]
+  fn options_go_anywhere() {
+    let options = parse(args(&["launch", "--profile", "modded", "489830", "--json"])).unwrap();
+    assert!(options.json);
+    assert_eq!(options.profile.as_deref(), Some("modded"));
+    assert_eq!(options.positional, args(&["launch", "489830"]));
+  }
+
+  #[test]
+  fn install_options() {
+    let options = parse(args(&["install", "489830", "mod.zip", "--name", "Mod", "--replace"])).unwrap();
+    assert!(options.replace);
+    assert_eq!(options.name.as_deref(), Some("Mod"));
+    assert_eq!(options.positional, args(&["install", "489830", "mod.zip"]));
+  }
+
+  #[test]
+  fn status_is_skipped() {
+    let options = parse(args(&["--status", "fd:3", "--status=fd:4", "list-games"])).unwrap();
+    assert_eq!(options.positional, args(&["list-games"]));
+  }
+
+  #[test]
+  fn bad_options() {
+    assert!(parse(args(&["deploy", "489830", "--profile"])).is_err());
+    assert!(parse(args(&["deploy", "--force"])).is_err());
+  }
+
+  #[test]
+  fn steam_commands() {
+    let command = os_args(&["launch", "/steam/ubuntu12_32/reaper", "SteamLaunch", "AppId=489830"]);
+    assert_eq!(steam_command(&command), Some(&command[1..]));
+    assert_eq!(steam_command(&os_args(&["launch", "489830"])), None);
+    assert_eq!(steam_command(&os_args(&["launch", "--profile", "modded", "489830"])), None);
+    assert_eq!(steam_command(&os_args(&["launch"])), None);
+    assert_eq!(steam_command(&os_args(&["deploy", "/steam/game"])), None);
+  }
+}
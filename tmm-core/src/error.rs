@@ -0,0 +1,220 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{fmt, io};
+
+//Errors sent to the frontend. The code says what went wrong and the context what
+//was being done, both are stable so the frontend can localize them and scripts
+//can branch on them, the params fill in the details (paths, mod names, ...)
+#[derive(Debug, Clone, Serialize)]
+pub struct Error {
+  code: ErrorCode,
+  context: Option<Context>,
+  params: Map<String, Value>,
+  //english fallback, mostly meant for logs
+  message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+  //a filesystem operation failed, the params hold its 'kind' and 'os_code'
+  Io,
+  MountFailed,
+  ConfigParseFailed,
+  NothingToBisect,
+  BisectionFinished,
+  NoBisection,
+  InvalidOverridePath,
+  TaskCrashed,
+  UnknownExecutable,
+  NoExperiment,
+  ModExists,
+  //a download source failed, the message is all there is to it
+  SourceFailed,
+  //the params hold 'available' and 'needed' in bytes
+  NotEnoughSpace,
+  InvalidScratchPath,
+  InvalidStagingName,
+  InvalidPattern,
+  InvalidGamePath,
+  GameExists,
+  //no store found anything and there's no Steam installation, most likely what the user expected to be found
+  SteamNotFound,
+  InvalidGameDefinition,
+  //the game's library is on an SD card or drive that isn't plugged in
+  MediaMissing,
+  GameNotManaged,
+  ModNotFound,
+  //a subdirectory picked out of an archive that leaves it or isn't in it
+  InvalidArchivePath,
+  InvalidModName,
+  //the archive needs a password, or the one given was wrong
+  ArchiveEncrypted,
+  //a program needed for the job isn't installed, the params hold its name as 'extractor'
+  ExtractorMissing,
+  //an archive with the same hash is installed, the params hold the mod as 'mod_name'
+  AlreadyInstalled,
+  NotInTrash,
+  UnsupportedArchiveFormat,
+  InvalidModFile,
+  VersionNotFound,
+  //the overlay is mounted with the overwrite as its upper layer
+  OverwriteInUse,
+  InvalidProfileName,
+  ProfileExists,
+  ProfileNotFound,
+  //a game always keeps one profile
+  LastProfile,
+  ProfileDeployed,
+  //the profile has its own saves or INIs, deleting it takes discard_saves
+  ProfileHasSaves,
+  //the game's definition doesn't say where its saves are
+  NoSavePath,
+  NoSyncPath,
+  //git failed pulling or pushing the sync repository, the message is its output
+  SyncFailed,
+  ToolExists,
+  ToolNotFound,
+  InvalidTool,
+  //the params hold the hook's 'command' and 'exit_code', its output is in the session log
+  HookFailed,
+  //Steam would write its config back over the change when it exits
+  SteamRunning,
+  //no Steam account logged in on this machine, or it can't be told which one did last
+  NoSteamAccount,
+  //deploying, purging and removing mods wait until the game exited
+  GameRunning,
+  //the game has to be started once for Proton or Wine to make the prefix
+  NoPrefix,
+  //protontricks or winetricks failed, the params hold its 'exit_code'
+  PrefixFixFailed,
+  //a profile's pinned Proton or Wine was removed since
+  RunnerNotFound,
+  //Proton can't run in a plain Wine prefix
+  InvalidRunner,
+  //mount options and such are text, the params hold the 'path' that isn't valid UTF-8
+  NonUtf8Path,
+  //a path of the game's config is somewhere the manager never puts it, the params hold
+  //the config's 'field' and the 'path'
+  UnsafePath,
+  //a value in settings.toml is out of range, the params hold which 'setting'
+  InvalidSettings,
+  //a config was written by a newer version of the manager, the params hold its 'path'
+  //and 'schema_version'
+  ConfigTooNew,
+  //the task was cancelled before it was done, the params hold its id as 'task'
+  Cancelled,
+  TaskNotFound,
+  //neither XDG_CONFIG_HOME nor HOME is set, there's nowhere to keep anything
+  NoConfigDir,
+  //where something should be written can't take it, e.g. an export that isn't a .zip
+  InvalidDestination,
+  //an exported profile is of another game, the params hold the 'appid' it's of and the
+  //'game_appid' it was imported into
+  WrongGame,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Context {
+  Deploy,
+  Purge,
+  RepairDeployment,
+  DeployStatus,
+  DeployPreview,
+  SaveConfig,
+  StorageReport,
+  TestSession,
+  Bisection,
+  OverrideRules,
+  Experiment,
+  Download,
+  StaleMounts,
+  Install,
+  VerifyDeployment,
+  ScanGames,
+  Launch,
+  Staging,
+  Pool,
+  Authenticate,
+  AddGame,
+  GameDefinitions,
+  SearchGames,
+  RemoveGame,
+  ModList,
+  CheckUpdates,
+  PeekArchive,
+  RenameMod,
+  ModMeta,
+  QueryMods,
+  Categories,
+  ModFiles,
+  Trash,
+  Export,
+  ModVersions,
+  Overwrite,
+  Profiles,
+  Plugins,
+  Sync,
+  Tools,
+  Prefix,
+  Logs,
+  Diagnostics,
+  Settings,
+  Tasks,
+}
+
+impl Error {
+  pub fn new(code: ErrorCode, message: impl Into<String>) -> Error {
+    Error { code, context: None, params: Map::new(), message: message.into() }
+  }
+
+  pub fn param<V: Serialize>(mut self, key: &str, value: V) -> Error {
+    self.params.insert(key.to_string(), serde_json::to_value(value).unwrap_or(Value::Null));
+    self
+  }
+
+  pub fn context(mut self, context: Context) -> Error {
+    self.context = Some(context);
+    self
+  }
+
+  //Errors created with into_io deeper down keep their own code,
+  //anything else is reported as a plain io error
+  pub fn io(context: Context, error: io::Error) -> Error {
+    if let Some(inner) = error.get_ref().and_then(|inner| inner.downcast_ref::<Error>()) {
+      return inner.clone().context(context);
+    }
+    let mut converted = Error::new(ErrorCode::Io, error.to_string())
+      .param("kind", snake_case(&format!("{:?}", error.kind())))
+      .context(context);
+    if let Some(os_code) = error.raw_os_error() {
+      converted = converted.param("os_code", os_code);
+    }
+    converted
+  }
+
+  //For code that works with io::Result but knows exactly what went wrong
+  pub fn into_io(self, kind: io::ErrorKind) -> io::Error {
+    io::Error::new(kind, self)
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.message)
+  }
+}
+
+impl std::error::Error for Error {}
+
+fn snake_case(name: &str) -> String {
+  let mut output = String::new();
+  for (index, character) in name.chars().enumerate() {
+    if character.is_uppercase() && index > 0 {
+      output.push('_');
+    }
+    output.extend(character.to_lowercase());
+  }
+  output
+}
@@ -0,0 +1,159 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::mod_downloader::download::Progress;
+use crate::mod_manager::game::{DeployMode, VersionChange};
+use crate::mod_manager::{conflicts, hooks, launch, mod_updates, protontricks, session, storage, updates, watcher, BatchReport};
+use crate::settings::Settings;
+use crate::tasks::TaskInfo;
+
+//The name everything goes out under, the frontend listens to just this one
+pub const CHANNEL: &str = "backend-event";
+
+//Whatever shows the events, the app forwards them to its window
+pub trait Emit: Send + Sync {
+  fn emit(&self, name: &str, payload: Value) -> Result<(), String>;
+}
+
+struct Nowhere;
+
+impl Emit for Nowhere {
+  fn emit(&self, _name: &str, _payload: Value) -> Result<(), String> {
+    Ok(())
+  }
+}
+
+//Cheap to clone, threads that report progress get their own
+#[derive(Clone)]
+pub struct Events(Arc<dyn Emit>);
+
+impl Events {
+  pub fn new<E: Emit + 'static>(emit: E) -> Events {
+    Events(Arc::new(emit))
+  }
+
+  //For running without a frontend
+  pub fn none() -> Events {
+    Events::new(Nowhere)
+  }
+
+  //To the frontend as a 'backend-event', and to the status stream with its type as the name.
+  //Nothing that happens in core stops because the frontend didn't get told
+  pub fn send(&self, event: BackendEvent) {
+    let name = event.name();
+    let value = match serde_json::to_value(&event) {
+      Ok(value) => value,
+      Err(e) => {
+        tracing::warn!("Couldn't serialize '{}': {}", name, e);
+        return;
+      }
+    };
+    crate::status::publish(name, &value["payload"]);
+    if let Err(e) = self.0.emit(CHANNEL, value) {
+      tracing::warn!("Something went wrong while trying to emit '{}' to frontend: {}", name, e);
+    }
+  }
+}
+
+//Everything the backend tells the frontend. Serialized as '{"type": "download-progress",
+//"payload": {...}}', the type is also what the status stream calls it
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "kebab-case")]
+pub enum BackendEvent {
+  DownloadStarted(Progress),
+  DownloadProgress(Progress),
+  DownloadFinished(Progress),
+  //the file's name in the downloads
+  AlreadyDownloaded(String),
+  DeployStarted(DeployEvent),
+  DeployFinished(DeployEvent),
+  DeployFailed(DeployFailed),
+  //files of the enabled mods that come from another mod now, after enabling, removing or moving mods
+  ConflictsChanged(ConflictsChanged),
+  //what one of the batch commands did
+  ModsChanged(BatchReport),
+  ModFolderChanged(watcher::FolderChanged),
+  DownloadFolderChanged(watcher::FolderChanged),
+  ModUpdatesAvailable(mod_updates::UpdateReport),
+  StorageQuotaWarning(storage::StorageReport),
+  GameUpdateStarted(updates::UpdateEvent),
+  GameVersionChanged(VersionChange),
+  GameStarting(launch::LaunchEvent),
+  GameRunning(launch::LaunchEvent),
+  GameExited(launch::ExitEvent),
+  HookFinished(hooks::HookEvent),
+  TestSessionStarted(session::SessionEvent),
+  TestSessionFinished(session::SessionEvent),
+  //the mods being tested
+  BisectTestStarted(Vec<String>),
+  BisectTestFinished(Vec<String>),
+  PrefixFixOutput(protontricks::OutputEvent),
+  PrefixFixFinished(protontricks::FinishedEvent),
+  SettingsChanged(Settings),
+  //queued, started or got further
+  TaskProgress(TaskInfo),
+  TaskFinished(TaskInfo),
+  TaskFailed(TaskInfo),
+  TaskCancelled(TaskInfo),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeployEvent {
+  pub appid: u32,
+  pub backend: DeployMode,
+  //the enabled mods going in, none when it's a purge
+  pub mods: Vec<String>,
+  pub purge: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeployFailed {
+  #[serde(flatten)]
+  pub deploy: DeployEvent,
+  pub error: Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictsChanged {
+  pub appid: u32,
+  pub changes: Vec<conflicts::OwnerChange>,
+}
+
+impl BackendEvent {
+  pub fn name(&self) -> &'static str {
+    match self {
+      BackendEvent::DownloadStarted(_) => "download-started",
+      BackendEvent::DownloadProgress(_) => "download-progress",
+      BackendEvent::DownloadFinished(_) => "download-finished",
+      BackendEvent::AlreadyDownloaded(_) => "already-downloaded",
+      BackendEvent::DeployStarted(_) => "deploy-started",
+      BackendEvent::DeployFinished(_) => "deploy-finished",
+      BackendEvent::DeployFailed(_) => "deploy-failed",
+      BackendEvent::ConflictsChanged(_) => "conflicts-changed",
+      BackendEvent::ModsChanged(_) => "mods-changed",
+      BackendEvent::ModFolderChanged(_) => "mod-folder-changed",
+      BackendEvent::DownloadFolderChanged(_) => "download-folder-changed",
+      BackendEvent::ModUpdatesAvailable(_) => "mod-updates-available",
+      BackendEvent::StorageQuotaWarning(_) => "storage-quota-warning",
+      BackendEvent::GameUpdateStarted(_) => "game-update-started",
+      BackendEvent::GameVersionChanged(_) => "game-version-changed",
+      BackendEvent::GameStarting(_) => "game-starting",
+      BackendEvent::GameRunning(_) => "game-running",
+      BackendEvent::GameExited(_) => "game-exited",
+      BackendEvent::HookFinished(_) => "hook-finished",
+      BackendEvent::TestSessionStarted(_) => "test-session-started",
+      BackendEvent::TestSessionFinished(_) => "test-session-finished",
+      BackendEvent::BisectTestStarted(_) => "bisect-test-started",
+      BackendEvent::BisectTestFinished(_) => "bisect-test-finished",
+      BackendEvent::PrefixFixOutput(_) => "prefix-fix-output",
+      BackendEvent::PrefixFixFinished(_) => "prefix-fix-finished",
+      BackendEvent::SettingsChanged(_) => "settings-changed",
+      BackendEvent::TaskProgress(_) => "task-progress",
+      BackendEvent::TaskFinished(_) => "task-finished",
+      BackendEvent::TaskFailed(_) => "task-failed",
+      BackendEvent::TaskCancelled(_) => "task-cancelled",
+    }
+  }
+}
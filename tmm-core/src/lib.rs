@@ -0,0 +1,22 @@
+//Everything the mod manager does, for the app, the CLI and anything else that wants to
+//manage mods the same way. Progress and state changes go out through events::Events
+pub mod error;
+pub mod events;
+pub mod status;
+pub mod logging;
+pub mod settings;
+pub mod schema;
+pub mod tasks;
+pub mod mod_manager;
+pub mod mod_downloader;
+
+use std::{io, path::PathBuf};
+
+//Where the manager keeps its configs, profiles and everything else, '~/.config/tmm'
+pub fn config_root() -> io::Result<PathBuf> {
+  match dirs::config_dir() {
+    Some(config) => Ok(config.join("tmm")),
+    None => Err(error::Error::new(error::ErrorCode::NoConfigDir, "there's no config directory, neither XDG_CONFIG_HOME nor HOME is set")
+      .into_io(io::ErrorKind::NotFound)),
+  }
+}
@@ -0,0 +1,152 @@
+use serde::Serialize;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+//What used to only go to stderr also goes to '<program>.log' in the logs dir, so it's there for
+//bug reports when nothing was started from a terminal. Every start begins a new file, the ones of
+//the last runs are kept as '<program>.1.log' and on, and a run that logs a lot rotates once its
+//file passes MAX_SIZE. The app, the CLI and Steam's launches each have their own, they run at
+//the same time. TMM_LOG takes the usual filter directives, like 'tmm_core=debug'
+const KEPT: usize = 5;
+const MAX_SIZE: u64 = 10_485_760;
+const DEFAULT_FILTER: &str = "info";
+
+static RUNS: AtomicU64 = AtomicU64::new(1);
+
+pub fn logs_dir() -> io::Result<PathBuf> {
+  Ok(crate::config_root()?.join("logs"))
+}
+
+pub fn log_path(program: &str) -> io::Result<PathBuf> {
+  Ok(logs_dir()?.join(format!("{}.log", program)))
+}
+
+fn rotated_path(program: &str, index: usize) -> io::Result<PathBuf> {
+  Ok(logs_dir()?.join(format!("{}.{}.log", program, index)))
+}
+
+//'<program>.log' becomes '<program>.1.log' and so on, the oldest one goes
+fn rotate(program: &str) -> io::Result<()> {
+  let oldest = rotated_path(program, KEPT)?;
+  if oldest.exists() {
+    fs::remove_file(&oldest)?;
+  }
+  for index in (1..KEPT).rev() {
+    let path = rotated_path(program, index)?;
+    if path.exists() {
+      fs::rename(&path, rotated_path(program, index + 1)?)?;
+    }
+  }
+  let current = log_path(program)?;
+  if current.exists() {
+    fs::rename(&current, rotated_path(program, 1)?)?;
+  }
+  Ok(())
+}
+
+struct OpenLog {
+  file: fs::File,
+  size: u64,
+}
+
+fn open(program: &str) -> io::Result<OpenLog> {
+  fs::create_dir_all(logs_dir()?)?;
+  let file = fs::OpenOptions::new().create(true).append(true).open(log_path(program)?)?;
+  let size = file.metadata()?.len();
+  Ok(OpenLog { file, size })
+}
+
+struct LogFile {
+  program: String,
+  open: Mutex<OpenLog>,
+}
+
+impl io::Write for &LogFile {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let mut open = self.open.lock().unwrap();
+    if open.size > 0 && open.size + buf.len() as u64 > MAX_SIZE {
+      rotate(&self.program)?;
+      *open = self::open(&self.program)?;
+    }
+    let written = open.file.write(buf)?;
+    open.size += written as u64;
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.open.lock().unwrap().file.flush()
+  }
+}
+
+impl<'a> MakeWriter<'a> for LogFile {
+  type Writer = &'a LogFile;
+
+  fn make_writer(&'a self) -> Self::Writer {
+    self
+  }
+}
+
+//Once per process, before anything gets logged. Without the file it still logs to stderr, the
+//error is for the caller to show
+pub fn init(program: &str) -> io::Result<()> {
+  let filter = EnvFilter::try_from_env("TMM_LOG").unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+  let (file, opened) = match rotate(program).and_then(|()| open(program)) {
+    Ok(open) => (Some(LogFile { program: program.to_string(), open: Mutex::new(open) }), Ok(())),
+    Err(e) => (None, Err(e)),
+  };
+  tracing_subscriber::registry()
+    .with(filter)
+    .with(file.map(|file| tracing_subscriber::fmt::layer().with_ansi(false).with_writer(file)))
+    .with(tracing_subscriber::fmt::layer().with_writer(io::stderr))
+    .try_init()
+    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+  opened
+}
+
+//Tells apart the spans of operations that can overlap, like two downloads, in the log
+pub fn run_id() -> u64 {
+  RUNS.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogTail {
+  //what to ask for next time, only what got logged since comes back then
+  offset: u64,
+  //to ask with as well, the file rotated once it's another one
+  file_id: u64,
+  text: String,
+  //the file was rotated since the offset, text is from the start of the new one
+  rotated: bool,
+}
+
+//Everything from offset on in the file of file_id. Another file, the new one having grown past the
+//offset or not, or an offset past the end is from before it rotated
+pub fn tail(program: &str, offset: u64, file_id: Option<u64>) -> io::Result<LogTail> {
+  let mut file = match fs::File::open(log_path(program)?) {
+    Ok(file) => file,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(LogTail { offset: 0, file_id: 0, text: String::new(), rotated: false }),
+    Err(e) => return Err(e),
+  };
+  let metadata = file.metadata()?;
+  let rotated = offset > metadata.len() || file_id.map_or(false, |file_id| file_id != metadata.ino());
+  let start = if rotated { 0 } else { offset };
+  file.seek(SeekFrom::Start(start))?;
+  let mut bytes: Vec<u8> = Vec::new();
+  file.read_to_end(&mut bytes)?;
+  //a line that's still being written waits for the next call
+  let complete = bytes.iter().rposition(|byte| *byte == b'\n').map_or(0, |index| index + 1);
+  bytes.truncate(complete);
+  Ok(LogTail { offset: start + complete as u64, file_id: metadata.ino(), text: String::from_utf8_lossy(&bytes).to_string(), rotated })
+}
+
+pub fn read(program: &str) -> io::Result<String> {
+  Ok(tail(program, 0, None)?.text)
+}
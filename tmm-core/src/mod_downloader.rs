@@ -0,0 +1,39 @@
+pub mod utils;
+pub mod download;
+pub mod core;
+pub mod source;
+pub mod sources;
+
+use failure::{format_err, Fallible};
+
+use crate::events::Events;
+use crate::logging;
+use crate::mod_manager::game::Game;
+use crate::mod_manager::meta;
+use crate::settings;
+
+//Downloads into the profile's downloads and returns the file's name there
+pub fn fetch(url: &str, game: &Game, events: &Events) -> Fallible<String> {
+    let span = tracing::info_span!("download", id = logging::run_id(), appid = game.appid, url);
+    let _entered = span.enter();
+    let save_path = game.profile_path.join("downloads");
+    let parsed_url = utils::parse_url(url)
+        .map_err(|e| format_err!("the url '{}' can't be parsed: {}", url, e))?;
+    let source = source::for_url(&parsed_url);
+    //kept for the install, that's when it ends up in the mod's meta. With the
+    //page it came from as the url, download links don't always stay valid
+    let metadata = source::ModMetadata {
+        url: url.to_string(),
+        ..source.resolve(&parsed_url).unwrap_or_else(|_| source::ModMetadata { source: source.id().to_string(), ..source::ModMetadata::default() })
+    };
+    let parsed_url = source.download_url(&parsed_url)
+        .map_err(|e| format_err!("'{}' didn't give the download of '{}': {}", source.name(), url, e))?;
+    let headers = source.download_headers()
+        .map_err(|e| format_err!("'{}' didn't give the download of '{}': {}", source.name(), url, e))?;
+    let file_name = download::http_download(parsed_url, headers, save_path, events.clone(), false, settings::current().downloads.concurrent, "0.1.0")?;
+    if let Err(e) = meta::record_download(game, &file_name, &metadata) {
+        tracing::warn!("Couldn't remember where '{}' came from: {}", file_name, e);
+    }
+    tracing::info!(file_name = file_name.as_str(), "downloaded");
+    Ok(file_name)
+}
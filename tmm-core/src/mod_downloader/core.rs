@@ -1,8 +1,8 @@
 use std::cell::RefCell;
 use std::fmt;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::io::Read;
-use std::sync::mpsc;
+use std::sync::{mpsc, Mutex};
 use std::path::PathBuf;
 
 use reqwest::header::{self, HeaderMap, HeaderValue};
@@ -12,6 +12,13 @@ use url::Url;
 use failure::{Fallible};
 
 use threadpool::ThreadPool;
+use crate::mod_manager::watcher;
+use crate::tasks;
+use once_cell::sync::Lazy;
+
+//When the next read of any download may start, so all of them together stay under the
+//bandwidth limit of the settings
+static NEXT_READ: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -28,6 +35,8 @@ pub struct Config {
     pub content_len: Option<u64>,
     pub chunk_offsets: Option<Vec<(u64, u64)>>,
     pub chunk_size: u64,
+    //bytes per second, shared with every other download
+    pub bandwidth_limit: Option<u64>,
 }
 
 #[allow(unused_variables)]
@@ -83,6 +92,8 @@ impl HttpDownload {
     }
 
     pub fn download(&mut self) -> Fallible<()> {
+        //the app knows about its own downloads from their events
+        let _writing = watcher::writing(&self.conf.save_path.join(&self.conf.file));
         let resp = self
             .client
             .get(self.url.as_ref())
@@ -149,7 +160,10 @@ impl HttpDownload {
         loop {
             let mut buffer = vec![0; self.conf.chunk_size as usize];
             let bcount = resp.read(&mut buffer)?;
+            throttle(bcount as u64, self.conf.bandwidth_limit);
             cnt += bcount;
+            tasks::checkpoint()?;
+            tasks::progress(cnt as u64, ct_len.map(|len| len as u64));
             buffer.truncate(bcount);
             if !buffer.is_empty() {
                 self.send_content(buffer.as_slice())?;
@@ -186,7 +200,8 @@ impl HttpDownload {
             let data_tx = data_tx.clone();
             let errors_tx = errors_tx.clone();
             let req = req.try_clone().unwrap();
-            worker_pool.execute(move || download_chunk(req, offsets, data_tx.clone(), errors_tx))
+            let limit = self.conf.bandwidth_limit;
+            worker_pool.execute(move || download_chunk(req, offsets, data_tx.clone(), errors_tx, limit))
         }
 
         let mut count = self.conf.bytes_on_disk.unwrap_or(0);
@@ -196,6 +211,9 @@ impl HttpDownload {
             }
             let (byte_count, offset, buf) = data_rx.recv()?;
             count += byte_count;
+            //the workers stop once there's nobody left to send their chunks to
+            tasks::checkpoint()?;
+            tasks::progress(count, Some(content_len));
             for hook in &self.hooks {
                 hook.borrow_mut()
                     .on_concurrent_content((byte_count, offset, &buf))?;
@@ -212,7 +230,8 @@ impl HttpDownload {
                     let data_tx = data_tx.clone();
                     let errors_tx = errors_tx.clone();
                     let req = req.try_clone().unwrap();
-                    worker_pool.execute(move || download_chunk(req, offsets, data_tx, errors_tx));
+                    let limit = self.conf.bandwidth_limit;
+                    worker_pool.execute(move || download_chunk(req, offsets, data_tx, errors_tx, limit));
                 }
             }
         }
@@ -244,13 +263,15 @@ fn download_chunk(
     req: Request,
     offsets: (u64, u64),
     sender: mpsc::Sender<(u64, u64, Vec<u8>)>,
-    errors: mpsc::Sender<(u64, u64)>
+    errors: mpsc::Sender<(u64, u64)>,
+    limit: Option<u64>
 ) {
     fn inner(
         mut req: Request,
         offsets: (u64, u64),
         sender: mpsc::Sender<(u64, u64, Vec<u8>)>,
-        start_offset: &mut u64
+        start_offset: &mut u64,
+        limit: Option<u64>
     ) -> Fallible<()> {
         let byte_range = format!("bytes={}-{}", offsets.0, offsets.1);
         let headers = req.headers_mut();
@@ -263,6 +284,7 @@ fn download_chunk(
         loop {
             let mut buf = vec![0; chunk_sz as usize];
             let byte_count = resp.read(&mut buf[..])?;
+            throttle(byte_count as u64, limit);
             cnt += byte_count as u64;
             buf.truncate(byte_count);
             if !buf.is_empty() {
@@ -280,10 +302,28 @@ fn download_chunk(
     }
     let mut start_offset = offsets.0;
     let end_offset = offsets.1;
-    match inner(req, offsets, sender, &mut start_offset) {
-        Ok(_) => {}
-        Err(_) => match errors.send((start_offset, end_offset)) {
-            _ => {}
-        }
+    if inner(req, offsets, sender, &mut start_offset, limit).is_err() {
+        let _ = errors.send((start_offset, end_offset));
+    }
+}
+
+//Pushes the next read back by how long these bytes take at the limit and waits for
+//its own turn, reads that come in between queue up behind it
+fn throttle(bytes: u64, limit: Option<u64>) {
+    let limit = match limit {
+        Some(limit) if bytes > 0 => limit,
+        _ => return,
+    };
+    let cost = Duration::from_secs_f64(bytes as f64 / limit as f64);
+    let wait_until = {
+        let mut next_read = NEXT_READ.lock().unwrap();
+        let now = Instant::now();
+        let start = next_read.filter(|next| *next > now).unwrap_or(now);
+        *next_read = Some(start + cost);
+        start
+    };
+    let now = Instant::now();
+    if wait_until > now {
+        std::thread::sleep(wait_until - now);
     }
-}
\ No newline at end of file
+}
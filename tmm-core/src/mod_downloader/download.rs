@@ -11,20 +11,23 @@ use failure::{format_err, Fallible};
 
 use crate::mod_downloader::utils::{decode_percent_coded_string, get_file_handle};
 use crate::mod_downloader::core::{Config, EventsHandler, HttpDownload};
+use crate::events::{BackendEvent, Events};
+use crate::settings;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Progress {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Progress {
     filename: String,
     filesize: Option<u64>,
     current: Option<u64>,
     finished: bool
 }
 
-pub fn http_download(url: Url, save_path: PathBuf, window: tauri::Window, resume_download: bool, concurrent_download: bool, version: &str) -> Fallible<()> {
+pub fn http_download(url: Url, extra_headers: HeaderMap, save_path: PathBuf, events: Events, resume_download: bool, concurrent_download: bool, version: &str) -> Fallible<String> {
     let user_agent = format!("TMM/{}", &version);
-    let timeout = 30u64;
-    let num_workers = 8usize;
-    let headers = request_headers(&url, timeout, "TMM/0.1.0")?;
+    let settings = settings::current().downloads;
+    let timeout = settings.timeout_seconds;
+    let num_workers = settings.workers;
+    let headers = request_headers(&url, &extra_headers, timeout, "TMM/0.1.0")?;
     let filename = gen_filename(&url, Some(&headers));
 
     let content_len = match headers.get("Content-Length") {
@@ -36,20 +39,17 @@ pub fn http_download(url: Url, save_path: PathBuf, window: tauri::Window, resume
         }
     };
 
-    let headers = prep_headers(&filename, resume_download, &user_agent)?;
+    let mut headers = prep_headers(&filename, resume_download, &user_agent)?;
+    headers.extend(extra_headers);
 
     let state_file_exists = Path::new(&format!("{}.st", filename)).exists();
-    let chunk_size = 512_000u64;
+    let chunk_size = settings.chunk_size;
 
     let chunk_offsets = match content_len {
-        Some(val) => {
-            if state_file_exists && resume_download && concurrent_download && val != 0 {
-                Some(get_resume_chunk_offsets(&filename, val, chunk_size)?)
-            } else {
-                None
-            }
+        Some(val) if state_file_exists && resume_download && concurrent_download && val != 0 => {
+            Some(get_resume_chunk_offsets(&filename, val, chunk_size)?)
         },
-        None => { None }
+        _ => { None }
     };
 
     let bytes_on_disk = if resume_download {
@@ -66,33 +66,30 @@ pub fn http_download(url: Url, save_path: PathBuf, window: tauri::Window, resume
         save_path: save_path.clone(),
         timeout,
         concurrent: concurrent_download,
-        max_retries: 100,
+        max_retries: settings.max_retries,
         num_workers,
         bytes_on_disk,
         content_len,
         chunk_offsets,
         chunk_size,
+        bandwidth_limit: settings.bandwidth_limit,
     };
 
     let file_handle = &save_path.join(&filename);
     let exists = file_handle.exists();
     if exists {
-        match window.emit("already-downloaded", &filename) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Something went wrong while trying to emit 'already-downloaded' to frontend: {}", e);
-            }
-        }
-        return Ok(());
+        events.send(BackendEvent::AlreadyDownloaded(filename.clone()));
+        return Ok(filename);
     }
 
     let mut client = HttpDownload::new(url.clone(), conf.clone());
-    let events_handler = DefaultEventsHandler::new(&filename, &save_path.to_str().unwrap(), window, content_len, resume_download, concurrent_download)?;
+    let save_path = save_path.to_str().ok_or_else(|| format_err!("{:?} isn't valid UTF-8", save_path))?;
+    let events_handler = DefaultEventsHandler::new(&filename, save_path, events, content_len, resume_download, concurrent_download)?;
     client.events_hook(events_handler).download()?;
-    Ok(())
+    Ok(filename)
 }
 
-fn request_headers(url: &Url, timeout: u64, ua: &str) -> Fallible<HeaderMap> {
+fn request_headers(url: &Url, extra_headers: &HeaderMap, timeout: u64, ua: &str) -> Fallible<HeaderMap> {
     // let mut url_string = "".to_string();
     // String::clone_from(&mut url_string, &url.as_ref().to_string());
     // let copy: Url = Url::parse(url_string.as_str()).unwrap();
@@ -104,6 +101,7 @@ fn request_headers(url: &Url, timeout: u64, ua: &str) -> Fallible<HeaderMap> {
         .timeout(Duration::from_secs(timeout))
         .header(header::USER_AGENT, HeaderValue::from_str(ua)?)
         .header(header::ACCEPT, HeaderValue::from_str("*/*")?)
+        .headers(extra_headers.clone())
         .send()?;
     Ok(response.headers().clone())
 }
@@ -122,10 +120,10 @@ fn gen_filename(url: &Url, headers: Option<&HeaderMap>) -> String {
         .and_then(|val| {
             let x = val
                 .rsplit(';')
-                .nth(0)
+                .next()
                 .unwrap_or("")
                 .rsplit('=')
-                .nth(0)
+                .next()
                 .unwrap_or("")
                 .trim_start_matches('"')
                 .trim_end_matches('"');
@@ -138,7 +136,7 @@ fn gen_filename(url: &Url, headers: Option<&HeaderMap>) -> String {
     match content_disposition {
         Some(val) => val,
         None => {
-            let name = &url.path().split('/').last().unwrap_or("");
+            let name = &url.path().split('/').next_back().unwrap_or("");
             if !name.is_empty() {
                 match decode_percent_coded_string(name) {
                     Ok(val) => val,
@@ -176,7 +174,7 @@ fn calc_bytes_on_disk(fname: &str) -> Fallible<Option<u64>>{
         for line in buf.lines() {
             let num_of_bytes = line?
                 .split(':')
-                .nth(0)
+                .next()
                 .ok_or_else(|| format_err!("failed to split state file line"))?
                 .parse::<u64>()?;
             byte_count += num_of_bytes;
@@ -224,7 +222,7 @@ fn get_resume_chunk_offsets(filename: &str, content_len: u64, chunk_size: u64) -
 }
 
 pub struct DefaultEventsHandler {
-    window: tauri::Window,
+    events: Events,
     progress: Option<Progress>,
     bytes_on_disk: Option<u64>,
     content_len: Option<u64>,
@@ -239,7 +237,7 @@ impl DefaultEventsHandler {
     pub fn new(
         filename: &str,
         save_path: &str,
-        window: tauri::Window,
+        events: Events,
         content_len: Option<u64>,
         resume: bool,
         concurrent: bool
@@ -257,20 +255,15 @@ impl DefaultEventsHandler {
             current: None,
             finished: false,
         };
-        match window.emit("download-started", &progress) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Something went wrong while trying to emit 'download-started' to frontend: {}", e);
-            }
-        }
+        events.send(BackendEvent::DownloadStarted(progress.clone()));
         Ok(DefaultEventsHandler {
-            window,
+            events,
             progress: Some(progress),
             bytes_on_disk: calc_bytes_on_disk(filename)?,
             content_len,
             filename: filename.to_owned(),
             save_path: save_path.to_owned(),
-            file: BufWriter::new(get_file_handle(&filename, save_path, &resume, &!concurrent)?),
+            file: BufWriter::new(get_file_handle(filename, save_path, &resume, &!concurrent)?),
             st_file,
             server_supports_resume: false
         })
@@ -305,8 +298,7 @@ impl EventsHandler for DefaultEventsHandler {
         //     ""
         // };
 
-        // println!("Type: {}", content_type);
-        // println!("Saving to: {}", &self.filename);
+        tracing::debug!("Saving to '{}/{}'", self.save_path, self.filename);
     }
 
     fn on_server_supports_resume(&mut self) {
@@ -318,11 +310,8 @@ impl EventsHandler for DefaultEventsHandler {
         self.file.write_all(content)?;
 
         self.inc(byte_count);
-        match self.window.emit("download-progress", &self.progress) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Something went wrong while trying to emit 'download-progress' to frontend: {}", e);
-            }
+        if let Some(progress) = &self.progress {
+            self.events.send(BackendEvent::DownloadProgress(progress.clone()));
         }
 
         Ok(())
@@ -336,20 +325,14 @@ impl EventsHandler for DefaultEventsHandler {
         
         if let Some(ref mut file) = self.st_file {
             writeln!(file, "{}:{}", byte_count, offset)?;
-            match file.flush() {
-                Err(error) => {
-                    eprintln!("Failed to flush file (concurrent download); {}", error);
-                },
-                Ok(_) => {}
+            if let Err(error) = file.flush() {
+                tracing::warn!("Failed to flush file (concurrent download); {}", error);
             }
         }
         
         self.inc(byte_count);
-        match self.window.emit("download-progress", &self.progress) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Something went wrong while trying to emit 'download-progress' to frontend: {}", e);
-            }
+        if let Some(progress) = &self.progress {
+            self.events.send(BackendEvent::DownloadProgress(progress.clone()));
         }
 
         Ok(())
@@ -371,43 +354,31 @@ impl EventsHandler for DefaultEventsHandler {
                 self.progress = Some(Progress { filename: self.filename.as_str().to_owned(), filesize: self.content_len, current: self.content_len, finished: true });
             }
         }
-        match self.window.emit("download-finished", &self.progress) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Something went wrong while trying to emit 'download-finished' to frontend: {}", e);
-            }
+        if let Some(progress) = &self.progress {
+            self.events.send(BackendEvent::DownloadFinished(progress.clone()));
         }
 
         match fs::remove_file(&st_file) {
             Ok(()) => {},
             Err(e) => {
-                eprintln!("Failed to remove '{}': {}", &st_file, e);
+                tracing::warn!("Failed to remove '{}': {}", &st_file, e);
             }
         }
     }
 
     fn on_max_retries(&mut self) {
-        // println!("Max retries exceeded, quitting!");
-        match self.file.flush() {
-            _ => {}
-        }
+        tracing::error!("'{}' failed more often than retries are allowed, quitting", self.filename);
+        let _ = self.file.flush();
         if let Some(ref mut file) = self.st_file {
-            match file.flush() {
-                _ => {}
-            }
+            let _ = file.flush();
         }
         ::std::process::exit(0);
     }
 
     fn on_failure_status(&self, status_code: i32) {
         if status_code == 416 {
-            // println!("The file is already fully retrieved, nothing to do.");
-            match self.window.emit("already-downloaded", &self.filename) {
-                Ok(()) => {}
-                Err(e) => {
-                    eprintln!("Something went wrong while trying to emit 'already-downloaded' to frontend: {}", e);
-                }
-            }
+            tracing::info!("'{}' is already fully retrieved, nothing to do", self.filename);
+            self.events.send(BackendEvent::AlreadyDownloaded(self.filename.clone()));
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+use failure::{format_err, Fallible};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::mod_downloader::sources;
+
+//What a source knows about a downloadable mod file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModMetadata {
+    pub source: String,
+    //the source's own id for the mod, e.g. 'owner/repo' on GitHub
+    pub mod_id: Option<String>,
+    pub name: Option<String>,
+    //who published it, as far as the source tells
+    #[serde(default)]
+    pub author: Option<String>,
+    pub version: Option<String>,
+    pub file_name: Option<String>,
+    pub url: String,
+    //where the changes of this version are described
+    #[serde(default)]
+    pub changelog_url: Option<String>,
+    //the source's own category for the mod, if it sorts them
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceInfo {
+    id: &'static str,
+    name: &'static str,
+    authenticated: bool,
+}
+
+//A site mods can be downloaded from. New providers implement this in their own
+//module under sources/, behind a 'source-<id>' feature, and get added to `all()`
+pub trait DownloadSource: Send + Sync {
+    fn id(&self) -> &'static str;
+
+    fn name(&self) -> &'static str;
+
+    fn handles(&self, url: &Url) -> bool;
+
+    fn resolve(&self, url: &Url) -> Fallible<ModMetadata>;
+
+    //The url the file actually gets downloaded from, which for most
+    //sites isn't the page the user copied
+    fn download_url(&self, url: &Url) -> Fallible<Url>;
+
+    //Sent along with every request for the download, keeps keys out of the url and so out of logs
+    fn download_headers(&self) -> Fallible<HeaderMap> {
+        Ok(HeaderMap::new())
+    }
+
+    //Returns the newer version if there is one
+    fn check_update(&self, metadata: &ModMetadata) -> Fallible<Option<ModMetadata>>;
+
+    //Sources that don't need an account just accept anything
+    fn authenticate(&self, token: &str) -> Fallible<()> {
+        save_token(self.id(), token)
+    }
+}
+
+//The direct source has to come last, it takes any url
+#[allow(clippy::vec_init_then_push)]
+pub fn all() -> Vec<Box<dyn DownloadSource>> {
+    //which sources there are depends on the features
+    let mut all: Vec<Box<dyn DownloadSource>> = Vec::new();
+    #[cfg(feature = "source-github")]
+    all.push(Box::new(sources::github::GitHub));
+    #[cfg(feature = "source-itch")]
+    all.push(Box::new(sources::itch::Itch));
+    all.push(Box::new(sources::direct::Direct));
+    all
+}
+
+pub fn for_url(url: &Url) -> Box<dyn DownloadSource> {
+    all().into_iter().find(|source| source.handles(url)).unwrap()
+}
+
+pub fn by_id(id: &str) -> Fallible<Box<dyn DownloadSource>> {
+    all().into_iter()
+        .find(|source| source.id() == id)
+        .ok_or_else(|| format_err!("there is no download source '{}'", id))
+}
+
+pub fn info() -> Vec<SourceInfo> {
+    all().iter()
+        .map(|source| SourceInfo { id: source.id(), name: source.name(), authenticated: load_token(source.id()).is_some() })
+        .collect()
+}
+
+fn credentials_path() -> std::io::Result<PathBuf> {
+    Ok(crate::config_root()?.join("credentials.json"))
+}
+
+fn load_credentials() -> BTreeMap<String, String> {
+    match credentials_path().and_then(fs::read_to_string) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+pub fn load_token(id: &str) -> Option<String> {
+    load_credentials().remove(id)
+}
+
+//Only the user gets to read the tokens. The file is made that way and moved over the old
+//one, so they're never in a file anyone else could read, not even for a moment
+pub fn save_token(id: &str, token: &str) -> Fallible<()> {
+    let mut credentials = load_credentials();
+    credentials.insert(id.to_string(), token.to_string());
+    let path = credentials_path()?;
+    fs::create_dir_all(crate::config_root()?)?;
+    let temporary = path.with_extension("json.tmp");
+    //left over from a save that didn't finish, it could have been made by anyone
+    match fs::remove_file(&temporary) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(&temporary)?;
+    file.write_all(serde_json::to_string(&credentials)?.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temporary, &path)?;
+    Ok(())
+}
@@ -0,0 +1,5 @@
+pub mod direct;
+#[cfg(feature = "source-github")]
+pub mod github;
+#[cfg(feature = "source-itch")]
+pub mod itch;
@@ -0,0 +1,44 @@
+use failure::Fallible;
+use url::Url;
+
+use crate::mod_downloader::source::{DownloadSource, ModMetadata};
+use crate::mod_downloader::utils::decode_percent_coded_string;
+
+//Any plain http(s) link to a file
+pub struct Direct;
+
+impl DownloadSource for Direct {
+    fn id(&self) -> &'static str {
+        "direct"
+    }
+
+    fn name(&self) -> &'static str {
+        "Direct link"
+    }
+
+    fn handles(&self, _url: &Url) -> bool {
+        true
+    }
+
+    fn resolve(&self, url: &Url) -> Fallible<ModMetadata> {
+        let file_name = url.path().split('/').next_back()
+            .filter(|name| !name.is_empty())
+            .map(|name| decode_percent_coded_string(name).unwrap_or_else(|_| name.to_string()));
+        Ok(ModMetadata {
+            source: self.id().to_string(),
+            name: file_name.as_ref().map(|name| name.split('.').next().unwrap_or("").to_string()),
+            file_name,
+            url: url.to_string(),
+            ..ModMetadata::default()
+        })
+    }
+
+    fn download_url(&self, url: &Url) -> Fallible<Url> {
+        Ok(url.clone())
+    }
+
+    //A plain link has no notion of versions
+    fn check_update(&self, _metadata: &ModMetadata) -> Fallible<Option<ModMetadata>> {
+        Ok(None)
+    }
+}
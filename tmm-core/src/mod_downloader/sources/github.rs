@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use failure::{format_err, Fallible};
+use reqwest::blocking::Client;
+use reqwest::header::{self, HeaderValue};
+use serde_json::Value;
+use url::Url;
+
+use crate::mod_downloader::source::{load_token, save_token, DownloadSource, ModMetadata};
+
+//Release assets of GitHub repositories, the first asset of a release counts as the mod
+pub struct GitHub;
+
+const API: &str = "https://api.github.com";
+
+impl GitHub {
+    //'owner/repo' and the release tag, if the url points to one
+    fn parse(url: &Url) -> Option<(String, Option<String>)> {
+        let segments: Vec<&str> = url.path_segments()?.filter(|segment| !segment.is_empty()).collect();
+        if segments.len() < 2 {
+            return None;
+        }
+        let repo = format!("{}/{}", segments[0], segments[1]);
+        let tag = match segments.get(2..4) {
+            Some(["releases", "tag"]) | Some(["releases", "download"]) => segments.get(4).map(|tag| tag.to_string()),
+            _ => None,
+        };
+        Some((repo, tag))
+    }
+
+    fn api(&self, path: &str) -> Fallible<Value> {
+        let mut request = Client::new()
+            .get(format!("{}{}", API, path))
+            .timeout(Duration::from_secs(30))
+            .header(header::USER_AGENT, HeaderValue::from_str("TMM/0.1.0")?)
+            .header(header::ACCEPT, HeaderValue::from_str("application/vnd.github+json")?);
+        if let Some(token) = load_token(self.id()) {
+            request = request.header(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token))?);
+        }
+        let response = request.send()?;
+        if !response.status().is_success() {
+            return Err(format_err!("GitHub answered {} for '{}'", response.status(), path));
+        }
+        Ok(serde_json::from_str(&response.text()?)?)
+    }
+
+    fn release(&self, repo: &str, tag: Option<&str>) -> Fallible<ModMetadata> {
+        let release = match tag {
+            Some(tag) => self.api(&format!("/repos/{}/releases/tags/{}", repo, tag))?,
+            None => self.api(&format!("/repos/{}/releases/latest", repo))?,
+        };
+        let asset = release["assets"].get(0).ok_or_else(|| format_err!("the release of '{}' has no files", repo))?;
+        Ok(ModMetadata {
+            source: self.id().to_string(),
+            mod_id: Some(repo.to_string()),
+            name: release["name"].as_str().or_else(|| repo.split('/').nth(1)).map(str::to_string),
+            author: repo.split('/').next().map(str::to_string),
+            version: release["tag_name"].as_str().map(str::to_string),
+            file_name: asset["name"].as_str().map(str::to_string),
+            url: asset["browser_download_url"].as_str().unwrap_or("").to_string(),
+            changelog_url: release["html_url"].as_str().map(str::to_string),
+            category: None,
+        })
+    }
+}
+
+impl DownloadSource for GitHub {
+    fn id(&self) -> &'static str {
+        "github"
+    }
+
+    fn name(&self) -> &'static str {
+        "GitHub Releases"
+    }
+
+    fn handles(&self, url: &Url) -> bool {
+        url.host_str() == Some("github.com") && GitHub::parse(url).is_some()
+    }
+
+    fn resolve(&self, url: &Url) -> Fallible<ModMetadata> {
+        let (repo, tag) = GitHub::parse(url).ok_or_else(|| format_err!("'{}' isn't a GitHub repository", url))?;
+        self.release(&repo, tag.as_deref())
+    }
+
+    //Links straight to an asset can be downloaded as they are
+    fn download_url(&self, url: &Url) -> Fallible<Url> {
+        if url.path().contains("/releases/download/") {
+            return Ok(url.clone());
+        }
+        Ok(Url::parse(&self.resolve(url)?.url)?)
+    }
+
+    fn check_update(&self, metadata: &ModMetadata) -> Fallible<Option<ModMetadata>> {
+        let repo = metadata.mod_id.as_ref().ok_or_else(|| format_err!("the mod has no GitHub repository"))?;
+        let latest = self.release(repo, None)?;
+        if latest.version != metadata.version {
+            Ok(Some(latest))
+        } else {
+            Ok(None)
+        }
+    }
+
+    //Checks the token against the API before keeping it
+    fn authenticate(&self, token: &str) -> Fallible<()> {
+        let response = Client::new()
+            .get(format!("{}/user", API))
+            .timeout(Duration::from_secs(30))
+            .header(header::USER_AGENT, HeaderValue::from_str("TMM/0.1.0")?)
+            .header(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token))?)
+            .send()?;
+        if !response.status().is_success() {
+            return Err(format_err!("GitHub didn't accept the token ({})", response.status()));
+        }
+        save_token(self.id(), token)
+    }
+}
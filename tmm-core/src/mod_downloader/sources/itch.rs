@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use failure::{format_err, Fallible};
+use reqwest::blocking::Client;
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use serde_json::Value;
+use url::Url;
+
+use crate::mod_downloader::source::{load_token, save_token, DownloadSource, ModMetadata};
+
+//Files uploaded to itch.io project pages, the first upload counts as the mod.
+//The uploads API needs an API key from the user's itch.io settings
+pub struct Itch;
+
+const API: &str = "https://api.itch.io";
+
+impl Itch {
+    fn request(url: &str) -> Fallible<reqwest::blocking::RequestBuilder> {
+        Ok(Client::new()
+            .get(url)
+            .timeout(Duration::from_secs(30))
+            .header(header::USER_AGENT, HeaderValue::from_str("TMM/0.1.0")?))
+    }
+
+    fn get(url: &str, key: Option<&str>) -> Fallible<Value> {
+        let mut request = Itch::request(url)?;
+        if let Some(key) = key {
+            request = request.header(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", key))?);
+        }
+        let response = request.send()?;
+        if !response.status().is_success() {
+            return Err(format_err!("itch.io answered {} for '{}'", response.status(), url));
+        }
+        Ok(serde_json::from_str(&response.text()?)?)
+    }
+
+    fn key(&self) -> Fallible<String> {
+        load_token(self.id()).ok_or_else(|| format_err!("downloading from itch.io needs an API key"))
+    }
+
+    //Project pages are '<user>.itch.io/<project>', their data.json has the game's id
+    fn game_id(url: &Url) -> Fallible<u64> {
+        let project = url.path_segments()
+            .and_then(|mut segments| segments.find(|segment| !segment.is_empty()))
+            .ok_or_else(|| format_err!("'{}' isn't an itch.io project page", url))?;
+        let host = url.host_str().unwrap_or("");
+        let data = Itch::get(&format!("https://{}/{}/data.json", host, project), None)?;
+        data["id"].as_u64().ok_or_else(|| format_err!("itch.io didn't say which game '{}' is", url))
+    }
+
+    //The first upload and its id, all the download needs
+    fn first_upload(game_id: u64, key: &str) -> Fallible<(Value, u64)> {
+        let mut uploads = Itch::get(&format!("{}/games/{}/uploads", API, game_id), Some(key))?;
+        let upload = uploads["uploads"].get_mut(0).map(Value::take).ok_or_else(|| format_err!("the itch.io project {} has no files", game_id))?;
+        let upload_id = upload["id"].as_u64().ok_or_else(|| format_err!("itch.io sent an upload without an id"))?;
+        Ok((upload, upload_id))
+    }
+
+    fn upload(&self, game_id: u64) -> Fallible<ModMetadata> {
+        let key = self.key()?;
+        let game = Itch::get(&format!("{}/games/{}", API, game_id), Some(&key))?;
+        let (upload, upload_id) = Itch::first_upload(game_id, &key)?;
+        Ok(ModMetadata {
+            source: self.id().to_string(),
+            mod_id: Some(game_id.to_string()),
+            name: game["game"]["title"].as_str().map(str::to_string),
+            author: game["game"]["user"]["display_name"].as_str()
+                .or_else(|| game["game"]["user"]["username"].as_str())
+                .map(str::to_string),
+            //uploads without builds don't have a version, their last change has to do
+            version: upload["build"]["user_version"].as_str()
+                .or_else(|| upload["updated_at"].as_str())
+                .map(str::to_string),
+            file_name: upload["filename"].as_str().map(str::to_string),
+            url: format!("{}/uploads/{}/download", API, upload_id),
+            //devlogs are on the project page
+            changelog_url: game["game"]["url"].as_str().map(str::to_string),
+            category: None,
+        })
+    }
+}
+
+impl DownloadSource for Itch {
+    fn id(&self) -> &'static str {
+        "itch"
+    }
+
+    fn name(&self) -> &'static str {
+        "itch.io"
+    }
+
+    fn handles(&self, url: &Url) -> bool {
+        url.host_str().map_or(false, |host| host.ends_with(".itch.io") && host != "api.itch.io")
+    }
+
+    fn resolve(&self, url: &Url) -> Fallible<ModMetadata> {
+        self.upload(Itch::game_id(url)?)
+    }
+
+    //The download endpoint redirects to the file once it has the key
+    fn download_url(&self, url: &Url) -> Fallible<Url> {
+        let (_, upload_id) = Itch::first_upload(Itch::game_id(url)?, &self.key()?)?;
+        Ok(Url::parse(&format!("{}/uploads/{}/download", API, upload_id))?)
+    }
+
+    fn download_headers(&self) -> Fallible<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", self.key()?))?);
+        Ok(headers)
+    }
+
+    fn check_update(&self, metadata: &ModMetadata) -> Fallible<Option<ModMetadata>> {
+        let game_id: u64 = metadata.mod_id.as_ref()
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| format_err!("the mod has no itch.io project"))?;
+        let latest = self.upload(game_id)?;
+        if latest.version != metadata.version {
+            Ok(Some(latest))
+        } else {
+            Ok(None)
+        }
+    }
+
+    //Checks the key against the API before keeping it
+    fn authenticate(&self, token: &str) -> Fallible<()> {
+        Itch::get(&format!("{}/profile", API), Some(token))
+            .map_err(|e| format_err!("itch.io didn't accept the API key ({})", e))?;
+        save_token(self.id(), token)
+    }
+}
@@ -3,14 +3,14 @@ use std::fs::OpenOptions;
 use std::io;
 use std::path::Path;
 
-use failure::{Fallible};
+use failure::{format_err, Fallible};
 
 use url::{ParseError, Url};
 
 pub fn parse_url(url_as_string: &str) -> Result<Url, ParseError> {
     match Url::parse(url_as_string) {
         Ok(url) => Ok(url),
-        Err(error) if error == ParseError::RelativeUrlWithoutBase => {
+        Err(ParseError::RelativeUrlWithoutBase) => {
             let url_with_base = format!("{}{}", "http://", url_as_string);
             parse_url(url_with_base.as_str())
         }
@@ -24,9 +24,12 @@ pub fn decode_percent_coded_string(data: &str) -> Fallible<String> {
     while let Some(b) = bytes.next() {
         match b as char {
             '%' => {
-                let bytes_to_decode = &[bytes.next().unwrap(), bytes.next().unwrap()];
-                let hex_str = std::str::from_utf8(bytes_to_decode).unwrap();
-                decoded_bytes.push(u8::from_str_radix(hex_str, 16).unwrap());
+                let (high, low) = match (bytes.next(), bytes.next()) {
+                    (Some(high), Some(low)) => (high, low),
+                    _ => return Err(format_err!("'{}' ends in the middle of a percent escape", data)),
+                };
+                let hex_str = std::str::from_utf8(&[high, low])?.to_string();
+                decoded_bytes.push(u8::from_str_radix(&hex_str, 16).map_err(|_| format_err!("'%{}' in '{}' isn't a percent escape", hex_str, data))?);
             }
             _ => {
                 decoded_bytes.push(b);
@@ -38,23 +41,15 @@ pub fn decode_percent_coded_string(data: &str) -> Fallible<String> {
 
 pub fn get_file_handle(filename: &str, save_path: &str, resume_download: &bool, append: &bool) -> io::Result<File> {
     let path = format!("{}/{}", save_path, filename);
-    // println!("Save Path: {}", path);
+    tracing::debug!("Save path: {}", path);
     if *resume_download && Path::new(&path).exists() {
         if *append {
-            match OpenOptions::new().append(true).open(&path) {
-                Ok(file) => Ok(file),
-                Err(error) => Err(error)
-            }
+            OpenOptions::new().append(true).open(&path)
         } else {
-            match OpenOptions::new().write(true).open(&path) {
-                Ok(file) => Ok(file),
-                Err(error) => Err(error)
-            }
+            OpenOptions::new().write(true).open(&path)
         }
     } else {
-        match OpenOptions::new().write(true).create(true).open(&path) {
-            Ok(file) => Ok(file),
-            Err(error) => Err(error)
-        }
+        //not resuming, whatever was there before gets written over
+        OpenOptions::new().write(true).create(true).truncate(true).open(&path)
     }
 }
\ No newline at end of file
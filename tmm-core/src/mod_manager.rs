@@ -0,0 +1,555 @@
+use std::{path::PathBuf, path::Path, fs, io};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Context, Error, ErrorCode};
+use crate::events::{BackendEvent, DeployEvent, DeployFailed, Events};
+use crate::{logging, tasks};
+
+use serde::{Deserialize, Serialize};
+use once_cell::sync::Lazy;
+use compress_tools::{uncompress_archive, Ownership};
+
+
+
+pub mod ofs;
+pub mod link;
+pub mod manifest;
+pub mod journal;
+pub mod baseline;
+pub mod case;
+pub mod merge;
+pub mod overrides;
+pub mod conflicts;
+pub mod launch;
+pub mod session;
+pub mod bisect;
+pub mod experiment;
+pub mod stale;
+pub mod migrate;
+pub mod tools;
+pub mod hooks;
+pub mod launch_options;
+pub mod monitor;
+pub mod localconfig;
+pub mod vdf;
+pub mod runners;
+pub mod steam_wrapper;
+pub mod protontricks;
+pub mod diagnostics;
+pub mod registry;
+pub mod deploy;
+pub mod game;
+pub mod storage;
+pub mod scratch;
+pub mod verify;
+pub mod reflink;
+pub mod relocate;
+pub mod staging;
+pub mod excludes;
+pub mod root;
+pub mod pool;
+pub mod archive;
+pub mod bain;
+pub mod split;
+pub mod folder;
+pub mod rename;
+pub mod query;
+pub mod trash;
+pub mod reinstall;
+pub mod export;
+pub mod versions;
+pub mod overwrite;
+pub mod profiles;
+pub mod portable;
+pub mod saves;
+pub mod configs;
+pub mod plugins;
+pub mod sync;
+pub mod meta;
+pub mod modlist;
+pub mod load_order;
+pub mod mod_updates;
+pub mod dependencies;
+pub mod scan;
+pub mod definitions;
+pub mod search;
+pub mod removable;
+pub mod forget;
+pub mod updates;
+pub mod watcher;
+pub mod lutris;
+pub mod itch;
+
+use game::Game;
+
+// #[derive(Serialize, Deserialize)]
+// pub struct Game {
+//   appid: u32,
+//   name: String,
+//   path: PathBuf,
+//   stage_path: PathBuf,
+//   work_path: PathBuf,
+// }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mod {
+  pub name: String,
+  //only filled in by get_mods, what the frontend sends back doesn't matter
+  #[serde(default)]
+  pub meta: meta::ModMeta,
+  #[serde(default)]
+  pub enabled: bool,
+  //filled in by get_mods too
+  #[serde(default)]
+  pub usage: modlist::Usage,
+  //the folder in 'mods/<name>' that's in use, as get_mods found it
+  #[serde(default)]
+  pub version: Option<String>,
+}
+
+impl Mod {
+  //The mods the manager puts together itself, they have no meta and are always on
+  pub fn generated(name: &str) -> Mod {
+    Mod { name: name.to_string(), meta: meta::ModMeta::default(), enabled: true, usage: modlist::Usage::default(), version: None }
+  }
+}
+
+//What enabling, disabling or removing a batch of mods did, also sent as 'mods-changed' so
+//every view updates once
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+  pub appid: u32,
+  pub changed: Vec<String>,
+  //files of the enabled mods that come from another mod now
+  pub changes: Vec<conflicts::OwnerChange>,
+  pub missing_dependencies: Vec<dependencies::MissingDependency>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub trashed: Vec<trash::TrashEntry>,
+}
+
+//Mods the manager generates itself start with this, they never get listed
+pub const GENERATED_MOD_PREFIX: &str = ".tmm_";
+
+//The persisted load order and enabled flags win over whatever the frontend sends
+pub fn deployable(game: &Game, mods: &[Mod]) -> io::Result<Vec<Mod>> {
+  let modlist = modlist::ModList::load(game)?;
+  Ok(modlist.enabled(&modlist.sorted(mods)))
+}
+
+//One lock per game that's being deployed or purged, commands run on a pool and a deploy
+//and a purge of the same game would otherwise take turns on its files
+static DEPLOYING: Lazy<Mutex<HashMap<u32, Arc<Mutex<()>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+//Held for as long as the game's directory is being changed. One that panicked while holding
+//it left a journal behind, the next deploy picks up from there
+fn deploy_lock(appid: u32) -> Arc<Mutex<()>> {
+  DEPLOYING.lock().unwrap().entry(appid).or_default().clone()
+}
+
+//Whatever deploys or purges, the app, the CLI, launches and test sessions, goes out as
+//'deploy-started' and then 'deploy-finished' or 'deploy-failed'
+fn announced<T>(game: &Game, mods: &[Mod], purge: bool, events: &Events, work: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+  let event = DeployEvent { appid: game.appid, backend: game.deploy_mode, mods: mods.iter().map(|elem| elem.name.clone()).collect(), purge };
+  events.send(BackendEvent::DeployStarted(event.clone()));
+  match work() {
+    Ok(done) => {
+      events.send(BackendEvent::DeployFinished(event));
+      Ok(done)
+    },
+    Err(e) => {
+      let kind = e.kind();
+      let error = Error::io(if purge { Context::Purge } else { Context::Deploy }, e).param("appid", game.appid);
+      events.send(BackendEvent::DeployFailed(DeployFailed { deploy: event, error: error.clone() }));
+      Err(error.into_io(kind))
+    },
+  }
+}
+
+pub fn deploy_mods(game: &Game, mods: &[Mod], events: &Events) -> io::Result<deploy::DeployReport> {
+  let lock = deploy_lock(game.appid);
+  let _deploying = lock.lock().unwrap_or_else(|e| e.into_inner());
+  announced(game, mods, false, events, || deploy_locked(game, mods))
+}
+
+fn deploy_locked(game: &Game, mods: &[Mod]) -> io::Result<deploy::DeployReport> {
+  //the merged and pinned mods get put together in there
+  let _writing = watcher::writing(&game.profile_path.join("mods"));
+  let span = tracing::info_span!("deploy", run = logging::run_id(), appid = game.appid, backend = ?game.deploy_mode);
+  let _entered = span.enter();
+  //a deployment only stops where the game is either as it was or purged, never halfway
+  tasks::checkpoint()?;
+  monitor::ensure_not_running(game)?;
+  let mut mods = mods.to_vec();
+  //the merged configs have to win over every mod they were merged from,
+  //and files pinned by override rules over everything else
+  if let Some(merged) = merge::refresh(game, &mods)? {
+    mods.push(merged);
+  }
+  if let Some(pinned) = overrides::refresh(game, &mods)? {
+    mods.push(pinned);
+  }
+  let returned = removable::ensure_present(game)?;
+  let backend = deploy::backend(game.deploy_mode);
+  //the baseline has to be taken with nothing of the previous deployment left,
+  //backends deploying over their last deployment keep the one from back then
+  if returned || !backend.incremental() || !backend.status(game)?.deployed {
+    backend.purge(game)?;
+    baseline::record(game)?;
+  }
+  tasks::checkpoint()?;
+  let report = backend.deploy(game, &mods)?;
+  plugins::write(game, &mods)?;
+  let mut modlist = modlist::ModList::load(game)?;
+  modlist.set_deployed(&mods.iter().map(|elem| elem.name.clone()).filter(|name| !name.starts_with(GENERATED_MOD_PREFIX)).collect::<Vec<String>>());
+  modlist.save(game)?;
+  tracing::info!(mods = mods.len(), case_conflicts = report.case_conflicts.len(), excluded = report.excluded.len(), "deployed");
+  Ok(report)
+}
+
+//The backend's purge with the game's lock held, for what purges on the way to something else,
+//ending a session, exiting, an update, switching profiles or removing the game
+pub(crate) fn purge_deployed(game: &Game, events: &Events) -> io::Result<()> {
+  let lock = deploy_lock(game.appid);
+  let _deploying = lock.lock().unwrap_or_else(|e| e.into_inner());
+  announced(game, &[], true, events, || deploy::backend(game.deploy_mode).purge(game))
+}
+
+//Takes every deployed file out of the game directory again and checks
+//that it's back to how it was right before deploying
+pub fn purge(game: &Game, events: &Events) -> io::Result<baseline::PurgeReport> {
+  let lock = deploy_lock(game.appid);
+  let _deploying = lock.lock().unwrap_or_else(|e| e.into_inner());
+  announced(game, &[], true, events, || {
+    monitor::ensure_not_running(game)?;
+    deploy::backend(game.deploy_mode).purge(game)?;
+    baseline::verify(game)
+  })
+}
+
+//For everything that can go on with no mods when they can't be read, the frontend gets the error
+//through load_mods
+pub fn get_mods(game: Game) -> Vec<Mod> {
+  load_mods(&game).unwrap_or_else(|e| {
+    tracing::warn!("Couldn't read the mods of '{}': {}", game.public_name, e);
+    Vec::new()
+  })
+}
+
+//The mods the frontend names as they're installed, in its order. It only gets to say which
+//ones and whether they're enabled, their meta comes from the profile
+pub fn resolve_mods(game: &Game, mods: &[Mod]) -> io::Result<Vec<Mod>> {
+  let installed = load_mods(game)?;
+  mods.iter().map(|elem| {
+    installed.iter().find(|installed| installed.name == elem.name)
+      .map(|installed| Mod { enabled: elem.enabled, ..installed.clone() })
+      .ok_or_else(|| Error::new(ErrorCode::ModNotFound, format!("'{}' isn't installed", elem.name))
+        .param("mod_name", &elem.name)
+        .into_io(io::ErrorKind::NotFound))
+  }).collect()
+}
+
+pub fn resolve_mod(game: &Game, elem: &Mod) -> io::Result<Mod> {
+  Ok(resolve_mods(game, std::slice::from_ref(elem))?.remove(0))
+}
+
+//The folder of the version of the mod that's in use, see versions
+pub fn mod_dir(game: &Game, elem: &Mod) -> io::Result<PathBuf> {
+  match &elem.version {
+    Some(version) => versions::active_in(&versions::mod_root(game, &elem.name), Some(version)),
+    None => versions::active(game, &elem.name),
+  }
+}
+
+pub fn load_mods(game: &Game) -> io::Result<Vec<Mod>> {
+  let modlist = modlist::ModList::load(game)?;
+  let mut mods: Vec<Mod> = Vec::new();
+  for root in get_directories(&game.profile_path.join("mods"))? {
+    let name = root.file_name().unwrap().to_string_lossy().to_string();
+    if name.starts_with(GENERATED_MOD_PREFIX) {
+      continue;
+    }
+    let path = versions::active_in(&root, modlist.version(&name))?;
+    let version = path.strip_prefix(&root).ok().map(|version| version.to_string_lossy().to_string()).filter(|version| !version.is_empty());
+    mods.push(Mod { enabled: modlist.is_enabled(&name), meta: meta::load(&path), usage: modlist.usage(&name), version, name });
+  }
+  //the directory listing has no order, mods the load order doesn't know yet go by name
+  mods.sort_by_key(|elem| elem.name.to_lowercase());
+  Ok(modlist.sorted(&mods))
+}
+
+pub fn switch_for_launch(game: Game, profile: Option<String>, events: &Events) -> Result<Game, Error> {
+  let appid = game.appid;
+  match profile {
+    Some(name) if name != profiles::active_name(&game) => {
+      profiles::switch(game, &name, events).map_err(|e| Error::io(Context::Profiles, e).param("appid", appid).param("profile", &name))
+    },
+    _ => Ok(game),
+  }
+}
+
+pub fn deploy_for_launch(game: &Game, events: &Events) -> Result<(), Error> {
+  //nothing gets to purge between finding it deployed and starting it
+  let lock = deploy_lock(game.appid);
+  let _deploying = lock.lock().unwrap_or_else(|e| e.into_inner());
+  let result = deploy::backend(game.deploy_mode).status(game).and_then(|status| {
+    if status.deployed {
+      return Ok(());
+    }
+    load_mods(game).and_then(|mods| deployable(game, &mods)).and_then(|mods| announced(game, &mods, false, events, || deploy_locked(game, &mods))).map(|_| ())
+  });
+  result.map_err(|e| Error::io(Context::Deploy, e).param("appid", game.appid))?;
+  saves::apply(game).and_then(|()| configs::deploy(game)).map_err(|e| Error::io(Context::Launch, e).param("appid", game.appid))
+}
+
+//Switches to the profile first if it's given and not the active one, then deploys unless the
+//profile is deployed already, so what gets started sees the modded game directory
+pub fn prepare_launch(game: Game, profile: Option<String>, events: &Events) -> Result<Game, Error> {
+  let game = switch_for_launch(game, profile, events)?;
+  deploy_for_launch(&game, events)?;
+  Ok(game)
+}
+
+pub fn make_tmm_game_directories(game: Game) -> io::Result<()> {
+  fs::create_dir_all(&game.profile_path)?;
+  fs::create_dir_all(game.profile_path.join("downloads/"))?;
+  fs::create_dir_all(game.profile_path.join("mods/"))?;
+  Ok(())
+}
+
+//A mod's name is its directory's name, so it has to be one. Generated mods count too,
+//a user mod with their name would clash, and so does their prefix
+pub fn check_mod_name(name: &str) -> io::Result<()> {
+  if name.trim().is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) || name.starts_with(GENERATED_MOD_PREFIX) {
+    return Err(Error::new(ErrorCode::InvalidModName, format!("'{}' can't be used as the name of a mod", name))
+      .param("mod_name", name)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  Ok(())
+}
+
+//For a new mod, the name also can't be taken
+pub fn validate_mod_name(game: &Game, name: &str) -> io::Result<()> {
+  check_mod_name(name)?;
+  if game.profile_path.join("mods").join(name).exists() {
+    return Err(Error::new(ErrorCode::ModExists, format!("there already is a mod called '{}'", name))
+      .param("mod_name", name)
+      .into_io(io::ErrorKind::AlreadyExists));
+  }
+  Ok(())
+}
+
+pub fn get_directories(path: &Path) -> io::Result<Vec<PathBuf>> {
+  let mut directories: Vec<PathBuf> = Vec::new();
+  if path.exists() {
+    for entry in path.read_dir()?.flatten() {
+      if entry.path().is_dir() {
+        directories.push(entry.path());
+      }
+    }
+  }
+  Ok(directories)
+}
+
+//The path with the suffix after its whole file name, unlike with_extension 'a.esp' and 'a.esm' don't end up the same
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  let mut name = path.as_os_str().to_owned();
+  name.push(suffix);
+  PathBuf::from(name)
+}
+
+pub fn get_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+  let mut files: Vec<PathBuf> = Vec::new();
+  if path.exists() {
+    for entry in path.read_dir()? {
+      let entry = entry?;
+      if entry.file_type()?.is_dir() {
+        files.append(&mut get_files(&entry.path())?);
+      } else {
+        files.push(entry.path());
+      }
+    }
+  }
+  Ok(files)
+}
+
+//Extracts into the scratch space first so a half extracted archive never shows up as a mod.
+//Any part of a split archive can be given, the others are looked for next to it
+pub fn extract(file_path: &Path, password: Option<&str>, game: &Game) -> io::Result<scratch::ScratchDir> {
+  let staging = scratch::ScratchDir::new(game, "extract", scratch::extraction_estimate(file_path)?)?;
+  let volumes = split::volumes(file_path);
+  let result = match (&volumes, password) {
+    (split::Volumes::Single(path), None) => uncompress_archive(fs::File::open(path)?, staging.path(), Ownership::Ignore),
+    (split::Volumes::Split(parts), None) => uncompress_archive(split::SplitReader::open(parts)?, staging.path(), Ownership::Ignore),
+    //7-Zip finds the other parts on its own
+    (_, password) => return split::extract_with_7z(volumes.first(), staging.path(), password).map(|()| staging),
+  };
+  match result {
+    Ok(()) => Ok(staging),
+    //libarchive doesn't tell, 7-Zip gets asked if it's around
+    Err(_) if split::is_encrypted(volumes.first()).unwrap_or(false) => Err(split::encrypted(file_path)),
+    Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+  }
+}
+
+//A version being replaced waits in the mod's folder under this, so it can be put back
+const REPLACED_PREFIX: &str = ".replaced ";
+
+//What a crash left while replacing a version, it's the only copy when the version isn't there
+fn recover_replaced(root: &Path) -> io::Result<()> {
+  if !root.is_dir() {
+    return Ok(());
+  }
+  for entry in root.read_dir()? {
+    let path = entry?.path();
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if let Some(version) = name.strip_prefix(REPLACED_PREFIX) {
+      if fs::symlink_metadata(root.join(version)).is_ok() {
+        meta::remove_mod(&path)?;
+      } else {
+        meta::move_mod(&path, &root.join(version))?;
+      }
+    }
+  }
+  Ok(())
+}
+
+//Over an installed mod it's a reinstall, the files the user changed get backed up and
+//put back in where the new version doesn't change them. The new version goes in next to
+//the one in use, which only stays if it's supposed to be kept
+pub fn install_extracted(source: &Path, mod_name: &str, installed: meta::ModMeta, keep_previous: bool, game: &Game) -> io::Result<Vec<reinstall::PreservedFile>> {
+  let root = versions::mod_root(game, mod_name);
+  let _writing = watcher::writing(&root);
+  recover_replaced(&root)?;
+  let previous = Some(versions::active(game, mod_name)?).filter(|previous| *previous != root);
+  //the same version again takes its place
+  let target = match &previous {
+    Some(previous) if !keep_previous && previous.file_name().map_or(false, |name| *name == *versions::version_key(&installed)) => previous.clone(),
+    _ => versions::free(game, mod_name, &installed),
+  };
+  let version = target.file_name().unwrap().to_string_lossy().to_string();
+  //dependencies a mod ships in its own meta.json are kept
+  let shipped = meta::load(source);
+  meta::save(source, &meta::ModMeta { dependencies: shipped.dependencies, ..installed })?;
+  let replacing = match previous.filter(|_| !keep_previous) {
+    Some(previous) => {
+      //a deployment can point at the files of the one in use
+      monitor::ensure_not_running(game)?;
+      let set_aside = reinstall::set_aside(game, &previous, mod_name)?;
+      let replaced = root.join(format!("{}{}", REPLACED_PREFIX, previous.file_name().unwrap().to_string_lossy()));
+      meta::move_mod(&previous, &replaced)?;
+      Some((set_aside, previous, replaced))
+    },
+    None => None,
+  };
+  //the old version only goes once the new one is in and shares its files with the other mods,
+  //until then it can be put back
+  let report = match scratch::move_dir(source, &target).and_then(|()| pool::ingest(&target)) {
+    Ok(report) => report,
+    Err(e) => {
+      if let Some((_, previous, replaced)) = &replacing {
+        let restored = if fs::symlink_metadata(&target).is_ok() { meta::remove_mod(&target) } else { Ok(()) };
+        //what went wrong installing is the error, the old version is still there under its
+        //'.replaced' name then and recover_replaced puts it back the next time
+        if let Err(restore_error) = restored.and_then(|()| meta::move_mod(replaced, previous)) {
+          tracing::error!("Couldn't put the previous version of '{}' back, it's kept at '{}': {}", mod_name, replaced.display(), restore_error);
+        }
+      }
+      return Err(e);
+    },
+  };
+  versions::set_active(game, mod_name, &version)?;
+  reinstall::record(&target, &report.hashes)?;
+  match replacing {
+    Some((set_aside, _, replaced)) => {
+      meta::remove_mod(&replaced)?;
+      reinstall::restore(set_aside, &target, &report.hashes)
+    },
+    None => Ok(Vec::new()),
+  }
+}
+
+//Without a subdirectory the layout gets fixed up to what the game expects. Packages are named
+//after both archives, without a choice that's all of them if there's nothing but archives
+//in there, none otherwise
+pub fn install_archive(file_path: &Path, file_name: &str, options: archive::InstallOptions, game: &Game) -> io::Result<archive::InstallReport> {
+  let span = tracing::info_span!("install", run = logging::run_id(), appid = game.appid, archive = %file_path.display());
+  let _entered = span.enter();
+  //the name comes from the archive's file name, it ends up as a directory
+  check_mod_name(file_name)?;
+  registry::check_source("file_path", file_path)?;
+  let installed = meta::for_install(game, file_path)?;
+  let existing = load_mods(game)?.into_iter().find(|elem| elem.meta.archive_hash.is_some() && elem.meta.archive_hash == installed.archive_hash);
+  let replacing = match (existing, options.duplicate) {
+    (Some(existing), None) => {
+      return Err(Error::new(ErrorCode::AlreadyInstalled, format!("this appears to already be installed as '{}'", existing.name))
+        .param("mod_name", &existing.name)
+        .into_io(io::ErrorKind::AlreadyExists));
+    },
+    (Some(existing), Some(archive::Duplicate::Replace)) => Some(existing.name),
+    //another version of a mod that's installed under the name
+    (None, Some(archive::Duplicate::Replace)) if game.profile_path.join("mods").join(file_name).is_dir() => Some(file_name.to_string()),
+    (None, _) if game.profile_path.join("mods").join(file_name).is_dir() => {
+      return Err(Error::new(ErrorCode::ModExists, format!("there already is a mod called '{}'", file_name))
+        .param("mod_name", file_name)
+        .into_io(io::ErrorKind::AlreadyExists));
+    },
+    _ => None,
+  };
+  tasks::checkpoint()?;
+  let staging = extract(file_path, options.password.as_deref(), game)?;
+  //nothing's in the mods yet, the staging goes away with it
+  tasks::checkpoint()?;
+  let nested = archive::nested(staging.path())?;
+  let packages = match options.packages {
+    Some(packages) => packages,
+    None if archive::only_packages(staging.path())? => nested.clone(),
+    None => Vec::new(),
+  };
+  let mut report = archive::InstallReport::default();
+  for package in &packages {
+    if !nested.contains(package) {
+      return Err(Error::new(ErrorCode::InvalidArchivePath, format!("'{}' isn't an archive in the archive", package.display()))
+        .param("path", package.display().to_string())
+        .into_io(io::ErrorKind::InvalidInput));
+    }
+    let package_path = staging.path().join(package);
+    let stem = package_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let name = format!("{} - {}", file_name, stem);
+    validate_mod_name(game, &name)?;
+    let package_staging = extract(&package_path, None, game)?;
+    let source = archive::restructure(package_staging.path(), game)?;
+    install_extracted(&source, &name, meta::ModMeta { archive_hash: Some(pool::hash_file(&package_path)?), ..installed.clone() }, false, game)?;
+    fs::remove_file(package_path)?;
+    report.mods.push(name);
+  }
+  //packages that weren't picked and the readmes next to them don't make a mod
+  if packages.is_empty() || !archive::only_packages(staging.path())? {
+    let source = match (&options.subdirectory, bain::find(staging.path())?) {
+      (Some(subdirectory), _) => archive::subdirectory(staging.path(), subdirectory)?,
+      (None, Some((dir, bain_packages))) => {
+        let picked = options.bain_packages.clone().unwrap_or_else(|| bain::default_packages(&bain_packages));
+        archive::restructure(&bain::merge(&dir, &bain_packages, &picked, staging.path())?, game)?
+      },
+      (None, None) => archive::restructure(staging.path(), game)?,
+    };
+    //only once everything is extracted, a broken archive leaves the old one alone.
+    //What the user wrote about it stays
+    let kept = replacing.is_some() && options.keep_previous;
+    let (name, installed) = match replacing {
+      Some(name) => {
+        let old = meta::load(&versions::active(game, &name)?);
+        //the version the user named is the one that's kept
+        let custom_version = if kept { None } else { old.custom_version };
+        (name, meta::ModMeta { notes: old.notes, custom_version, color: old.color, tags: old.tags, category: old.category.or(installed.category), ..installed })
+      },
+      None => {
+        validate_mod_name(game, file_name)?;
+        (file_name.to_string(), installed)
+      },
+    };
+    report.preserved = install_extracted(&source, &name, installed, kept, game)?;
+    report.mods.push(name);
+  }
+  Ok(report)
+}
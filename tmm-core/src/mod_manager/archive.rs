@@ -0,0 +1,243 @@
+use crate::mod_manager::{bain, get_files, registry, split};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::meta::META_FILE_NAME;
+use crate::mod_manager::reinstall::PreservedFile;
+use crate::mod_manager::root::ROOT_DIR_NAME;
+use crate::error::{Error, ErrorCode};
+use compress_tools::{ArchiveContents, ArchiveIterator};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+//What to do when the archive was installed before
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Duplicate {
+  //the installed mod gets the new files and keeps its name and place in the load order
+  Replace,
+  //installed next to it as a second mod
+  Keep,
+}
+
+//How uncompress installs an archive, everything is optional
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InstallOptions {
+  //only this folder of the archive becomes the mod, as it is
+  #[serde(default)]
+  pub subdirectory: Option<PathBuf>,
+  //archives inside the archive that become mods of their own, as peek lists them
+  #[serde(default)]
+  pub packages: Option<Vec<PathBuf>>,
+  #[serde(default)]
+  pub password: Option<String>,
+  //without one an archive that's installed already fails with 'already_installed'
+  #[serde(default)]
+  pub duplicate: Option<Duplicate>,
+  //a replaced mod is kept as another version of it to switch back to, instead of reinstalled over
+  #[serde(default)]
+  pub keep_previous: bool,
+  //the packages of a BAIN archive to merge into the mod, as peek lists them
+  #[serde(default)]
+  pub bain_packages: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InstallReport {
+  pub mods: Vec<String>,
+  //what the user had changed in the mod that got reinstalled
+  pub preserved: Vec<PreservedFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveEntry {
+  path: PathBuf,
+  //unpacked
+  size: u64,
+  is_dir: bool,
+}
+
+//What's in an archive, read without extracting anything to disk
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivePeek {
+  entries: Vec<ArchiveEntry>,
+  total_size: u64,
+  //the one folder everything is wrapped in, if there is one
+  root: Option<PathBuf>,
+  //archives inside the archive, they can be installed as mods of their own
+  nested: Vec<PathBuf>,
+  //the numbered folders of a BAIN archive, in install order
+  bain_packages: Vec<String>,
+}
+
+//Everything in the archive gets read through once, that's the only way to get at the
+//unpacked sizes, but nothing is kept. Directories are only there if the archive lists them
+pub fn peek(archive: &Path) -> io::Result<ArchivePeek> {
+  registry::check_source("file_path", archive)?;
+  match split::volumes(archive) {
+    split::Volumes::Split(parts) => peek_from(split::SplitReader::open(&parts)?),
+    volumes => peek_from(File::open(volumes.first())?),
+  }
+}
+
+fn peek_from<R: Read + Seek>(source: R) -> io::Result<ArchivePeek> {
+  let invalid = |e: compress_tools::Error| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+  let mut iterator = ArchiveIterator::from_read(source).map_err(invalid)?;
+  let mut entries: Vec<ArchiveEntry> = Vec::new();
+  for content in &mut iterator {
+    match content {
+      ArchiveContents::StartOfEntry(name) => entries.push(ArchiveEntry {
+        is_dir: name.ends_with('/'),
+        path: PathBuf::from(name.trim_end_matches('/')),
+        size: 0,
+      }),
+      ArchiveContents::DataChunk(chunk) => {
+        if let Some(entry) = entries.last_mut() {
+          entry.size += chunk.len() as u64;
+        }
+      },
+      ArchiveContents::EndOfEntry => {},
+      ArchiveContents::Err(e) => return Err(invalid(e)),
+    }
+  }
+  iterator.close().map_err(invalid)?;
+  let total_size = entries.iter().map(|entry| entry.size).sum();
+  let root = wrapper_dir(&entries);
+  let nested = entries.iter().filter(|entry| !entry.is_dir && is_archive(&entry.path)).map(|entry| entry.path.clone()).collect();
+  let bain_packages = bain_packages(&entries, root.as_deref());
+  Ok(ArchivePeek { entries, total_size, root, nested, bain_packages })
+}
+
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "7z", "rar"];
+//What gets shipped next to the packages of an archive of archives
+const DOCUMENTATION_EXTENSIONS: &[&str] = &["txt", "md", "pdf", "html", "htm", "url", "nfo", "png", "jpg"];
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+  path.extension().map_or(false, |extension| extensions.iter().any(|known| extension.to_string_lossy().eq_ignore_ascii_case(known)))
+}
+
+fn is_archive(path: &Path) -> bool {
+  has_extension(path, ARCHIVE_EXTENSIONS)
+}
+
+//The archives in an extracted archive, relative to it
+pub fn nested(extracted: &Path) -> io::Result<Vec<PathBuf>> {
+  Ok(get_files(extracted)?.into_iter()
+    .filter(|file| is_archive(file))
+    .map(|file| file.strip_prefix(extracted).unwrap().to_path_buf())
+    .collect())
+}
+
+//Whether there's nothing but archives and their readmes
+pub fn only_packages(extracted: &Path) -> io::Result<bool> {
+  let files = get_files(extracted)?;
+  Ok(files.iter().any(|file| is_archive(file)) && files.iter().all(|file| is_archive(file) || has_extension(file, DOCUMENTATION_EXTENSIONS)))
+}
+
+//Inside the wrapping folder, if there is one
+fn bain_packages(entries: &[ArchiveEntry], root: Option<&Path>) -> Vec<String> {
+  let base = root.unwrap_or_else(|| Path::new(""));
+  let dirs: BTreeSet<String> = entries.iter()
+    .filter_map(|entry| entry.path.strip_prefix(base).ok())
+    .filter(|relative| relative.components().count() > 1)
+    .filter_map(|relative| relative.components().next())
+    .map(|top| top.as_os_str().to_string_lossy().to_string())
+    .collect();
+  bain::package_names(dirs.iter().map(String::as_str)).unwrap_or_default()
+}
+
+fn wrapper_dir(entries: &[ArchiveEntry]) -> Option<PathBuf> {
+  let tops: BTreeSet<PathBuf> = entries.iter()
+    .filter_map(|entry| entry.path.components().next())
+    .map(|top| PathBuf::from(top.as_os_str()))
+    .collect();
+  if tops.len() != 1 {
+    return None;
+  }
+  //a lone file at the top isn't a folder
+  let top = tops.into_iter().next()?;
+  if entries.iter().any(|entry| entry.path == top && !entry.is_dir) {
+    return None;
+  }
+  Some(top)
+}
+
+//A subdirectory picked out of the archive has to stay inside it
+pub fn subdirectory(extracted: &Path, picked: &Path) -> io::Result<PathBuf> {
+  let inside = !picked.as_os_str().is_empty()
+    && picked.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+  let path = extracted.join(picked);
+  if !inside || !path.is_dir() {
+    return Err(Error::new(ErrorCode::InvalidArchivePath, format!("'{}' isn't a folder in the archive", picked.display()))
+      .param("path", picked.display().to_string())
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  Ok(path)
+}
+
+//Folders and file types mods put straight into the directory they deploy to,
+//finding one of them means the layout is right already
+const KNOWN_DIRS: &[&str] = &[
+  "meshes", "textures", "scripts", "interface", "sound", "music", "strings", "video", "materials",
+  "shaders", "seq", "skse", "f4se", "obse", "nvse", "mcm", "fomod", "bepinex", "plugins", "mods",
+];
+const KNOWN_EXTENSIONS: &[&str] = &["esp", "esm", "esl", "bsa", "ba2", "pak", "dll", "asi"];
+
+fn children(dir: &Path) -> io::Result<Vec<(String, PathBuf, bool)>> {
+  let mut children = Vec::new();
+  for entry in dir.read_dir()? {
+    let entry = entry?;
+    children.push((entry.file_name().to_string_lossy().to_lowercase(), entry.path(), entry.file_type()?.is_dir()));
+  }
+  Ok(children)
+}
+
+fn is_content(game_dirs: &[String], name: &str, is_dir: bool) -> bool {
+  if is_dir {
+    name == ROOT_DIR_NAME || KNOWN_DIRS.contains(&name) || game_dirs.iter().any(|game_dir| game_dir == name)
+  } else {
+    name == META_FILE_NAME || has_extension(Path::new(name), KNOWN_EXTENSIONS)
+  }
+}
+
+//Finds the folder inside the extracted archive that should become the mod and moves things
+//around so it deploys right. Folders wrapping everything get skipped until something the game
+//or these lists know shows up. If there's a folder named like the last part of the path
+//extension, the archive is laid out from the game's root: that folder becomes the mod and the
+//things next to it go into its root folder
+pub fn restructure(extracted: &Path, game: &Game) -> io::Result<PathBuf> {
+  let target = game.install_path.join(&game.path_extension);
+  let game_dirs: Vec<String> = match children(&target) {
+    Ok(children) => children.into_iter().filter(|child| child.2).map(|child| child.0).collect(),
+    Err(_) => Vec::new(),
+  };
+  let data_dir = game.path_extension.file_name().map(|name| name.to_string_lossy().to_lowercase());
+  let mut dir = extracted.to_path_buf();
+  loop {
+    let children = children(&dir)?;
+    if let Some(data) = children.iter().find(|child| child.2 && Some(&child.0) == data_dir.as_ref()) {
+      let data = data.1.clone();
+      for (name, path, _) in children.iter().filter(|child| child.1 != data) {
+        //a root folder next to it becomes its root folder
+        let moved = if name == META_FILE_NAME || (name == ROOT_DIR_NAME && !data.join(ROOT_DIR_NAME).exists()) {
+          data.join(path.file_name().unwrap())
+        } else {
+          data.join(ROOT_DIR_NAME).join(path.file_name().unwrap())
+        };
+        if let Some(parent) = moved.parent() {
+          fs::create_dir_all(parent)?;
+        }
+        fs::rename(path, moved)?;
+      }
+      return Ok(data);
+    }
+    if children.iter().any(|(name, _, is_dir)| is_content(&game_dirs, name, *is_dir)) {
+      return Ok(dir);
+    }
+    match children.as_slice() {
+      [(_, path, true)] => dir = path.clone(),
+      _ => return Ok(dir),
+    }
+  }
+}
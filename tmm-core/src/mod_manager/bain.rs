@@ -0,0 +1,111 @@
+use crate::mod_manager::{get_files, link};
+use crate::error::{Error, ErrorCode};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//Wrye Bash's layout: numbered folders like '00 Core' and '01 Optional HD', installed
+//in that order with later ones overwriting earlier ones
+pub fn is_package_name(name: &str) -> bool {
+  let digits = name.chars().take_while(|c| c.is_ascii_digit()).count();
+  digits >= 2 && name[digits..].starts_with([' ', '-', '_', '.'])
+}
+
+//Loose files next to the packages are readmes and wizard scripts, so only the folders count.
+//It takes two numbered folders to be sure it's not just a mod with a folder like '01 Music'
+pub fn package_names<'a>(dirs: impl Iterator<Item = &'a str>) -> Option<Vec<String>> {
+  let dirs: Vec<&str> = dirs.collect();
+  let mut packages: Vec<String> = dirs.iter().filter(|dir| is_package_name(dir)).map(|dir| dir.to_string()).collect();
+  if packages.len() < 2 || packages.len() != dirs.len() {
+    return None;
+  }
+  packages.sort();
+  Some(packages)
+}
+
+fn child_dirs(dir: &Path) -> io::Result<Vec<String>> {
+  let mut dirs: Vec<String> = Vec::new();
+  for entry in dir.read_dir()? {
+    let entry = entry?;
+    if entry.file_type()?.is_dir() {
+      dirs.push(entry.file_name().to_string_lossy().to_string());
+    }
+  }
+  Ok(dirs)
+}
+
+//The folder holding the packages and what they are, looking through folders wrapping everything
+pub fn find(extracted: &Path) -> io::Result<Option<(PathBuf, Vec<String>)>> {
+  let mut dir = extracted.to_path_buf();
+  loop {
+    let dirs = child_dirs(&dir)?;
+    if let Some(packages) = package_names(dirs.iter().map(String::as_str)) {
+      return Ok(Some((dir, packages)));
+    }
+    match dirs.as_slice() {
+      [only] if fs::read_dir(&dir)?.count() == 1 => dir = dir.join(only),
+      _ => return Ok(None),
+    }
+  }
+}
+
+//Without a choice it's the core packages, numbered 00, or all of them if there is none
+pub fn default_packages(packages: &[String]) -> Vec<String> {
+  let core: Vec<String> = packages.iter().filter(|package| package.starts_with("00")).cloned().collect();
+  if core.is_empty() { packages.to_vec() } else { core }
+}
+
+//Moves the picked packages into one folder in the scratch dir they were extracted to, in package order
+pub fn merge(dir: &Path, packages: &[String], picked: &[String], scratch: &Path) -> io::Result<PathBuf> {
+  if let Some(unknown) = picked.iter().find(|package| !packages.contains(package)) {
+    return Err(Error::new(ErrorCode::InvalidArchivePath, format!("'{}' isn't a package of the archive", unknown))
+      .param("path", unknown)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  let merged = scratch.join(".tmm_bain_merged");
+  fs::create_dir_all(&merged)?;
+  for package in packages.iter().filter(|package| picked.contains(package)) {
+    let package_dir = dir.join(package);
+    for file in get_files(&package_dir)? {
+      let target = merged.join(link::relative(&file, &package_dir)?);
+      if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      if fs::symlink_metadata(&target).is_ok() {
+        fs::remove_file(&target)?;
+      }
+      fs::rename(&file, &target)?;
+    }
+  }
+  Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn package_names_are_numbered() {
+    assert!(is_package_name("00 Core"));
+    assert!(is_package_name("10-Optional"));
+    assert!(is_package_name("01_HD"));
+    assert!(!is_package_name("0 Core"));
+    assert!(!is_package_name("00Core"));
+    assert!(!is_package_name("Textures"));
+  }
+
+  #[test]
+  fn it_takes_two_packages_and_nothing_else() {
+    let packages = package_names(["10 Patches", "00 Core", "01 Optional HD"].into_iter());
+    assert_eq!(packages.unwrap(), ["00 Core", "01 Optional HD", "10 Patches"]);
+    assert!(package_names(["01 Music"].into_iter()).is_none());
+    assert!(package_names(["00 Core", "01 Optional", "Docs"].into_iter()).is_none());
+  }
+
+  #[test]
+  fn core_packages_by_default() {
+    let packages = vec!["00 Core".to_string(), "00 Core Patches".to_string(), "01 Optional".to_string()];
+    assert_eq!(default_packages(&packages), ["00 Core", "00 Core Patches"]);
+    let packages = vec!["01 Textures".to_string(), "02 Meshes".to_string()];
+    assert_eq!(default_packages(&packages), packages);
+  }
+}
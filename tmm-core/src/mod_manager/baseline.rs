@@ -0,0 +1,89 @@
+use crate::mod_manager::{get_files, link};
+use crate::mod_manager::game::Game;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//What the game directory looked like right before mods got deployed,
+//only metadata so taking it is cheap even for big games
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+  //relative path -> (size, modified time in nanoseconds, is a symlink)
+  files: BTreeMap<PathBuf, (u64, i128, bool)>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeReport {
+  //false if there was no baseline to compare against
+  verified: bool,
+  //files of the baseline that are gone
+  missing: Vec<PathBuf>,
+  //files that are there but differ from the baseline
+  changed: Vec<PathBuf>,
+  //files that weren't there before, e.g. saves or logs the game wrote
+  unexpected: Vec<PathBuf>,
+}
+
+impl PurgeReport {
+  //The game's own files are all as they were, whatever the game wrote itself aside
+  pub fn restored(&self) -> bool {
+    self.missing.is_empty() && self.changed.is_empty()
+  }
+}
+
+fn baseline_path(game: &Game) -> PathBuf {
+  game.profile_path.join("baseline.json")
+}
+
+fn snapshot(root: &Path) -> io::Result<Baseline> {
+  let mut baseline = Baseline::default();
+  for file in get_files(root)? {
+    let metadata = fs::symlink_metadata(&file)?;
+    let modified = metadata.mtime() as i128 * 1_000_000_000 + metadata.mtime_nsec() as i128;
+    let relative = link::relative(&file, root)?.to_path_buf();
+    baseline.files.insert(relative, (metadata.len(), modified, metadata.file_type().is_symlink()));
+  }
+  Ok(baseline)
+}
+
+//Has to be called while nothing is deployed
+pub fn record(game: &Game) -> io::Result<()> {
+  let baseline = snapshot(&game.install_path)?;
+  fs::write(baseline_path(game), serde_json::to_string(&baseline)?)
+}
+
+//The game's own files relative to the install path, None without a baseline
+pub fn vanilla_files(game: &Game) -> io::Result<Option<Vec<PathBuf>>> {
+  let path = baseline_path(game);
+  if !path.exists() {
+    return Ok(None);
+  }
+  let baseline: Baseline = serde_json::from_str(&fs::read_to_string(path)?)?;
+  Ok(Some(baseline.files.into_keys().collect()))
+}
+
+pub fn verify(game: &Game) -> io::Result<PurgeReport> {
+  let path = baseline_path(game);
+  if !path.exists() {
+    return Ok(PurgeReport::default());
+  }
+  let baseline: Baseline = serde_json::from_str(&fs::read_to_string(path)?)?;
+  let current = snapshot(&game.install_path)?;
+  let mut report = PurgeReport { verified: true, ..PurgeReport::default() };
+
+  for (file, expected) in &baseline.files {
+    match current.files.get(file) {
+      Some(actual) if actual == expected => {},
+      Some(_) => report.changed.push(file.clone()),
+      None => report.missing.push(file.clone()),
+    }
+  }
+  for file in current.files.keys() {
+    if !baseline.files.contains_key(file) {
+      report.unexpected.push(file.clone());
+    }
+  }
+  Ok(report)
+}
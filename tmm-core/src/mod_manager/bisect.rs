@@ -0,0 +1,131 @@
+use crate::mod_manager::{Mod, deploy_mods};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::session;
+use crate::mod_manager::deploy::DeployReport;
+use crate::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::{fs, io};
+
+use crate::events::{BackendEvent, Events};
+
+//Binary search for the mod that makes the game crash. It assumes the full set
+//crashes and exactly one mod is to blame: every step disables half of the
+//remaining suspects and the verdict tells which half the culprit is in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bisection {
+  //the enabled mods the bisection started from, in load order
+  mods: Vec<Mod>,
+  //mods that could still be the culprit
+  suspects: Vec<String>,
+  //the suspects that stay enabled in the current step
+  candidate: Vec<String>,
+  history: Vec<Step>,
+  found: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+  candidate: Vec<String>,
+  crashed: bool,
+}
+
+impl Bisection {
+  pub fn start(mods: Vec<Mod>) -> io::Result<Bisection> {
+    if mods.is_empty() {
+      return Err(Error::new(ErrorCode::NothingToBisect, "there are no enabled mods to bisect").into_io(io::ErrorKind::InvalidInput));
+    }
+    let suspects: Vec<String> = mods.iter().map(|elem| elem.name.clone()).collect();
+    let mut bisection = Bisection { mods, suspects, candidate: Vec::new(), history: Vec::new(), found: None };
+    bisection.split();
+    Ok(bisection)
+  }
+
+  fn split(&mut self) {
+    if self.suspects.len() == 1 {
+      self.found = self.suspects.first().cloned();
+      self.candidate.clear();
+    } else {
+      self.candidate = self.suspects[..self.suspects.len() / 2].to_vec();
+    }
+  }
+
+  //Every mod except the suspects that are left out in this step,
+  //mods that were already ruled out stay enabled
+  pub fn candidate_mods(&self) -> Vec<Mod> {
+    self.mods.iter()
+      .filter(|elem| !self.suspects.contains(&elem.name) || self.candidate.contains(&elem.name))
+      .cloned()
+      .collect()
+  }
+
+  pub fn verdict(&mut self, crashed: bool) -> io::Result<()> {
+    if self.found.is_some() {
+      return Err(Error::new(ErrorCode::BisectionFinished, "the bisection is already finished")
+        .param("mod_name", &self.found)
+        .into_io(io::ErrorKind::InvalidInput));
+    }
+    self.history.push(Step { candidate: self.candidate.clone(), crashed });
+    if crashed {
+      self.suspects = self.candidate.clone();
+    } else {
+      self.suspects.retain(|name| !self.candidate.contains(name));
+    }
+    self.split();
+    Ok(())
+  }
+
+  pub fn original_mods(&self) -> &[Mod] {
+    &self.mods
+  }
+
+  fn path(game: &Game) -> PathBuf {
+    game.profile_path.join("bisect.json")
+  }
+
+  pub fn load(game: &Game) -> io::Result<Option<Bisection>> {
+    let path = Bisection::path(game);
+    if !path.exists() {
+      return Ok(None);
+    }
+    let contents = fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+  }
+
+  pub fn save(&self, game: &Game) -> io::Result<()> {
+    fs::write(Bisection::path(game), serde_json::to_string(self)?)
+  }
+
+  pub fn remove(game: &Game) -> io::Result<()> {
+    let path = Bisection::path(game);
+    if path.exists() {
+      fs::remove_file(path)?;
+    }
+    Ok(())
+  }
+}
+
+pub fn current(game: &Game) -> io::Result<Bisection> {
+  match Bisection::load(game)? {
+    Some(bisection) => Ok(bisection),
+    None => Err(Error::new(ErrorCode::NoBisection, "there is no bisection running for this game")
+      .param("appid", game.appid)
+      .into_io(io::ErrorKind::NotFound)),
+  }
+}
+
+pub fn deploy_candidate(game: &Game, events: &Events) -> io::Result<DeployReport> {
+  let bisection = current(game)?;
+  deploy_mods(game, &bisection.candidate_mods(), events)
+}
+
+//Launches the current step in a test session, the verdict
+//still has to come from the user afterwards
+pub fn test_candidate(game: &Game, events: &Events) -> io::Result<()> {
+  let bisection = current(game)?;
+  let result = session::run_isolated(game, bisection.original_mods(), &bisection.candidate_mods(), events, || {
+    events.send(BackendEvent::BisectTestStarted(bisection.candidate.clone()));
+  });
+  events.send(BackendEvent::BisectTestFinished(bisection.candidate.clone()));
+  result
+}
@@ -0,0 +1,75 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//A mod path that can't be mapped onto the game's tree, because
+//more than one existing entry only differs from it by case
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseConflict {
+  pub mod_name: String,
+  pub path: PathBuf,
+  pub candidates: Vec<PathBuf>,
+}
+
+//Windows games don't care about case, so under Proton `Textures/` and `textures/`
+//are the same directory. This maps mod paths onto whatever casing the game's
+//tree (plus everything deployed so far) already uses
+pub struct CaseResolver {
+  root: PathBuf,
+  //relative dir -> lowercase name -> the actual names with that spelling
+  listings: HashMap<PathBuf, HashMap<String, Vec<String>>>,
+}
+
+impl CaseResolver {
+  pub fn new(root: PathBuf) -> CaseResolver {
+    CaseResolver { root, listings: HashMap::new() }
+  }
+
+  fn listing(&mut self, dir: &Path) -> io::Result<&mut HashMap<String, Vec<String>>> {
+    if !self.listings.contains_key(dir) {
+      let mut listing: HashMap<String, Vec<String>> = HashMap::new();
+      let path = self.root.join(dir);
+      if path.is_dir() {
+        for entry in path.read_dir()? {
+          let name = entry?.file_name().to_string_lossy().to_string();
+          listing.entry(name.to_lowercase()).or_default().push(name);
+        }
+      }
+      self.listings.insert(dir.to_path_buf(), listing);
+    }
+    Ok(self.listings.get_mut(dir).unwrap())
+  }
+
+  //The inner Err holds the existing paths an ambiguous one could mean
+  pub fn resolve(&mut self, relative: &Path) -> io::Result<Result<PathBuf, Vec<PathBuf>>> {
+    let mut resolved = PathBuf::new();
+    for component in relative.components() {
+      let name = component.as_os_str().to_string_lossy().to_string();
+      let matches = self.listing(&resolved)?.get(&name.to_lowercase()).cloned().unwrap_or_default();
+      match matches.len() {
+        0 => resolved.push(&name),
+        1 => resolved.push(&matches[0]),
+        _ => {
+          return Ok(Err(matches.iter().map(|candidate| resolved.join(candidate)).collect()));
+        }
+      }
+    }
+    Ok(Ok(resolved))
+  }
+
+  //Has to be called for every path that gets created, so later
+  //mods line up with what earlier ones put there
+  pub fn added(&mut self, resolved: &Path) -> io::Result<()> {
+    let mut dir = PathBuf::new();
+    for component in resolved.components() {
+      let name = component.as_os_str().to_string_lossy().to_string();
+      let spellings = self.listing(&dir)?.entry(name.to_lowercase()).or_default();
+      if !spellings.contains(&name) {
+        spellings.push(name.clone());
+      }
+      dir.push(name);
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,78 @@
+use crate::mod_manager::game::{Game, GameConfigFile, ConfigBase};
+use crate::mod_manager::reflink;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//The profile's copies of the game's INIs, 'configs/user/...' for the ones in the prefix's
+//Windows user and 'configs/game/...' for the ones in the game directory
+fn profile_copy(profile_path: &Path, file: &GameConfigFile) -> PathBuf {
+  let base = match file.base {
+    ConfigBase::User => "user",
+    ConfigBase::Game => "game",
+  };
+  profile_path.join("configs").join(base).join(&file.path)
+}
+
+fn live_path(game: &Game, file: &GameConfigFile) -> Option<PathBuf> {
+  match file.base {
+    //native games don't have a prefix, their configs are in the home
+    ConfigBase::User if game.uses_compatibility() => Some(game.prefix_user_dir()?.join(&file.path)),
+    ConfigBase::User => Some(dirs::home_dir()?.join(&file.path)),
+    ConfigBase::Game => Some(game.install_path.join(&file.path)),
+  }
+}
+
+//Written next to it and renamed over it, the game might hold the old one open
+fn replace(source: &Path, target: &Path) -> io::Result<()> {
+  if let Some(parent) = target.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let temporary = target.with_file_name(format!(".{}.tmm", target.file_name().unwrap().to_string_lossy()));
+  reflink::copy(source, &temporary)?;
+  fs::rename(temporary, target)
+}
+
+//Puts the profile's copies in place before the game starts. A profile without a copy yet takes
+//the file as it is, that way a new profile starts with the settings there were. One that's
+//different from the copy was changed since it was put there, in the game or by hand, and that
+//goes into the profile instead of getting lost
+pub fn deploy(game: &Game) -> io::Result<()> {
+  put_in_place(game, true)
+}
+
+//Right after switching profiles, what's there is the previous profile's and sync_back took it already
+pub fn deploy_switched(game: &Game) -> io::Result<()> {
+  put_in_place(game, false)
+}
+
+fn put_in_place(game: &Game, capture: bool) -> io::Result<()> {
+  for file in &game.config_files {
+    let live = match live_path(game, file) {
+      Some(live) => live,
+      None => continue,
+    };
+    let copy = profile_copy(&game.profile_path, file);
+    if copy.is_file() && capture && live.is_file() && fs::read(&live)? != fs::read(&copy)? {
+      tracing::info!("'{}' was changed outside of the profile, keeping it", live.display());
+      replace(&live, &copy)?;
+    } else if copy.is_file() {
+      replace(&copy, &live)?;
+    } else if live.is_file() {
+      replace(&live, &copy)?;
+    }
+  }
+  Ok(())
+}
+
+//What the game or the user changed goes back into the profile, after the game exited
+//and before another profile's copies replace it
+pub fn sync_back(game: &Game) -> io::Result<()> {
+  for file in &game.config_files {
+    if let Some(live) = live_path(game, file) {
+      if live.is_file() {
+        replace(&live, &profile_copy(&game.profile_path, file))?;
+      }
+    }
+  }
+  Ok(())
+}
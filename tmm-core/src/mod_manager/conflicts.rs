@@ -0,0 +1,228 @@
+use crate::mod_manager::{Mod, get_files, link, mod_dir};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{merge, overrides};
+use crate::mod_manager::excludes::{Excludes, Filtered};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+#[derive(Debug, Serialize)]
+pub struct DeployPreview {
+  //path in the game directory -> the mod it would come from
+  files: BTreeMap<PathBuf, String>,
+  pub conflicts: Vec<Conflict>,
+  shadowed: Vec<ShadowedFiles>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Conflict {
+  //relative to the game's path extension, like inside the mods
+  path: PathBuf,
+  winner: String,
+  //the other mods shipping the path, in load order
+  overridden: Vec<String>,
+  //config files ending up in the merged configs instead of one mod winning
+  merged: bool,
+  //the winner comes from an override rule instead of the load order
+  pinned: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShadowedFiles {
+  mod_name: String,
+  files: Vec<PathBuf>,
+  //none of the mod's files would make it into the game directory
+  fully_shadowed: bool,
+}
+
+//A file that comes from another mod than before, e.g. after reordering
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnerChange {
+  path: PathBuf,
+  from: String,
+  to: String,
+}
+
+//Relative path -> every mod shipping it, in load order
+//Excluded files don't count, they never get deployed
+pub fn index(game: &Game, mods: &[Mod]) -> io::Result<BTreeMap<PathBuf, Vec<String>>> {
+  let excludes = Excludes::for_game(game)?;
+  let mut providers: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+  for elem in mods {
+    let mod_root = mod_dir(game, elem)?;
+    for source in get_files(&mod_root)? {
+      let relative = link::relative(&source, &mod_root)?.to_path_buf();
+      if excludes.filter(elem, &relative) != Filtered::Deployed {
+        continue;
+      }
+      providers.entry(relative).or_default().push(elem.name.clone());
+    }
+  }
+  Ok(providers)
+}
+
+//What deploying the mods would do, without touching anything on disk
+pub fn preview(game: &Game, mods: &[Mod]) -> io::Result<DeployPreview> {
+  let target_root = game.install_path.join(&game.path_extension);
+  let mut files: BTreeMap<PathBuf, String> = BTreeMap::new();
+  let mut conflicts: Vec<Conflict> = Vec::new();
+  let mut shadowed: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+  let mut provided: BTreeMap<String, usize> = BTreeMap::new();
+  let rules = overrides::load(game)?;
+
+  for (path, providers) in index(game, mods)? {
+    for provider in &providers {
+      *provided.entry(provider.clone()).or_default() += 1;
+    }
+    let pinned = overrides::pinned(&rules, &path, &providers);
+    let winner = pinned.unwrap_or_else(|| providers.last().unwrap()).to_string();
+    files.insert(target_root.join(&path), winner.clone());
+    if providers.len() > 1 {
+      let merged = pinned.is_none() && merge::is_mergeable(&path);
+      let overridden: Vec<String> = providers.iter().filter(|provider| **provider != winner).cloned().collect();
+      if !merged {
+        for loser in &overridden {
+          shadowed.entry(loser.clone()).or_default().push(path.clone());
+        }
+      }
+      conflicts.push(Conflict { path, winner, overridden, merged, pinned: pinned.is_some() });
+    }
+  }
+
+  let shadowed = shadowed.into_iter()
+    .map(|(mod_name, files)| {
+      let fully_shadowed = provided.get(&mod_name) == Some(&files.len());
+      ShadowedFiles { mod_name, files, fully_shadowed }
+    })
+    .collect();
+  Ok(DeployPreview { files, conflicts, shadowed })
+}
+
+//The mods winning at least one conflict and the ones losing at least one, merged configs count for neither
+pub fn involvement(game: &Game, mods: &[Mod]) -> io::Result<(BTreeSet<String>, BTreeSet<String>)> {
+  let mut winning: BTreeSet<String> = BTreeSet::new();
+  let mut losing: BTreeSet<String> = BTreeSet::new();
+  for conflict in preview(game, mods)?.conflicts.into_iter().filter(|conflict| !conflict.merged) {
+    winning.insert(conflict.winner);
+    losing.extend(conflict.overridden);
+  }
+  Ok((winning, losing))
+}
+
+//Relative path -> the mod it gets deployed from
+fn winners(game: &Game, mods: &[Mod]) -> io::Result<BTreeMap<PathBuf, String>> {
+  let rules = overrides::load(game)?;
+  Ok(index(game, mods)?.into_iter()
+    .map(|(path, providers)| {
+      let winner = overrides::pinned(&rules, &path, &providers).unwrap_or_else(|| providers.last().unwrap()).to_string();
+      (path, winner)
+    })
+    .collect())
+}
+
+//Files only in one of the two lists aren't changes of owner, the set of mods is the same when reordering
+pub fn owner_changes(game: &Game, before: &[Mod], after: &[Mod]) -> io::Result<Vec<OwnerChange>> {
+  let before = winners(game, before)?;
+  let mut changes: Vec<OwnerChange> = Vec::new();
+  for (path, to) in winners(game, after)? {
+    if let Some(from) = before.get(&path) {
+      if *from != to {
+        changes.push(OwnerChange { path, from: from.clone(), to });
+      }
+    }
+  }
+  Ok(changes)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum FileStatus {
+  //no other enabled mod ships it
+  Unique,
+  Winning { overridden: Vec<String>, pinned: bool },
+  Overridden { winner: String, pinned: bool },
+  //goes into the merged config with the other mods' versions
+  Merged { others: Vec<String> },
+  //goes to the game's root, no conflicts are tracked there
+  Root,
+  //never deployed, an exclude pattern or the manager's own file
+  Excluded,
+  //hidden by the user
+  Hidden,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileNode {
+  name: String,
+  //relative to the mod
+  path: PathBuf,
+  size: u64,
+  //directories have children and no status
+  #[serde(skip_serializing_if = "Option::is_none")]
+  status: Option<FileStatus>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  children: Vec<FileNode>,
+}
+
+fn status(elem: &Mod, relative: &Path, providers: &BTreeMap<PathBuf, Vec<String>>, rules: &[overrides::OverrideRule], excludes: &Excludes) -> FileStatus {
+  let name = elem.name.as_str();
+  match excludes.filter(elem, relative) {
+    Filtered::Hidden => return FileStatus::Hidden,
+    Filtered::Root => return FileStatus::Root,
+    _ => {},
+  }
+  let providers = match providers.get(relative) {
+    Some(providers) => providers,
+    None => return FileStatus::Excluded,
+  };
+  let others: Vec<String> = providers.iter().filter(|provider| *provider != name).cloned().collect();
+  if others.is_empty() {
+    return FileStatus::Unique;
+  }
+  let pinned = overrides::pinned(rules, relative, providers);
+  if pinned.is_none() && merge::is_mergeable(relative) {
+    return FileStatus::Merged { others };
+  }
+  let winner = pinned.unwrap_or_else(|| providers.last().unwrap());
+  if winner == name {
+    FileStatus::Winning { overridden: others, pinned: pinned.is_some() }
+  } else {
+    FileStatus::Overridden { winner: winner.to_string(), pinned: pinned.is_some() }
+  }
+}
+
+fn tree(dir: &Path, mod_root: &Path, elem: &Mod, providers: &BTreeMap<PathBuf, Vec<String>>, rules: &[overrides::OverrideRule], excludes: &Excludes) -> io::Result<Vec<FileNode>> {
+  let mut nodes: Vec<FileNode> = Vec::new();
+  for entry in dir.read_dir()? {
+    let entry = entry?;
+    let path = entry.path();
+    let relative = link::relative(&path, mod_root)?.to_path_buf();
+    let node_name = entry.file_name().to_string_lossy().to_string();
+    if entry.file_type()?.is_dir() {
+      let children = tree(&path, mod_root, elem, providers, rules, excludes)?;
+      let size = children.iter().map(|child| child.size).sum();
+      nodes.push(FileNode { name: node_name, path: relative, size, status: None, children });
+    } else {
+      let size = fs::symlink_metadata(&path)?.len();
+      let status = status(elem, &relative, providers, rules, excludes);
+      nodes.push(FileNode { name: node_name, path: relative, size, status: Some(status), children: Vec::new() });
+    }
+  }
+  //directories first, like file managers do
+  nodes.sort_by(|a, b| b.status.is_none().cmp(&a.status.is_none()).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+  Ok(nodes)
+}
+
+//The mod's files with how each of them fares against the other mods. A disabled
+//mod gets compared as if it was enabled where it is in the load order
+pub fn mod_files(game: &Game, mods: &[Mod], mod_name: &str) -> io::Result<Vec<FileNode>> {
+  let compared: Vec<Mod> = mods.iter().filter(|elem| elem.enabled || elem.name == mod_name).cloned().collect();
+  let providers = index(game, &compared)?;
+  let rules = overrides::load(game)?;
+  let excludes = Excludes::for_game(game)?;
+  let elem = compared.iter().find(|elem| elem.name == mod_name)
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("'{}' isn't installed", mod_name)))?;
+  let mod_root = mod_dir(game, elem)?;
+  tree(&mod_root, &mod_root, elem, &providers, &rules, &excludes)
+}
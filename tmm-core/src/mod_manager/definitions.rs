@@ -0,0 +1,116 @@
+use crate::mod_manager::excludes;
+use crate::mod_manager::scan::SupportedGame;
+use crate::error::{Context, Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::{fs, io};
+
+//A file holds either one game or a list of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DefinitionFile {
+  One(SupportedGame),
+  Many(Vec<SupportedGame>),
+}
+
+//The games users defined themselves, broken files and entries end up in errors
+//instead of failing the whole scan
+#[derive(Serialize)]
+pub struct Definitions {
+  pub games: Vec<SupportedGame>,
+  pub errors: Vec<Error>,
+}
+
+//Users add support for a game by dropping its definition in here
+pub fn definitions_dir() -> io::Result<PathBuf> {
+  Ok(crate::config_root()?.join("supported_games.d"))
+}
+
+fn invalid(path: &Path, message: String) -> Error {
+  Error::new(ErrorCode::InvalidGameDefinition, message).context(Context::GameDefinitions).param("path", path)
+}
+
+//Paths in a definition are relative to the install path, so nothing may climb out of it
+fn inside_game(path: &Path) -> bool {
+  path.components().all(|component| matches!(component, Component::RootDir | Component::CurDir | Component::Normal(_)))
+}
+
+fn validate(game: &SupportedGame) -> Result<(), String> {
+  if game.app_id == 0 {
+    return Err("the app_id is missing".to_string());
+  }
+  if game.public_name.trim().is_empty() {
+    return Err(format!("{} has no public_name", game.app_id));
+  }
+  if !inside_game(&game.path_extension) {
+    return Err(format!("the path_extension '{}' of {} leaves the game directory", game.path_extension.display(), game.app_id));
+  }
+  for binary in &game.known_binaries {
+    if binary.binary_path.as_os_str().is_empty() || !inside_game(&binary.binary_path) || !inside_game(&binary.startin_path) {
+      return Err(format!("the binary '{}' of {} isn't a path inside the game directory", binary.name, game.app_id));
+    }
+  }
+  if game.save_path.as_ref().map_or(false, |save_path| save_path.is_absolute() || !inside_game(save_path)) {
+    return Err(format!("the save_path of {} has to be relative to the Windows user", game.app_id));
+  }
+  if let Some(file) = game.config_files.iter().find(|file| file.path.is_absolute() || !inside_game(&file.path) || file.path.as_os_str().is_empty()) {
+    return Err(format!("the config file '{}' of {} isn't a relative path", file.path.display(), game.app_id));
+  }
+  if game.load_order_file.as_ref().map_or(false, |file| file.path.is_absolute() || !inside_game(&file.path) || file.path.as_os_str().is_empty()) {
+    return Err(format!("the load_order_file of {} has to be relative to the Windows user", game.app_id));
+  }
+  excludes::validate(&game.deploy_excludes).map_err(|e| format!("the deploy_excludes of {} are invalid: {}", game.app_id, e))
+}
+
+fn load_file(path: &Path) -> io::Result<Vec<SupportedGame>> {
+  let games = match serde_json::from_str(&fs::read_to_string(path)?)? {
+    DefinitionFile::One(game) => vec![game],
+    DefinitionFile::Many(games) => games,
+  };
+  Ok(games)
+}
+
+pub fn load() -> Definitions {
+  let mut definitions = Definitions { games: Vec::new(), errors: Vec::new() };
+  let entries = match definitions_dir().and_then(|dir| dir.read_dir()) {
+    Ok(entries) => entries,
+    Err(_) => return definitions,
+  };
+  let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path())
+    .filter(|path| path.extension().map_or(false, |extension| extension == "json"))
+    .collect();
+  //later files win, so the order has to be the same every time
+  paths.sort();
+  for path in paths {
+    let games = match load_file(&path) {
+      Ok(games) => games,
+      Err(e) => {
+        tracing::warn!("Skipping the game definitions in '{}': {}", path.display(), e);
+        definitions.errors.push(invalid(&path, e.to_string()));
+        continue;
+      }
+    };
+    for game in games {
+      match validate(&game) {
+        Ok(()) => {
+          definitions.games.retain(|other| other.app_id != game.app_id);
+          definitions.games.push(game);
+        }
+        Err(message) => {
+          tracing::warn!("Skipping a game definition in '{}': {}", path.display(), message);
+          definitions.errors.push(invalid(&path, message));
+        }
+      }
+    }
+  }
+  definitions
+}
+
+//The user's definitions replace bundled ones for the same game
+pub fn merge(bundled: Vec<SupportedGame>, user: Vec<SupportedGame>) -> Vec<SupportedGame> {
+  let mut games: Vec<SupportedGame> = bundled.into_iter()
+    .filter(|game| !user.iter().any(|other| other.app_id == game.app_id))
+    .collect();
+  games.extend(user);
+  games
+}
@@ -0,0 +1,201 @@
+use crate::mod_manager::Mod;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+//Another mod a mod needs, declared in its meta.json. Matched by the source's mod id
+//if there is one, otherwise by the mod's name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dependency {
+  #[serde(default)]
+  pub mod_id: Option<String>,
+  #[serde(default)]
+  pub name: Option<String>,
+  //comparisons joined by commas, e.g. '>=1.2, <2', no range takes any version
+  #[serde(default)]
+  pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case", tag = "reason")]
+pub enum Unmet {
+  NotInstalled,
+  Disabled { provider: String },
+  WrongVersion { provider: String, installed: Option<String> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingDependency {
+  pub mod_name: String,
+  pub dependency: Dependency,
+  #[serde(flatten)]
+  pub unmet: Unmet,
+}
+
+fn provides(elem: &Mod, dependency: &Dependency) -> bool {
+  match (&dependency.mod_id, &dependency.name) {
+    (Some(mod_id), _) if elem.meta.mod_id.is_some() => elem.meta.mod_id.as_ref() == Some(mod_id),
+    (_, Some(name)) => elem.name.eq_ignore_ascii_case(name),
+    _ => false,
+  }
+}
+
+//Versions get compared number by number, whatever isn't a number compares as text
+fn compare_versions(a: &str, b: &str) -> Ordering {
+  let parts = |version: &str| -> Vec<String> {
+    version.trim().trim_start_matches('v').split(['.', '-']).map(str::to_string).collect()
+  };
+  let (a, b) = (parts(a), parts(b));
+  for index in 0..a.len().max(b.len()) {
+    let left = a.get(index).map(String::as_str).unwrap_or("0");
+    let right = b.get(index).map(String::as_str).unwrap_or("0");
+    let ordering = match (left.parse::<u64>(), right.parse::<u64>()) {
+      (Ok(left), Ok(right)) => left.cmp(&right),
+      _ => left.cmp(right),
+    };
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+  Ordering::Equal
+}
+
+fn satisfies(installed: Option<&str>, range: &str) -> bool {
+  let installed = match installed {
+    Some(installed) => installed,
+    None => return false,
+  };
+  range.split(',').map(str::trim).filter(|part| !part.is_empty()).all(|part| {
+    let (operator, version) = match part.find(|c: char| !"<>=!".contains(c)) {
+      Some(index) => part.split_at(index),
+      None => return false,
+    };
+    let ordering = compare_versions(installed, version);
+    match operator.trim() {
+      ">=" => ordering != Ordering::Less,
+      ">" => ordering == Ordering::Greater,
+      "<=" => ordering != Ordering::Greater,
+      "<" => ordering == Ordering::Less,
+      "!=" => ordering != Ordering::Equal,
+      "=" | "==" | "" => ordering == Ordering::Equal,
+      _ => false,
+    }
+  })
+}
+
+//Which dependencies of the enabled mods aren't installed, enabled or the right version
+pub fn check(mods: &[Mod]) -> Vec<MissingDependency> {
+  let mut missing: Vec<MissingDependency> = Vec::new();
+  for elem in mods.iter().filter(|elem| elem.enabled) {
+    for dependency in &elem.meta.dependencies {
+      let providers: Vec<&Mod> = mods.iter().filter(|other| other.name != elem.name && provides(other, dependency)).collect();
+      let fitting = providers.iter().find(|provider| {
+        dependency.version.as_ref().map_or(true, |range| satisfies(provider.meta.version.as_deref(), range))
+      });
+      let unmet = match (fitting, providers.first()) {
+        (Some(provider), _) if provider.enabled => continue,
+        (Some(provider), _) => Unmet::Disabled { provider: provider.name.clone() },
+        (None, Some(provider)) => Unmet::WrongVersion { provider: provider.name.clone(), installed: provider.meta.version.clone() },
+        (None, None) => Unmet::NotInstalled,
+      };
+      missing.push(MissingDependency { mod_name: elem.name.clone(), dependency: dependency.clone(), unmet });
+    }
+  }
+  missing
+}
+
+//Moves dependencies in front of the mods needing them and keeps everything else where it was,
+//mods depending on each other in a circle are left in their order
+pub fn sort(mods: &[Mod]) -> Vec<Mod> {
+  let mut sorted: Vec<Mod> = Vec::new();
+  let mut visiting: Vec<String> = Vec::new();
+  fn visit(elem: &Mod, mods: &[Mod], sorted: &mut Vec<Mod>, visiting: &mut Vec<String>) {
+    if sorted.iter().any(|done| done.name == elem.name) || visiting.contains(&elem.name) {
+      return;
+    }
+    visiting.push(elem.name.clone());
+    for dependency in &elem.meta.dependencies {
+      for provider in mods.iter().filter(|other| other.name != elem.name && provides(other, dependency)) {
+        visit(provider, mods, sorted, visiting);
+      }
+    }
+    visiting.pop();
+    sorted.push(elem.clone());
+  }
+  for elem in mods {
+    visit(elem, mods, &mut sorted, &mut visiting);
+  }
+  sorted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mod_manager::meta::ModMeta;
+
+  fn installed(name: &str, version: Option<&str>, enabled: bool, dependencies: Vec<Dependency>) -> Mod {
+    let meta = ModMeta { version: version.map(str::to_string), dependencies, ..ModMeta::default() };
+    Mod { enabled, meta, ..Mod::generated(name) }
+  }
+
+  fn on(name: &str, version: Option<&str>) -> Dependency {
+    Dependency { mod_id: None, name: Some(name.to_string()), version: version.map(str::to_string) }
+  }
+
+  #[test]
+  fn versions_compare_by_number() {
+    assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+    assert_eq!(compare_versions("v2.0", "2"), Ordering::Equal);
+    assert_eq!(compare_versions("1.0-beta", "1.0-alpha"), Ordering::Greater);
+    assert!(satisfies(Some("1.5"), ">=1.2, <2"));
+    assert!(!satisfies(Some("2.0"), ">=1.2, <2"));
+    assert!(satisfies(Some("1.2"), "1.2"));
+    assert!(!satisfies(Some("1.2"), "!=1.2"));
+    assert!(!satisfies(None, ">=1"));
+  }
+
+  #[test]
+  fn unmet_dependencies() {
+    let mods = vec![
+      installed("SKSE", Some("2.0"), false, Vec::new()),
+      installed("SkyUI", Some("5.1"), true, vec![on("skse", None)]),
+      installed("Old UI", Some("4"), true, Vec::new()),
+      installed("Patch", None, true, vec![on("Old UI", Some(">=5")), on("Missing", None)]),
+    ];
+    let missing = check(&mods);
+    assert_eq!(missing.len(), 3);
+    assert!(matches!(&missing[0].unmet, Unmet::Disabled { provider } if provider == "SKSE"));
+    assert!(matches!(&missing[1].unmet, Unmet::WrongVersion { provider, installed } if provider == "Old UI" && installed.as_deref() == Some("4")));
+    assert!(matches!(missing[2].unmet, Unmet::NotInstalled));
+  }
+
+  #[test]
+  fn mod_ids_win_over_names() {
+    let mut provider = installed("Renamed", None, true, Vec::new());
+    provider.meta.mod_id = Some("266".to_string());
+    let dependency = Dependency { mod_id: Some("266".to_string()), name: Some("Original".to_string()), version: None };
+    assert!(provides(&provider, &dependency));
+    let dependency = Dependency { mod_id: Some("300".to_string()), name: Some("Renamed".to_string()), version: None };
+    assert!(!provides(&provider, &dependency));
+  }
+
+  #[test]
+  fn dependencies_go_first() {
+    let mods = vec![
+      installed("A", None, true, vec![on("C", None)]),
+      installed("B", None, true, Vec::new()),
+      installed("C", None, true, Vec::new()),
+    ];
+    let names: Vec<String> = sort(&mods).into_iter().map(|elem| elem.name).collect();
+    assert_eq!(names, ["C", "A", "B"]);
+  }
+
+  #[test]
+  fn circles_keep_their_order() {
+    let mods = vec![
+      installed("A", None, true, vec![on("B", None)]),
+      installed("B", None, true, vec![on("A", None)]),
+    ];
+    let names: Vec<String> = sort(&mods).into_iter().map(|elem| elem.name).collect();
+    assert_eq!(names, ["B", "A"]);
+  }
+}
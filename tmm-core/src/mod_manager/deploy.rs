@@ -0,0 +1,90 @@
+use crate::mod_manager::Mod;
+use crate::mod_manager::game::{Game, DeployMode};
+use crate::mod_manager::link::{LinkBackend, LinkKind};
+use crate::mod_manager::ofs::OverlayBackend;
+use crate::mod_manager::staging::StagingBackend;
+use crate::mod_manager::root::WithRootFiles;
+use crate::mod_manager::case::CaseConflict;
+use crate::mod_manager::excludes::ExcludedFile;
+use crate::mod_manager::dependencies::MissingDependency;
+use serde::Serialize;
+use std::io;
+
+//A way of getting mods into a game directory. Mods are always passed
+//in load order, when two of them ship the same file the later one wins
+pub trait DeployBackend {
+  fn deploy(&self, game: &Game, mods: &[Mod]) -> io::Result<DeployReport>;
+
+  //Undoes whatever deploy did, without touching anything else
+  fn purge(&self, game: &Game) -> io::Result<()>;
+
+  fn status(&self, game: &Game) -> io::Result<DeployStatus>;
+
+  fn capabilities(&self, game: &Game) -> Capabilities;
+
+  //Whether deploy can go over an existing deployment of the backend without purging it first
+  fn incremental(&self) -> bool {
+    false
+  }
+}
+
+//Anything about a deployment the user should know about
+#[derive(Debug, Default, Serialize)]
+pub struct DeployReport {
+  //mod files that were left out, because the game couldn't tell
+  //apart the existing paths they'd be deployed to
+  pub case_conflicts: Vec<CaseConflict>,
+  //what differed from the previous deployment, backends that
+  //always redo everything leave this empty
+  pub changes: DeployChanges,
+  //mod files skipped because of the exclude patterns, the overlay
+  //can't leave out single files so it never skips any
+  pub excluded: Vec<ExcludedFile>,
+  //deployed anyway, the game might not start like this
+  pub missing_dependencies: Vec<MissingDependency>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DeployChanges {
+  pub added: usize,
+  pub replaced: usize,
+  pub removed: usize,
+  pub unchanged: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeployStatus {
+  pub backend: DeployMode,
+  pub deployed: bool,
+  //how many files are in the game directory because of the deployment,
+  //only known by backends that keep track of them
+  pub files: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+  pub backend: DeployMode,
+  pub available: bool,
+  //why the backend can't be used for this game
+  pub reason: Option<String>,
+  //whether deploying has to be authorized through polkit
+  pub privileged: bool,
+  //whether deployed files take up space on top of the installed mods
+  pub uses_space: bool,
+}
+
+//Every backend deploys the mods' root folders to the game root on top of what it does itself
+pub fn backend(mode: DeployMode) -> Box<dyn DeployBackend> {
+  let inner: Box<dyn DeployBackend> = match mode {
+    DeployMode::Overlay => Box::new(OverlayBackend),
+    DeployMode::Copy => Box::new(LinkBackend { kind: LinkKind::Copy }),
+    DeployMode::Symlink => Box::new(LinkBackend { kind: LinkKind::Symlink }),
+    DeployMode::Hardlink => Box::new(LinkBackend { kind: LinkKind::Hardlink }),
+    DeployMode::Staging => Box::new(StagingBackend),
+  };
+  Box::new(WithRootFiles { inner })
+}
+
+pub fn all_modes() -> [DeployMode; 5] {
+  [DeployMode::Overlay, DeployMode::Copy, DeployMode::Symlink, DeployMode::Hardlink, DeployMode::Staging]
+}
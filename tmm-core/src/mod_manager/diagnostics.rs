@@ -0,0 +1,138 @@
+use crate::logging;
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{deploy, hooks, load_mods, ofs, profiles, registry, split};
+use crate::error::{Error, ErrorCode};
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, io};
+
+//What the manager runs, whether they're there is often the whole bug
+const TOOLS: &[&str] = &["7z", "pkexec", "fuse-overlayfs", "git", "protontricks", "winetricks", "wine", "lutris", "gamescope", "gamemoderun", "mangohud"];
+
+#[derive(Debug, Serialize)]
+struct SystemInfo {
+  version: &'static str,
+  //PRETTY_NAME of os-release
+  os: Option<String>,
+  kernel: Option<String>,
+  session_type: Option<String>,
+  desktop: Option<String>,
+  appimage: bool,
+  mount_helper: bool,
+  tools: Vec<ToolInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolInfo {
+  name: &'static str,
+  found: bool,
+}
+
+fn system_info() -> SystemInfo {
+  let os = fs::read_to_string("/etc/os-release").ok().and_then(|contents| {
+    contents.lines()
+      .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+      .map(|name| name.trim_matches('"').to_string())
+  });
+  let kernel = fs::read_to_string("/proc/sys/kernel/osrelease").ok().map(|release| release.trim().to_string());
+  SystemInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    os,
+    kernel,
+    session_type: env::var("XDG_SESSION_TYPE").ok(),
+    desktop: env::var("XDG_CURRENT_DESKTOP").ok(),
+    appimage: env::var_os("APPIMAGE").is_some(),
+    mount_helper: ofs::helper().is_some(),
+    tools: TOOLS.iter().map(|name| ToolInfo { name, found: ofs::in_path(name) }).collect(),
+  }
+}
+
+fn write_json<S: Serialize>(path: &Path, value: &S) -> io::Result<()> {
+  fs::write(path, serde_json::to_string_pretty(value)?)
+}
+
+//A game whose state can't be read still gets the error written down in its place, that's
+//usually what the report is about
+fn collect_game(game: &Game, dir: &Path) -> io::Result<()> {
+  fs::create_dir_all(dir)?;
+  write_json(&dir.join("game.json"), game)?;
+  let profile = profiles::active_name(game);
+  match profiles::list(game) {
+    Ok(profiles) => write_json(&dir.join("profiles.json"), &profiles)?,
+    Err(e) => fs::write(dir.join("profiles.error"), e.to_string())?,
+  }
+  match deploy::backend(game.deploy_mode).status(game) {
+    Ok(status) => write_json(&dir.join("deployment.json"), &status)?,
+    Err(e) => fs::write(dir.join("deployment.error"), e.to_string())?,
+  }
+  match load_mods(game) {
+    Ok(mods) => write_json(&dir.join(format!("mods.{}.json", profile)), &mods)?,
+    Err(e) => fs::write(dir.join(format!("mods.{}.error", profile)), e.to_string())?,
+  }
+  let session_log = hooks::session_log_path(game);
+  if session_log.is_file() {
+    fs::copy(&session_log, dir.join("session.log"))?;
+  }
+  Ok(())
+}
+
+fn collect(building: &Path) -> io::Result<()> {
+  fs::create_dir_all(building)?;
+  write_json(&building.join("system.json"), &system_info())?;
+  let logs = building.join("logs");
+  fs::create_dir_all(&logs)?;
+  let logs_dir = logging::logs_dir()?;
+  if logs_dir.is_dir() {
+    for entry in logs_dir.read_dir()? {
+      let path = entry?.path();
+      if path.is_file() && path.extension().map_or(false, |extension| extension == "log") {
+        fs::copy(&path, logs.join(path.file_name().unwrap()))?;
+      }
+    }
+  }
+  match Game::all() {
+    Ok(games) => {
+      for game in games {
+        collect_game(&game, &building.join("games").join(game.appid.to_string()))?;
+      }
+    },
+    Err(e) => fs::write(building.join("games.error"), e.to_string())?,
+  }
+  Ok(())
+}
+
+fn pack(building: &Path, dest: &Path) -> io::Result<()> {
+  let temporary = dest.with_extension("tmm_diagnostics.zip");
+  if temporary.exists() {
+    fs::remove_file(&temporary)?;
+  }
+  let mut command = Command::new("7z");
+  command.arg("a").arg("-y").arg("-tzip").arg(&temporary).arg(building.join("*"));
+  let output = split::run_7z(&mut command)?;
+  if !output.status.success() {
+    fs::remove_file(&temporary).ok();
+    return Err(io::Error::new(io::ErrorKind::Other, String::from_utf8_lossy(&output.stderr).trim().to_string()));
+  }
+  fs::rename(temporary, dest)
+}
+
+//A zip for bug reports with the logs of every run that's kept, what the manager needs from the
+//system and the config, deployment and mods of every game. Credentials of the download sources
+//stay out of it, paths are left in since they're often what the bug is about
+pub fn export(dest: &Path) -> io::Result<()> {
+  registry::check_user_path("dest", dest)?;
+  if dest.extension().map_or(true, |extension| extension.to_string_lossy().to_lowercase() != "zip") {
+    return Err(Error::new(ErrorCode::InvalidDestination, format!("'{}' has to end in .zip", dest.display()))
+      .param("path", dest)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or(0);
+  let building = logging::logs_dir()?.with_file_name(format!(".diagnostics-{}", nanos));
+  let result = collect(&building).and_then(|()| pack(&building, dest));
+  if let Err(e) = fs::remove_dir_all(&building).or_else(|e| if e.kind() == io::ErrorKind::NotFound { Ok(()) } else { Err(e) }) {
+    tracing::warn!("Couldn't clean up '{}': {}", building.display(), e);
+  }
+  result
+}
@@ -0,0 +1,130 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{Mod, meta, reinstall};
+use crate::mod_manager::root::ROOT_DIR_NAME;
+use crate::error::{Error, ErrorCode};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//A mod file that matched one of the game's exclude patterns and wasn't deployed
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedFile {
+  pub mod_name: String,
+  pub path: PathBuf,
+}
+
+//Patterns are matched against paths relative to the mod's root, ignoring case,
+//'*' goes across directories so '*.txt' catches readmes anywhere
+pub struct Excludes {
+  set: GlobSet,
+}
+
+fn build(patterns: &[String]) -> io::Result<GlobSet> {
+  let mut builder = GlobSetBuilder::new();
+  for pattern in patterns {
+    let glob = GlobBuilder::new(pattern).case_insensitive(true).build().map_err(|e| {
+      Error::new(ErrorCode::InvalidPattern, e.to_string())
+        .param("pattern", pattern)
+        .into_io(io::ErrorKind::InvalidInput)
+    })?;
+    builder.add(glob);
+  }
+  builder.build().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+}
+
+//What deploying does with a mod file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filtered {
+  Deployed,
+  Hidden,
+  //deployed on its own, to the game's root
+  Root,
+  Manager,
+  //matched one of the game's patterns, the only ones that get reported
+  Excluded,
+}
+
+impl Excludes {
+  pub fn for_game(game: &Game) -> io::Result<Excludes> {
+    Ok(Excludes { set: build(&game.deploy_excludes)? })
+  }
+
+  //The mods' root folders get deployed on their own, so their files aren't excluded but aren't deployed here either
+  pub fn is_root_file(relative: &Path) -> bool {
+    match relative.components().next() {
+      Some(first) if relative.components().count() > 1 => first.as_os_str().to_string_lossy().eq_ignore_ascii_case(ROOT_DIR_NAME),
+      _ => false,
+    }
+  }
+
+  //The manager's own files in the mod's root, nothing that places single files deploys them
+  pub fn is_manager_file(relative: &Path) -> bool {
+    meta::is_meta_file(relative) || reinstall::is_manifest_file(relative)
+  }
+
+  //Hidden by the user, relative is to the mod's root like the hidden paths
+  pub fn is_hidden(elem: &Mod, relative: &Path) -> bool {
+    elem.meta.hidden.iter().any(|hidden| hidden == relative)
+  }
+
+  pub fn is_excluded(&self, relative: &Path) -> bool {
+    self.set.is_match(relative)
+  }
+
+  //Everything that keeps a file out of what the backends deploy, in the order it's checked
+  pub fn filter(&self, elem: &Mod, relative: &Path) -> Filtered {
+    if Excludes::is_hidden(elem, relative) {
+      Filtered::Hidden
+    } else if Excludes::is_root_file(relative) {
+      Filtered::Root
+    } else if Excludes::is_manager_file(relative) {
+      Filtered::Manager
+    } else if self.is_excluded(relative) {
+      Filtered::Excluded
+    } else {
+      Filtered::Deployed
+    }
+  }
+}
+
+pub fn validate(patterns: &[String]) -> io::Result<()> {
+  build(patterns).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn excludes(patterns: &[&str]) -> Excludes {
+    Excludes { set: build(&patterns.iter().map(|pattern| pattern.to_string()).collect::<Vec<String>>()).unwrap() }
+  }
+
+  #[test]
+  fn patterns_ignore_case_and_cross_directories() {
+    let excludes = excludes(&["*.txt", "fomod/**"]);
+    assert!(excludes.is_excluded(Path::new("README.TXT")));
+    assert!(excludes.is_excluded(Path::new("docs/changes/readme.txt")));
+    assert!(excludes.is_excluded(Path::new("FOMod/ModuleConfig.xml")));
+    assert!(!excludes.is_excluded(Path::new("textures/sky.dds")));
+  }
+
+  #[test]
+  fn what_keeps_a_file_out_in_order() {
+    let excludes = excludes(&["*.esp"]);
+    let mut elem = Mod::generated("Mod");
+    elem.meta.hidden = vec![PathBuf::from("Hidden.esp")];
+    assert_eq!(excludes.filter(&elem, Path::new("Hidden.esp")), Filtered::Hidden);
+    assert_eq!(excludes.filter(&elem, Path::new("Root/skse64_loader.exe")), Filtered::Root);
+    assert_eq!(excludes.filter(&elem, Path::new("root")), Filtered::Deployed);
+    assert_eq!(excludes.filter(&elem, Path::new("meta.json")), Filtered::Manager);
+    assert_eq!(excludes.filter(&elem, Path::new("Mod.esp")), Filtered::Excluded);
+    assert_eq!(excludes.filter(&elem, Path::new("textures/sky.dds")), Filtered::Deployed);
+  }
+
+  #[test]
+  fn broken_patterns_are_rejected() {
+    assert!(validate(&["[".to_string()]).is_err());
+    assert!(validate(&["**/*.txt".to_string()]).is_ok());
+  }
+}
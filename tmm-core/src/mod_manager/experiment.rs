@@ -0,0 +1,132 @@
+use crate::mod_manager::{get_files, link, meta, validate_mod_name, versions};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{launch, monitor, ofs};
+use crate::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+//A tool run inside a throwaway overlay on top of the game directory as it
+//currently is, whatever the tool writes ends up in the experiment's own layer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+  id: String,
+  executable: String,
+  started: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExperimentFiles {
+  //files the tool created or changed
+  written: Vec<PathBuf>,
+  //files the tool deleted, promoting doesn't carry those over
+  deleted: Vec<PathBuf>,
+}
+
+fn experiments_path(game: &Game) -> PathBuf {
+  game.profile_path.join("experiments")
+}
+
+fn experiment_path(game: &Game, id: &str) -> io::Result<PathBuf> {
+  let path = experiments_path(game).join(id);
+  if id.is_empty() || id.contains('/') || !path.join("experiment.json").exists() {
+    return Err(Error::new(ErrorCode::NoExperiment, format!("there is no experiment '{}'", id))
+      .param("id", id)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  Ok(path)
+}
+
+//Runs the executable and blocks until it exits, the overlay is taken
+//down again afterwards no matter how the tool went
+pub fn run(game: &Game, executable_name: &str) -> io::Result<Experiment> {
+  let executable = match game.executables.iter().find(|executable| executable.name == executable_name) {
+    Some(executable) => executable,
+    None => {
+      return Err(Error::new(ErrorCode::UnknownExecutable, format!("'{}' has no executable '{}'", game.public_name, executable_name))
+        .param("executable", executable_name)
+        .into_io(io::ErrorKind::NotFound));
+    }
+  };
+  let started = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+  let experiment = Experiment { id: started.to_string(), executable: executable_name.to_string(), started };
+  let path = experiments_path(game).join(&experiment.id);
+  //the work dir has to be on the same filesystem as the layer
+  fs::create_dir_all(path.join("upper"))?;
+  fs::create_dir_all(path.join("work"))?;
+  fs::write(path.join("experiment.json"), serde_json::to_string(&experiment)?)?;
+
+  let mount = ofs::mount_path(game);
+  ofs::init_overlay_fs(game, vec![mount.clone()], &path.join("upper"), &mount, &path.join("work"))?;
+  let result = launch::run_executable(game, executable, &[]).and_then(|child| monitor::wait(game.appid, child));
+  ofs::unmount(game, &mount)?;
+  result?;
+  Ok(experiment)
+}
+
+pub fn list(game: &Game) -> io::Result<Vec<Experiment>> {
+  let mut experiments: Vec<Experiment> = Vec::new();
+  let path = experiments_path(game);
+  if path.exists() {
+    for entry in path.read_dir()? {
+      let info = entry?.path().join("experiment.json");
+      if let Ok(contents) = fs::read_to_string(info) {
+        experiments.push(serde_json::from_str(&contents)?);
+      }
+    }
+  }
+  experiments.sort_by_key(|experiment| experiment.started);
+  Ok(experiments)
+}
+
+pub fn files(game: &Game, id: &str) -> io::Result<ExperimentFiles> {
+  let upper = experiment_path(game, id)?.join("upper");
+  let mut files = ExperimentFiles { written: Vec::new(), deleted: Vec::new() };
+  for file in get_files(&upper)? {
+    let relative = link::relative(&file, &upper)?.to_path_buf();
+    if is_whiteout(&file)? {
+      files.deleted.push(relative);
+    } else {
+      files.written.push(relative);
+    }
+  }
+  Ok(files)
+}
+
+//Turns what the tool wrote into a normal mod
+pub fn promote(game: &Game, id: &str, mod_name: &str) -> io::Result<()> {
+  let path = experiment_path(game, id)?;
+  validate_mod_name(game, mod_name)?;
+  let upper = path.join("upper");
+  for file in get_files(&upper)? {
+    if is_whiteout(&file)? {
+      fs::remove_file(file)?;
+    }
+  }
+  let target = versions::free(game, mod_name, &meta::ModMeta::default());
+  fs::create_dir_all(versions::mod_root(game, mod_name))?;
+  fs::rename(&upper, target)?;
+  remove(&path)
+}
+
+pub fn discard(game: &Game, id: &str) -> io::Result<()> {
+  remove(&experiment_path(game, id)?)
+}
+
+//Overlayfs leaves deletions in the layer as 0:0 character devices
+pub fn is_whiteout(path: &Path) -> io::Result<bool> {
+  Ok(fs::symlink_metadata(path)?.file_type().is_char_device())
+}
+
+//The kernel leaves an unreadable, but empty, 'work' dir inside the
+//work dir, it has to go first or removing the rest fails
+fn remove(path: &Path) -> io::Result<()> {
+  match fs::remove_dir(path.join("work").join("work")) {
+    Ok(()) => {},
+    Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+    Err(e) => return Err(e),
+  }
+  fs::remove_dir_all(path)
+}
@@ -0,0 +1,45 @@
+use crate::mod_manager::{registry, split, versions};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::reinstall::INSTALL_MANIFEST_NAME;
+use crate::error::{Error, ErrorCode};
+use std::path::Path;
+use std::process::Command;
+use std::{fs, io};
+
+//Packs the mod with its meta.json into a zip or 7z, whichever dest ends in, so it can be
+//installed somewhere else like any downloaded archive. What's at dest gets replaced
+pub fn export(game: &Game, mod_name: &str, dest: &Path) -> io::Result<()> {
+  registry::check_user_path("dest", dest)?;
+  //the version that's in use
+  let mod_dir = versions::active(game, mod_name)?;
+  if mod_name.is_empty() || mod_dir == versions::mod_root(game, mod_name) || !mod_dir.is_dir() {
+    return Err(Error::new(ErrorCode::ModNotFound, format!("'{}' isn't installed", mod_name))
+      .param("mod_name", mod_name)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  let format = match dest.extension().map(|extension| extension.to_string_lossy().to_lowercase()) {
+    Some(extension) if extension == "zip" || extension == "7z" => extension,
+    _ => {
+      return Err(Error::new(ErrorCode::UnsupportedArchiveFormat, format!("'{}' has to end in .zip or .7z", dest.display()))
+        .param("path", dest)
+        .into_io(io::ErrorKind::InvalidInput));
+    }
+  };
+  //7-Zip adds to archives that are there already
+  let temporary = dest.with_extension(format!("tmm_export.{}", format));
+  if temporary.exists() {
+    fs::remove_file(&temporary)?;
+  }
+  let mut command = Command::new("7z");
+  command.arg("a").arg("-y").arg(format!("-t{}", format))
+    .arg(format!("-x!{}", INSTALL_MANIFEST_NAME))
+    .arg(&temporary)
+    //7-Zip expands the wildcard itself, that way the files are at the archive's root
+    .arg(mod_dir.join("*"));
+  let output = split::run_7z(&mut command)?;
+  if !output.status.success() {
+    fs::remove_file(&temporary).ok();
+    return Err(io::Error::new(io::ErrorKind::Other, String::from_utf8_lossy(&output.stderr).trim().to_string()));
+  }
+  fs::rename(temporary, dest)
+}
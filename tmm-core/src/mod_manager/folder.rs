@@ -0,0 +1,72 @@
+use crate::mod_manager::{get_files, link, meta, pool, reflink, registry, reinstall, scratch, validate_mod_name, versions, watcher};
+use crate::mod_manager::game::Game;
+use serde::Deserialize;
+use std::path::Path;
+use std::{fs, io};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderInstall {
+  #[default]
+  Copy,
+  //the folder is gone from where it was afterwards
+  Move,
+  //the mod stays a link to the folder, so changes to it show up on the next deploy
+  Link,
+}
+
+fn folder_size(path: &Path) -> io::Result<u64> {
+  let mut size = 0;
+  for file in get_files(path)? {
+    size += fs::symlink_metadata(file)?.len();
+  }
+  Ok(size)
+}
+
+//Copies into the scratch space first like archives are extracted, a half copied folder
+//never shows up as a mod. A meta.json the folder comes with is kept
+pub fn install(source: &Path, mod_name: &str, mode: FolderInstall, game: &Game) -> io::Result<()> {
+  validate_mod_name(game, mod_name)?;
+  registry::check_source("src_path", source)?;
+  if !source.is_dir() {
+    return Err(io::Error::new(io::ErrorKind::NotFound, format!("'{}' isn't a folder", source.display())));
+  }
+  let source = source.canonicalize()?;
+  let _writing = watcher::writing(&versions::mod_root(game, mod_name));
+  let installed = meta::ModMeta { installed_at: meta::now(), ..meta::load(&source) };
+  let target = versions::free(game, mod_name, &installed);
+  match mode {
+    FolderInstall::Copy => {
+      let staging = scratch::ScratchDir::new(game, "copy", folder_size(&source)?)?;
+      for file in get_files(&source)? {
+        let copy = staging.path().join(link::relative(&file, &source)?);
+        if let Some(parent) = copy.parent() {
+          fs::create_dir_all(parent)?;
+        }
+        if fs::symlink_metadata(&file)?.file_type().is_symlink() {
+          std::os::unix::fs::symlink(fs::read_link(&file)?, &copy)?;
+        } else {
+          reflink::copy(&file, &copy)?;
+        }
+      }
+      meta::save(staging.path(), &installed)?;
+      scratch::move_dir(staging.path(), &target)?;
+    },
+    FolderInstall::Move => {
+      scratch::move_dir(&source, &target)?;
+      meta::save(&target, &installed)?;
+    },
+    //the meta goes next to the link, not into the user's folder,
+    //and the pool stays out of it so editing a file there can't change other mods
+    FolderInstall::Link => {
+      if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      std::os::unix::fs::symlink(&source, &target)?;
+      meta::save(&target, &installed)?;
+      return Ok(());
+    },
+  }
+  let report = pool::ingest(&target)?;
+  reinstall::record(&target, &report.hashes)
+}
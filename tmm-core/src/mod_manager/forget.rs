@@ -0,0 +1,79 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{deploy, monitor, pool, purge_deployed, staging};
+use crate::error::{Error, ErrorCode};
+use crate::events::Events;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::{fs, io};
+
+//What removing a game did, or would do on a dry run
+#[derive(Debug, Default, Serialize)]
+pub struct RemoveGameReport {
+  pub appid: u32,
+  pub dry_run: bool,
+  //whether a deployment had to be taken down first
+  pub purged: bool,
+  pub deleted: Vec<PathBuf>,
+  //the user's mods and downloads, left alone unless asked to delete them
+  pub kept: Vec<PathBuf>,
+}
+
+//The directories next to the game's work dir the backends keep their things in
+fn work_dirs(game: &Game) -> Vec<PathBuf> {
+  vec![
+    game.work_path.clone(),
+    game.work_path.with_extension("backup"),
+    game.work_path.with_extension("root-backup"),
+    staging::stagings_path(game),
+  ]
+}
+
+//Stops managing a game, its deployment always gets taken down so the game is
+//left like it was installed. The game itself is never touched
+pub fn remove_game(appid: u32, delete_profile_data: bool, dry_run: bool, events: &Events) -> io::Result<RemoveGameReport> {
+  let config_path = Game::config_path(appid)?;
+  if !config_path.exists() {
+    return Err(Error::new(ErrorCode::GameNotManaged, format!("{} isn't managed", appid))
+      .param("appid", appid)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  let game = Game::load(&config_path)?;
+  let mut report = RemoveGameReport { appid, dry_run, ..RemoveGameReport::default() };
+
+  let backend = deploy::backend(game.deploy_mode);
+  //nothing can be deployed into a library that isn't there
+  if game.install_path.is_dir() && backend.status(&game)?.deployed {
+    report.purged = true;
+    if !dry_run {
+      monitor::ensure_not_running(&game)?;
+      purge_deployed(&game, events)?;
+    }
+  }
+
+  let mut deleted = vec![config_path];
+  let mut data = work_dirs(&game);
+  data.push(game.profile_path.clone());
+  let existing = data.into_iter().filter(|path| path.exists());
+  if delete_profile_data {
+    deleted.extend(existing);
+  } else {
+    report.kept.extend(existing);
+  }
+  for path in &deleted {
+    if dry_run {
+      continue;
+    }
+    if path.is_dir() {
+      fs::remove_dir_all(path)?;
+    } else {
+      fs::remove_file(path)?;
+    }
+  }
+  report.deleted = deleted;
+
+  //the pooled copies of the deleted mods aren't linked from anywhere anymore
+  if delete_profile_data && !dry_run {
+    pool::collect_garbage()?;
+  }
+  Ok(report)
+}
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::schema;
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub public_name: String,
+    pub appid: u32,
+    pub install_path: PathBuf,
+    pub profile_path: PathBuf,
+    //What the backends' folders next to the game's library are named after, e.g. the
+    //link backup is '<work_path>.backup'. Nothing goes in the folder itself anymore
+    pub work_path: PathBuf,
+    //Basically if the content of mods should be put into
+    //another subpath instead of the root install folder
+    //e.g. on bethesda titles /Data/
+    pub path_extension: PathBuf,
+    pub executables: Vec<Executable>,
+    //How the mods get put into the game directory,
+    //configs written before this existed fall back to the overlay
+    #[serde(default)]
+    pub deploy_mode: DeployMode,
+    //Soft limit in bytes for the downloads and mods of this game
+    #[serde(default)]
+    pub storage_quota: Option<u64>,
+    //Where extraction and such gets staged instead of the profile,
+    //e.g. a bigger drive
+    #[serde(default)]
+    pub scratch_path: Option<PathBuf>,
+    //If copies can be reflinks, probed when the game gets set up,
+    //None for configs from before that
+    #[serde(default)]
+    pub reflink: Option<bool>,
+    //Purge once the game launched through the manager exits,
+    //and before Steam starts updating it
+    #[serde(default)]
+    pub auto_purge: bool,
+    //The staging deployments go to, None is the default one
+    #[serde(default)]
+    pub staging: Option<String>,
+    //Globs of mod files that never get deployed, starts out with the
+    //defaults of the supported game
+    #[serde(default)]
+    pub deploy_excludes: Vec<String>,
+    //Where the game was found, configs from before there
+    //were other stores are all Steam games
+    #[serde(default)]
+    pub store: Store,
+    //The Wine prefix Windows games run in, if it's known,
+    //for Steam games that's Proton's 'compatdata/<appid>/pfx'
+    #[serde(default)]
+    pub wine_prefix: Option<PathBuf>,
+    //The store's own id when it isn't the appid, e.g. the Lutris slug
+    #[serde(default)]
+    pub store_id: Option<String>,
+    //Start the game through its store instead of directly
+    #[serde(default)]
+    pub launch_through_store: bool,
+    //The Steam library folder the game is installed in, e.g. one on an SD card
+    #[serde(default)]
+    pub library: Option<PathBuf>,
+    //The store's build of the game as of the last scan, e.g. Steam's buildid
+    #[serde(default)]
+    pub version: Option<String>,
+    //Installed on an SD card or USB drive that can go missing
+    #[serde(default)]
+    pub removable: bool,
+    //How many hours apart the mods get checked for updates in the background, None is never
+    #[serde(default)]
+    pub update_check_hours: Option<u64>,
+    //How many days removed mods stay in the trash, None is the default of 30 and 0 forever
+    #[serde(default)]
+    pub trash_retention_days: Option<u64>,
+    //Where the saves are, inside the prefix's Windows user or the home for native games
+    #[serde(default)]
+    pub save_path: Option<PathBuf>,
+    //Each profile gets its own saves, the save folder is a link to the active profile's
+    #[serde(default)]
+    pub isolate_saves: bool,
+    //INIs and such that each profile keeps its own copy of
+    #[serde(default)]
+    pub config_files: Vec<GameConfigFile>,
+    //The file the game reads its plugin order from, written on deploy
+    #[serde(default)]
+    pub load_order_file: Option<LoadOrderFile>,
+    //A directory, or git repository, the profiles get synced through with other machines
+    #[serde(default)]
+    pub sync_path: Option<PathBuf>,
+    //Programs like xEdit or LOOT the user runs against the modded game
+    #[serde(default)]
+    pub tools: Vec<Tool>,
+    //Which version of the config this is, older ones get upgraded when they're read
+    #[serde(default)]
+    pub schema_version: u32,
+}
+
+impl Game {
+    pub fn config_path(appid: u32) -> io::Result<PathBuf> {
+        Ok(crate::config_root()?.join(format!("{}.json", appid)))
+    }
+
+    //Reads a game config, one from an older version of the manager gets upgraded and written back first
+    pub fn load(path: &Path) -> io::Result<Game> {
+        let mut value: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let upgraded = schema::upgrade(&schema::GAME, path, &mut value)?;
+        let game: Game = serde_json::from_value(value)?;
+        if upgraded {
+            schema::replace(path, &serde_json::to_string(&game)?)?;
+        }
+        Ok(game)
+    }
+
+    //Every game that has a config, the other files in the config dir get skipped
+    pub fn all() -> io::Result<Vec<Game>> {
+        let mut games: Vec<Game> = Vec::new();
+        let config_dir = crate::config_root()?;
+        if !config_dir.exists() {
+            return Ok(games);
+        }
+        for entry in config_dir.read_dir()? {
+            let path = entry?.path();
+            let is_game = path.extension().map_or(false, |extension| extension == "json")
+                && path.file_stem().and_then(|stem| stem.to_str()).map_or(false, |stem| stem.parse::<u32>().is_ok());
+            if !is_game {
+                continue;
+            }
+            match Game::load(&path) {
+                Ok(game) => games.push(game),
+                Err(e) => tracing::warn!("Skipping the game config '{}': {}", path.display(), e),
+            }
+        }
+        Ok(games)
+    }
+
+    //Windows games running through Proton
+    pub fn uses_compatibility(&self) -> bool {
+        self.executables.iter().any(|executable| executable.use_compatibility)
+    }
+
+    //The Windows user's home inside the prefix, where 'Documents/My Games'
+    //and 'AppData' are. Proton always calls the user steamuser
+    pub fn prefix_user_dir(&self) -> Option<PathBuf> {
+        let prefix = self.wine_prefix.as_ref()?;
+        let user = match self.store {
+            Store::Steam => "steamuser".to_string(),
+            _ => std::env::var("USER").ok()?,
+        };
+        Some(prefix.join("drive_c").join("users").join(user))
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        crate::mod_manager::registry::validate(self)?;
+        let json = serde_json::to_string(self)?;
+        fs::write(Game::config_path(self.appid)?, json)
+    }
+}
+
+//A game installed in 'game' with its profile in 'profile' of the directory, for the tests
+//of what works on a game without it being set up
+#[cfg(test)]
+impl Game {
+    pub fn in_dir(dir: &Path) -> Game {
+        serde_json::from_value(serde_json::json!({
+            "public_name": "Test Game",
+            "appid": 489830,
+            "install_path": dir.join("game"),
+            "profile_path": dir.join("profile"),
+            "work_path": dir.join(".tmm_work/489830"),
+            "path_extension": "Data",
+            "executables": [],
+        })).unwrap()
+    }
+}
+
+//Where in the prefix mods usually need files, INIs and plugin lists
+#[derive(Debug, Clone, Serialize)]
+pub struct PrefixPaths {
+    pub prefix: PathBuf,
+    pub documents: PathBuf,
+    pub my_games: PathBuf,
+    pub app_data_local: PathBuf,
+    pub app_data_roaming: PathBuf,
+}
+
+impl PrefixPaths {
+    pub fn for_game(game: &Game) -> Option<PrefixPaths> {
+        let user_dir = game.prefix_user_dir()?;
+        Some(PrefixPaths {
+            prefix: game.wine_prefix.clone()?,
+            my_games: user_dir.join("Documents").join("My Games"),
+            documents: user_dir.join("Documents"),
+            app_data_local: user_dir.join("AppData").join("Local"),
+            app_data_roaming: user_dir.join("AppData").join("Roaming"),
+        })
+    }
+}
+
+//What a scan found out about one installed app
+#[derive(Debug, Serialize)]
+pub struct ScannedGame {
+    pub state: ScanState,
+    pub appid: u32,
+    pub name: String,
+    //only there for added and managed games
+    pub game: Option<Game>,
+    pub error: Option<Error>,
+    //set when a managed game got updated since the last scan
+    pub version_change: Option<VersionChange>,
+}
+
+//The game got updated underneath its mods, they might need a look
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionChange {
+    pub appid: u32,
+    pub previous: String,
+    pub current: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanState {
+    //supported and set up by this scan
+    Added,
+    //had a config already
+    Managed,
+    //installed, but there's no support for it
+    Unsupported,
+    //setting it up or reading its config failed
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Executable {
+    pub name: String,
+    pub use_compatibility: bool,
+    pub binary_path: PathBuf,
+    pub startin_path: PathBuf,
+    pub output_mod: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    //absolute, or relative to the game directory
+    pub path: PathBuf,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    //the tool's own folder if there's none
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    //runs inside the game's prefix, like its Windows executables
+    #[serde(default)]
+    pub use_compatibility: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameConfigFile {
+    pub path: PathBuf,
+    //what the path is relative to
+    #[serde(default)]
+    pub base: ConfigBase,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigBase {
+    //the prefix's Windows user, like save paths
+    #[default]
+    User,
+    Game,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadOrderFile {
+    //relative to the prefix's Windows user, or home for native games, e.g. 'AppData/Local/Skyrim Special Edition/plugins.txt'
+    pub path: PathBuf,
+    pub format: LoadOrderFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadOrderFormat {
+    //every plugin in order, the enabled ones start with a '*', Skyrim SE and Fallout 4
+    Asterisk,
+    //only the enabled plugins in order, Oblivion, Fallout 3 and New Vegas
+    Plain,
+    //the package ids of the enabled mods in the activeMods of RimWorld's ModsConfig.xml
+    ModsConfig,
+    //every mod by name with whether it's enabled, Factorio's mod-list.json
+    ModList,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployMode {
+    #[default]
+    Overlay,
+    Copy,
+    Symlink,
+    //falls back to symlinks if the mods and the game
+    //aren't on the same filesystem
+    Hardlink,
+    //a linked copy of the game with the mods in it, the game
+    //gets started from there instead of the install
+    Staging,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Store {
+    #[default]
+    Steam,
+    //the appid is GOG's game id
+    Gog,
+    //the store id is the slug, the appid is made up from it
+    Lutris,
+    //added by hand, the appid is made up from the install path
+    Custom,
+    //the store id is the itch.io game id, the appid is made up from it
+    Itch,
+}
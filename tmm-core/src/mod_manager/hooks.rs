@@ -0,0 +1,147 @@
+use crate::mod_manager::{meta, profiles};
+use crate::mod_manager::game::Game;
+use crate::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::events::{BackendEvent, Events};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStage {
+  //before the profile gets deployed for the launch
+  PreLaunch,
+  PostExit,
+}
+
+//Run with sh in the profile's directory, it gets the game and profile in TMM_* variables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hook {
+  command: String,
+  //the hooks after it don't run if it fails, before a launch the game doesn't start either
+  #[serde(default)]
+  abort_on_failure: bool,
+  //seconds it gets before it's killed, which counts as failing. 0 lets it run as long as it takes
+  #[serde(default = "default_timeout")]
+  timeout: u64,
+}
+
+fn default_timeout() -> u64 {
+  300
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Hooks {
+  #[serde(default)]
+  pre_launch: Vec<Hook>,
+  #[serde(default)]
+  post_exit: Vec<Hook>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HookEvent {
+  appid: u32,
+  stage: HookStage,
+  command: String,
+  //None if it got killed
+  exit_code: Option<i32>,
+  timed_out: bool,
+}
+
+fn hooks_path(game: &Game) -> PathBuf {
+  game.profile_path.join("hooks.json")
+}
+
+//What the hooks of the last launch printed, started over on every launch
+pub fn session_log_path(game: &Game) -> PathBuf {
+  game.profile_path.join("session.log")
+}
+
+pub fn load(game: &Game) -> io::Result<Hooks> {
+  match fs::read_to_string(hooks_path(game)) {
+    Ok(contents) => Ok(serde_json::from_str(&contents)?),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Hooks::default()),
+    Err(e) => Err(e),
+  }
+}
+
+pub fn save(game: &Game, hooks: &Hooks) -> io::Result<()> {
+  fs::write(hooks_path(game), serde_json::to_string_pretty(hooks)?)
+}
+
+//None once it ran out of time and got killed
+fn wait_for(child: &mut Child, timeout: u64) -> io::Result<Option<ExitStatus>> {
+  if timeout == 0 {
+    return child.wait().map(Some);
+  }
+  let deadline = Instant::now() + Duration::from_secs(timeout);
+  loop {
+    if let Some(status) = child.try_wait()? {
+      return Ok(Some(status));
+    }
+    if Instant::now() >= deadline {
+      child.kill()?;
+      child.wait()?;
+      return Ok(None);
+    }
+    thread::sleep(Duration::from_millis(100));
+  }
+}
+
+//Every hook of the stage in order, output goes to the session log and each one finishing gets
+//emitted if there's somewhere to emit to. Stops at the first failing hook that aborts on failure
+pub fn run(game: &Game, stage: HookStage, events: Option<&Events>) -> io::Result<()> {
+  let hooks = load(game)?;
+  let hooks = match stage {
+    HookStage::PreLaunch => hooks.pre_launch,
+    HookStage::PostExit => hooks.post_exit,
+  };
+  let mut log = OpenOptions::new()
+    .create(true)
+    .write(true)
+    .append(stage != HookStage::PreLaunch)
+    .truncate(stage == HookStage::PreLaunch)
+    .open(session_log_path(game))?;
+  for hook in hooks {
+    writeln!(log, "[{}] {:?} '{}'", meta::now().unwrap_or(0), stage, hook.command)?;
+    log.flush()?;
+    let mut child = Command::new("sh")
+      .arg("-c")
+      .arg(&hook.command)
+      .current_dir(&game.profile_path)
+      .env("TMM_APPID", game.appid.to_string())
+      .env("TMM_PROFILE", profiles::active_name(game))
+      .env("TMM_PROFILE_PATH", &game.profile_path)
+      .env("TMM_GAME_PATH", &game.install_path)
+      .stdin(Stdio::null())
+      .stdout(Stdio::from(log.try_clone()?))
+      .stderr(Stdio::from(log.try_clone()?))
+      .spawn()?;
+    let status = wait_for(&mut child, hook.timeout)?;
+    let exit_code = status.and_then(|status| status.code());
+    match status {
+      Some(_) => writeln!(log, "[{}] '{}' exited with {:?}", meta::now().unwrap_or(0), hook.command, exit_code)?,
+      None => writeln!(log, "[{}] '{}' was killed after {}s", meta::now().unwrap_or(0), hook.command, hook.timeout)?,
+    }
+    if let Some(events) = events {
+      events.send(BackendEvent::HookFinished(HookEvent { appid: game.appid, stage, command: hook.command.clone(), exit_code, timed_out: status.is_none() }));
+    }
+    if !status.map_or(false, |status| status.success()) && hook.abort_on_failure {
+      let message = match status {
+        Some(_) => format!("the hook '{}' failed", hook.command),
+        None => format!("the hook '{}' didn't finish within {}s", hook.command, hook.timeout),
+      };
+      return Err(Error::new(ErrorCode::HookFailed, message)
+        .param("command", &hook.command)
+        .param("exit_code", exit_code)
+        .param("timeout", hook.timeout)
+        .into_io(io::ErrorKind::Other));
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,67 @@
+use crate::mod_manager::scan::{self, Found};
+use crate::mod_manager::game::Store;
+use serde_json::Value;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+//Where the itch app installs games unless the user added other install locations,
+//those are only in its butler database
+pub(crate) fn install_locations() -> Vec<PathBuf> {
+  let mut locations: Vec<PathBuf> = Vec::new();
+  if let Some(config) = dirs::config_dir() {
+    locations.push(config.join("itch").join("apps"));
+  }
+  if let Some(home) = dirs::home_dir() {
+    locations.push(home.join(".var/app/io.itch.itch/config/itch/apps"));
+  }
+  locations
+}
+
+//The itch app leaves a gzipped receipt in every game it installed
+fn receipt(install_path: &Path) -> Option<Value> {
+  let file = File::open(install_path.join(".itch").join("receipt.json.gz")).ok()?;
+  let mut json: Vec<u8> = Vec::new();
+  compress_tools::uncompress_data(file, &mut json).ok()?;
+  serde_json::from_slice(&json).ok()
+}
+
+fn itch_game(install_path: PathBuf) -> Option<Found> {
+  let receipt = receipt(&install_path)?;
+  //tools, assets and such get installed the same way
+  if receipt["game"]["classification"].as_str().map_or(false, |classification| classification != "game") {
+    return None;
+  }
+  let game_id = receipt["game"]["id"].as_u64()?;
+  let appid = scan::made_up_appid(&format!("itch:{}", game_id));
+  Some(Found {
+    store: Store::Itch,
+    appid,
+    name: receipt["game"]["title"].as_str().unwrap_or("").to_string(),
+    work_path: install_path.parent()?.join(".tmm_work").join(appid.to_string()),
+    wine_prefix: scan::wine_prefix_of(&install_path),
+    install_path,
+    //the receipt doesn't say what to start, the supported game's binaries are used
+    executables: Vec::new(),
+    store_id: Some(game_id.to_string()),
+    library: None,
+    version: receipt["build"]["userVersion"].as_str()
+      .or_else(|| receipt["upload"]["updatedAt"].as_str())
+      .map(str::to_string),
+  })
+}
+
+pub fn scan_for_itch_games() -> Vec<Found> {
+  let mut found: Vec<Found> = Vec::new();
+  for location in install_locations() {
+    let entries = match location.read_dir() {
+      Ok(entries) => entries,
+      Err(_) => continue,
+    };
+    for entry in entries.flatten() {
+      if let Some(game) = itch_game(entry.path()) {
+        found.push(game);
+      }
+    }
+  }
+  found
+}
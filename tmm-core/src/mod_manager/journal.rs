@@ -0,0 +1,164 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::manifest::{Deployment, DeploymentManifest};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+//Every operation gets written down before it's done, so after a crash
+//it's known what a deployment might have touched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+  CreateDir { path: PathBuf },
+  //a directory the deployment made that nothing needs anymore
+  RemoveDir { path: PathBuf },
+  Displace { original: PathBuf, backup: PathBuf },
+  //a game file that was moved out of the way going back
+  Restore { original: PathBuf, backup: PathBuf },
+  Place { path: PathBuf },
+  //a file of the previous deployment taken out
+  Remove { path: PathBuf },
+}
+
+pub struct Journal {
+  file: File,
+  path: PathBuf,
+}
+
+fn journal_path(game: &Game, deployment: Deployment) -> PathBuf {
+  game.profile_path.join(format!("{}.journal", deployment.file_stem()))
+}
+
+impl Journal {
+  pub fn begin(game: &Game, deployment: Deployment) -> io::Result<Journal> {
+    let path = journal_path(game, deployment);
+    let file = OpenOptions::new().write(true).create(true).truncate(true).open(&path)?;
+    Ok(Journal { file, path })
+  }
+
+  pub fn record(&mut self, operation: &Operation) -> io::Result<()> {
+    writeln!(self.file, "{}", serde_json::to_string(operation)?)?;
+    self.file.sync_data()
+  }
+
+  //The manifest was written with everything recorded so far, only what comes after has to be in here
+  pub fn restart(&mut self) -> io::Result<()> {
+    self.file.set_len(0)?;
+    self.file.seek(SeekFrom::Start(0))?;
+    self.file.sync_data()
+  }
+
+  //The deployment went through, nothing to roll back anymore
+  pub fn commit(self) -> io::Result<()> {
+    drop(self.file);
+    fs::remove_file(self.path)
+  }
+}
+
+//Operations of a deployment that never got committed, a half
+//written last line just means that operation never happened
+pub fn pending(game: &Game, deployment: Deployment) -> io::Result<Option<Vec<Operation>>> {
+  let path = journal_path(game, deployment);
+  if !path.exists() {
+    return Ok(None);
+  }
+  let mut operations: Vec<Operation> = Vec::new();
+  for line in BufReader::new(File::open(&path)?).lines() {
+    match serde_json::from_str(&line?) {
+      Ok(operation) => operations.push(operation),
+      Err(_) => break,
+    }
+  }
+  Ok(Some(operations))
+}
+
+pub fn discard(game: &Game, deployment: Deployment) -> io::Result<()> {
+  let path = journal_path(game, deployment);
+  if path.exists() {
+    fs::remove_file(path)?;
+  }
+  Ok(())
+}
+
+fn exists(path: &Path) -> bool {
+  fs::symlink_metadata(path).is_ok()
+}
+
+//Brings the manifest up to what's in the game directory after the operations. Any of the last
+//ones may or may not have actually happened before things went wrong, so whether they did is
+//looked up. Only what's surely gone leaves the manifest, a file in there that isn't there is fine
+pub fn replay(manifest: &mut DeploymentManifest, operations: &[Operation]) {
+  for operation in operations {
+    let happened = match operation {
+      Operation::CreateDir { path } => path.is_dir(),
+      Operation::RemoveDir { path } => !exists(path),
+      Operation::Displace { backup, .. } => exists(backup),
+      Operation::Restore { backup, .. } => !exists(backup),
+      Operation::Place { .. } => true,
+      Operation::Remove { path } => !exists(path),
+    };
+    if happened {
+      manifest.apply(operation);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn only_what_happened_is_taken_in() {
+    let dir = std::env::temp_dir().join(format!("tmm-journal-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("made")).unwrap();
+    fs::write(dir.join("placed"), "").unwrap();
+    fs::write(dir.join("backup"), "").unwrap();
+    let operations = vec![
+      Operation::CreateDir { path: dir.join("made") },
+      Operation::CreateDir { path: dir.join("never made") },
+      Operation::Displace { original: dir.join("original"), backup: dir.join("backup") },
+      Operation::Displace { original: dir.join("other"), backup: dir.join("never moved") },
+      Operation::Place { path: dir.join("placed") },
+      //might be half written, it's in the manifest so purging takes care of it
+      Operation::Place { path: dir.join("not placed") },
+    ];
+    let mut manifest = DeploymentManifest::default();
+    replay(&mut manifest, &operations);
+    assert_eq!(manifest.dirs, [dir.join("made")]);
+    assert_eq!(manifest.displaced, [(dir.join("original"), dir.join("backup"))]);
+    assert!(manifest.files.contains(&dir.join("placed")) && manifest.files.contains(&dir.join("not placed")));
+
+    let undoing = vec![
+      Operation::Remove { path: dir.join("placed") },
+      Operation::Remove { path: dir.join("not placed") },
+      Operation::Restore { original: dir.join("original"), backup: dir.join("backup") },
+      Operation::RemoveDir { path: dir.join("made") },
+    ];
+    replay(&mut manifest, &undoing);
+    assert_eq!(manifest.files.iter().collect::<Vec<&PathBuf>>(), [&dir.join("placed")]);
+    assert_eq!(manifest.displaced.len(), 1);
+    assert_eq!(manifest.dirs.len(), 1);
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn a_half_written_line_ends_it() {
+    let dir = std::env::temp_dir().join(format!("tmm-journal-pending-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let game = Game::in_dir(&dir);
+    fs::create_dir_all(&game.profile_path).unwrap();
+    assert!(pending(&game, Deployment::Backend).unwrap().is_none());
+    let mut journal = Journal::begin(&game, Deployment::Backend).unwrap();
+    journal.record(&Operation::Place { path: dir.join("placed") }).unwrap();
+    drop(journal);
+    let path = journal_path(&game, Deployment::Backend);
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    write!(file, "{{\"op\":\"place\",\"pa").unwrap();
+    assert_eq!(pending(&game, Deployment::Backend).unwrap().unwrap(), [Operation::Place { path: dir.join("placed") }]);
+    discard(&game, Deployment::Backend).unwrap();
+    assert!(!path.exists());
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
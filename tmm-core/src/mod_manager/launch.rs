@@ -0,0 +1,239 @@
+use crate::mod_manager::game::{Game, Executable, DeployMode, Store};
+use crate::mod_manager::{configs, hooks, launch_options, lutris, monitor, purge_deployed, runners, staging, vdf};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+use std::{fs, io, thread};
+
+use crate::events::{BackendEvent, Events};
+use crate::settings;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+//Goes through Steam so Proton, the overlay and playtime tracking all work like usual,
+//except for stagings and games from other stores, those get started directly
+pub fn launch(game: &Game) -> io::Result<()> {
+  if game.launch_through_store && game.store == Store::Lutris && game.deploy_mode != DeployMode::Staging {
+    if let Some(slug) = &game.store_id {
+      return lutris::launch(slug);
+    }
+  }
+  if game.deploy_mode == DeployMode::Staging || game.store != Store::Steam {
+    if let Some(executable) = game.executables.first() {
+      monitor::watch(game.appid, run_executable(game, executable, &launch_options::load(game)?.args)?);
+      return Ok(());
+    }
+  }
+  match Command::new("steam").arg("-applaunch").arg(game.appid.to_string()).spawn() {
+    Ok(_) => Ok(()),
+    //the Flatpak and Snap ones have no 'steam' on the PATH but handle the URL
+    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+      Command::new("xdg-open").arg(format!("steam://rungameid/{}", game.appid)).spawn()?;
+      Ok(())
+    },
+    Err(e) => Err(e),
+  }
+}
+
+//Where the game's files are deployed to, executables and tools relative to the game are in there
+pub fn game_root(game: &Game) -> PathBuf {
+  if game.deploy_mode == DeployMode::Staging { staging::staging_root(game) } else { game.install_path.clone() }
+}
+
+//Windows binaries go through the profile's runner if it pinned one, protontricks-launch otherwise
+//so they run inside the game's Proton prefix. The active profile's environment variables get set either way
+pub fn command_for(game: &Game, binary: &Path, use_compatibility: bool) -> io::Result<Command> {
+  let options = launch_options::load(game)?;
+  let mut command = if let (true, Some(runner)) = (use_compatibility, &options.runner) {
+    runners::command(runner, game.wine_prefix.as_deref(), binary)?
+  } else if use_compatibility && game.store != Store::Steam {
+    //games from other stores have no Proton prefix, just their own Wine one
+    let mut command = Command::new("wine");
+    command.arg(binary);
+    if let Some(prefix) = &game.wine_prefix {
+      command.env("WINEPREFIX", prefix);
+    }
+    command
+  } else if use_compatibility {
+    let mut command = Command::new("protontricks-launch");
+    command.arg("--appid").arg(game.appid.to_string()).arg(binary);
+    //tools started from the game look for the prefix in here, Proton for 'compatdata/<appid>'
+    if let Some(prefix) = &game.wine_prefix {
+      command.env("WINEPREFIX", prefix);
+      if let Some(compat_data) = prefix.parent() {
+        command.env("STEAM_COMPAT_DATA_PATH", compat_data);
+      }
+    }
+    command
+  } else {
+    Command::new(binary)
+  };
+  command.envs(options.env);
+  Ok(command)
+}
+
+//Anything started for the game besides the game itself, its executables and the tools,
+//inside the profile's wrappers like the game
+pub fn spawn(game: &Game, binary: &Path, use_compatibility: bool, args: &[String], working_dir: &Path) -> io::Result<Child> {
+  let mut command = command_for(game, binary, use_compatibility)?;
+  command.args(args);
+  launch_options::wrap(command, &launch_options::load(game)?.wrappers).current_dir(working_dir).spawn()
+}
+
+//Starts one of the game's executables directly
+pub fn run_executable(game: &Game, executable: &Executable, args: &[String]) -> io::Result<Child> {
+  let root = game_root(game);
+  let binary = root.join(executable.binary_path.strip_prefix("/").unwrap_or(&executable.binary_path));
+  let start_in = if executable.startin_path.as_os_str().is_empty() {
+    binary.parent().unwrap_or(&root).to_path_buf()
+  } else {
+    root.join(executable.startin_path.strip_prefix("/").unwrap_or(&executable.startin_path))
+  };
+  spawn(game, &binary, executable.use_compatibility, args, &start_in)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchEvent {
+  appid: u32,
+  profile: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitEvent {
+  appid: u32,
+  profile: String,
+  //false if it never showed up as running
+  started: bool,
+  purged: bool,
+  //the profile has the game's INIs as they were when it exited
+  configs_synced: bool,
+  //Steam ran it through the manager, which took care of the configs, hooks and purging
+  through_manager: bool,
+}
+
+//Its client process, not the launcher script or the web helpers
+pub fn steam_running() -> bool {
+  let processes = match fs::read_dir("/proc") {
+    Ok(processes) => processes,
+    Err(_) => return false,
+  };
+  processes.flatten().any(|entry| {
+    fs::read_to_string(entry.path().join("comm")).map_or(false, |comm| comm.trim() == "steam")
+  })
+}
+
+//Steam keeps "Running" "1" in the app's block of its registry while it runs
+fn steam_reports_running(appid: u32) -> bool {
+  let registry = match dirs::home_dir().map(|home| home.join(".steam").join("registry.vdf")) {
+    Some(registry) => registry,
+    None => return false,
+  };
+  let entries = match vdf::read(&registry) {
+    Ok(entries) => entries,
+    Err(_) => return false,
+  };
+  let appid = appid.to_string();
+  let running = vdf::lookup(&entries, &["Registry", "HKCU", "Software", "Valve", "Steam", "apps", &appid, "Running"]);
+  matches!(running, Some(vdf::Vdf::Value(value)) if value == "1")
+}
+
+//A game counts as running if the manager started it and it hasn't exited, if Steam says so or any
+//process has one of its known binaries on the command line, that works for native games and ones
+//running through Proton
+pub fn is_running(game: &Game) -> bool {
+  if monitor::tracked(game.appid) || steam_reports_running(game.appid) {
+    return true;
+  }
+  let binaries: Vec<String> = game.executables.iter()
+    .filter_map(|executable| executable.binary_path.file_name())
+    .map(|name| name.to_string_lossy().to_string())
+    .collect();
+  if binaries.is_empty() {
+    return false;
+  }
+  let processes = match fs::read_dir("/proc") {
+    Ok(processes) => processes,
+    Err(_) => return false,
+  };
+  for entry in processes.flatten() {
+    if let Ok(cmdline) = fs::read(entry.path().join("cmdline")) {
+      let cmdline = String::from_utf8_lossy(&cmdline);
+      if binaries.iter().any(|binary| cmdline.contains(binary.as_str())) {
+        return true;
+      }
+    }
+  }
+  false
+}
+
+//False if it doesn't show up within the start timeout of the settings
+fn wait_for_start(game: &Game) -> bool {
+  let timeout = Duration::from_secs(settings::current().launch.start_timeout_seconds);
+  let started = Instant::now();
+  while !is_running(game) {
+    if started.elapsed() > timeout {
+      tracing::warn!("'{}' didn't start within {} seconds", game.public_name, timeout.as_secs());
+      return false;
+    }
+    thread::sleep(POLL_INTERVAL);
+  }
+  true
+}
+
+//Blocks until the game has started and exited again, if it never
+//shows up within the start timeout that counts as having exited. False then
+pub fn wait_for_exit(game: &Game) -> bool {
+  if !wait_for_start(game) {
+    return false;
+  }
+  while is_running(game) {
+    thread::sleep(POLL_INTERVAL);
+  }
+  true
+}
+
+//Keeps the configs the game changed, runs the post-exit hooks and purges if the game wants
+//that, whatever fails gets logged. Returns whether the configs were synced and it was purged
+pub fn after_exit(game: &Game, events: Option<&Events>) -> (bool, bool) {
+  let configs_synced = match configs::sync_back(game) {
+    Ok(()) => true,
+    Err(e) => {
+      tracing::warn!("Couldn't keep the configs of '{}' after it exited: {}", game.public_name, e);
+      false
+    }
+  };
+  if let Err(e) = hooks::run(game, hooks::HookStage::PostExit, events) {
+    tracing::warn!("A post-exit hook of '{}' failed: {}", game.public_name, e);
+  }
+  let none = Events::none();
+  let purged = game.auto_purge && match purge_deployed(game, events.unwrap_or(&none)) {
+    Ok(()) => true,
+    Err(e) => {
+      tracing::warn!("Couldn't purge '{}' after it exited: {}", game.public_name, e);
+      false
+    }
+  };
+  (configs_synced, purged)
+}
+
+//Tells the frontend when the game is starting, running and exited again, so it can keep
+//anything that changes the game directory from happening in between. With auto purge on the
+//deployment gets taken down again once the game exits, so nothing patches or verifies a game
+//directory that still has mods in it. The game's INIs go back into the profile either way,
+//the profile's post-exit hooks run after that
+//With through_manager what comes after it exited is left to the manager Steam started it with
+pub fn watch(game: Game, profile: String, through_manager: bool, events: Events) {
+  events.send(BackendEvent::GameStarting(LaunchEvent { appid: game.appid, profile: profile.clone() }));
+  thread::spawn(move || {
+    let started = wait_for_start(&game);
+    if started {
+      events.send(BackendEvent::GameRunning(LaunchEvent { appid: game.appid, profile: profile.clone() }));
+      while is_running(&game) {
+        thread::sleep(POLL_INTERVAL);
+      }
+    }
+    let (configs_synced, purged) = if through_manager { (false, false) } else { after_exit(&game, Some(&events)) };
+    events.send(BackendEvent::GameExited(ExitEvent { appid: game.appid, profile, started, purged, configs_synced, through_manager }));
+  });
+}
@@ -0,0 +1,170 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::runners::Runner;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::{fs, io};
+
+//How the profile wants the game started, e.g. 'PROTON_USE_WINED3D' or 'MANGOHUD' set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LaunchOptions {
+  //after the game's binary, the tools and other executables get their own
+  #[serde(default)]
+  pub args: Vec<String>,
+  //for everything started directly, the game, its executables and tools
+  #[serde(default)]
+  pub env: BTreeMap<String, String>,
+  //the Proton or Wine the executables and tools run with instead of the game's own
+  #[serde(default)]
+  pub runner: Option<Runner>,
+  //what the game and its executables get started in, first one outermost
+  #[serde(default)]
+  pub wrappers: Vec<Wrapper>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Wrapper {
+  //Valve's compositor the Deck runs games in, unset sizes are left to gamescope
+  Gamescope {
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+    //what the game renders at gets upscaled to this
+    #[serde(default)]
+    output_width: Option<u32>,
+    #[serde(default)]
+    output_height: Option<u32>,
+    #[serde(default)]
+    fps_limit: Option<u32>,
+    #[serde(default)]
+    fullscreen: bool,
+    #[serde(default)]
+    extra_args: Vec<String>,
+  },
+  Gamemode,
+  Mangohud,
+  //anything else that takes the command after its own arguments, like 'steamtinkerlaunch'
+  Custom { command: Vec<String> },
+}
+
+impl Wrapper {
+  fn words(&self) -> Vec<String> {
+    match self {
+      Wrapper::Gamescope { width, height, output_width, output_height, fps_limit, fullscreen, extra_args } => {
+        let mut words = vec!["gamescope".to_string()];
+        let sizes = [("-w", width), ("-h", height), ("-W", output_width), ("-H", output_height), ("-r", fps_limit)];
+        for (flag, value) in sizes.iter() {
+          if let Some(value) = value {
+            words.push(flag.to_string());
+            words.push(value.to_string());
+          }
+        }
+        if *fullscreen {
+          words.push("-f".to_string());
+        }
+        words.extend(extra_args.iter().cloned());
+        words.push("--".to_string());
+        words
+      },
+      Wrapper::Gamemode => vec!["gamemoderun".to_string()],
+      Wrapper::Mangohud => vec!["mangohud".to_string()],
+      Wrapper::Custom { command } => command.clone(),
+    }
+  }
+}
+
+//Every wrapper's words in order, what comes before the game's command
+fn wrapper_words(wrappers: &[Wrapper]) -> Vec<String> {
+  wrappers.iter().flat_map(|wrapper| wrapper.words()).collect()
+}
+
+//The same command started inside the wrappers, with the environment it had
+pub fn wrap(command: Command, wrappers: &[Wrapper]) -> Command {
+  let mut words = wrapper_words(wrappers).into_iter();
+  let mut wrapped = match words.next() {
+    Some(program) => Command::new(program),
+    None => return command,
+  };
+  wrapped.args(words).arg(command.get_program()).args(command.get_args());
+  for (key, value) in command.get_envs() {
+    match value {
+      Some(value) => wrapped.env(key, value),
+      None => wrapped.env_remove(key),
+    };
+  }
+  wrapped
+}
+
+fn options_path(game: &Game) -> PathBuf {
+  game.profile_path.join("launch.json")
+}
+
+pub fn load(game: &Game) -> io::Result<LaunchOptions> {
+  match fs::read_to_string(options_path(game)) {
+    Ok(contents) => Ok(serde_json::from_str(&contents)?),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(LaunchOptions::default()),
+    Err(e) => Err(e),
+  }
+}
+
+pub fn save(game: &Game, options: &LaunchOptions) -> io::Result<()> {
+  fs::write(options_path(game), serde_json::to_string_pretty(options)?)
+}
+
+//Steam runs its launch options through a shell, anything with spaces or quotes has to be quoted
+pub fn quote(word: &str) -> String {
+  if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_=./:,+%@".contains(c)) {
+    return word.to_string();
+  }
+  format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+//The same as Steam launch options, 'KEY=value wrappers %command% args'
+pub fn steam_string(options: &LaunchOptions) -> String {
+  let mut words: Vec<String> = options.env.iter().map(|(key, value)| format!("{}={}", key, quote(value))).collect();
+  words.extend(wrapper_words(&options.wrappers).iter().map(|word| quote(word)));
+  words.push("%command%".to_string());
+  words.extend(options.args.iter().map(|arg| quote(arg)));
+  words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn quotes_only_what_the_shell_would_split() {
+    assert_eq!(quote("-skipintro"), "-skipintro");
+    assert_eq!(quote("%command%"), "%command%");
+    assert_eq!(quote(""), "''");
+    assert_eq!(quote("two words"), "'two words'");
+    assert_eq!(quote("it's"), "'it'\\''s'");
+  }
+
+  #[test]
+  fn wrappers_go_first_outermost() {
+    let wrappers = vec![
+      Wrapper::Gamescope { width: Some(1280), height: Some(800), output_width: None, output_height: None, fps_limit: Some(40), fullscreen: true, extra_args: Vec::new() },
+      Wrapper::Mangohud,
+    ];
+    let mut command = Command::new("game.sh");
+    command.arg("-windowed").env("DXVK_HUD", "1");
+    let wrapped = wrap(command, &wrappers);
+    assert_eq!(wrapped.get_program(), "gamescope");
+    let args: Vec<&std::ffi::OsStr> = wrapped.get_args().collect();
+    assert_eq!(args, ["-w", "1280", "-h", "800", "-r", "40", "-f", "--", "mangohud", "game.sh", "-windowed"]);
+    assert!(wrapped.get_envs().any(|(key, value)| key == "DXVK_HUD" && value == Some("1".as_ref())));
+    assert_eq!(wrap(Command::new("game.sh"), &[]).get_program(), "game.sh");
+  }
+
+  #[test]
+  fn steam_string_puts_the_command_in_the_middle() {
+    let mut options = LaunchOptions { args: vec!["-skip intro".to_string()], wrappers: vec![Wrapper::Gamemode], ..LaunchOptions::default() };
+    options.env.insert("PROTON_LOG".to_string(), "1".to_string());
+    options.env.insert("WINEDLLOVERRIDES".to_string(), "winhttp=n,b".to_string());
+    assert_eq!(steam_string(&options), "PROTON_LOG=1 WINEDLLOVERRIDES=winhttp=n,b gamemoderun %command% '-skip intro'");
+  }
+}
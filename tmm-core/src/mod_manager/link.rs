@@ -0,0 +1,503 @@
+use crate::mod_manager::{Mod, get_files, mod_dir, pool, reflink};
+use crate::mod_manager::game::{Game, DeployMode};
+use crate::mod_manager::manifest::{Deployment, DeploymentManifest};
+use crate::mod_manager::journal::{self, Journal, Operation};
+use crate::mod_manager::deploy::{DeployBackend, DeployReport, DeployStatus, Capabilities};
+use crate::mod_manager::case::{CaseConflict, CaseResolver};
+use crate::mod_manager::excludes::{ExcludedFile, Excludes, Filtered};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::os::unix::fs::{symlink, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//How many operations go by between writing the manifest while deploying,
+//the journal has the ones since
+const SAVE_EVERY: usize = 500;
+
+//How the files of a mod end up in the game directory,
+//all of them get tracked by the deployment manifest the same way
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+  Copy,
+  #[default]
+  Symlink,
+  Hardlink,
+}
+
+pub struct LinkBackend {
+  pub kind: LinkKind,
+}
+
+impl DeployBackend for LinkBackend {
+  fn deploy(&self, game: &Game, mods: &[Mod]) -> io::Result<DeployReport> {
+    repair(game)?;
+    let kind = self.effective_kind(game)?;
+    //only manifests that know where each file came from can be deployed over,
+    //anything else gets purged so it doesn't get recorded as original game files
+    let previous = match DeploymentManifest::load(game)? {
+      Some(previous) if previous.kind == kind && previous.sources.len() == previous.files.len() => previous,
+      _ => {
+        self.purge(game)?;
+        DeploymentManifest { kind, ..DeploymentManifest::default() }
+      }
+    };
+
+    let mut report = DeployReport::default();
+    let mut placement = Placement::begin(game, Deployment::Backend, previous)?;
+    match place_mods(game, mods, &mut placement, &mut report) {
+      Ok(()) => {
+        placement.finish()?;
+        Ok(report)
+      },
+      Err(e) => {
+        //a half finished deployment gets undone right away instead of being left around,
+        //what's left of the previous one goes too since it's been partly replaced
+        if let Err(purge_error) = placement.abort() {
+          tracing::error!("Couldn't purge the deployment of '{}' that failed: {}", game.public_name, purge_error);
+        }
+        Err(e)
+      }
+    }
+  }
+
+  fn incremental(&self) -> bool {
+    true
+  }
+
+  fn purge(&self, game: &Game) -> io::Result<()> {
+    purge(game)
+  }
+
+  fn status(&self, game: &Game) -> io::Result<DeployStatus> {
+    let manifest = DeploymentManifest::load(game)?;
+    Ok(DeployStatus {
+      backend: self.mode(),
+      deployed: manifest.is_some(),
+      files: manifest.map(|manifest| manifest.files.len()),
+    })
+  }
+
+  fn capabilities(&self, game: &Game) -> Capabilities {
+    let (available, reason) = match self.kind {
+      LinkKind::Hardlink => match same_device(game) {
+        Ok(true) => (true, None),
+        Ok(false) => (false, Some("the mods and the game are on different filesystems".to_string())),
+        Err(e) => (false, Some(e.to_string())),
+      },
+      _ => (true, None),
+    };
+    Capabilities {
+      backend: self.mode(),
+      available,
+      reason,
+      privileged: false,
+      uses_space: self.kind == LinkKind::Copy && game.reflink != Some(true),
+    }
+  }
+}
+
+impl LinkBackend {
+  fn mode(&self) -> DeployMode {
+    match self.kind {
+      LinkKind::Copy => DeployMode::Copy,
+      LinkKind::Symlink => DeployMode::Symlink,
+      LinkKind::Hardlink => DeployMode::Hardlink,
+    }
+  }
+
+  //Hardlinks only work within one filesystem, so they fall back
+  //to symlinks if the mods and the game install don't share a device
+  fn effective_kind(&self, game: &Game) -> io::Result<LinkKind> {
+    if self.kind == LinkKind::Hardlink && !same_device(game)? {
+      tracing::info!("Mods and '{}' are on different filesystems, deploying with symlinks instead of hardlinks", game.public_name);
+      return Ok(LinkKind::Symlink);
+    }
+    Ok(self.kind)
+  }
+}
+
+//The path under the root, for paths that were found under it
+pub fn relative<'a>(path: &'a Path, root: &Path) -> io::Result<&'a Path> {
+  path.strip_prefix(root).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("'{}' isn't in '{}'", path.display(), root.display())))
+}
+
+//Changes whenever the file could have, the ctime can't be set back by whatever changed it
+pub fn fingerprint(path: &Path) -> io::Result<u64> {
+  let metadata = fs::symlink_metadata(path)?;
+  let mut hasher = blake3::Hasher::new();
+  for value in [metadata.len() as i64, metadata.mtime(), metadata.mtime_nsec(), metadata.ctime(), metadata.ctime_nsec(), metadata.ino() as i64, metadata.dev() as i64] {
+    hasher.update(&value.to_le_bytes());
+  }
+  let mut print = [0; 8];
+  print.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+  Ok(u64::from_le_bytes(print))
+}
+
+//Works out where every mod file goes, later mods win a conflict
+fn plan(game: &Game, mods: &[Mod], target_root: &Path, report: &mut DeployReport) -> io::Result<Vec<(PathBuf, PathBuf, String)>> {
+  let mut planned: Vec<(PathBuf, PathBuf, String)> = Vec::new();
+  let mut positions: HashMap<PathBuf, usize> = HashMap::new();
+  //native games really can have 'a' and 'A' next to each other
+  let mut resolver = if game.uses_compatibility() { Some(CaseResolver::new(target_root.to_path_buf())) } else { None };
+  let excludes = Excludes::for_game(game)?;
+
+  for elem in mods {
+    let mod_root = mod_dir(game, elem)?;
+    for source in get_files(&mod_root)? {
+      let mut relative = relative(&source, &mod_root)?.to_path_buf();
+      match excludes.filter(elem, &relative) {
+        Filtered::Deployed => {},
+        Filtered::Excluded => {
+          report.excluded.push(ExcludedFile { mod_name: elem.name.clone(), path: relative });
+          continue;
+        },
+        _ => continue,
+      }
+      if let Some(ref mut resolver) = resolver {
+        match resolver.resolve(&relative)? {
+          Ok(resolved) => {
+            resolver.added(&resolved)?;
+            relative = resolved;
+          },
+          Err(candidates) => {
+            report.case_conflicts.push(CaseConflict { mod_name: elem.name.clone(), path: relative, candidates });
+            continue;
+          }
+        }
+      }
+      let target = target_root.join(&relative);
+      match positions.get(&target) {
+        Some(&position) => planned[position] = (target, source, elem.name.clone()),
+        None => {
+          positions.insert(target.clone(), planned.len());
+          planned.push((target, source, elem.name.clone()));
+        }
+      }
+    }
+  }
+  Ok(planned)
+}
+
+//What's going to be deployed where and what's in it
+pub struct PlannedFile {
+  pub target: PathBuf,
+  pub source: PathBuf,
+  pub mod_name: String,
+  pub print: u64,
+  pub hash: String,
+}
+
+//The sources get hashed to tell if they changed, but only the ones whose metadata
+//changed since the previous deployment, the others are known already
+pub fn with_hashes(planned: Vec<(PathBuf, PathBuf, String)>, previous: &DeploymentManifest) -> io::Result<Vec<PlannedFile>> {
+  let mut files: Vec<PlannedFile> = Vec::new();
+  for (target, source, mod_name) in planned {
+    let print = fingerprint(&source)?;
+    let known = match (previous.sources.get(&target), previous.hashes.get(&target)) {
+      (Some((_, old_print)), Some(hash)) if *old_print == print => Some(hash.clone()),
+      _ => None,
+    };
+    let hash = match known {
+      Some(hash) => hash,
+      None => pool::hash_file(&source)?,
+    };
+    files.push(PlannedFile { target, source, mod_name, print, hash });
+  }
+  Ok(files)
+}
+
+//A deployed file stays if it's from the same mod and has the same contents as before
+pub fn unchanged(previous: &DeploymentManifest, file: &PlannedFile) -> bool {
+  previous.sources.get(&file.target).map(|(mod_name, _)| mod_name) == Some(&file.mod_name)
+    && previous.hashes.get(&file.target) == Some(&file.hash)
+}
+
+//Only touches what differs from the previous deployment, with nothing deployed before that's everything
+fn place_mods(game: &Game, mods: &[Mod], placement: &mut Placement, report: &mut DeployReport) -> io::Result<()> {
+  let target_root: PathBuf = game.install_path.join(&game.path_extension);
+  let backup_root: PathBuf = backup_path(game);
+  let kind = placement.manifest.kind;
+  //older configs never got probed
+  let reflinks = kind == LinkKind::Copy && game.reflink.unwrap_or_else(|| reflink::probe(game));
+  let previous = placement.manifest.clone();
+
+  let planned = with_hashes(plan(game, mods, &target_root, report)?, &previous)?;
+  let wanted: HashMap<&PathBuf, &PlannedFile> = planned.iter().map(|file| (&file.target, file)).collect();
+
+  //whatever changed or isn't wanted anymore comes out first
+  for file in &previous.files {
+    match wanted.get(file) {
+      Some(planned) if unchanged(&previous, planned) && fs::symlink_metadata(file).is_ok() => continue,
+      Some(_) => report.changes.replaced += 1,
+      None => report.changes.removed += 1,
+    }
+    placement.remove(file)?;
+  }
+  for (original, backup) in &previous.displaced {
+    //still displaced otherwise, the new file goes where it was
+    if !wanted.contains_key(original) {
+      placement.restore(original, backup)?;
+    }
+  }
+
+  for file in &planned {
+    if placement.manifest.files.contains(&file.target) {
+      report.changes.unchanged += 1;
+    } else {
+      if !previous.sources.contains_key(&file.target) {
+        report.changes.added += 1;
+      }
+      let relative = relative(&file.target, &target_root)?;
+      placement.place(&file.target, &backup_root.join(relative), |target| match kind {
+        LinkKind::Copy if reflinks => reflink::copy(&file.source, target),
+        LinkKind::Copy => fs::copy(&file.source, target).map(|_| ()),
+        LinkKind::Symlink => symlink(&file.source, target),
+        LinkKind::Hardlink => fs::hard_link(&file.source, target),
+      })?;
+    }
+    placement.manifest.sources.insert(file.target.clone(), (file.mod_name.clone(), file.print));
+    placement.manifest.hashes.insert(file.target.clone(), file.hash.clone());
+  }
+
+  //directories the previous deployment made that nothing needs anymore
+  placement.remove_empty_dirs()
+}
+
+//Changes to the game directory as they're made. Each one is journaled before it's done and taken
+//into the manifest once it went through, and the manifest gets written every so often, so whenever
+//things go wrong the manifest and what's in the journal since tell everything that's in there
+pub struct Placement<'a> {
+  game: &'a Game,
+  deployment: Deployment,
+  pub manifest: DeploymentManifest,
+  journal: Journal,
+  unsaved: usize,
+}
+
+impl<'a> Placement<'a> {
+  //Starts from what's deployed already, written first so it's what a crash goes back to
+  pub fn begin(game: &'a Game, deployment: Deployment, manifest: DeploymentManifest) -> io::Result<Placement<'a>> {
+    manifest.save_of(game, deployment)?;
+    let journal = Journal::begin(game, deployment)?;
+    Ok(Placement { game, deployment, manifest, journal, unsaved: 0 })
+  }
+
+  fn run(&mut self, operation: Operation, action: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+    self.journal.record(&operation)?;
+    action()?;
+    self.manifest.apply(&operation);
+    self.unsaved += 1;
+    if self.unsaved >= SAVE_EVERY {
+      self.manifest.save_of(self.game, self.deployment)?;
+      self.journal.restart()?;
+      self.unsaved = 0;
+    }
+    Ok(())
+  }
+
+  //A file of the deployment comes out, gone already is fine
+  pub fn remove(&mut self, file: &Path) -> io::Result<()> {
+    self.run(Operation::Remove { path: file.to_path_buf() }, || match fs::remove_file(file) {
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+      result => result,
+    })
+  }
+
+  //A game file that was moved out of the way goes back, already back is fine
+  pub fn restore(&mut self, original: &Path, backup: &Path) -> io::Result<()> {
+    self.run(Operation::Restore { original: original.to_path_buf(), backup: backup.to_path_buf() }, || {
+      if fs::symlink_metadata(backup).is_ok() {
+        fs::rename(backup, original)?;
+      }
+      Ok(())
+    })
+  }
+
+  //Puts a file at the target with `put`. A file of the deployment that's there gets replaced,
+  //anything else is the game's and gets moved to the backup first
+  pub fn place(&mut self, target: &Path, backup: &Path, put: impl FnOnce(&Path) -> io::Result<()>) -> io::Result<()> {
+    self.create_parent_dirs(target)?;
+    if self.manifest.files.contains(target) {
+      self.remove(target)?;
+    } else if !self.manifest.displaced.iter().any(|(original, _)| original == target) && fs::symlink_metadata(target).is_ok() {
+      if let Some(parent) = backup.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      self.run(Operation::Displace { original: target.to_path_buf(), backup: backup.to_path_buf() }, || fs::rename(target, backup))?;
+    }
+    self.run(Operation::Place { path: target.to_path_buf() }, || put(target))
+  }
+
+  fn create_parent_dirs(&mut self, target: &Path) -> io::Result<()> {
+    let mut missing: Vec<PathBuf> = Vec::new();
+    let mut parent = target.parent();
+    while let Some(dir) = parent {
+      if dir.exists() {
+        break;
+      }
+      missing.push(dir.to_path_buf());
+      parent = dir.parent();
+    }
+    for dir in missing.into_iter().rev() {
+      self.run(Operation::CreateDir { path: dir.clone() }, || fs::create_dir(&dir))?;
+    }
+    Ok(())
+  }
+
+  //Directories made for the deployment that ended up empty
+  pub fn remove_empty_dirs(&mut self) -> io::Result<()> {
+    for dir in self.manifest.dirs.clone().iter().rev() {
+      let empty = fs::read_dir(dir).map(|mut entries| entries.next().is_none()).unwrap_or(false);
+      if empty {
+        if let Err(e) = self.run(Operation::RemoveDir { path: dir.clone() }, || fs::remove_dir(dir)) {
+          tracing::warn!("Couldn't remove deployed directory '{}': {}", dir.display(), e);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  pub fn finish(self) -> io::Result<DeploymentManifest> {
+    self.manifest.save_of(self.game, self.deployment)?;
+    self.journal.commit()?;
+    Ok(self.manifest)
+  }
+
+  //Writes down what got deployed before things went wrong and purges it
+  pub fn abort(self) -> io::Result<()> {
+    self.manifest.save_of(self.game, self.deployment)?;
+    self.journal.commit()?;
+    purge_of(self.game, self.deployment)
+  }
+}
+
+pub fn purge(game: &Game) -> io::Result<()> {
+  purge_of(game, Deployment::Backend)
+}
+
+pub fn purge_of(game: &Game, deployment: Deployment) -> io::Result<()> {
+  let manifest = match DeploymentManifest::load_of(game, deployment)? {
+    Some(manifest) => manifest,
+    None => return Ok(()),
+  };
+
+  for file in &manifest.files {
+    match fs::remove_file(file) {
+      Ok(()) => {},
+      Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+      Err(e) => return Err(e),
+    }
+  }
+  for (original, backup) in &manifest.displaced {
+    //already restored if a purge got interrupted before
+    if fs::symlink_metadata(backup).is_ok() {
+      fs::rename(backup, original)?;
+    }
+  }
+  for dir in manifest.dirs.iter().rev() {
+    //only fails if something else put files in there since, then it stays
+    match fs::remove_dir(dir) {
+      Ok(()) => {},
+      Err(e) => {
+        tracing::warn!("Couldn't remove deployed directory '{}': {}", dir.display(), e);
+      }
+    }
+  }
+  DeploymentManifest::remove_of(game, deployment)
+}
+
+//Purges a deployment that never finished, with what the journal says it got to taken into the
+//manifest first. Returns how many operations it had gotten to since the manifest was last written
+pub fn repair(game: &Game) -> io::Result<usize> {
+  repair_of(game, Deployment::Backend)
+}
+
+pub fn repair_of(game: &Game, deployment: Deployment) -> io::Result<usize> {
+  let operations = match journal::pending(game, deployment)? {
+    Some(operations) => operations,
+    None => return Ok(0),
+  };
+  let mut manifest = DeploymentManifest::load_of(game, deployment)?.unwrap_or_default();
+  journal::replay(&mut manifest, &operations);
+  manifest.save_of(game, deployment)?;
+  journal::discard(game, deployment)?;
+  purge_of(game, deployment)?;
+  Ok(operations.len())
+}
+
+fn same_device(game: &Game) -> io::Result<bool> {
+  let mods_device = fs::metadata(game.profile_path.join("mods"))?.dev();
+  let game_device = fs::metadata(&game.install_path)?.dev();
+  Ok(mods_device == game_device)
+}
+
+//Displaced files get moved next to the work directory, which is on the
+//same filesystem as the game, so moving them is just a rename
+fn backup_path(game: &Game) -> PathBuf {
+  game.work_path.with_extension("backup")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+  }
+
+  fn deploy(game: &Game, mods: &[Mod]) -> DeployReport {
+    let previous = DeploymentManifest::load(game).unwrap().unwrap_or(DeploymentManifest { kind: LinkKind::Copy, ..DeploymentManifest::default() });
+    let mut placement = Placement::begin(game, Deployment::Backend, previous).unwrap();
+    let mut report = DeployReport::default();
+    place_mods(game, mods, &mut placement, &mut report).unwrap();
+    placement.finish().unwrap();
+    report
+  }
+
+  fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap()
+  }
+
+  #[test]
+  fn later_mods_win_and_only_changes_get_redone() {
+    let dir = std::env::temp_dir().join(format!("tmm-link-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let game = Game { reflink: Some(false), ..Game::in_dir(&dir) };
+    let data = game.install_path.join("Data");
+    write(&data.join("shared.txt"), "game");
+    write(&game.profile_path.join("mods/A/a.txt"), "a");
+    write(&game.profile_path.join("mods/A/shared.txt"), "from a");
+    write(&game.profile_path.join("mods/B/shared.txt"), "from b");
+    let (a, b) = (Mod::generated("A"), Mod::generated("B"));
+
+    let mut report = DeployReport::default();
+    let planned = plan(&game, &[a.clone(), b.clone()], &data, &mut report).unwrap();
+    assert_eq!(planned.len(), 2);
+    assert!(planned.iter().any(|(target, _, mod_name)| *target == data.join("shared.txt") && mod_name == "B"));
+
+    let report = deploy(&game, &[a.clone(), b.clone()]);
+    assert_eq!((report.changes.added, report.changes.unchanged), (2, 0));
+    assert_eq!(read(&data.join("shared.txt")), "from b");
+    assert_eq!(read(&backup_path(&game).join("shared.txt")), "game");
+
+    let report = deploy(&game, &[a.clone(), b.clone()]);
+    assert_eq!((report.changes.added, report.changes.replaced, report.changes.unchanged), (0, 0, 2));
+
+    write(&game.profile_path.join("mods/B/shared.txt"), "from b, changed");
+    let report = deploy(&game, &[a.clone(), b]);
+    assert_eq!((report.changes.replaced, report.changes.unchanged), (1, 1));
+    assert_eq!(read(&data.join("shared.txt")), "from b, changed");
+
+    let report = deploy(&game, &[a]);
+    assert_eq!(report.changes.replaced, 1);
+    assert_eq!(read(&data.join("shared.txt")), "from a");
+
+    purge(&game).unwrap();
+    assert_eq!(read(&data.join("shared.txt")), "game");
+    assert!(!data.join("a.txt").exists());
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
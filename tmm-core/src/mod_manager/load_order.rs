@@ -0,0 +1,150 @@
+use crate::mod_manager::{BatchReport, Mod, check_mod_name, conflicts, dependencies, deployable, load_mods, modlist, resolve_mod, resolve_mods, trash};
+use crate::mod_manager::game::Game;
+use crate::error::{Context, Error, ErrorCode};
+use crate::events::{BackendEvent, ConflictsChanged, Events};
+use serde::Serialize;
+use std::io;
+
+//Changing the order and which mods are enabled, for the app and the CLI. The mods given
+//only name them, what they are comes from the profile
+
+#[derive(Debug, Serialize)]
+pub struct MoveReport {
+  mods: Vec<Mod>,
+  changes: Vec<conflicts::OwnerChange>,
+}
+
+fn not_installed(name: &str) -> io::Error {
+  Error::new(ErrorCode::ModNotFound, format!("'{}' isn't installed", name))
+    .param("mod", name)
+    .into_io(io::ErrorKind::NotFound)
+}
+
+fn send_changes(game: &Game, changes: &[conflicts::OwnerChange], events: &Events) {
+  if !changes.is_empty() {
+    events.send(BackendEvent::ConflictsChanged(ConflictsChanged { appid: game.appid, changes: changes.to_vec() }));
+  }
+}
+
+fn save_order(game: &Game, names: &[String]) -> io::Result<()> {
+  let mut modlist = modlist::ModList::load(game)?;
+  modlist.set_order(names);
+  modlist.save(game)
+}
+
+//Moves one mod to its new place in the whole load order, the changes are which
+//files come from another mod now, of the enabled ones
+pub fn move_mod(game: &Game, elem: &Mod, new_index: usize, events: &Events) -> io::Result<MoveReport> {
+  let elem = resolve_mod(game, elem)?;
+  let before = load_mods(game)?;
+  let mut names: Vec<String> = before.iter().map(|other| other.name.clone()).filter(|name| *name != elem.name).collect();
+  names.insert(new_index.min(names.len()), elem.name.clone());
+  let mut modlist = modlist::ModList::load(game)?;
+  modlist.set_order(&names);
+  modlist.save(game)?;
+  let after = modlist.sorted(&before);
+  let changes = conflicts::owner_changes(game, &modlist.enabled(&before), &modlist.enabled(&after))?;
+  send_changes(game, &changes, events);
+  Ok(MoveReport { mods: after, changes })
+}
+
+//Every name has to be an installed mod, installed ones that aren't named keep their place after them
+pub fn set_order(game: &Game, mods: &[Mod]) -> io::Result<Vec<Mod>> {
+  let names: Vec<String> = resolve_mods(game, mods)?.into_iter().map(|elem| elem.name).collect();
+  for name in &names {
+    check_mod_name(name)?;
+  }
+  if let Some(missing) = names.iter().find(|name| !game.profile_path.join("mods").join(name).is_dir()) {
+    return Err(not_installed(missing));
+  }
+  save_order(game, &names)?;
+  load_mods(game)
+}
+
+//Moves dependencies in front of the mods that need them, nothing else changes place
+pub fn sort(game: &Game) -> io::Result<Vec<Mod>> {
+  let names: Vec<String> = dependencies::sort(&load_mods(game)?).into_iter().map(|elem| elem.name).collect();
+  save_order(game, &names)?;
+  load_mods(game)
+}
+
+//Returns what the enabled mods are missing now, e.g. the dependencies of the one just enabled
+//or the mods that needed the one just disabled
+pub fn set_enabled(game: &Game, elem: &Mod, enabled: bool) -> io::Result<Vec<dependencies::MissingDependency>> {
+  let elem = resolve_mod(game, elem)?;
+  let mut modlist = modlist::ModList::load(game)?;
+  modlist.set_enabled(&elem.name, enabled);
+  modlist.save(game)?;
+  Ok(dependencies::check(&load_mods(game)?))
+}
+
+fn batch_report(game: &Game, changed: Vec<String>, changes: Vec<conflicts::OwnerChange>, trashed: Vec<trash::TrashEntry>, events: &Events) -> io::Result<BatchReport> {
+  let report = BatchReport { appid: game.appid, changed, changes, missing_dependencies: dependencies::check(&load_mods(game)?), trashed };
+  send_changes(game, &report.changes, events);
+  events.send(BackendEvent::ModsChanged(report.clone()));
+  Ok(report)
+}
+
+//The mod list gets written once for all of them
+pub fn set_all_enabled(game: &Game, mods: &[Mod], enabled: bool, events: &Events) -> io::Result<BatchReport> {
+  let names: Vec<String> = resolve_mods(game, mods)?.into_iter().map(|elem| elem.name).collect();
+  let before = load_mods(game)?;
+  let mut modlist = modlist::ModList::load(game)?;
+  let before_enabled = modlist.enabled(&before);
+  for name in &names {
+    modlist.set_enabled(name, enabled);
+  }
+  modlist.save(game)?;
+  let changes = conflicts::owner_changes(game, &before_enabled, &modlist.enabled(&before))?;
+  batch_report(game, names, changes, Vec::new(), events)
+}
+
+//All of them go into the trash, a failure stops there and the ones before it stay removed
+pub fn remove_all(game: &Game, mods: &[Mod], events: &Events) -> io::Result<BatchReport> {
+  let names: Vec<String> = resolve_mods(game, mods)?.into_iter().map(|elem| elem.name).collect();
+  let before = deployable(game, &load_mods(game)?)?;
+  let mut trashed: Vec<trash::TrashEntry> = Vec::new();
+  for name in &names {
+    let entry = trash::remove(game, name).map_err(|e| {
+      let kind = e.kind();
+      Error::io(Context::Trash, e).param("mod_name", name).into_io(kind)
+    })?;
+    trashed.push(entry);
+  }
+  let after: Vec<Mod> = before.iter().filter(|elem| !names.contains(&elem.name)).cloned().collect();
+  let changes = conflicts::owner_changes(game, &before, &after)?;
+  batch_report(game, names, changes, trashed, events)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+
+  fn names(mods: &[Mod]) -> Vec<&str> {
+    mods.iter().map(|elem| elem.name.as_str()).collect()
+  }
+
+  #[test]
+  fn orders_and_enables_installed_mods() {
+    let dir = std::env::temp_dir().join(format!("tmm-load-order-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let game = Game::in_dir(&dir);
+    for name in ["A", "B", "C"] {
+      fs::create_dir_all(game.profile_path.join("mods").join(name).join("1.0")).unwrap();
+    }
+    let events = Events::none();
+    let report = move_mod(&game, &Mod::generated("C"), 0, &events).unwrap();
+    assert_eq!(names(&report.mods), ["C", "A", "B"]);
+    assert_eq!(names(&set_order(&game, &[Mod::generated("B")]).unwrap()), ["B", "C", "A"]);
+    assert_eq!(set_order(&game, &[Mod::generated("D")]).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+    let report = set_all_enabled(&game, &[Mod::generated("A"), Mod::generated("C")], false, &events).unwrap();
+    assert_eq!(report.changed, ["A", "C"]);
+    let enabled: Vec<String> = load_mods(&game).unwrap().into_iter().filter(|elem| elem.enabled).map(|elem| elem.name).collect();
+    assert_eq!(enabled, ["B"]);
+    set_enabled(&game, &Mod::generated("C"), true).unwrap();
+    assert!(load_mods(&game).unwrap().iter().any(|elem| elem.name == "C" && elem.enabled));
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
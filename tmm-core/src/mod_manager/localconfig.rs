@@ -0,0 +1,137 @@
+use crate::mod_manager::{launch, meta, scan};
+use crate::mod_manager::vdf::{self, Vdf};
+use crate::error::{Error, ErrorCode};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+const APPS_PATH: &[&str] = &["UserLocalConfigStore", "Software", "Valve", "Steam", "apps"];
+//the files as they were before the last few writes are kept next to it
+const BACKUPS_KEPT: usize = 5;
+//what a SteamID64 is over the account id the userdata folders are named after
+const STEAM_ID_BASE: u64 = 76561197960265728;
+
+//The account that logged in last, the one loginusers.vdf has as MostRecent
+fn most_recent_account(root: &Path) -> Option<u64> {
+  let entries = vdf::read(&root.join("config").join("loginusers.vdf")).ok()?;
+  let users = match vdf::lookup(&entries, &["users"])? {
+    Vdf::Block(users) => users,
+    _ => return None,
+  };
+  users.iter().find_map(|(steam_id, user)| match user.inner() {
+    Vdf::Block(fields) if matches!(vdf::child(fields, "MostRecent"), Some(Vdf::Value(value)) if value == "1") => {
+      steam_id.parse::<u64>().ok()?.checked_sub(STEAM_ID_BASE)
+    },
+    _ => None,
+  })
+}
+
+//The one of the account Steam logs in with, the others' launch options aren't what the game starts with.
+//A single account on the machine is that one even without loginusers.vdf
+fn localconfig() -> Option<PathBuf> {
+  for root in scan::steam_dirs().into_iter().map(|steam_dir| steam_dir.path) {
+    let userdata = root.join("userdata");
+    let account = most_recent_account(&root).map(|account| userdata.join(account.to_string())).or_else(|| {
+      let accounts: Vec<PathBuf> = userdata.read_dir().ok()?.flatten().map(|user| user.path()).filter(|user| user.is_dir()).collect();
+      match accounts.as_slice() {
+        [only] => Some(only.clone()),
+        _ => None,
+      }
+    });
+    let path = match account {
+      Some(account) => account.join("config").join("localconfig.vdf"),
+      None => continue,
+    };
+    if path.is_file() {
+      return Some(path);
+    }
+  }
+  None
+}
+
+fn launch_options_in(entries: &[(String, Vdf)], appid: u32) -> Option<String> {
+  let appid = appid.to_string();
+  let keys: Vec<&str> = APPS_PATH.iter().copied().chain([appid.as_str(), "LaunchOptions"]).collect();
+  match vdf::lookup(entries, &keys)? {
+    Vdf::Value(value) => Some(value.clone()),
+    _ => None,
+  }
+}
+
+//What the active account has set for the game, None if it has none
+pub fn launch_options(appid: u32) -> io::Result<Option<String>> {
+  match localconfig() {
+    Some(path) => Ok(launch_options_in(&vdf::read(&path)?, appid)),
+    None => Ok(None),
+  }
+}
+
+//'localconfig.vdf.tmm-backup.<secs>', the oldest go once there are more than BACKUPS_KEPT
+fn back_up(path: &Path) -> io::Result<()> {
+  let prefix = format!("{}.tmm-backup.", path.file_name().unwrap_or_default().to_string_lossy());
+  fs::copy(path, path.with_file_name(format!("{}{}", prefix, meta::now().unwrap_or(0))))?;
+  let mut backups: Vec<PathBuf> = match path.parent() {
+    Some(parent) => parent.read_dir()?.flatten()
+      .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+      .map(|entry| entry.path())
+      .collect(),
+    None => return Ok(()),
+  };
+  backups.sort_by_key(|backup| backup.extension().and_then(|secs| secs.to_str()?.parse::<u64>().ok()).unwrap_or(0));
+  while backups.len() > BACKUPS_KEPT {
+    fs::remove_file(backups.remove(0))?;
+  }
+  Ok(())
+}
+
+//For the active account. Steam writes the file back from memory when it exits, so it has to be closed
+//or the change would be gone again. The file as it was is kept next to it
+pub fn set_launch_options(appid: u32, options: &str) -> io::Result<()> {
+  if launch::steam_running() {
+    return Err(Error::new(ErrorCode::SteamRunning, "Steam has to be closed to change the launch options")
+      .into_io(io::ErrorKind::Other));
+  }
+  let path = match localconfig() {
+    Some(path) => path,
+    None => {
+      return Err(Error::new(ErrorCode::NoSteamAccount, "there's no Steam account that logged in last to set the launch options for")
+        .into_io(io::ErrorKind::NotFound));
+    }
+  };
+  let appid = appid.to_string();
+  let mut entries = vdf::read(&path)?;
+  let mut current = &mut entries;
+  for key in APPS_PATH.iter().copied().chain(std::iter::once(appid.as_str())) {
+    current = vdf::block_mut(current, key);
+  }
+  current.retain(|(name, _)| !name.eq_ignore_ascii_case("LaunchOptions"));
+  current.push(("LaunchOptions".to_string(), Vdf::Value(options.to_string())));
+
+  let mut contents = String::new();
+  vdf::render(&entries, 0, &mut contents);
+  back_up(&path)?;
+  let temporary = path.with_extension("vdf.tmp");
+  fs::write(&temporary, contents)?;
+  fs::rename(&temporary, &path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn the_accounts_launch_options() {
+    let dir = std::env::temp_dir().join(format!("tmm-localconfig-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("config")).unwrap();
+    let users = format!("\"users\" {{ \"{}\" {{ \"MostRecent\" \"0\" }} \"{}\" {{ \"MostRecent\" \"1\" }} }}", STEAM_ID_BASE + 1, STEAM_ID_BASE + 42);
+    fs::write(dir.join("config").join("loginusers.vdf"), users).unwrap();
+    assert_eq!(most_recent_account(&dir), Some(42));
+
+    let path = dir.join("localconfig.vdf");
+    fs::write(&path, "\"UserLocalConfigStore\" { \"Software\" { \"valve\" { \"Steam\" { \"apps\" { \"489830\" { \"LaunchOptions\" \"-console\" } } } } } }").unwrap();
+    let entries = vdf::read(&path).unwrap();
+    assert_eq!(launch_options_in(&entries, 489830).as_deref(), Some("-console"));
+    assert_eq!(launch_options_in(&entries, 72850), None);
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
@@ -0,0 +1,142 @@
+use crate::mod_manager::scan::{self, Found};
+use crate::mod_manager::game::{Executable, Store};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::fs;
+
+//What `lutris --list-games --installed --json` says about a game
+#[derive(Clone, Deserialize)]
+struct LutrisGame {
+  slug: String,
+  name: String,
+  runner: Option<String>,
+  directory: Option<PathBuf>,
+}
+
+//Lutris games have no number of their own, so they get a made up one from the slug
+pub fn appid_for(slug: &str) -> u32 {
+  scan::made_up_appid(&format!("lutris:{}", slug))
+}
+
+//Game configs are '<slug>-<timestamp>.yml', older Lutris versions kept them in ~/.config
+fn game_config(slug: &str) -> Option<Value> {
+  let dirs = [
+    dirs::data_dir().map(|dir| dir.join("lutris").join("games")),
+    dirs::config_dir().map(|dir| dir.join("lutris").join("games")),
+  ];
+  for dir in dirs.iter().flatten() {
+    let entries = match dir.read_dir() {
+      Ok(entries) => entries,
+      Err(_) => continue,
+    };
+    for entry in entries.flatten() {
+      let name = entry.file_name().to_string_lossy().to_string();
+      //'<slug>-<timestamp>' of a game whose slug starts the same doesn't count
+      let timestamp = name.strip_prefix(slug).and_then(|rest| rest.strip_prefix('-')).and_then(|rest| rest.strip_suffix(".yml"));
+      if timestamp.map_or(false, |timestamp| !timestamp.is_empty() && timestamp.bytes().all(|byte| byte.is_ascii_digit())) {
+        if let Ok(config) = fs::read_to_string(entry.path()).map(|contents| serde_yaml::from_str(&contents)) {
+          return config.ok();
+        }
+      }
+    }
+  }
+  None
+}
+
+fn absolute(path: &str, directory: &Option<PathBuf>) -> PathBuf {
+  let path = PathBuf::from(path);
+  match directory {
+    Some(directory) if path.is_relative() => directory.join(path),
+    _ => path,
+  }
+}
+
+fn lutris_game(game: LutrisGame) -> Option<Found> {
+  let config = game_config(&game.slug);
+  let section = config.as_ref().map(|config| &config["game"]);
+  let exe = section.and_then(|section| section["exe"].as_str()).map(|exe| absolute(exe, &game.directory));
+  let wine_prefix = section.and_then(|section| section["prefix"].as_str()).map(PathBuf::from);
+  //the game's own directory, the one Lutris has might be the whole prefix
+  let install_path = match &exe {
+    Some(exe) => exe.parent()?.to_path_buf(),
+    None => game.directory.clone()?,
+  };
+  let executables = match &exe {
+    Some(exe) => vec![Executable {
+      name: game.name.clone(),
+      use_compatibility: game.runner.as_deref() == Some("wine"),
+      binary_path: PathBuf::from("/").join(exe.strip_prefix(&install_path).ok()?),
+      startin_path: PathBuf::new(),
+      output_mod: "overwrite".to_string(),
+    }],
+    None => Vec::new(),
+  };
+  let appid = appid_for(&game.slug);
+  Some(Found {
+    store: Store::Lutris,
+    appid,
+    name: game.name,
+    work_path: install_path.parent().unwrap_or(Path::new("/")).join(".tmm_work").join(appid.to_string()),
+    install_path,
+    executables,
+    wine_prefix,
+    store_id: Some(game.slug),
+    library: None,
+    version: None,
+  })
+}
+
+//The installed games by the mtime of Lutris' database, which changes with every install and
+//removal. Starting Lutris takes seconds, only worth it when the database changed
+type Listed = (Option<SystemTime>, Vec<LutrisGame>);
+static LISTED: Lazy<Mutex<Option<Listed>>> = Lazy::new(|| Mutex::new(None));
+
+fn database_mtime() -> Option<SystemTime> {
+  let database = dirs::data_dir()?.join("lutris").join("pga.db");
+  fs::metadata(database).and_then(|metadata| metadata.modified()).ok()
+}
+
+pub fn scan_for_lutris_games() -> Vec<Found> {
+  let mtime = database_mtime();
+  let mut listed = LISTED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+  let games = match listed.as_ref() {
+    Some((listed_mtime, games)) if mtime.is_some() && *listed_mtime == mtime => games.clone(),
+    _ => {
+      let games = list_games();
+      *listed = Some((mtime, games.clone()));
+      games
+    }
+  };
+  games.into_iter().filter_map(lutris_game).collect()
+}
+
+fn list_games() -> Vec<LutrisGame> {
+  let output = match Command::new("lutris").arg("--list-games").arg("--installed").arg("--json").output() {
+    Ok(output) if output.status.success() => output,
+    _ => return Vec::new(),
+  };
+  //Lutris logs to stdout before the json sometimes
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let json = match stdout.find('[') {
+    Some(start) => &stdout[start..],
+    None => return Vec::new(),
+  };
+  match serde_json::from_str(json) {
+    Ok(games) => games,
+    Err(e) => {
+      tracing::warn!("Couldn't read the games Lutris listed: {}", e);
+      Vec::new()
+    }
+  }
+}
+
+//Lutris sets up the runner, prefix and everything else itself
+pub fn launch(slug: &str) -> std::io::Result<()> {
+  Command::new("lutris").arg(format!("lutris:rungame/{}", slug)).spawn()?;
+  Ok(())
+}
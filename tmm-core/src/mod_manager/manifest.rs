@@ -0,0 +1,118 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::journal::Operation;
+use crate::mod_manager::link::LinkKind;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::{fs, io, path::PathBuf};
+
+//Which deployment a manifest and its journal belong to, the backend's own or the
+//root files that get put next to whatever the backend did
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Deployment {
+  Backend,
+  Root,
+}
+
+impl Deployment {
+  pub fn file_stem(self) -> &'static str {
+    match self {
+      Deployment::Backend => "deployment",
+      Deployment::Root => "root_deployment",
+    }
+  }
+}
+
+//Everything a deployment did to the game directory, so purging
+//can undo exactly that and nothing else
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeploymentManifest {
+  //every file that was put inside the game directory
+  pub files: BTreeSet<PathBuf>,
+  //directories that didn't exist before deploying, in creation order
+  pub dirs: Vec<PathBuf>,
+  //original game files that had to be moved out of the way (original, backup)
+  pub displaced: Vec<(PathBuf, PathBuf)>,
+  //how the files got there
+  #[serde(default)]
+  pub kind: LinkKind,
+  //deployed file -> the mod it came from and a fingerprint of the source's metadata
+  #[serde(default)]
+  pub sources: BTreeMap<PathBuf, (String, u64)>,
+  //deployed file -> the hash of the source's contents, what lets the next deployment
+  //only redo what changed. Only hashed again once the metadata changed
+  #[serde(default)]
+  pub hashes: BTreeMap<PathBuf, String>,
+}
+
+impl DeploymentManifest {
+  pub fn path(game: &Game) -> PathBuf {
+    DeploymentManifest::path_of(game, Deployment::Backend)
+  }
+
+  pub fn path_of(game: &Game, deployment: Deployment) -> PathBuf {
+    game.profile_path.join(format!("{}.json", deployment.file_stem()))
+  }
+
+  pub fn load(game: &Game) -> io::Result<Option<DeploymentManifest>> {
+    DeploymentManifest::load_of(game, Deployment::Backend)
+  }
+
+  pub fn load_of(game: &Game, deployment: Deployment) -> io::Result<Option<DeploymentManifest>> {
+    let path = DeploymentManifest::path_of(game, deployment);
+    if !path.exists() {
+      return Ok(None);
+    }
+    let contents = fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+  }
+
+  pub fn save(&self, game: &Game) -> io::Result<()> {
+    self.save_of(game, Deployment::Backend)
+  }
+
+  //Written next to the old one and moved over it, a crash leaves one or the other
+  pub fn save_of(&self, game: &Game, deployment: Deployment) -> io::Result<()> {
+    let path = DeploymentManifest::path_of(game, deployment);
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, serde_json::to_string(self)?)?;
+    fs::rename(temporary, path)
+  }
+
+  pub fn remove(game: &Game) -> io::Result<()> {
+    DeploymentManifest::remove_of(game, Deployment::Backend)
+  }
+
+  pub fn remove_of(game: &Game, deployment: Deployment) -> io::Result<()> {
+    let path = DeploymentManifest::path_of(game, deployment);
+    if path.exists() {
+      fs::remove_file(path)?;
+    }
+    Ok(())
+  }
+
+  //Takes in an operation that went through
+  pub fn apply(&mut self, operation: &Operation) {
+    match operation {
+      Operation::CreateDir { path } => {
+        if !self.dirs.contains(path) {
+          self.dirs.push(path.clone());
+        }
+      },
+      Operation::RemoveDir { path } => self.dirs.retain(|dir| dir != path),
+      Operation::Displace { original, backup } => {
+        if !self.displaced.iter().any(|(displaced, _)| displaced == original) {
+          self.displaced.push((original.clone(), backup.clone()));
+        }
+      },
+      Operation::Restore { original, .. } => self.displaced.retain(|(displaced, _)| displaced != original),
+      Operation::Place { path } => {
+        self.files.insert(path.clone());
+      },
+      Operation::Remove { path } => {
+        self.files.remove(path);
+        self.sources.remove(path);
+        self.hashes.remove(path);
+      },
+    }
+  }
+}
@@ -0,0 +1,247 @@
+use crate::mod_manager::{Mod, GENERATED_MOD_PREFIX, get_files, link, mod_dir};
+use crate::mod_manager::excludes::{Excludes, Filtered};
+use crate::mod_manager::game::Game;
+use crate::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use xmltree::{Element, EmitterConfig, XMLNode};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use std::{fs, io};
+
+//The generated mod lives with the others so every deploy mode can
+//handle it like any other mod, get_mods just doesn't list it
+pub const MERGED_MOD_NAME: &str = ".tmm_merged_configs";
+
+//What the merged mod was generated from, if any of it
+//changes the merged files get regenerated
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct MergeState {
+  //relative path -> (the mod's folder in 'mods', so which version, modified time in nanoseconds, size) in merge order
+  contributors: BTreeMap<PathBuf, Vec<(String, u128, u64)>>,
+}
+
+#[derive(Clone, Copy)]
+enum ConfigFormat {
+  Json,
+  Ini,
+  Xml,
+}
+
+impl ConfigFormat {
+  fn of(path: &Path) -> Option<ConfigFormat> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+      "json" => Some(ConfigFormat::Json),
+      "ini" => Some(ConfigFormat::Ini),
+      "xml" => Some(ConfigFormat::Xml),
+      _ => None,
+    }
+  }
+}
+
+pub fn is_mergeable(path: &Path) -> bool {
+  ConfigFormat::of(path).is_some()
+}
+
+//Regenerates the merged configs mod for the given load order if anything it
+//depends on changed, returns the mod to deploy on top if there is anything merged
+pub fn refresh(game: &Game, mods: &[Mod]) -> io::Result<Option<Mod>> {
+  let state = current_state(game, mods)?;
+  let merged_path = game.profile_path.join("mods").join(MERGED_MOD_NAME);
+  let state_path = game.profile_path.join("merged_configs.json");
+
+  if state.contributors.is_empty() {
+    if merged_path.exists() {
+      fs::remove_dir_all(&merged_path)?;
+    }
+    if state_path.exists() {
+      fs::remove_file(&state_path)?;
+    }
+    return Ok(None);
+  }
+
+  let previous: Option<MergeState> = match fs::read_to_string(&state_path) {
+    Ok(contents) => serde_json::from_str(&contents).ok(),
+    Err(_) => None,
+  };
+  if previous.as_ref() != Some(&state) || !merged_path.exists() {
+    if merged_path.exists() {
+      fs::remove_dir_all(&merged_path)?;
+    }
+    for (relative, contributors) in &state.contributors {
+      let sources: Vec<PathBuf> = contributors.iter()
+        .map(|(folder, _, _)| game.profile_path.join("mods").join(folder).join(relative))
+        .collect();
+      let vanilla = game.install_path.join(&game.path_extension).join(relative);
+      let merged = merge_file(relative, &vanilla, &sources)?;
+      let target = merged_path.join(relative);
+      if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      fs::write(target, merged)?;
+    }
+    fs::write(&state_path, serde_json::to_string(&state)?)?;
+  }
+
+  Ok(Some(Mod::generated(MERGED_MOD_NAME)))
+}
+
+//Every config file that more than one of the mods ships, only what would get deployed counts
+fn current_state(game: &Game, mods: &[Mod]) -> io::Result<MergeState> {
+  let excludes = Excludes::for_game(game)?;
+  let mut providers: BTreeMap<PathBuf, Vec<(String, u128, u64)>> = BTreeMap::new();
+  for elem in mods.iter().filter(|elem| !elem.name.starts_with(GENERATED_MOD_PREFIX)) {
+    let mod_root = mod_dir(game, elem)?;
+    let folder = mod_root.strip_prefix(game.profile_path.join("mods")).unwrap_or(&mod_root).to_string_lossy().to_string();
+    for source in get_files(&mod_root)? {
+      let relative = link::relative(&source, &mod_root)?.to_path_buf();
+      if ConfigFormat::of(&source).is_none() || excludes.filter(elem, &relative) != Filtered::Deployed {
+        continue;
+      }
+      let metadata = fs::metadata(&source)?;
+      let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or(0);
+      providers.entry(relative).or_default().push((folder.clone(), modified, metadata.len()));
+    }
+  }
+  providers.retain(|_, contributors| contributors.len() > 1);
+  Ok(MergeState { contributors: providers })
+}
+
+//Merges the sources on top of the vanilla file (if the game has one),
+//when two mods set the same value the later one wins
+fn merge_file(relative: &Path, vanilla: &Path, sources: &[PathBuf]) -> io::Result<Vec<u8>> {
+  let mut layers: Vec<Vec<u8>> = Vec::new();
+  if vanilla.is_file() {
+    layers.push(fs::read(vanilla)?);
+  }
+  for source in sources {
+    layers.push(fs::read(source)?);
+  }
+
+  match ConfigFormat::of(relative).unwrap() {
+    ConfigFormat::Json => {
+      let mut merged = Value::Null;
+      for layer in layers {
+        let layer = serde_json::from_slice(&layer).map_err(|e| parse_error(relative, e.to_string()))?;
+        merge_json(&mut merged, layer);
+      }
+      Ok(serde_json::to_vec_pretty(&merged)?)
+    },
+    ConfigFormat::Ini => {
+      let mut merged: Vec<(String, Vec<(String, String)>)> = Vec::new();
+      for layer in layers {
+        merge_ini(&mut merged, &String::from_utf8_lossy(&layer));
+      }
+      Ok(write_ini(&merged).into_bytes())
+    },
+    ConfigFormat::Xml => {
+      let mut merged: Option<Element> = None;
+      for layer in layers {
+        let element = Element::parse(layer.as_slice())
+          .map_err(|e| parse_error(relative, e.to_string()))?;
+        match merged {
+          Some(ref mut base) => merge_xml(base, element),
+          None => merged = Some(element),
+        }
+      }
+      let mut output: Vec<u8> = Vec::new();
+      let merged = merged.ok_or_else(|| parse_error(relative, "there's nothing to merge".to_string()))?;
+      merged.write_with_config(&mut output, EmitterConfig::new().perform_indent(true))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+      Ok(output)
+    }
+  }
+}
+
+fn parse_error(relative: &Path, detail: String) -> io::Error {
+  Error::new(ErrorCode::ConfigParseFailed, format!("'{}': {}", relative.display(), detail))
+    .param("path", relative)
+    .into_io(io::ErrorKind::InvalidData)
+}
+
+fn merge_json(base: &mut Value, layer: Value) {
+  match (base, layer) {
+    (Value::Object(base), Value::Object(layer)) => {
+      for (key, value) in layer {
+        merge_json(base.entry(key).or_insert(Value::Null), value);
+      }
+    },
+    (base, layer) => *base = layer,
+  }
+}
+
+//Sections and keys keep the order they first showed up in,
+//keys are compared case insensitively like most games do
+fn merge_ini(base: &mut Vec<(String, Vec<(String, String)>)>, layer: &str) {
+  let mut section = String::new();
+  for line in layer.lines() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+      continue;
+    }
+    if line.starts_with('[') && line.ends_with(']') {
+      section = line[1..line.len() - 1].trim().to_string();
+      continue;
+    }
+    let (key, value) = match line.split_once('=') {
+      Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+      None => (line.to_string(), String::new()),
+    };
+    let index = match base.iter().position(|(name, _)| name.eq_ignore_ascii_case(&section)) {
+      Some(index) => index,
+      None => {
+        base.push((section.clone(), Vec::new()));
+        base.len() - 1
+      }
+    };
+    let entries = &mut base[index].1;
+    match entries.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case(&key)) {
+      Some(entry) => entry.1 = value,
+      None => entries.push((key, value)),
+    }
+  }
+}
+
+fn write_ini(sections: &[(String, Vec<(String, String)>)]) -> String {
+  let mut output = String::new();
+  for (section, entries) in sections {
+    if !section.is_empty() {
+      output.push_str(&format!("[{}]\n", section));
+    }
+    for (key, value) in entries {
+      output.push_str(&format!("{}={}\n", key, value));
+    }
+    output.push('\n');
+  }
+  output
+}
+
+//Child elements are matched up by their tag and identifying attribute,
+//matched ones get merged recursively, new ones get appended
+fn merge_xml(base: &mut Element, layer: Element) {
+  for (key, value) in layer.attributes {
+    base.attributes.insert(key, value);
+  }
+  let has_elements = layer.children.iter().any(|child| child.as_element().is_some());
+  if !has_elements {
+    base.children = layer.children;
+    return;
+  }
+  for child in layer.children {
+    if let XMLNode::Element(child) = child {
+      let existing = base.children.iter_mut()
+        .filter_map(|node| node.as_mut_element())
+        .find(|node| node.name == child.name && xml_identity(node) == xml_identity(&child));
+      match existing {
+        Some(existing) => merge_xml(existing, child),
+        None => base.children.push(XMLNode::Element(child)),
+      }
+    }
+  }
+}
+
+fn xml_identity(element: &Element) -> Option<&String> {
+  ["id", "name", "key"].iter().find_map(|attribute| element.attributes.get(*attribute))
+}
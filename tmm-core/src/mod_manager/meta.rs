@@ -0,0 +1,187 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{check_mod_name, overrides, pool, versions, with_suffix};
+use crate::mod_manager::dependencies::Dependency;
+use crate::mod_downloader::source::ModMetadata;
+use crate::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+//Sits in the mod's root, the backends that place single files leave it out,
+//the overlay can't so it shows up in the game directory there
+pub const META_FILE_NAME: &str = "meta.json";
+
+//Everything known about an installed mod besides its files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModMeta {
+  #[serde(default)]
+  pub version: Option<String>,
+  #[serde(default)]
+  pub author: Option<String>,
+  //the id of the download source, e.g. 'github'
+  #[serde(default)]
+  pub source: Option<String>,
+  #[serde(default)]
+  pub source_url: Option<String>,
+  //the source's own id for the mod
+  #[serde(default)]
+  pub mod_id: Option<String>,
+  //seconds since the epoch
+  #[serde(default)]
+  pub installed_at: Option<u64>,
+  //blake3 of the archive it was installed from
+  #[serde(default)]
+  pub archive_hash: Option<String>,
+  #[serde(default)]
+  pub category: Option<String>,
+  //the user's, a mod can have any number of them
+  #[serde(default)]
+  pub tags: Vec<String>,
+  //other mods this one needs, shipped in the archive's meta.json or written in by the user
+  #[serde(default)]
+  pub dependencies: Vec<Dependency>,
+  //the user's own, installs and updates leave them alone
+  #[serde(default)]
+  pub notes: Option<String>,
+  //shown instead of the version, for mods patched by hand
+  #[serde(default)]
+  pub custom_version: Option<String>,
+  //whatever the frontend makes of it, e.g. '#e06c75'
+  #[serde(default)]
+  pub color: Option<String>,
+  //files of the mod, relative to it, that don't take part in conflicts and don't get
+  //deployed, except by the overlay which can't leave single files out
+  #[serde(default)]
+  pub hidden: Vec<PathBuf>,
+}
+
+pub fn is_meta_file(relative: &Path) -> bool {
+  relative == Path::new(META_FILE_NAME)
+}
+
+//Linked mods are a link to the user's own folder, the manager doesn't write into it.
+//Their meta sits next to the link instead and moves along with it
+fn sidecar_path(mod_dir: &Path) -> PathBuf {
+  with_suffix(mod_dir, ".tmm_meta.json")
+}
+
+fn is_linked(mod_dir: &Path) -> bool {
+  fs::symlink_metadata(mod_dir).map_or(false, |metadata| metadata.file_type().is_symlink())
+}
+
+//The meta a linked folder came with counts until there's one of its own
+pub fn load(mod_dir: &Path) -> ModMeta {
+  let sidecar = sidecar_path(mod_dir);
+  let path = if is_linked(mod_dir) && sidecar.exists() { sidecar } else { mod_dir.join(META_FILE_NAME) };
+  fs::read_to_string(path).ok()
+    .and_then(|contents| serde_json::from_str(&contents).ok())
+    .unwrap_or_default()
+}
+
+//Written next to the old one and renamed over it, so a meta file the pool shares
+//with another mod gets replaced instead of changed for both
+pub fn save(mod_dir: &Path, meta: &ModMeta) -> io::Result<()> {
+  let path = if is_linked(mod_dir) { sidecar_path(mod_dir) } else { mod_dir.join(META_FILE_NAME) };
+  let temporary = with_suffix(&path, ".tmp");
+  fs::write(&temporary, serde_json::to_string_pretty(meta)?)?;
+  fs::rename(temporary, path)
+}
+
+//The meta of the version of the mod that's in use
+pub fn update(game: &Game, mod_name: &str, change: impl FnOnce(ModMeta) -> ModMeta) -> io::Result<()> {
+  check_mod_name(mod_name)?;
+  let mod_dir = versions::active(game, mod_name)?;
+  if mod_dir == versions::mod_root(game, mod_name) || !mod_dir.is_dir() {
+    return Err(Error::new(ErrorCode::ModNotFound, format!("'{}' isn't installed", mod_name))
+      .param("mod_name", mod_name)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  save(&mod_dir, &change(load(&mod_dir)))
+}
+
+//The path is relative to the mod, hiding it keeps it out of deployments and conflicts
+pub fn set_file_hidden(game: &Game, mod_name: &str, path: &Path, hidden: bool) -> io::Result<()> {
+  check_mod_name(mod_name)?;
+  if !overrides::is_relative_inside(path) || !versions::active(game, mod_name)?.join(path).is_file() {
+    return Err(Error::new(ErrorCode::InvalidModFile, format!("'{}' isn't a file of '{}'", path.display(), mod_name))
+      .param("path", path)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  update(game, mod_name, |meta| {
+    let mut hidden_files: Vec<PathBuf> = meta.hidden.into_iter().filter(|other| other != path).collect();
+    if hidden {
+      hidden_files.push(path.to_path_buf());
+    }
+    ModMeta { hidden: hidden_files, ..meta }
+  })
+}
+
+//Moves a mod's directory, or the link of a linked mod and its meta
+pub fn move_mod(from: &Path, to: &Path) -> io::Result<()> {
+  fs::rename(from, to)?;
+  let sidecar = sidecar_path(from);
+  if sidecar.exists() {
+    fs::rename(sidecar, sidecar_path(to))?;
+  }
+  Ok(())
+}
+
+//Only the link goes for a linked mod, never the folder it points to
+pub fn remove_mod(mod_dir: &Path) -> io::Result<()> {
+  if is_linked(mod_dir) {
+    fs::remove_file(mod_dir)?;
+  } else {
+    fs::remove_dir_all(mod_dir)?;
+  }
+  match fs::remove_file(sidecar_path(mod_dir)) {
+    Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+    _ => Ok(()),
+  }
+}
+
+//What the download sources said about the files they downloaded, by file name,
+//so installing one of them later knows where it came from
+fn sources_path(game: &Game) -> PathBuf {
+  game.profile_path.join("download_sources.json")
+}
+
+fn load_sources(game: &Game) -> BTreeMap<String, ModMetadata> {
+  fs::read_to_string(sources_path(game)).ok()
+    .and_then(|contents| serde_json::from_str(&contents).ok())
+    .unwrap_or_default()
+}
+
+pub fn record_download(game: &Game, file_name: &str, metadata: &ModMetadata) -> io::Result<()> {
+  let mut sources = load_sources(game);
+  sources.insert(file_name.to_string(), metadata.clone());
+  fs::write(sources_path(game), serde_json::to_string(&sources)?)
+}
+
+//Seconds since the epoch, for installed_at
+pub fn now() -> Option<u64> {
+  SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_secs()).ok()
+}
+
+//The meta of a mod freshly installed from an archive
+pub fn for_install(game: &Game, archive: &Path) -> io::Result<ModMeta> {
+  let source = archive.file_name()
+    .and_then(|name| load_sources(game).remove(name.to_string_lossy().as_ref()));
+  Ok(ModMeta {
+    version: source.as_ref().and_then(|source| source.version.clone()),
+    author: source.as_ref().and_then(|source| source.author.clone()),
+    source_url: source.as_ref().map(|source| source.url.clone()).filter(|url| !url.is_empty()),
+    mod_id: source.as_ref().and_then(|source| source.mod_id.clone()),
+    category: source.as_ref().and_then(|source| source.category.clone()),
+    source: source.map(|source| source.source),
+    installed_at: now(),
+    archive_hash: Some(pool::hash_file(archive)?),
+    tags: Vec::new(),
+    dependencies: Vec::new(),
+    notes: None,
+    custom_version: None,
+    color: None,
+    hidden: Vec::new(),
+  })
+}
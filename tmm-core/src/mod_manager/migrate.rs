@@ -0,0 +1,153 @@
+use crate::mod_manager::{deploy, get_directories, make_tmm_game_directories, meta, modlist, monitor, profiles, scratch, trash, versions, GENERATED_MOD_PREFIX};
+use crate::mod_manager::game::Game;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::{fs, io};
+
+//Configs kept how their profiles were laid out in 'profile_layout' before it became a step of
+//their schema, 0 is from before there were profiles, 1 from before the mods had their versions
+//in their folders
+const PROFILE_LAYOUT: u64 = 2;
+
+fn backup_dir(appid: u32) -> io::Result<PathBuf> {
+  Ok(crate::config_root()?.join("backups").join("profiles-migration").join(appid.to_string()))
+}
+
+//The game's config and the JSONs of its profile as they were, a backup that's there
+//already is from an earlier try and the older one
+fn back_up(game: &Game) -> io::Result<()> {
+  let backup = backup_dir(game.appid)?;
+  if backup.exists() {
+    return Ok(());
+  }
+  let building = backup.with_extension("tmp");
+  if building.exists() {
+    fs::remove_dir_all(&building)?;
+  }
+  fs::create_dir_all(building.join("profile"))?;
+  fs::copy(Game::config_path(game.appid)?, building.join(format!("{}.json", game.appid)))?;
+  if game.profile_path.is_dir() {
+    for entry in game.profile_path.read_dir()? {
+      let path = entry?.path();
+      if path.is_file() && path.extension().map_or(false, |extension| extension == "json") {
+        fs::copy(&path, building.join("profile").join(path.file_name().unwrap()))?;
+      }
+    }
+  }
+  fs::rename(building, backup)
+}
+
+//Every mod becomes the only version in its folder, the versions that were kept in 'versions/<mod>'
+//are put next to it. Which mods are done already is in the load order, it's saved after each
+fn nest_mods(profile: &Game) -> io::Result<()> {
+  let mods = profile.profile_path.join("mods");
+  let kept = profile.profile_path.join("versions");
+  let mut modlist = modlist::ModList::load(profile)?;
+  //one that was being moved when things went wrong is put back first
+  let nesting = format!("{}nesting_", GENERATED_MOD_PREFIX);
+  for path in get_directories(&mods)? {
+    let name = path.file_name().unwrap().to_string_lossy().to_string();
+    if let Some(name) = name.strip_prefix(&nesting) {
+      let root = mods.join(name);
+      if root.is_dir() && root.read_dir()?.next().is_none() {
+        fs::remove_dir(&root)?;
+      }
+      if fs::symlink_metadata(&root).is_err() {
+        meta::move_mod(&path, &root)?;
+      }
+    }
+  }
+  for root in get_directories(&mods)? {
+    let name = root.file_name().unwrap().to_string_lossy().to_string();
+    let nested = modlist.version(&name).map_or(false, |version| root.join(version).is_dir());
+    if name.starts_with(GENERATED_MOD_PREFIX) || nested {
+      continue;
+    }
+    let version = versions::nest(&root)?;
+    if kept.join(&name).is_dir() {
+      for path in get_directories(&kept.join(&name))? {
+        let version = path.file_name().unwrap().to_string_lossy().to_string();
+        let mut target = root.join(&version);
+        let mut number = 2;
+        while fs::symlink_metadata(&target).is_ok() {
+          target = root.join(format!("{} ({})", version, number));
+          number += 1;
+        }
+        meta::move_mod(&path, &target)?;
+      }
+      fs::remove_dir(kept.join(&name))?;
+    }
+    modlist.set_version(&name, Some(version));
+    modlist.save(profile)?;
+  }
+  if kept.is_dir() {
+    fs::remove_dir(&kept)?;
+  }
+  Ok(())
+}
+
+fn nest_versions(game: &Game) -> io::Result<()> {
+  //a deployment points into the mod folders as they were, the next deploy puts it back
+  let backend = deploy::backend(game.deploy_mode);
+  if backend.status(game)?.deployed {
+    monitor::ensure_not_running(game)?;
+    backend.purge(game)?;
+  }
+  for path in profiles::paths(game)? {
+    let profile = Game { profile_path: path, ..game.clone() };
+    nest_mods(&profile)?;
+    trash::nest(&profile)?;
+  }
+  Ok(())
+}
+
+//The mods of a game from before profiles become its default profile. Those in 'profiles/<appid>'
+//are that already, a config pointing somewhere else gets its profile moved there, otherwise
+//the other profiles would be looked for next to it and switching back couldn't find it
+fn migrate(game: Game, layout: u64) -> io::Result<Game> {
+  let default_path = profiles::profile_path(game.appid, profiles::DEFAULT_PROFILE)?;
+  //one next to the default profile is a profile already
+  let moving = game.profile_path.parent() != default_path.parent() && game.profile_path.is_dir() && !default_path.exists();
+  back_up(&game)?;
+  //a deployment has the old paths in it, the next deploy puts it back
+  let backend = deploy::backend(game.deploy_mode);
+  if moving && backend.status(&game)?.deployed {
+    monitor::ensure_not_running(&game)?;
+    backend.purge(&game)?;
+  }
+  let mut game = game;
+  if moving {
+    //copied over only if it can't be renamed, the old one is removed once everything is there
+    scratch::move_dir(&game.profile_path, &default_path)?;
+    game = Game { profile_path: default_path, ..game };
+  }
+  if layout < 2 {
+    nest_versions(&game)?;
+  }
+  make_tmm_game_directories(game.clone())?;
+  Ok(game)
+}
+
+//The game schema's step from 1, configs that still have a 'profile_layout' below the last one
+//get their profiles laid out like they are now and point to where they ended up. One that goes
+//wrong halfway is picked up from there the next time the config is read
+pub fn profiles(config: &mut Map<String, Value>) -> io::Result<()> {
+  let layout = config.remove("profile_layout").and_then(|layout| layout.as_u64()).unwrap_or(0);
+  if layout >= PROFILE_LAYOUT {
+    return Ok(());
+  }
+  let game: Game = serde_json::from_value(Value::Object(config.clone()))?;
+  let (appid, name) = (game.appid, game.public_name.clone());
+  let game = migrate(game, layout)?;
+  tracing::info!("Migrated the profiles of '{}'/{}, its default profile is '{}'", name, appid, game.profile_path.display());
+  config.insert("profile_path".to_string(), serde_json::to_value(&game.profile_path)?);
+  Ok(())
+}
+
+//Before anything gets to look at the profiles. Reading the configs upgrades them, a game that
+//fails keeps its config as it was and gets skipped
+pub fn run_on_startup() {
+  if let Err(e) = Game::all() {
+    tracing::warn!("Couldn't read the game configs to migrate them: {}", e);
+  }
+}
@@ -0,0 +1,132 @@
+use crate::mod_manager::{Mod, get_mods};
+use crate::mod_manager::game::Game;
+use crate::mod_downloader::source::{self, ModMetadata};
+use crate::error::{Context, Error, ErrorCode};
+use crate::tasks;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::events::{BackendEvent, Events};
+
+//How often the scheduler looks if a game's checks are due
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(600);
+
+static SCHEDULER: OnceCell<()> = OnceCell::new();
+
+//One installed mod the source was asked about
+#[derive(Debug, Clone, Serialize)]
+pub struct ModUpdate {
+  mod_name: String,
+  source: String,
+  current: Option<String>,
+  //only there when there's a newer version
+  latest: Option<String>,
+  changelog_url: Option<String>,
+  error: Option<Error>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateReport {
+  appid: u32,
+  //mods from a source that knows versions, the rest can't be checked
+  checked: Vec<ModUpdate>,
+  unchecked: Vec<String>,
+}
+
+impl UpdateReport {
+  fn has_updates(&self) -> bool {
+    self.checked.iter().any(|update| update.latest.is_some())
+  }
+}
+
+//What the mod's meta says about where it came from, the way the sources describe it
+fn metadata(elem: &Mod) -> Option<ModMetadata> {
+  let meta = &elem.meta;
+  let source = meta.source.clone()?;
+  meta.mod_id.as_ref()?;
+  Some(ModMetadata {
+    source,
+    mod_id: meta.mod_id.clone(),
+    name: Some(elem.name.clone()),
+    author: meta.author.clone(),
+    version: meta.version.clone(),
+    file_name: None,
+    url: meta.source_url.clone().unwrap_or_default(),
+    changelog_url: None,
+    category: meta.category.clone(),
+  })
+}
+
+//Asks each mod's source for something newer than the installed version, one failing doesn't stop the others.
+//Only the named mods if there are names
+pub fn check(game: &Game, only: Option<&[String]>) -> UpdateReport {
+  let mut report = UpdateReport { appid: game.appid, checked: Vec::new(), unchecked: Vec::new() };
+  let mods: Vec<Mod> = get_mods(game.clone()).into_iter().filter(|elem| only.map_or(true, |names| names.contains(&elem.name))).collect();
+  let total = mods.len() as u64;
+  for (done, elem) in mods.into_iter().enumerate() {
+    //the rest isn't asked for, the task ends up cancelled and what got checked isn't shown
+    if tasks::cancelled() {
+      break;
+    }
+    tasks::progress(done as u64, Some(total));
+    let metadata = match metadata(&elem) {
+      Some(metadata) => metadata,
+      None => {
+        report.unchecked.push(elem.name);
+        continue;
+      }
+    };
+    let mut update = ModUpdate {
+      mod_name: elem.name.clone(),
+      source: metadata.source.clone(),
+      current: metadata.version.clone(),
+      latest: None,
+      changelog_url: None,
+      error: None,
+    };
+    match source::by_id(&metadata.source).and_then(|download_source| download_source.check_update(&metadata)) {
+      Ok(Some(latest)) => {
+        update.latest = latest.version;
+        update.changelog_url = latest.changelog_url;
+      }
+      Ok(None) => {}
+      Err(e) => {
+        tracing::warn!("Couldn't check '{}' for updates: {}", elem.name, e);
+        update.error = Some(Error::new(ErrorCode::SourceFailed, e.to_string()).context(Context::CheckUpdates).param("mod", &elem.name));
+      }
+    }
+    report.checked.push(update);
+  }
+  report
+}
+
+//Checks the games that have an update interval whenever it passed and tells the
+//frontend about the ones with updates. Only ever starts once
+pub fn schedule(events: Events) {
+  if SCHEDULER.set(()).is_err() {
+    return;
+  }
+  thread::spawn(move || {
+    let mut last_checks: HashMap<u32, Instant> = HashMap::new();
+    loop {
+      for game in Game::all().unwrap_or_default() {
+        let interval = match game.update_check_hours {
+          Some(hours) if hours > 0 => Duration::from_secs(hours * 3600),
+          _ => continue,
+        };
+        if last_checks.get(&game.appid).map_or(false, |last| last.elapsed() < interval) {
+          continue;
+        }
+        last_checks.insert(game.appid, Instant::now());
+        let report = check(&game, None);
+        if report.has_updates() {
+          events.send(BackendEvent::ModUpdatesAvailable(report));
+        }
+      }
+      thread::sleep(SCHEDULER_INTERVAL);
+    }
+  });
+}
@@ -0,0 +1,214 @@
+use crate::mod_manager::{Mod, meta};
+use crate::mod_manager::game::Game;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::{fs, io};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModEntry {
+  pub name: String,
+  pub enabled: bool,
+  //which of the versions in 'mods/<name>' is the mod
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub version: Option<String>,
+  #[serde(default)]
+  pub usage: Usage,
+}
+
+//When the user last did something with the mod, in seconds since the epoch like installed_at
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+  #[serde(default)]
+  pub enabled_at: Option<u64>,
+  #[serde(default)]
+  pub disabled_at: Option<u64>,
+  //the last deployment it was part of
+  #[serde(default)]
+  pub deployed_at: Option<u64>,
+  //whether it was part of the last deployment at all
+  #[serde(default)]
+  pub deployed: bool,
+}
+
+impl Usage {
+  //Enabling or deploying it, mods never touched since count from their install
+  pub fn last_used(&self, installed_at: Option<u64>) -> Option<u64> {
+    self.enabled_at.max(self.deployed_at).or(installed_at)
+  }
+}
+
+//What the user did with the mods of a profile, kept apart from the mod folders
+//so turning one off doesn't touch its files. The entries are in load order, later
+//ones win conflicts. Mods that aren't in here are enabled and go last
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModList {
+  pub mods: Vec<ModEntry>,
+}
+
+fn modlist_path(game: &Game) -> PathBuf {
+  game.profile_path.join("modlist.json")
+}
+
+impl ModList {
+  pub fn load(game: &Game) -> io::Result<ModList> {
+    let path = modlist_path(game);
+    if !path.exists() {
+      return Ok(ModList::default());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+  }
+
+  pub fn save(&self, game: &Game) -> io::Result<()> {
+    let path = modlist_path(game);
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, serde_json::to_string_pretty(self)?)?;
+    fs::rename(temporary, path)
+  }
+
+  pub fn is_enabled(&self, name: &str) -> bool {
+    self.mods.iter().find(|entry| entry.name == name).map_or(true, |entry| entry.enabled)
+  }
+
+  pub fn usage(&self, name: &str) -> Usage {
+    self.mods.iter().find(|entry| entry.name == name).map(|entry| entry.usage.clone()).unwrap_or_default()
+  }
+
+  //Only an actual change counts as using it
+  pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+    if self.is_enabled(name) != enabled {
+      let mut usage = self.usage(name);
+      if enabled {
+        usage.enabled_at = meta::now();
+      } else {
+        usage.disabled_at = meta::now();
+      }
+      self.entry(name).usage = usage;
+    }
+    self.entry(name).enabled = enabled;
+  }
+
+  //Mods that aren't in the list yet get added at the end, enabled like get_mods sees them
+  fn entry(&mut self, name: &str) -> &mut ModEntry {
+    match self.position(name) {
+      Some(position) => &mut self.mods[position],
+      None => {
+        self.mods.push(ModEntry { name: name.to_string(), enabled: true, version: None, usage: Usage::default() });
+        self.mods.last_mut().unwrap()
+      },
+    }
+  }
+
+  //Everything else wasn't part of it
+  pub fn set_deployed(&mut self, names: &[String]) {
+    let now = meta::now();
+    for name in names {
+      self.entry(name).usage.deployed_at = now;
+    }
+    for entry in self.mods.iter_mut() {
+      entry.usage.deployed = names.contains(&entry.name);
+    }
+  }
+
+  pub fn version(&self, name: &str) -> Option<&str> {
+    self.mods.iter().find(|entry| entry.name == name).and_then(|entry| entry.version.as_deref())
+  }
+
+  pub fn set_version(&mut self, name: &str, version: Option<String>) {
+    self.entry(name).version = version;
+  }
+
+  pub fn remove(&mut self, name: &str) {
+    self.mods.retain(|entry| entry.name != name);
+  }
+
+  pub fn rename(&mut self, old_name: &str, new_name: &str) {
+    for entry in self.mods.iter_mut().filter(|entry| entry.name == old_name) {
+      entry.name = new_name.to_string();
+    }
+  }
+
+  fn position(&self, name: &str) -> Option<usize> {
+    self.mods.iter().position(|entry| entry.name == name)
+  }
+
+  //The sort is stable, so mods the list doesn't know stay in the order they came in
+  pub fn sorted(&self, mods: &[Mod]) -> Vec<Mod> {
+    let mut sorted = mods.to_vec();
+    sorted.sort_by_key(|elem| self.position(&elem.name).unwrap_or(usize::MAX));
+    sorted
+  }
+
+  //Entries keep whether they're enabled, ones that aren't named go after the named ones
+  pub fn set_order(&mut self, names: &[String]) {
+    let mut ordered: Vec<ModEntry> = names.iter()
+      .map(|name| ModEntry {
+        name: name.clone(),
+        enabled: self.is_enabled(name),
+        version: self.mods.iter().find(|entry| &entry.name == name).and_then(|entry| entry.version.clone()),
+        usage: self.usage(name),
+      })
+      .collect();
+    ordered.extend(self.mods.iter().filter(|entry| !names.contains(&entry.name)).cloned());
+    self.mods = ordered;
+  }
+
+  //The mods that should actually get deployed
+  pub fn enabled(&self, mods: &[Mod]) -> Vec<Mod> {
+    mods.iter().filter(|elem| self.is_enabled(&elem.name)).cloned().collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn names(mods: &[Mod]) -> Vec<&str> {
+    mods.iter().map(|elem| elem.name.as_str()).collect()
+  }
+
+  #[test]
+  fn unknown_mods_are_enabled_and_last() {
+    let mut modlist = ModList::default();
+    modlist.set_enabled("B", false);
+    modlist.set_enabled("A", true);
+    let mods = vec![Mod::generated("C"), Mod::generated("A"), Mod::generated("D"), Mod::generated("B")];
+    assert_eq!(names(&modlist.sorted(&mods)), ["B", "A", "C", "D"]);
+    assert_eq!(names(&modlist.enabled(&modlist.sorted(&mods))), ["A", "C", "D"]);
+  }
+
+  #[test]
+  fn reordering_keeps_the_entries() {
+    let mut modlist = ModList::default();
+    modlist.set_enabled("A", false);
+    modlist.set_version("A", Some("1.0".to_string()));
+    modlist.set_enabled("B", true);
+    modlist.set_enabled("C", true);
+    modlist.set_order(&["C".to_string(), "A".to_string()]);
+    let order: Vec<&str> = modlist.mods.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(order, ["C", "A", "B"]);
+    assert!(!modlist.is_enabled("A"));
+    assert_eq!(modlist.version("A"), Some("1.0"));
+  }
+
+  #[test]
+  fn only_a_change_counts_as_use() {
+    let mut modlist = ModList::default();
+    modlist.set_enabled("A", true);
+    assert_eq!(modlist.usage("A"), Usage::default());
+    modlist.set_enabled("A", false);
+    assert!(modlist.usage("A").disabled_at.is_some());
+    modlist.set_deployed(&["B".to_string()]);
+    assert!(modlist.usage("B").deployed && modlist.usage("B").deployed_at.is_some());
+    assert!(!modlist.usage("A").deployed);
+  }
+
+  #[test]
+  fn renamed_and_removed() {
+    let mut modlist = ModList::default();
+    modlist.set_enabled("Old", false);
+    modlist.rename("Old", "New");
+    assert!(!modlist.is_enabled("New") && modlist.is_enabled("Old"));
+    modlist.remove("New");
+    assert!(modlist.mods.is_empty());
+  }
+}
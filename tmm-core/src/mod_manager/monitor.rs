@@ -0,0 +1,68 @@
+use crate::mod_manager::launch;
+use crate::mod_manager::game::Game;
+use crate::error::{Error, ErrorCode};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::process::{Child, ExitStatus};
+use std::sync::Mutex;
+use std::{fs, io, thread};
+
+//The processes the manager started itself by appid, the game as well as its executables and tools.
+//Those are known for sure, games started some other way are found through Steam or /proc
+static STARTED: Lazy<Mutex<HashMap<u32, Vec<u32>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+//The game this process is the launch process of when Steam started it through the manager
+static WRAPPED: Lazy<Mutex<Option<u32>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn wrapping(appid: u32) {
+  *WRAPPED.lock().unwrap() = Some(appid);
+}
+
+//A zombie has exited already, it only hasn't been waited for
+fn alive(pid: u32) -> bool {
+  match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+    //the state comes right after the command, which is in parentheses and can have spaces
+    Ok(stat) => stat.rsplit(')').next().and_then(|rest| rest.trim_start().chars().next()).map_or(false, |state| state != 'Z'),
+    Err(_) => false,
+  }
+}
+
+pub fn tracked(appid: u32) -> bool {
+  let mut started = STARTED.lock().unwrap();
+  let pids = match started.get_mut(&appid) {
+    Some(pids) => pids,
+    None => return false,
+  };
+  pids.retain(|pid| alive(*pid));
+  !pids.is_empty()
+}
+
+//Blocks until the child exits, it counts as the game running until then
+pub fn wait(appid: u32, mut child: Child) -> io::Result<ExitStatus> {
+  let pid = child.id();
+  STARTED.lock().unwrap().entry(appid).or_default().push(pid);
+  let status = child.wait();
+  if let Some(pids) = STARTED.lock().unwrap().get_mut(&appid) {
+    pids.retain(|other| *other != pid);
+  }
+  status
+}
+
+//For children nothing waits on, they'd be left as zombies otherwise
+pub fn watch(appid: u32, child: Child) {
+  thread::spawn(move || {
+    if let Err(e) = wait(appid, child) {
+      tracing::warn!("Couldn't wait for a process of {}: {}", appid, e);
+    }
+  });
+}
+
+//Deploying, purging or removing mods under a running game changes files it has open. Steam
+//reports the game it started the manager for as running already, that one isn't yet
+pub fn ensure_not_running(game: &Game) -> io::Result<()> {
+  if *WRAPPED.lock().unwrap() != Some(game.appid) && launch::is_running(game) {
+    return Err(Error::new(ErrorCode::GameRunning, format!("'{}' is running", game.public_name))
+      .param("appid", game.appid)
+      .into_io(io::ErrorKind::Other));
+  }
+  Ok(())
+}
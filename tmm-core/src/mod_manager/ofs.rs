@@ -0,0 +1,256 @@
+use crate::mod_manager::{Mod, get_files, mod_dir, overwrite, reflink};
+use crate::mod_manager::game::{Game, DeployMode};
+use crate::mod_manager::excludes::{ExcludedFile, Excludes, Filtered};
+use crate::mod_manager::link::relative;
+use crate::mod_manager::deploy::{DeployBackend, DeployReport, DeployStatus, Capabilities};
+use crate::error::{Error, ErrorCode};
+use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+pub struct OverlayBackend;
+
+impl DeployBackend for OverlayBackend {
+  fn deploy(&self, game: &Game, mods: &[Mod]) -> io::Result<DeployReport> {
+    let mount: PathBuf = mount_path(game);
+    if is_mounted(&mount)? {
+      self.purge(game)?;
+    }
+    remove_layers(game)?;
+    let mut report = DeployReport::default();
+    if mods.is_empty() {
+      return Ok(report);
+    }
+
+    //the first lower dir of an overlay is the one on top,
+    //so the load order has to be reversed for later mods to win
+    let excludes = Excludes::for_game(game)?;
+    let mut mod_paths: Vec<PathBuf> = Vec::new();
+    for elem in mods.iter().rev() {
+      mod_paths.push(layer(game, elem, &excludes, &mut report)?);
+    }
+    //the game directory is the bottom layer, what gets written goes into the overwrite and it stays vanilla
+    mod_paths.push(mount.clone());
+    let upper_path = overwrite::overwrite_path(game);
+    let work_path = overwrite::overlay_work_path(game);
+    fs::create_dir_all(&upper_path)?;
+    fs::create_dir_all(&work_path)?;
+
+    init_overlay_fs(game, mod_paths, &upper_path, &mount, &work_path)?;
+    Ok(report)
+  }
+
+  fn purge(&self, game: &Game) -> io::Result<()> {
+    let mount = mount_path(game);
+    if is_mounted(&mount)? {
+      unmount(game, &mount)?;
+    }
+    remove_layers(game)
+  }
+
+  fn status(&self, game: &Game) -> io::Result<DeployStatus> {
+    Ok(DeployStatus { backend: DeployMode::Overlay, deployed: is_mounted(&mount_path(game))?, files: None })
+  }
+
+  fn capabilities(&self, _game: &Game) -> Capabilities {
+    let available = in_path("pkexec");
+    Capabilities {
+      backend: DeployMode::Overlay,
+      available,
+      reason: match (available, helper()) {
+        (false, _) => Some("pkexec isn't installed".to_string()),
+        (true, None) => Some(format!("the mount helper isn't installed at '{}', mounting needs full root rights", HELPER_PATH)),
+        (true, Some(_)) => None,
+      },
+      privileged: true,
+      uses_space: false,
+    }
+  }
+}
+
+//Mods with files that can't show up in the game, the excluded, hidden and root files and the
+//manager's own, get a layer with only the rest in it. Hardlinks are enough, the overlay never
+//writes to its lower layers
+pub fn layers_path(game: &Game) -> PathBuf {
+  game.profile_path.join("layers")
+}
+
+fn layer(game: &Game, elem: &Mod, excludes: &Excludes, report: &mut DeployReport) -> io::Result<PathBuf> {
+  let mod_root = mod_dir(game, elem)?;
+  let mut deployed: Vec<PathBuf> = Vec::new();
+  let mut filtered = false;
+  for source in get_files(&mod_root)? {
+    let relative = relative(&source, &mod_root)?.to_path_buf();
+    match excludes.filter(elem, &relative) {
+      Filtered::Deployed => deployed.push(relative),
+      Filtered::Excluded => {
+        report.excluded.push(ExcludedFile { mod_name: elem.name.clone(), path: relative });
+        filtered = true;
+      },
+      _ => filtered = true,
+    }
+  }
+  if !filtered {
+    return Ok(mod_root);
+  }
+
+  let layer = layers_path(game).join(&elem.name);
+  fs::create_dir_all(&layer)?;
+  for relative in deployed {
+    let (source, target) = (mod_root.join(&relative), layer.join(&relative));
+    if let Some(parent) = target.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    //linked mods can be on another filesystem
+    if fs::hard_link(&source, &target).is_err() {
+      reflink::copy(&source, &target)?;
+    }
+  }
+  Ok(layer)
+}
+
+fn remove_layers(game: &Game) -> io::Result<()> {
+  match fs::remove_dir_all(layers_path(game)) {
+    Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+    _ => Ok(()),
+  }
+}
+
+pub fn mount_path(game: &Game) -> PathBuf {
+  PathBuf::new().join(&game.install_path).join(&game.path_extension)
+}
+
+fn utf8(path: &Path) -> io::Result<&str> {
+  path.to_str().ok_or_else(|| {
+    Error::new(ErrorCode::NonUtf8Path, format!("{:?} isn't valid UTF-8", path))
+      .param("path", path.to_string_lossy())
+      .into_io(io::ErrorKind::InvalidInput)
+  })
+}
+
+//Mount points in /proc/mounts have spaces and such escaped as octal
+pub fn is_mounted(path: &Path) -> io::Result<bool> {
+  let mounts = fs::read_to_string("/proc/mounts")?;
+  let wanted = utf8(path)?.trim_end_matches('/')
+    .replace('\\', "\\134").replace(' ', "\\040").replace('\t', "\\011").replace('\n', "\\012");
+  Ok(mounts.lines().any(|line| line.split(' ').nth(1) == Some(wanted.as_str())))
+}
+
+pub struct MountEntry {
+  pub path: PathBuf,
+  pub fs_type: String,
+  pub options: String,
+}
+
+fn unescape(field: &str) -> String {
+  field.replace("\\040", " ").replace("\\011", "\t").replace("\\012", "\n").replace("\\134", "\\")
+}
+
+//Everything in /proc/mounts in mount order, so later entries sit on top of earlier ones
+pub fn mounts() -> io::Result<Vec<MountEntry>> {
+  let mounts = fs::read_to_string("/proc/mounts")?;
+  Ok(mounts.lines().filter_map(|line| {
+    let fields: Vec<&str> = line.split(' ').collect();
+    if fields.len() < 4 {
+      return None;
+    }
+    Some(MountEntry { path: PathBuf::from(unescape(fields[1])), fs_type: fields[2].to_string(), options: unescape(fields[3]) })
+  }).collect())
+}
+
+//Where packages put the mount helper, it only mounts and unmounts on registered
+//game paths so polkit can let it do that without handing out a root shell
+const HELPER_PATH: &str = "/usr/lib/tmm/tmm-mount-helper";
+
+pub fn helper() -> Option<PathBuf> {
+  let path = PathBuf::from(HELPER_PATH);
+  if path.exists() { Some(path) } else { None }
+}
+
+//pkexec drops the environment, the helper gets told where the game configs are
+fn helper_command(helper: &Path, operation: &str, game: &Game) -> io::Result<Command> {
+  let mut command = Command::new("pkexec");
+  command.arg(helper).arg(operation).arg(crate::config_root()?).arg(game.appid.to_string());
+  Ok(command)
+}
+
+//Only takes off the topmost mount if several are stacked on the path
+pub fn unmount(game: &Game, mount: &Path) -> io::Result<()> {
+  let mut command = match helper() {
+    Some(helper) => helper_command(&helper, "umount", game)?,
+    None => {
+      let mut command = Command::new("pkexec");
+      command.arg("umount");
+      command
+    },
+  };
+  let status = command
+    .arg(mount)
+    .status()?;
+  if !status.success() {
+    return Err(Error::new(ErrorCode::MountFailed, format!("umount exited with {}", status))
+      .param("path", mount)
+      .param("exit_code", status.code())
+      .into_io(io::ErrorKind::Other));
+  }
+  Ok(())
+}
+
+pub fn in_path(binary: &str) -> bool {
+  match env::var_os("PATH") {
+    Some(paths) => env::split_paths(&paths).any(|dir| dir.join(binary).exists()),
+    None => false,
+  }
+}
+
+pub fn init_overlay_fs(game: &Game, lower: Vec<PathBuf>, upper: &PathBuf, mount: &PathBuf, workdir: &PathBuf) -> io::Result<()> {
+  if let Some(helper) = helper() {
+    //the helper works out the mount point from the game's config itself
+    let status = helper_command(&helper, "mount", game)?
+      .arg(upper)
+      .arg(workdir)
+      .args(&lower)
+      .status()?;
+    if !status.success() {
+      return Err(Error::new(ErrorCode::MountFailed, format!("the mount helper exited with {}", status))
+        .param("path", mount)
+        .param("exit_code", status.code())
+        .into_io(io::ErrorKind::Other));
+    }
+    return Ok(());
+  }
+
+  let mut lower_arg: String = String::from("lowerdir=");
+  let upper_arg: String = String::from("upperdir=").to_owned()+utf8(upper)?;
+  let work_arg: String = String::from("workdir=").to_owned()+utf8(workdir)?;
+
+  let mut index = 0;
+  for path in &lower {
+    lower_arg.push_str(utf8(path)?);
+    index += 1;
+    if index < lower.len() {
+      lower_arg.push(':');
+    }
+  }
+  
+  let status = Command::new("pkexec")
+    .arg("mount")
+    .arg("-t")
+    .arg("overlay")
+    .arg("overlay")
+    .arg("-o")
+    .arg(&lower_arg)
+    .arg("-o")
+    .arg(upper_arg)
+    .arg("-o")
+    .arg(work_arg)
+    .arg(mount)
+    .status()?;
+  if !status.success() {
+    return Err(Error::new(ErrorCode::MountFailed, format!("mount exited with {}", status))
+      .param("path", mount)
+      .param("exit_code", status.code())
+      .into_io(io::ErrorKind::Other));
+  }
+  Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::mod_manager::{Mod, mod_dir};
+use crate::mod_manager::game::Game;
+use crate::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::{fs, io};
+
+//The pinned files get put into a generated mod deployed on top of everything,
+//that way every deploy backend respects the rules without knowing about them
+pub const OVERRIDES_MOD_NAME: &str = ".tmm_overrides";
+
+//Always take `path` from `mod_name`, no matter the load order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverrideRule {
+  pub path: PathBuf,
+  pub mod_name: String,
+}
+
+fn rules_path(game: &Game) -> PathBuf {
+  game.profile_path.join("overrides.json")
+}
+
+pub fn load(game: &Game) -> io::Result<Vec<OverrideRule>> {
+  let path = rules_path(game);
+  if !path.exists() {
+    return Ok(Vec::new());
+  }
+  let contents = fs::read_to_string(path)?;
+  Ok(serde_json::from_str(&contents)?)
+}
+
+pub fn save(game: &Game, rules: &[OverrideRule]) -> io::Result<()> {
+  fs::write(rules_path(game), serde_json::to_string(rules)?)
+}
+
+//There's only ever one rule per path, adding another one replaces it
+pub fn add(game: &Game, rule: OverrideRule) -> io::Result<Vec<OverrideRule>> {
+  if !is_relative_inside(&rule.path) {
+    return Err(Error::new(ErrorCode::InvalidOverridePath, format!("'{}' isn't a path inside a mod", rule.path.display()))
+      .param("path", &rule.path)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  let mut rules = load(game)?;
+  rules.retain(|existing| existing.path != rule.path);
+  rules.push(rule);
+  save(game, &rules)?;
+  Ok(rules)
+}
+
+pub fn remove(game: &Game, path: &Path) -> io::Result<Vec<OverrideRule>> {
+  let mut rules = load(game)?;
+  rules.retain(|existing| existing.path != path);
+  save(game, &rules)?;
+  Ok(rules)
+}
+
+pub fn rename_mod(game: &Game, old_name: &str, new_name: &str) -> io::Result<()> {
+  let mut rules = load(game)?;
+  for rule in rules.iter_mut().filter(|rule| rule.mod_name == old_name) {
+    rule.mod_name = new_name.to_string();
+  }
+  save(game, &rules)
+}
+
+//The mod a rule pins the path to, if that mod is enabled and actually ships it
+pub fn pinned<'a>(rules: &'a [OverrideRule], path: &Path, providers: &[String]) -> Option<&'a str> {
+  rules.iter()
+    .find(|rule| rule.path == path && providers.contains(&rule.mod_name))
+    .map(|rule| rule.mod_name.as_str())
+}
+
+//Rebuilds the generated mod from the rules that apply to the enabled mods
+pub fn refresh(game: &Game, mods: &[Mod]) -> io::Result<Option<Mod>> {
+  let overrides_path = game.profile_path.join("mods").join(OVERRIDES_MOD_NAME);
+  if overrides_path.exists() {
+    fs::remove_dir_all(&overrides_path)?;
+  }
+
+  let mut pinned_any = false;
+  for rule in load(game)? {
+    let source = match mods.iter().find(|elem| elem.name == rule.mod_name) {
+      Some(elem) => mod_dir(game, elem)?.join(&rule.path),
+      None => continue,
+    };
+    if !source.is_file() {
+      tracing::warn!("Override rule for '{}' ignored, '{}' doesn't ship it", rule.path.display(), rule.mod_name);
+      continue;
+    }
+    let target = overrides_path.join(&rule.path);
+    if let Some(parent) = target.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    //both are inside the profile, so a hardlink usually works and saves the space
+    if fs::hard_link(&source, &target).is_err() {
+      fs::copy(&source, &target)?;
+    }
+    pinned_any = true;
+  }
+
+  if pinned_any {
+    Ok(Some(Mod::generated(OVERRIDES_MOD_NAME)))
+  } else {
+    Ok(None)
+  }
+}
+
+pub fn is_relative_inside(path: &Path) -> bool {
+  path.components().count() > 0 && path.components().all(|component| matches!(component, Component::Normal(_)))
+}
@@ -0,0 +1,165 @@
+use crate::mod_manager::{get_files, meta, ofs, overrides, pool, reinstall, root, staging, validate_mod_name, versions};
+use crate::mod_manager::link::{fingerprint, relative};
+use crate::mod_manager::experiment::is_whiteout;
+use crate::mod_manager::game::{Game, DeployMode};
+use crate::error::{Error, ErrorCode};
+use serde::Serialize;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//What the game and tools write while they run, like MO2's Overwrite. The overlay writes into it
+//directly as its upper layer, stagings get the files they didn't have before moved into it.
+//Laid out like a mod, files that went to the game's root are in its root folder
+pub fn overwrite_path(game: &Game) -> PathBuf {
+  game.profile_path.join("overwrite")
+}
+
+//Has to be on the same filesystem as the upper layer
+pub fn overlay_work_path(game: &Game) -> PathBuf {
+  game.profile_path.join("overwrite.work")
+}
+
+#[derive(Debug, Serialize)]
+pub struct OverwriteFiles {
+  written: Vec<PathBuf>,
+  //files the overlay hides from the layers below, promoting doesn't carry those over
+  deleted: Vec<PathBuf>,
+}
+
+//The overlay's upper layer can't be changed under it
+fn ensure_unmounted(game: &Game) -> io::Result<()> {
+  if game.deploy_mode == DeployMode::Overlay && ofs::is_mounted(&ofs::mount_path(game))? {
+    return Err(Error::new(ErrorCode::OverwriteInUse, "the overwrite is in use by the deployed overlay, purge it first")
+      .into_io(io::ErrorKind::WouldBlock));
+  }
+  Ok(())
+}
+
+pub fn files(game: &Game) -> io::Result<OverwriteFiles> {
+  let path = overwrite_path(game);
+  let mut files = OverwriteFiles { written: Vec::new(), deleted: Vec::new() };
+  for file in get_files(&path)? {
+    let relative = relative(&file, &path)?.to_path_buf();
+    if is_whiteout(&file)? {
+      files.deleted.push(relative);
+    } else {
+      files.written.push(relative);
+    }
+  }
+  Ok(files)
+}
+
+//The picked files, or all of them without a pick
+fn picked(game: &Game, paths: Option<&[PathBuf]>) -> io::Result<Vec<PathBuf>> {
+  let path = overwrite_path(game);
+  match paths {
+    None => get_files(&path),
+    Some(paths) => paths.iter().map(|relative| {
+      if !overrides::is_relative_inside(relative) || fs::symlink_metadata(path.join(relative)).is_err() {
+        return Err(Error::new(ErrorCode::InvalidModFile, format!("'{}' isn't in the overwrite", relative.display()))
+          .param("path", relative)
+          .into_io(io::ErrorKind::NotFound));
+      }
+      Ok(path.join(relative))
+    }).collect(),
+  }
+}
+
+//Directories left empty go too, the overwrite itself stays
+fn remove_empty_dirs(dir: &Path, keep: bool) -> io::Result<()> {
+  for entry in dir.read_dir()? {
+    let entry = entry?;
+    if entry.file_type()?.is_dir() {
+      remove_empty_dirs(&entry.path(), false)?;
+    }
+  }
+  if !keep && dir.read_dir()?.next().is_none() {
+    fs::remove_dir(dir)?;
+  }
+  Ok(())
+}
+
+pub fn clean(game: &Game, paths: Option<&[PathBuf]>) -> io::Result<()> {
+  ensure_unmounted(game)?;
+  for file in picked(game, paths)? {
+    fs::remove_file(file)?;
+  }
+  let path = overwrite_path(game);
+  if path.is_dir() {
+    remove_empty_dirs(&path, true)?;
+  }
+  Ok(())
+}
+
+//Turns the files into a normal mod, deletions the overlay recorded are dropped
+pub fn promote(game: &Game, mod_name: &str, paths: Option<&[PathBuf]>) -> io::Result<()> {
+  ensure_unmounted(game)?;
+  validate_mod_name(game, mod_name)?;
+  let path = overwrite_path(game);
+  let target = versions::free(game, mod_name, &meta::ModMeta::default());
+  let mut moved = false;
+  for file in picked(game, paths)? {
+    if is_whiteout(&file)? {
+      if paths.is_none() {
+        fs::remove_file(file)?;
+      }
+      continue;
+    }
+    let destination = target.join(relative(&file, &path)?);
+    if let Some(parent) = destination.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    fs::rename(&file, destination)?;
+    moved = true;
+  }
+  if path.is_dir() {
+    remove_empty_dirs(&path, true)?;
+  }
+  if !moved {
+    return Ok(());
+  }
+  let report = pool::ingest(&target)?;
+  reinstall::record(&target, &report.hashes)
+}
+
+//Files that aren't what the staging was built with were written while the game ran from it, new
+//ones and the ones it changed in place. Called before a staging goes away, root files are purged by then
+pub fn capture(game: &Game, staging_root: &Path) -> io::Result<usize> {
+  if !staging_root.is_dir() {
+    return Ok(0);
+  }
+  let path = overwrite_path(game);
+  let target_root = staging_root.join(&game.path_extension);
+  let built = staging::built(staging_root)?;
+  let mut captured = 0;
+  for file in get_files(staging_root)? {
+    let metadata = fs::symlink_metadata(&file)?;
+    if !metadata.file_type().is_file() {
+      continue;
+    }
+    let in_staging = relative(&file, staging_root)?;
+    let written = match &built {
+      Some(built) => built.get(in_staging) != Some(&fingerprint(&file)?),
+      //stagings of older versions were linked together, only new files have a single link
+      None => metadata.nlink() == 1,
+    };
+    if !written {
+      continue;
+    }
+    let destination = match file.strip_prefix(&target_root) {
+      Ok(relative) => path.join(relative),
+      Err(_) => path.join(root::ROOT_DIR_NAME).join(in_staging),
+    };
+    if let Some(parent) = destination.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    //across filesystems the staging can't keep it, it goes once it's copied
+    if fs::rename(&file, &destination).is_err() {
+      fs::copy(&file, &destination)?;
+      fs::remove_file(&file)?;
+    }
+    captured += 1;
+  }
+  Ok(captured)
+}
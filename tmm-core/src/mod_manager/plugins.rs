@@ -0,0 +1,423 @@
+use crate::mod_manager::{Mod, mod_dir, pool};
+use crate::mod_manager::game::{Game, LoadOrderFile, LoadOrderFormat};
+use crate::mod_manager::manifest::DeploymentManifest;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+//Masters first, the game refuses to load a plugin before the masters it needs
+const PLUGIN_EXTENSIONS: &[&str] = &["esm", "esl", "esp"];
+
+//The profile's side of the load order file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PluginState {
+  //plugins named here go in this order, the others after them in the order of their mods
+  #[serde(default)]
+  order: Vec<String>,
+  #[serde(default)]
+  disabled: Vec<String>,
+  //of the file as it was last written, anything else means it got edited outside
+  #[serde(default)]
+  written_hash: Option<String>,
+  //the enabled ones it was written with, in the formats that only list those one missing
+  //from it now got disabled outside
+  #[serde(default)]
+  written: Vec<String>,
+}
+
+//What gets synced, the hash is only about this machine's file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginOrder {
+  order: Vec<String>,
+  disabled: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plugin {
+  //RimWorld's package id or Factorio's mod name for those games
+  name: String,
+  //None for the game's own plugins, set_plugins only looks at the name and enabled
+  #[serde(default)]
+  mod_name: Option<String>,
+  enabled: bool,
+}
+
+fn state_path(game: &Game) -> PathBuf {
+  game.profile_path.join("plugins.json")
+}
+
+fn load_state(game: &Game) -> PluginState {
+  fs::read_to_string(state_path(game)).ok()
+    .and_then(|contents| serde_json::from_str(&contents).ok())
+    .unwrap_or_default()
+}
+
+fn save_state(game: &Game, state: &PluginState) -> io::Result<()> {
+  let path = state_path(game);
+  let temporary = path.with_extension("json.tmp");
+  fs::write(&temporary, serde_json::to_string_pretty(state)?)?;
+  fs::rename(temporary, path)
+}
+
+//Inside the prefix's Windows user like the saves, or the home for native games
+fn live_path(game: &Game, file: &LoadOrderFile) -> Option<PathBuf> {
+  let base = if game.uses_compatibility() { game.prefix_user_dir()? } else { dirs::home_dir()? };
+  Some(base.join(&file.path))
+}
+
+fn plugin_rank(name: &str) -> Option<usize> {
+  let extension = Path::new(name).extension()?.to_string_lossy().to_lowercase();
+  PLUGIN_EXTENSIONS.iter().position(|plugin| *plugin == extension)
+}
+
+fn is_master(name: &str) -> bool {
+  plugin_rank(name).map_or(false, |rank| rank < 2)
+}
+
+//Whether the file has the disabled ones too, otherwise they're only missing from it
+fn lists_disabled(format: LoadOrderFormat) -> bool {
+  matches!(format, LoadOrderFormat::Asterisk | LoadOrderFormat::ModList)
+}
+
+//Where the game has its own, RimWorld's Core and DLCs and Factorio's base are next to the mods
+fn game_dir(game: &Game, format: LoadOrderFormat) -> PathBuf {
+  match format {
+    LoadOrderFormat::Asterisk | LoadOrderFormat::Plain => game.install_path.join(&game.path_extension),
+    LoadOrderFormat::ModsConfig => game.install_path.join("Data"),
+    LoadOrderFormat::ModList => game.install_path.join("data"),
+  }
+}
+
+//RimWorld goes by the packageId in 'About/About.xml', in lower case like ModsConfig.xml has it
+fn package_id(dir: &Path) -> Option<String> {
+  let about = fs::read(dir.join("About").join("About.xml")).ok()?;
+  let element = Element::parse(about.as_slice()).ok()?;
+  let id = element.get_child("packageId")?.get_text()?;
+  Some(id.trim().to_lowercase())
+}
+
+//Factorio's name is in 'info.json', zipped ones are also called '<name>_<version>.zip'
+fn factorio_name(path: &Path, is_dir: bool) -> Option<String> {
+  if is_dir {
+    let info: Value = serde_json::from_str(&fs::read_to_string(path.join("info.json")).ok()?).ok()?;
+    return Some(info.get("name")?.as_str()?.to_string());
+  }
+  let file_name = path.file_name()?.to_string_lossy().to_string();
+  let stem = file_name.strip_suffix(".zip")?;
+  Some(stem.rsplit_once('_').map_or(stem, |(name, _)| name).to_string())
+}
+
+//What a folder has for the load order file: the plugins at the top of the data folder, the game
+//doesn't look anywhere else, or the mods of RimWorld and Factorio. A mod's folder can be one of
+//those itself or have several in it. Files a link deployment put there aren't the game's
+fn plugins_in(dir: &Path, elem: Option<&Mod>, format: LoadOrderFormat, deployed: &BTreeSet<PathBuf>) -> io::Result<Vec<String>> {
+  let mut plugins: Vec<String> = Vec::new();
+  if !dir.is_dir() {
+    return Ok(plugins);
+  }
+  let own = match format {
+    LoadOrderFormat::ModsConfig => package_id(dir),
+    LoadOrderFormat::ModList if dir.join("info.json").is_file() => factorio_name(dir, true),
+    _ => None,
+  };
+  if let Some(own) = own.filter(|_| elem.is_some()) {
+    plugins.push(own);
+    return Ok(plugins);
+  }
+  for entry in dir.read_dir()? {
+    let entry = entry?;
+    let path = entry.path();
+    let name = entry.file_name().to_string_lossy().to_string();
+    let hidden = elem.map_or(false, |elem| elem.meta.hidden.iter().any(|hidden| hidden == Path::new(&name)));
+    if hidden || deployed.contains(&path) {
+      continue;
+    }
+    let is_dir = entry.file_type()?.is_dir();
+    let plugin = match format {
+      LoadOrderFormat::Asterisk | LoadOrderFormat::Plain if !is_dir && plugin_rank(&name).is_some() => Some(name),
+      LoadOrderFormat::ModsConfig if is_dir => package_id(&path),
+      //the game's core is always loaded and never listed
+      LoadOrderFormat::ModList if elem.is_some() || name != "core" => factorio_name(&path, is_dir),
+      _ => None,
+    };
+    plugins.extend(plugin);
+  }
+  plugins.sort_by_key(|name| (plugin_rank(name), name.to_lowercase()));
+  Ok(plugins)
+}
+
+fn deployed_files(game: &Game) -> io::Result<BTreeSet<PathBuf>> {
+  Ok(DeploymentManifest::load(game)?.map(|manifest| manifest.files).unwrap_or_default())
+}
+
+//The game's own plugins and then those of the mods in load order. A plugin two mods ship
+//is the later one's, like any other conflict, and goes where that mod is
+fn collect(game: &Game, mods: &[Mod], format: LoadOrderFormat) -> io::Result<Vec<Plugin>> {
+  let mut plugins: Vec<Plugin> = plugins_in(&game_dir(game, format), None, format, &deployed_files(game)?)?.into_iter()
+    .map(|name| Plugin { name, mod_name: None, enabled: true })
+    .collect();
+  for elem in mods {
+    for name in plugins_in(&mod_dir(game, elem)?, Some(elem), format, &BTreeSet::new())? {
+      plugins.retain(|plugin| !plugin.name.eq_ignore_ascii_case(&name));
+      plugins.push(Plugin { name, mod_name: Some(elem.name.clone()), enabled: true });
+    }
+  }
+  Ok(plugins)
+}
+
+fn position(order: &[String], name: &str) -> usize {
+  order.iter().position(|ordered| ordered.eq_ignore_ascii_case(name)).unwrap_or(usize::MAX)
+}
+
+fn arrange(mut plugins: Vec<Plugin>, state: &PluginState) -> Vec<Plugin> {
+  //stable, so plugins the profile hasn't ordered keep the order of their mods. Masters
+  //go first whatever the order says and the game's own before the mods'
+  plugins.sort_by_key(|plugin| (!is_master(&plugin.name), plugin.mod_name.is_some(), position(&state.order, &plugin.name)));
+  for plugin in plugins.iter_mut() {
+    plugin.enabled = !state.disabled.iter().any(|disabled| disabled.eq_ignore_ascii_case(&plugin.name));
+  }
+  plugins
+}
+
+fn invalid(detail: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, detail)
+}
+
+fn parse(contents: &str, format: LoadOrderFormat) -> io::Result<Vec<Plugin>> {
+  let plugin = |name: &str, enabled: bool| Plugin { name: name.trim().to_string(), mod_name: None, enabled };
+  let lines = || contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+  Ok(match format {
+    LoadOrderFormat::Asterisk => lines().map(|line| match line.strip_prefix('*') {
+      Some(name) => plugin(name, true),
+      None => plugin(line, false),
+    }).collect(),
+    LoadOrderFormat::Plain => lines().map(|line| plugin(line, true)).collect(),
+    LoadOrderFormat::ModsConfig => {
+      let config = Element::parse(contents.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+      config.get_child("activeMods").map_or_else(Vec::new, |active| {
+        active.children.iter()
+          .filter_map(|child| child.as_element())
+          .filter_map(|li| li.get_text())
+          .map(|id| plugin(&id.to_lowercase(), true))
+          .collect()
+      })
+    },
+    LoadOrderFormat::ModList => {
+      let list: Value = serde_json::from_str(contents)?;
+      list.get("mods").and_then(Value::as_array).map_or_else(Vec::new, |mods| {
+        mods.iter()
+          .filter_map(|elem| Some(plugin(elem.get("name")?.as_str()?, elem.get("enabled").and_then(Value::as_bool).unwrap_or(true))))
+          .collect()
+      })
+    },
+  })
+}
+
+//The newer Bethesda games load their own masters no matter what and complain when they're listed.
+//RimWorld's file has more than the active mods, the rest of what was there stays
+fn render(plugins: &[Plugin], format: LoadOrderFormat, previous: Option<&str>) -> io::Result<String> {
+  let mut contents = String::new();
+  match format {
+    LoadOrderFormat::Asterisk | LoadOrderFormat::Plain => {
+      contents.push_str("# Generated by TMM from the active profile\n");
+      for plugin in plugins {
+        match format {
+          LoadOrderFormat::Asterisk if plugin.mod_name.is_some() => {
+            contents.push_str(&format!("{}{}\n", if plugin.enabled { "*" } else { "" }, plugin.name));
+          },
+          LoadOrderFormat::Plain if plugin.enabled => contents.push_str(&format!("{}\n", plugin.name)),
+          _ => {},
+        }
+      }
+    },
+    LoadOrderFormat::ModsConfig => {
+      let mut config = previous.and_then(|previous| Element::parse(previous.as_bytes()).ok())
+        .unwrap_or_else(|| Element::new("ModsConfigData"));
+      let mut active = Element::new("activeMods");
+      for plugin in plugins.iter().filter(|plugin| plugin.enabled) {
+        let mut li = Element::new("li");
+        li.children.push(XMLNode::Text(plugin.name.clone()));
+        active.children.push(XMLNode::Element(li));
+      }
+      match config.children.iter().position(|child| child.as_element().map_or(false, |element| element.name == "activeMods")) {
+        Some(index) => config.children[index] = XMLNode::Element(active),
+        None => config.children.push(XMLNode::Element(active)),
+      }
+      let mut output: Vec<u8> = Vec::new();
+      config.write_with_config(&mut output, EmitterConfig::new().perform_indent(true))
+        .map_err(|e| invalid(e.to_string()))?;
+      contents = String::from_utf8_lossy(&output).to_string();
+    },
+    LoadOrderFormat::ModList => {
+      let mods: Vec<Value> = plugins.iter().map(|plugin| json!({ "name": plugin.name, "enabled": plugin.enabled })).collect();
+      contents = serde_json::to_string_pretty(&json!({ "mods": mods }))?;
+    },
+  }
+  Ok(contents)
+}
+
+//A file that isn't what got written last was edited by the user or another tool,
+//its order and enabled plugins go into the profile before it's written again. One that
+//can't be read anymore gets written over
+fn read_back(game: &Game, file: &LoadOrderFile, state: &mut PluginState) -> io::Result<()> {
+  let live = match live_path(game, file) {
+    Some(live) if live.is_file() => live,
+    _ => return Ok(()),
+  };
+  let hash = pool::hash_file(&live)?;
+  if state.written_hash.as_ref() == Some(&hash) {
+    return Ok(());
+  }
+  let edited = match parse(&fs::read_to_string(&live)?, file.format) {
+    Ok(edited) => edited,
+    Err(e) => {
+      tracing::warn!("Couldn't read the load order in '{}', it gets written again: {}", live.display(), e);
+      return Ok(());
+    }
+  };
+  let listed: Vec<String> = edited.iter().map(|plugin| plugin.name.clone()).collect();
+  if lists_disabled(file.format) {
+    state.disabled = edited.iter().filter(|plugin| !plugin.enabled).map(|plugin| plugin.name.clone()).collect();
+  } else {
+    //there's no telling a removed plugin from a disabled one, one that was written and isn't there anymore got disabled
+    for name in &state.written {
+      if position(&listed, name) == usize::MAX && position(&state.disabled, name) == usize::MAX {
+        state.disabled.push(name.clone());
+      }
+    }
+    state.disabled.retain(|name| position(&listed, name) == usize::MAX);
+  }
+  //the ones that aren't in it anymore keep their place after those
+  let rest: Vec<String> = state.order.iter().filter(|name| position(&listed, name) == usize::MAX).cloned().collect();
+  state.order = listed.into_iter().chain(rest).collect();
+  state.written_hash = Some(hash);
+  save_state(game, state)
+}
+
+pub fn list(game: &Game, mods: &[Mod]) -> io::Result<Vec<Plugin>> {
+  let mut state = load_state(game);
+  if let Some(file) = &game.load_order_file {
+    read_back(game, file, &mut state)?;
+  }
+  let format = game.load_order_file.as_ref().map_or(LoadOrderFormat::Asterisk, |file| file.format);
+  Ok(arrange(collect(game, mods, format)?, &state))
+}
+
+pub fn order(game: &Game) -> io::Result<PluginOrder> {
+  let mut state = load_state(game);
+  if let Some(file) = &game.load_order_file {
+    read_back(game, file, &mut state)?;
+  }
+  Ok(PluginOrder { order: state.order, disabled: state.disabled })
+}
+
+pub fn set_order(game: &Game, order: PluginOrder) -> io::Result<()> {
+  save_state(game, &PluginState { order: order.order, disabled: order.disabled, ..load_state(game) })
+}
+
+//Takes the order the frontend shows, plugins it leaves out keep their place after those
+pub fn set(game: &Game, plugins: &[Plugin]) -> io::Result<()> {
+  let mut state = load_state(game);
+  let named: Vec<String> = plugins.iter().map(|plugin| plugin.name.clone()).collect();
+  let rest: Vec<String> = state.order.iter().filter(|name| position(&named, name) == usize::MAX).cloned().collect();
+  state.order = named.iter().cloned().chain(rest).collect();
+  state.disabled.retain(|name| position(&named, name) == usize::MAX);
+  state.disabled.extend(plugins.iter().filter(|plugin| !plugin.enabled).map(|plugin| plugin.name.clone()));
+  save_state(game, &state)
+}
+
+//From the enabled mods in load order, for games without a load order file there's nothing to do
+pub fn write(game: &Game, mods: &[Mod]) -> io::Result<()> {
+  let file = match &game.load_order_file {
+    Some(file) => file,
+    None => return Ok(()),
+  };
+  let live = match live_path(game, file) {
+    Some(live) => live,
+    None => return Ok(()),
+  };
+  let mut state = load_state(game);
+  read_back(game, file, &mut state)?;
+  let plugins = arrange(collect(game, mods, file.format)?, &state);
+  if let Some(parent) = live.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let previous = fs::read_to_string(&live).ok();
+  let temporary = live.with_extension("tmm");
+  fs::write(&temporary, render(&plugins, file.format, previous.as_deref())?)?;
+  fs::rename(&temporary, &live)?;
+  state.written_hash = Some(pool::hash_file(&live)?);
+  state.written = plugins.into_iter().filter(|plugin| plugin.enabled).map(|plugin| plugin.name).collect();
+  save_state(game, &state)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn plugin(name: &str, mod_name: Option<&str>, enabled: bool) -> Plugin {
+    Plugin { name: name.to_string(), mod_name: mod_name.map(str::to_string), enabled }
+  }
+
+  fn names(plugins: &[Plugin]) -> Vec<&str> {
+    plugins.iter().map(|plugin| plugin.name.as_str()).collect()
+  }
+
+  #[test]
+  fn masters_first_then_the_profiles_order() {
+    let plugins = vec![
+      plugin("Patch.esp", Some("Patch"), true),
+      plugin("Skyrim.esm", None, true),
+      plugin("Lib.esm", Some("Lib"), true),
+      plugin("Quest.esp", Some("Quest"), true),
+      plugin("Light.esl", Some("Light"), true),
+    ];
+    let state = PluginState { order: vec!["quest.esp".to_string(), "Patch.esp".to_string()], disabled: vec!["PATCH.esp".to_string()], ..PluginState::default() };
+    let arranged = arrange(plugins, &state);
+    assert_eq!(names(&arranged), ["Skyrim.esm", "Lib.esm", "Light.esl", "Quest.esp", "Patch.esp"]);
+    assert!(arranged.iter().all(|plugin| plugin.enabled != (plugin.name == "Patch.esp")));
+  }
+
+  #[test]
+  fn asterisk_lists_every_mod_plugin() {
+    let plugins = vec![plugin("Skyrim.esm", None, true), plugin("A.esp", Some("A"), true), plugin("B.esp", Some("B"), false)];
+    let contents = render(&plugins, LoadOrderFormat::Asterisk, None).unwrap();
+    assert_eq!(contents, "# Generated by TMM from the active profile\n*A.esp\nB.esp\n");
+    let parsed = parse(&contents, LoadOrderFormat::Asterisk).unwrap();
+    assert_eq!(names(&parsed), ["A.esp", "B.esp"]);
+    assert!(parsed[0].enabled && !parsed[1].enabled);
+  }
+
+  #[test]
+  fn plain_only_has_the_enabled() {
+    let plugins = vec![plugin("Oblivion.esm", None, true), plugin("A.esp", Some("A"), false), plugin("B.esp", Some("B"), true)];
+    let contents = render(&plugins, LoadOrderFormat::Plain, None).unwrap();
+    assert_eq!(names(&parse(&contents, LoadOrderFormat::Plain).unwrap()), ["Oblivion.esm", "B.esp"]);
+  }
+
+  #[test]
+  fn mods_config_keeps_the_rest_of_the_file() {
+    let previous = "<ModsConfigData><version>1.4</version><activeMods><li>ludeon.rimworld</li></activeMods></ModsConfigData>";
+    let plugins = vec![plugin("ludeon.rimworld", None, true), plugin("unlimitedhugs.hugslib", Some("HugsLib"), true), plugin("off.mod", Some("Off"), false)];
+    let contents = render(&plugins, LoadOrderFormat::ModsConfig, Some(previous)).unwrap();
+    assert!(contents.contains("<version>1.4</version>"));
+    assert_eq!(names(&parse(&contents, LoadOrderFormat::ModsConfig).unwrap()), ["ludeon.rimworld", "unlimitedhugs.hugslib"]);
+  }
+
+  #[test]
+  fn mod_list_round_trips() {
+    let plugins = vec![plugin("base", None, true), plugin("space-exploration", Some("SE"), false)];
+    let parsed = parse(&render(&plugins, LoadOrderFormat::ModList, None).unwrap(), LoadOrderFormat::ModList).unwrap();
+    assert_eq!(names(&parsed), ["base", "space-exploration"]);
+    assert!(parsed[0].enabled && !parsed[1].enabled);
+  }
+
+  #[test]
+  fn factorio_names() {
+    assert_eq!(factorio_name(Path::new("mods/even-distribution_1.0.10.zip"), false).as_deref(), Some("even-distribution"));
+    assert_eq!(factorio_name(Path::new("mods/readme.txt"), false), None);
+  }
+}
@@ -0,0 +1,139 @@
+use crate::mod_manager::{get_files, with_suffix};
+use crate::mod_manager::game::Game;
+use crate::tasks;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::fs;
+
+//Every installed mod file is a hardlink into this pool, named after the hash of its
+//contents, so the same file in several mods, variants or profiles only takes up space once.
+//Files changed in place change for everything sharing them, tools should replace files instead.
+//A blob that got changed like that doesn't match its name anymore, it leaves the pool the
+//next time a file would be linked to it so nothing else picks up the changes
+pub fn pool_path() -> io::Result<PathBuf> {
+  Ok(crate::config_root()?.join("pool"))
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DedupReport {
+  files: usize,
+  //files that turned out to be in the pool already
+  deduplicated: usize,
+  saved_bytes: u64,
+  //files that couldn't share the pool's filesystem
+  skipped: usize,
+  //by path, for whoever needs them after
+  #[serde(skip)]
+  pub hashes: BTreeMap<PathBuf, String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct GcReport {
+  removed: usize,
+  freed_bytes: u64,
+}
+
+pub fn hash_file(path: &Path) -> io::Result<String> {
+  let mut file = fs::File::open(path)?;
+  let mut hasher = blake3::Hasher::new();
+  let mut buffer = vec![0; 65_536];
+  loop {
+    let count = file.read(&mut buffer)?;
+    if count == 0 {
+      break;
+    }
+    hasher.update(&buffer[..count]);
+  }
+  Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn blob_path(hash: &str) -> io::Result<PathBuf> {
+  Ok(pool_path()?.join(&hash[..2]).join(hash))
+}
+
+//Swaps the file for a link to the blob without there ever being no file at the path
+fn link_to_blob(blob: &Path, file: &Path) -> io::Result<()> {
+  let temporary = with_suffix(file, ".tmm_dedup");
+  fs::hard_link(blob, &temporary)?;
+  fs::rename(&temporary, file)
+}
+
+pub fn ingest(path: &Path) -> io::Result<DedupReport> {
+  let mut report = DedupReport::default();
+  let files = get_files(path)?;
+  let total = files.len() as u64;
+  for (done, file) in files.into_iter().enumerate() {
+    tasks::checkpoint()?;
+    tasks::progress(done as u64, Some(total));
+    let metadata = fs::symlink_metadata(&file)?;
+    if !metadata.file_type().is_file() {
+      continue;
+    }
+    report.files += 1;
+    let hash = match hash_file(&file) {
+      Ok(hash) => hash,
+      Err(e) => {
+        tracing::warn!("Couldn't hash '{}', it stays out of the pool: {}", file.display(), e);
+        report.skipped += 1;
+        continue;
+      }
+    };
+    let blob = blob_path(&hash)?;
+    report.hashes.insert(file.clone(), hash.clone());
+    match fs::symlink_metadata(&blob) {
+      Ok(blob_metadata) if blob_metadata.ino() == metadata.ino() && blob_metadata.dev() == metadata.dev() => {},
+      Ok(_) if !matches!(hash_file(&blob), Ok(ref blob_hash) if *blob_hash == hash) => {
+        //changed in place through one of its links, the files sharing it keep it and this one takes its place
+        tracing::warn!("'{}' doesn't have the contents it's named after anymore, replacing it", blob.display());
+        fs::remove_file(&blob)?;
+        match fs::hard_link(&file, &blob) {
+          Ok(()) => {},
+          Err(e) if e.raw_os_error() == Some(libc::EXDEV) => report.skipped += 1,
+          Err(e) => return Err(e),
+        }
+      },
+      Ok(_) => {
+        match link_to_blob(&blob, &file) {
+          Ok(()) => {
+            report.deduplicated += 1;
+            report.saved_bytes += metadata.len();
+          },
+          Err(e) if e.raw_os_error() == Some(libc::EXDEV) => report.skipped += 1,
+          Err(e) => return Err(e),
+        }
+      },
+      Err(_) => {
+        if let Some(parent) = blob.parent() {
+          fs::create_dir_all(parent)?;
+        }
+        match fs::hard_link(&file, &blob) {
+          Ok(()) => {},
+          Err(e) if e.raw_os_error() == Some(libc::EXDEV) => report.skipped += 1,
+          Err(e) => return Err(e),
+        }
+      }
+    }
+  }
+  Ok(report)
+}
+
+pub fn ingest_game(game: &Game) -> io::Result<DedupReport> {
+  ingest(&game.profile_path.join("mods"))
+}
+
+//A blob only the pool links to isn't part of any mod anymore
+pub fn collect_garbage() -> io::Result<GcReport> {
+  let mut report = GcReport::default();
+  for blob in get_files(&pool_path()?)? {
+    let metadata = fs::symlink_metadata(&blob)?;
+    if metadata.nlink() <= 1 {
+      fs::remove_file(&blob)?;
+      report.removed += 1;
+      report.freed_bytes += metadata.len();
+    }
+  }
+  Ok(report)
+}
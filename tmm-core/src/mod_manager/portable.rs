@@ -0,0 +1,208 @@
+use crate::mod_manager::{check_mod_name, extract, install_archive, load_mods, meta, profiles, registry, scratch, split, versions};
+use crate::mod_manager::archive::InstallOptions;
+use crate::mod_manager::game::{Game, DeployMode};
+use crate::mod_manager::reinstall::INSTALL_MANIFEST_NAME;
+use crate::mod_downloader;
+use crate::error::{Context, Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::{fs, io};
+
+use crate::events::Events;
+
+//Describes the profile inside the archive, next to the files below and the mods if they're included
+const MANIFEST_NAME: &str = "profile.json";
+const PROFILE_FILES: &[&str] = &["modlist.json", "overrides.json"];
+//2 has every mod's versions in its folder
+const FORMAT: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExportedMod {
+  name: String,
+  enabled: bool,
+  //the files are in the archive's mods folder
+  included: bool,
+  meta: meta::ModMeta,
+}
+
+//The game's own settings at the time, importing doesn't change the game's
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileSettings {
+  deploy_mode: DeployMode,
+  deploy_excludes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProfileManifest {
+  format: u32,
+  appid: u32,
+  game_name: String,
+  profile: String,
+  mods: Vec<ExportedMod>,
+  settings: ProfileSettings,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+  profile: String,
+  //came with the archive
+  included: Vec<String>,
+  downloaded: Vec<String>,
+  //from the source, but the download isn't the archive it was installed from, likely a newer version
+  changed: Vec<String>,
+  //neither in the archive nor from a known source
+  missing: Vec<String>,
+  failed: Vec<Error>,
+  settings: Option<ProfileSettings>,
+}
+
+//A zip or 7z, whichever dest ends in. Without the mods it's only the list of them,
+//importing downloads the ones from a source again
+pub fn export(game: &Game, profile: &str, dest: &Path, include_mods: bool) -> io::Result<()> {
+  registry::check_user_path("dest", dest)?;
+  let path = profiles::existing(game, profile)?;
+  let format = match dest.extension().map(|extension| extension.to_string_lossy().to_lowercase()) {
+    Some(extension) if extension == "zip" || extension == "7z" => extension,
+    _ => {
+      return Err(Error::new(ErrorCode::InvalidDestination, format!("'{}' has to end in .zip or .7z", dest.display()))
+        .param("path", dest)
+        .into_io(io::ErrorKind::InvalidInput));
+    }
+  };
+  let profile_game = Game { profile_path: path.clone(), ..game.clone() };
+  let manifest = ProfileManifest {
+    format: FORMAT,
+    appid: game.appid,
+    game_name: game.public_name.clone(),
+    profile: profile.to_string(),
+    mods: load_mods(&profile_game)?.into_iter()
+      .map(|elem| ExportedMod { name: elem.name, enabled: elem.enabled, included: include_mods, meta: elem.meta })
+      .collect(),
+    settings: ProfileSettings { deploy_mode: game.deploy_mode, deploy_excludes: game.deploy_excludes.clone() },
+  };
+  let staging = scratch::ScratchDir::new(game, "export", 0)?;
+  fs::write(staging.path().join(MANIFEST_NAME), serde_json::to_string_pretty(&manifest)?)?;
+
+  //7-Zip adds to archives that are there already
+  let temporary = dest.with_extension(format!("tmm_export.{}", format));
+  if temporary.exists() {
+    fs::remove_file(&temporary)?;
+  }
+  let mut command = Command::new("7z");
+  //run from the profile so the paths in the archive are relative to it, the manifest is added by its
+  //full path and ends up at the root
+  command.current_dir(&path).arg("a").arg("-y").arg(format!("-t{}", format))
+    .arg(format!("-xr!{}", INSTALL_MANIFEST_NAME))
+    .arg(&temporary)
+    .arg(staging.path().join(MANIFEST_NAME));
+  command.args(PROFILE_FILES.iter().filter(|name| path.join(name).exists()));
+  if include_mods && path.join("mods").is_dir() {
+    command.arg("mods");
+  }
+  let output = split::run_7z(&mut command)?;
+  if !output.status.success() {
+    fs::remove_file(&temporary).ok();
+    return Err(io::Error::new(io::ErrorKind::Other, String::from_utf8_lossy(&output.stderr).trim().to_string()));
+  }
+  fs::rename(temporary, dest)
+}
+
+//The user's own fields come from the export, the rest from what got installed now
+pub fn with_user_fields(installed: meta::ModMeta, exported: &meta::ModMeta) -> meta::ModMeta {
+  meta::ModMeta {
+    tags: exported.tags.clone(),
+    notes: exported.notes.clone(),
+    custom_version: exported.custom_version.clone(),
+    color: exported.color.clone(),
+    category: exported.category.clone().or(installed.category.clone()),
+    hidden: exported.hidden.clone(),
+    dependencies: if installed.dependencies.is_empty() { exported.dependencies.clone() } else { installed.dependencies.clone() },
+    ..installed
+  }
+}
+
+pub fn download(game: &Game, mod_name: &str, exported: &meta::ModMeta, url: &str, events: &Events) -> io::Result<bool> {
+  let file_name = mod_downloader::fetch(url, game, events)
+    .map_err(|e| Error::new(ErrorCode::SourceFailed, e.to_string()).param("url", url).into_io(io::ErrorKind::Other))?;
+  let report = install_archive(&game.profile_path.join("downloads").join(&file_name), mod_name, InstallOptions::default(), game)?;
+  let mod_dir = versions::active(game, mod_name)?;
+  let installed = meta::load(&mod_dir);
+  let changed = installed.archive_hash != exported.archive_hash;
+  meta::save(&mod_dir, &with_user_fields(installed, exported))?;
+  //a package archive would have made several mods, those stay as they are
+  Ok(changed || report.mods.len() != 1)
+}
+
+//Into a new profile, the active one stays active. Mods that aren't in the archive get downloaded
+//again where the source is known, one failing doesn't stop the others
+pub fn import(game: &Game, archive: &Path, profile: &str, events: &Events) -> io::Result<ImportReport> {
+  registry::check_source("archive", archive)?;
+  let target = profiles::free(game, profile)?;
+  let staging = extract(archive, None, game)?;
+  let manifest: ProfileManifest = match fs::read_to_string(staging.path().join(MANIFEST_NAME)) {
+    Ok(contents) => serde_json::from_str(&contents)?,
+    Err(_) => {
+      return Err(Error::new(ErrorCode::InvalidModFile, format!("'{}' isn't an exported profile", archive.display()))
+        .param("path", archive)
+        .into_io(io::ErrorKind::InvalidData));
+    }
+  };
+  if manifest.appid != game.appid {
+    return Err(Error::new(ErrorCode::WrongGame, format!("'{}' is a profile of {} ({}), not of {}", archive.display(), manifest.game_name, manifest.appid, game.public_name))
+      .param("path", archive)
+      .param("appid", manifest.appid)
+      .param("game_appid", game.appid)
+      .into_io(io::ErrorKind::InvalidData));
+  }
+  //every name ends up as a directory in the profile
+  for exported in &manifest.mods {
+    check_mod_name(&exported.name)?;
+  }
+  let mut report = ImportReport { profile: profile.to_string(), settings: Some(manifest.settings.clone()), ..ImportReport::default() };
+
+  //built next to it so a failed import doesn't look like a profile
+  let building = target.with_file_name(format!(".{}.tmp", target.file_name().unwrap().to_string_lossy()));
+  if building.exists() {
+    fs::remove_dir_all(&building)?;
+  }
+  fs::create_dir_all(building.join("mods"))?;
+  for name in PROFILE_FILES {
+    if staging.path().join(name).is_file() {
+      fs::rename(staging.path().join(name), building.join(name))?;
+    }
+  }
+  for exported in manifest.mods.iter().filter(|exported| exported.included) {
+    let mod_dir = staging.path().join("mods").join(&exported.name);
+    if mod_dir.is_dir() {
+      if manifest.format < 2 {
+        versions::nest(&mod_dir)?;
+      }
+      scratch::move_dir(&mod_dir, &building.join("mods").join(&exported.name))?;
+      report.included.push(exported.name.clone());
+    }
+  }
+  fs::rename(&building, &target)?;
+
+  let profile_game = Game { profile_path: target, ..game.clone() };
+  for exported in manifest.mods.iter().filter(|exported| !report.included.contains(&exported.name)) {
+    let url = match exported.meta.source_url.as_deref() {
+      Some(url) if exported.meta.source.is_some() => url,
+      _ => {
+        report.missing.push(exported.name.clone());
+        continue;
+      }
+    };
+    match download(&profile_game, &exported.name, &exported.meta, url, events) {
+      Ok(changed) => {
+        if changed {
+          report.changed.push(exported.name.clone());
+        }
+        report.downloaded.push(exported.name.clone());
+      },
+      Err(e) => report.failed.push(Error::io(Context::Profiles, e).param("mod_name", &exported.name)),
+    }
+  }
+  Ok(report)
+}
+
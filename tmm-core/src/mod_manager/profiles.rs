@@ -0,0 +1,264 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{configs, deploy, get_directories, monitor, pool, purge_deployed, reflink, saves};
+use crate::error::{Error, ErrorCode};
+use crate::events::Events;
+use serde::Serialize;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//The profile a game is set up with lives in 'profiles/<appid>', others next to it
+//in 'profiles/<appid>.<name>'. The game's config points at the active one
+pub const DEFAULT_PROFILE: &str = "default";
+
+//What belongs to a deployment of the profile, is only scratch or ties it to its synced copy,
+//a clone starts without any of it
+const DEPLOYMENT_STATE: &[&str] = &[
+  "deployment.json", "deployment.journal", "root_deployment.json", "root_deployment.journal", "baseline.json", "bisect.json",
+  "experiments", "layers", "overwrite.work", ".tmp", "trash", "backups", "sync.json",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+  name: String,
+  active: bool,
+  mods: usize,
+}
+
+pub fn profiles_root() -> io::Result<PathBuf> {
+  Ok(crate::config_root()?.join("profiles"))
+}
+
+pub fn profile_path(appid: u32, name: &str) -> io::Result<PathBuf> {
+  if name == DEFAULT_PROFILE {
+    Ok(profiles_root()?.join(appid.to_string()))
+  } else {
+    Ok(profiles_root()?.join(format!("{}.{}", appid, name)))
+  }
+}
+
+//Profiles from before there were several are the default one, wherever they are
+pub fn active_name(game: &Game) -> String {
+  let directory = game.profile_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+  match directory.strip_prefix(&format!("{}.", game.appid)) {
+    Some(name) if profiles_root().map_or(false, |root| game.profile_path.parent() == Some(root.as_path())) => name.to_string(),
+    _ => DEFAULT_PROFILE.to_string(),
+  }
+}
+
+fn path_of(game: &Game, name: &str) -> io::Result<PathBuf> {
+  if name == active_name(game) {
+    Ok(game.profile_path.clone())
+  } else {
+    profile_path(game.appid, name)
+  }
+}
+
+pub fn validate_name(name: &str) -> io::Result<()> {
+  if name.trim().is_empty() || name.contains(['/', '\0']) || name.starts_with('.') {
+    return Err(Error::new(ErrorCode::InvalidProfileName, format!("'{}' can't be used as the name of a profile", name))
+      .param("profile", name)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  Ok(())
+}
+
+pub fn existing(game: &Game, name: &str) -> io::Result<PathBuf> {
+  validate_name(name)?;
+  let path = path_of(game, name)?;
+  if !path.is_dir() {
+    return Err(Error::new(ErrorCode::ProfileNotFound, format!("'{}' has no profile '{}'", game.public_name, name))
+      .param("profile", name)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  Ok(path)
+}
+
+pub fn free(game: &Game, name: &str) -> io::Result<PathBuf> {
+  validate_name(name)?;
+  let path = path_of(game, name)?;
+  if path.exists() {
+    return Err(Error::new(ErrorCode::ProfileExists, format!("there already is a profile called '{}'", name))
+      .param("profile", name)
+      .into_io(io::ErrorKind::AlreadyExists));
+  }
+  Ok(path)
+}
+
+pub fn list(game: &Game) -> io::Result<Vec<Profile>> {
+  let active = active_name(game);
+  let mut names: Vec<String> = vec![DEFAULT_PROFILE.to_string()];
+  let prefix = format!("{}.", game.appid);
+  let root = profiles_root()?;
+  if root.is_dir() {
+    for path in get_directories(&root)? {
+      let directory = path.file_name().unwrap().to_string_lossy().to_string();
+      if let Some(name) = directory.strip_prefix(&prefix) {
+        names.push(name.to_string());
+      }
+    }
+  }
+  if !names.contains(&active) {
+    names.push(active.clone());
+  }
+  names.sort();
+  let mut profiles: Vec<Profile> = Vec::new();
+  for name in names {
+    let path = path_of(game, &name)?;
+    if !path.is_dir() {
+      continue;
+    }
+    let mods = get_directories(&path.join("mods"))?.len();
+    profiles.push(Profile { mods, active: name == active, name });
+  }
+  Ok(profiles)
+}
+
+//Where every profile of the game is, the active one first
+pub fn paths(game: &Game) -> io::Result<Vec<PathBuf>> {
+  let mut paths: Vec<PathBuf> = vec![game.profile_path.clone()];
+  for profile in list(game)? {
+    let path = path_of(game, &profile.name)?;
+    if !paths.contains(&path) {
+      paths.push(path);
+    }
+  }
+  Ok(paths)
+}
+
+//Downloads get hardlinked, nothing writes into an archive. Everything else is copied, reflinked where the
+//filesystem can, editing a mod's files in one profile can't change them in the other
+fn clone_tree(source: &Path, target: &Path, linked: bool) -> io::Result<()> {
+  fs::create_dir_all(target)?;
+  for entry in source.read_dir()? {
+    let entry = entry?;
+    let from = entry.path();
+    let to = target.join(entry.file_name());
+    let file_type = entry.file_type()?;
+    if file_type.is_symlink() {
+      //mods installed as a link keep pointing at their folder
+      symlink(fs::read_link(&from)?, &to)?;
+    } else if file_type.is_dir() {
+      clone_tree(&from, &to, linked)?;
+    } else if !linked || fs::hard_link(&from, &to).is_err() {
+      reflink::copy(&from, &to)?;
+    }
+  }
+  Ok(())
+}
+
+//The enabled mods and load order, override rules, merged configs and everything else the profile
+//has set, without its deployment
+pub fn clone(game: &Game, source: &str, new_name: &str) -> io::Result<()> {
+  let from = existing(game, source)?;
+  let to = free(game, new_name)?;
+  //built next to it so a failed clone doesn't look like a profile
+  let building = to.with_file_name(format!(".{}.tmp", to.file_name().unwrap().to_string_lossy()));
+  if building.exists() {
+    fs::remove_dir_all(&building)?;
+  }
+  let result = (|| {
+    fs::create_dir_all(&building)?;
+    for entry in from.read_dir()? {
+      let entry = entry?;
+      let name = entry.file_name().to_string_lossy().to_string();
+      if DEPLOYMENT_STATE.contains(&name.as_str()) {
+        continue;
+      }
+      let linked = name == "downloads";
+      if entry.file_type()?.is_dir() {
+        clone_tree(&entry.path(), &building.join(&name), linked)?;
+      } else {
+        reflink::copy(&entry.path(), &building.join(&name))?;
+      }
+    }
+    fs::rename(&building, &to)
+  })();
+  if result.is_err() {
+    let _ = fs::remove_dir_all(&building);
+  }
+  result
+}
+
+//The deployment of the profile that was active comes down first, it's kept track of in that profile
+pub fn switch(game: Game, name: &str, events: &Events) -> io::Result<Game> {
+  let path = existing(&game, name)?;
+  if path == game.profile_path {
+    return Ok(game);
+  }
+  let backend = deploy::backend(game.deploy_mode);
+  if game.install_path.is_dir() && backend.status(&game)?.deployed {
+    monitor::ensure_not_running(&game)?;
+    purge_deployed(&game, events)?;
+  }
+  configs::sync_back(&game)?;
+  let game = Game { profile_path: path, ..game };
+  saves::apply(&game)?;
+  configs::deploy_switched(&game)?;
+  game.save()?;
+  Ok(game)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteReport {
+  //with the profile that's active now, another one if the deleted one was
+  game: Game,
+  //another profile has a mod of the same name, the files the pool shares with it stay
+  shared: Vec<String>,
+  removed: Vec<String>,
+  //only when the pool got cleaned up, the files of the removed mods aren't gone otherwise
+  garbage: Option<pool::GcReport>,
+}
+
+fn has_files(dir: &Path) -> io::Result<bool> {
+  match dir.read_dir() {
+    Ok(mut entries) => Ok(entries.next().is_some()),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+    Err(e) => Err(e),
+  }
+}
+
+//A game always keeps one profile. The deployed one needs force, it gets purged first, and the
+//active one gets switched away from before it goes, to the default profile if that's still there.
+//Its own saves, the ones that lost a merge and its INIs are only in there, they only go with discard_saves
+pub fn delete(game: Game, name: &str, force: bool, discard_saves: bool, collect_garbage: bool, events: &Events) -> io::Result<DeleteReport> {
+  let path = existing(&game, name)?;
+  let own = has_files(&saves::profile_saves(&path))? || has_files(&saves::conflicts_dir(&path))? || has_files(&path.join("configs"))?;
+  if !discard_saves && own {
+    return Err(Error::new(ErrorCode::ProfileHasSaves, format!("'{}' has its own saves or INIs, they'd be deleted with it", name))
+      .param("profile", name)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  let others: Vec<String> = list(&game)?.into_iter().map(|profile| profile.name).filter(|other| other != name).collect();
+  if others.is_empty() {
+    return Err(Error::new(ErrorCode::LastProfile, format!("'{}' is the only profile of '{}'", name, game.public_name))
+      .param("profile", name)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  let mut game = game;
+  if path == game.profile_path {
+    //only the active profile can be deployed, switching purges every other one
+    if game.install_path.is_dir() && deploy::backend(game.deploy_mode).status(&game)?.deployed && !force {
+      return Err(Error::new(ErrorCode::ProfileDeployed, format!("'{}' is deployed, purge it first", name))
+        .param("profile", name)
+        .into_io(io::ErrorKind::InvalidInput));
+    }
+    let next = others.iter().find(|other| other.as_str() == DEFAULT_PROFILE).unwrap_or(&others[0]).clone();
+    game = switch(game, &next, events)?;
+  }
+
+  let mut shared: Vec<String> = Vec::new();
+  let mut removed: Vec<String> = Vec::new();
+  let other_mods: Vec<PathBuf> = others.iter().map(|other| Ok(path_of(&game, other)?.join("mods"))).collect::<io::Result<_>>()?;
+  for mod_dir in get_directories(&path.join("mods"))? {
+    let mod_name = mod_dir.file_name().unwrap().to_string_lossy().to_string();
+    if other_mods.iter().any(|mods| mods.join(&mod_name).is_dir()) {
+      shared.push(mod_name);
+    } else {
+      removed.push(mod_name);
+    }
+  }
+  fs::remove_dir_all(&path)?;
+  let garbage = if collect_garbage { Some(pool::collect_garbage()?) } else { None };
+  Ok(DeleteReport { game, shared, removed, garbage })
+}
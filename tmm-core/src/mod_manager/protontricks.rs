@@ -0,0 +1,125 @@
+use crate::mod_manager::game::{Game, Store};
+use crate::error::{Error, ErrorCode};
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::events::{BackendEvent, Events};
+
+//The winetricks categories that matter for mods, runtimes like vcrun2019 and dotnet48 are in 'dlls'
+const CATEGORIES: &[&str] = &["dlls", "fonts", "settings", "apps"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Verb {
+  name: String,
+  category: String,
+  description: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputEvent {
+  appid: u32,
+  line: String,
+  //'stdout' or 'stderr', winetricks prints most of its progress to stderr
+  stream: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FinishedEvent {
+  appid: u32,
+  verbs: Vec<String>,
+  exit_code: Option<i32>,
+}
+
+//Steam games go through protontricks so the prefix gets Proton's Wine, the others use
+//winetricks with their own prefix
+fn command(game: &Game) -> io::Result<(Command, &'static str)> {
+  let prefix = match &game.wine_prefix {
+    Some(prefix) if prefix.is_dir() => prefix,
+    _ => {
+      return Err(Error::new(ErrorCode::NoPrefix, format!("'{}' has no Wine prefix yet, it has to be started once", game.public_name))
+        .param("appid", game.appid)
+        .into_io(io::ErrorKind::NotFound));
+    }
+  };
+  if game.store == Store::Steam {
+    let mut command = Command::new("protontricks");
+    command.arg(game.appid.to_string());
+    Ok((command, "protontricks"))
+  } else {
+    let mut command = Command::new("winetricks");
+    command.env("WINEPREFIX", prefix);
+    Ok((command, "winetricks"))
+  }
+}
+
+fn missing(program: &str, e: io::Error) -> io::Error {
+  if e.kind() != io::ErrorKind::NotFound {
+    return e;
+  }
+  Error::new(ErrorCode::ExtractorMissing, format!("'{}' has to be installed to fix up prefixes", program))
+    .param("extractor", program)
+    .into_io(io::ErrorKind::NotFound)
+}
+
+//'dotnet48                 MS .NET 4.8 (Microsoft, 2019) [downloadable]'
+fn parse_verb(line: &str, category: &str) -> Option<Verb> {
+  let line = line.trim();
+  let split = line.find(char::is_whitespace)?;
+  let name = &line[..split];
+  if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '=') {
+    return None;
+  }
+  Some(Verb { name: name.to_string(), category: category.to_string(), description: line[split..].trim().to_string() })
+}
+
+pub fn verbs(game: &Game) -> io::Result<Vec<Verb>> {
+  let mut verbs: Vec<Verb> = Vec::new();
+  for category in CATEGORIES {
+    let (mut command, program) = command(game)?;
+    let output = command.arg(category).arg("list").output().map_err(|e| missing(program, e))?;
+    if !output.status.success() {
+      return Err(Error::new(ErrorCode::PrefixFixFailed, String::from_utf8_lossy(&output.stderr).trim().to_string())
+        .param("exit_code", output.status.code())
+        .into_io(io::ErrorKind::Other));
+    }
+    verbs.extend(String::from_utf8_lossy(&output.stdout).lines().filter_map(|line| parse_verb(line, category)));
+  }
+  Ok(verbs)
+}
+
+fn stream<R: Read + Send + 'static>(reader: R, appid: u32, name: &'static str, events: Events) -> thread::JoinHandle<()> {
+  thread::spawn(move || {
+    for line in BufReader::new(reader).lines().map_while(Result::ok) {
+      events.send(BackendEvent::PrefixFixOutput(OutputEvent { appid, line, stream: name }));
+    }
+  })
+}
+
+//Unattended, every line it prints gets emitted as it comes. Blocks until it's done
+pub fn run(game: &Game, verbs: &[String], events: &Events) -> io::Result<()> {
+  if verbs.iter().any(|verb| verb.is_empty() || verb.starts_with('-')) {
+    return Err(Error::new(ErrorCode::PrefixFixFailed, "verbs can't be empty or options")
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  let (mut command, program) = command(game)?;
+  let mut child = command.arg("-q").args(verbs)
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| missing(program, e))?;
+  let stdout = stream(child.stdout.take().unwrap(), game.appid, "stdout", events.clone());
+  let stderr = stream(child.stderr.take().unwrap(), game.appid, "stderr", events.clone());
+  let status = child.wait()?;
+  let _ = stdout.join();
+  let _ = stderr.join();
+  events.send(BackendEvent::PrefixFixFinished(FinishedEvent { appid: game.appid, verbs: verbs.to_vec(), exit_code: status.code() }));
+  if !status.success() {
+    return Err(Error::new(ErrorCode::PrefixFixFailed, format!("{} failed installing {}", program, verbs.join(", ")))
+      .param("exit_code", status.code())
+      .into_io(io::ErrorKind::Other));
+  }
+  Ok(())
+}
@@ -0,0 +1,236 @@
+use crate::mod_manager::{Mod, conflicts, deployable};
+use crate::mod_manager::game::Game;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+
+//Offered even before any mod uses them, whatever else the mods use comes on top
+const DEFAULT_CATEGORIES: &[&str] = &[
+  "Gameplay", "Textures", "Models", "Audio", "Animations", "User Interface", "Patches", "Utilities", "Other",
+];
+
+//Every part that's there has to match, an empty filter matches everything
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ModFilter {
+  #[serde(default)]
+  category: Option<String>,
+  //mods need all of them
+  #[serde(default)]
+  tags: Vec<String>,
+  //looked for in the name and the notes
+  #[serde(default)]
+  text: Option<String>,
+  #[serde(default)]
+  enabled: Option<bool>,
+  //only enabled mods take part in conflicts
+  #[serde(default)]
+  conflicts: Option<ConflictFilter>,
+  //seconds since the epoch, e.g. a week ago for the mods added this week
+  #[serde(default)]
+  installed_since: Option<u64>,
+  //the mods not enabled or deployed since then, to find ones to prune
+  #[serde(default)]
+  unused_since: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictFilter {
+  None,
+  //wins or loses at least one
+  Any,
+  Winning,
+  Overridden,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+  #[default]
+  LoadOrder,
+  Name,
+  Category,
+  InstalledAt,
+  LastUsed,
+}
+
+//Ties stay in load order, so a page never shows a mod another page showed already
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ModSort {
+  #[serde(default)]
+  field: SortField,
+  #[serde(default)]
+  descending: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModPage {
+  //how many match, over all pages
+  total: usize,
+  offset: usize,
+  mods: Vec<Mod>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Category {
+  name: String,
+  mods: usize,
+}
+
+fn has_tag(elem: &Mod, tag: &str) -> bool {
+  elem.meta.tags.iter().any(|own| own.eq_ignore_ascii_case(tag))
+}
+
+fn matches(elem: &Mod, filter: &ModFilter) -> bool {
+  let category = filter.category.as_ref().map_or(true, |category| {
+    elem.meta.category.as_ref().map_or(false, |own| own.eq_ignore_ascii_case(category))
+  });
+  let text = filter.text.as_ref().map(|text| text.to_lowercase()).map_or(true, |text| {
+    elem.name.to_lowercase().contains(&text)
+      || elem.meta.notes.as_ref().map_or(false, |notes| notes.to_lowercase().contains(&text))
+  });
+  let last_used = elem.usage.last_used(elem.meta.installed_at);
+  category && text
+    && filter.tags.iter().all(|tag| has_tag(elem, tag))
+    && filter.enabled.map_or(true, |enabled| elem.enabled == enabled)
+    && filter.installed_since.map_or(true, |since| elem.meta.installed_at.map_or(false, |installed_at| installed_at >= since))
+    && filter.unused_since.map_or(true, |since| last_used.map_or(true, |last_used| last_used < since))
+}
+
+fn in_conflicts(elem: &Mod, filter: ConflictFilter, (winning, losing): &(BTreeSet<String>, BTreeSet<String>)) -> bool {
+  let (wins, loses) = (winning.contains(&elem.name), losing.contains(&elem.name));
+  match filter {
+    ConflictFilter::None => !wins && !loses,
+    ConflictFilter::Any => wins || loses,
+    ConflictFilter::Winning => wins,
+    ConflictFilter::Overridden => loses,
+  }
+}
+
+//The conflicts only get looked at when the filter asks about them, that means indexing every enabled mod.
+//Without a limit it's all of the rest
+pub fn query(game: &Game, mods: Vec<Mod>, filter: &ModFilter, sort: ModSort, offset: usize, limit: Option<usize>) -> io::Result<ModPage> {
+  let involvement = match filter.conflicts {
+    Some(_) => Some(conflicts::involvement(game, &deployable(game, &mods)?)?),
+    None => None,
+  };
+  let mut matching: Vec<Mod> = mods.into_iter()
+    .filter(|elem| matches(elem, filter))
+    .filter(|elem| match (filter.conflicts, &involvement) {
+      (Some(conflict_filter), Some(involvement)) => in_conflicts(elem, conflict_filter, involvement),
+      _ => true,
+    })
+    .collect();
+  //sort_by is stable, flipping the comparison instead of reversing the list keeps ties in load order
+  let compare = |a: &Mod, b: &Mod| match sort.field {
+    SortField::LoadOrder => Ordering::Equal,
+    SortField::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    SortField::Category => lowercase(&a.meta.category).cmp(&lowercase(&b.meta.category)),
+    SortField::InstalledAt => a.meta.installed_at.cmp(&b.meta.installed_at),
+    SortField::LastUsed => a.usage.last_used(a.meta.installed_at).cmp(&b.usage.last_used(b.meta.installed_at)),
+  };
+  match (sort.field, sort.descending) {
+    (SortField::LoadOrder, true) => matching.reverse(),
+    (_, true) => matching.sort_by(|a, b| compare(b, a)),
+    (_, false) => matching.sort_by(|a, b| compare(a, b)),
+  }
+  let total = matching.len();
+  let mods = matching.into_iter().skip(offset).take(limit.unwrap_or(usize::MAX)).collect();
+  Ok(ModPage { total, offset, mods })
+}
+
+fn lowercase(value: &Option<String>) -> Option<String> {
+  value.as_ref().map(|value| value.to_lowercase())
+}
+
+pub fn categories(mods: &[Mod]) -> Vec<Category> {
+  let mut counts: BTreeMap<String, usize> = DEFAULT_CATEGORIES.iter().map(|name| (name.to_string(), 0)).collect();
+  for category in mods.iter().filter_map(|elem| elem.meta.category.as_ref()) {
+    let name = counts.keys().find(|name| name.eq_ignore_ascii_case(category)).cloned().unwrap_or_else(|| category.clone());
+    *counts.entry(name).or_insert(0) += 1;
+  }
+  counts.into_iter().map(|(name, mods)| Category { name, mods }).collect()
+}
+
+//Trimmed, without empty ones or the same tag twice
+pub fn clean_tags(tags: Vec<String>) -> Vec<String> {
+  let mut cleaned: Vec<String> = Vec::new();
+  for tag in tags.iter().map(|tag| tag.trim()).filter(|tag| !tag.is_empty()) {
+    if !cleaned.iter().any(|other| other.eq_ignore_ascii_case(tag)) {
+      cleaned.push(tag.to_string());
+    }
+  }
+  cleaned
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::Path;
+
+  fn installed(name: &str, category: Option<&str>, tags: &[&str], enabled: bool, installed_at: u64) -> Mod {
+    let mut elem = Mod { enabled, ..Mod::generated(name) };
+    elem.meta.category = category.map(str::to_string);
+    elem.meta.tags = tags.iter().map(|tag| tag.to_string()).collect();
+    elem.meta.installed_at = Some(installed_at);
+    elem
+  }
+
+  fn mods() -> Vec<Mod> {
+    vec![
+      installed("SkyUI", Some("User Interface"), &["ui"], true, 300),
+      installed("Better Water", Some("textures"), &["water", "HD"], false, 100),
+      installed("Alternate Start", Some("Gameplay"), &[], true, 200),
+    ]
+  }
+
+  fn names(page: &ModPage) -> Vec<&str> {
+    page.mods.iter().map(|elem| elem.name.as_str()).collect()
+  }
+
+  #[test]
+  fn every_part_of_the_filter_has_to_match() {
+    let game = Game::in_dir(Path::new("/tmp/tmm-query"));
+    let filter = ModFilter { tags: vec!["hd".to_string()], category: Some("Textures".to_string()), ..ModFilter::default() };
+    assert_eq!(names(&query(&game, mods(), &filter, ModSort::default(), 0, None).unwrap()), ["Better Water"]);
+    let filter = ModFilter { text: Some("START".to_string()), enabled: Some(true), ..ModFilter::default() };
+    assert_eq!(names(&query(&game, mods(), &filter, ModSort::default(), 0, None).unwrap()), ["Alternate Start"]);
+    let filter = ModFilter { installed_since: Some(200), ..ModFilter::default() };
+    assert_eq!(names(&query(&game, mods(), &filter, ModSort::default(), 0, None).unwrap()), ["SkyUI", "Alternate Start"]);
+    let filter = ModFilter { unused_since: Some(150), ..ModFilter::default() };
+    assert_eq!(names(&query(&game, mods(), &filter, ModSort::default(), 0, None).unwrap()), ["Better Water"]);
+  }
+
+  #[test]
+  fn sorted_and_paged() {
+    let game = Game::in_dir(Path::new("/tmp/tmm-query"));
+    let by_name = ModSort { field: SortField::Name, descending: false };
+    let page = query(&game, mods(), &ModFilter::default(), by_name, 1, Some(1)).unwrap();
+    assert_eq!((page.total, names(&page)), (3, vec!["Better Water"]));
+    let newest = ModSort { field: SortField::InstalledAt, descending: true };
+    assert_eq!(names(&query(&game, mods(), &ModFilter::default(), newest, 0, None).unwrap()), ["SkyUI", "Alternate Start", "Better Water"]);
+    let reversed = ModSort { field: SortField::LoadOrder, descending: true };
+    assert_eq!(names(&query(&game, mods(), &ModFilter::default(), reversed, 0, None).unwrap()), ["Alternate Start", "Better Water", "SkyUI"]);
+  }
+
+  #[test]
+  fn conflicts() {
+    let involvement = (BTreeSet::from(["A".to_string()]), BTreeSet::from(["B".to_string()]));
+    let (a, b, c) = (Mod::generated("A"), Mod::generated("B"), Mod::generated("C"));
+    assert!(in_conflicts(&a, ConflictFilter::Winning, &involvement) && !in_conflicts(&b, ConflictFilter::Winning, &involvement));
+    assert!(in_conflicts(&b, ConflictFilter::Overridden, &involvement));
+    assert!(in_conflicts(&a, ConflictFilter::Any, &involvement) && in_conflicts(&b, ConflictFilter::Any, &involvement));
+    assert!(in_conflicts(&c, ConflictFilter::None, &involvement) && !in_conflicts(&a, ConflictFilter::None, &involvement));
+  }
+
+  #[test]
+  fn categories_and_tags() {
+    let categories = categories(&mods());
+    let textures = categories.iter().find(|category| category.name == "Textures").unwrap();
+    assert_eq!(textures.mods, 1);
+    assert_eq!(categories.len(), DEFAULT_CATEGORIES.len());
+    let tags = clean_tags(vec![" HD ".to_string(), "hd".to_string(), "".to_string(), "Water".to_string()]);
+    assert_eq!(tags, ["HD", "Water"]);
+  }
+}
@@ -0,0 +1,75 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::ofs;
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+//_IOW(0x94, 9, int), not in every libc version
+const FICLONE: u64 = 0x40049409;
+
+//Shares the source's blocks with the target instead of copying them, only works
+//within one filesystem that supports it (Btrfs, XFS, bcachefs, ...)
+fn clone_file(source: &File, target: &File) -> io::Result<()> {
+  if unsafe { libc::ioctl(target.as_raw_fd(), FICLONE as _, source.as_raw_fd()) } != 0 {
+    return Err(io::Error::last_os_error());
+  }
+  Ok(())
+}
+
+//copy_file_range lets the filesystem share blocks too where FICLONE isn't there,
+//and otherwise still copies without going through userspace
+fn copy_range(source: &File, target: &File, len: u64) -> io::Result<()> {
+  let mut left = len;
+  while left > 0 {
+    let copied = unsafe {
+      libc::copy_file_range(source.as_raw_fd(), std::ptr::null_mut(), target.as_raw_fd(), std::ptr::null_mut(), left as usize, 0)
+    };
+    if copied < 0 {
+      return Err(io::Error::last_os_error());
+    }
+    if copied == 0 {
+      break;
+    }
+    left -= copied as u64;
+  }
+  Ok(())
+}
+
+//Like fs::copy, but tries a reflink first
+pub fn copy(source: &Path, target: &Path) -> io::Result<()> {
+  let source_file = File::open(source)?;
+  let metadata = source_file.metadata()?;
+  let target_file = File::create(target)?;
+  let result = clone_file(&source_file, &target_file)
+    .or_else(|_| copy_range(&source_file, &target_file, metadata.len()));
+  drop(target_file);
+  match result {
+    Ok(()) => fs::set_permissions(target, metadata.permissions()),
+    Err(_) => fs::copy(source, target).map(|_| ()),
+  }
+}
+
+//Whether files can be cloned from the mods into the game directory, done once when
+//the game gets set up and remembered in its config. A clone never leaves its filesystem,
+//so with both on one it's tried within the profile instead of writing into the game
+pub fn probe(game: &Game) -> bool {
+  let mods_path = game.profile_path.join("mods");
+  let same_filesystem = fs::create_dir_all(&mods_path)
+    .and_then(|()| Ok(fs::metadata(&mods_path)?.dev() == fs::metadata(ofs::mount_path(game))?.dev()));
+  if !matches!(same_filesystem, Ok(true)) {
+    return false;
+  }
+  let source = mods_path.join(".tmm_reflink_probe");
+  let target = game.profile_path.join(".tmm_reflink_probe");
+  let result = fs::write(&source, b"tmm")
+    .and_then(|()| {
+      let target_file = File::create(&target)?;
+      clone_file(&File::open(&source)?, &target_file)
+    });
+  for file in [&source, &target] {
+    let _ = fs::remove_file(file);
+  }
+  result.is_ok()
+}
@@ -0,0 +1,219 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{itch, profiles, scan, search};
+use crate::error::{Error, ErrorCode};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use std::{env, fs, io};
+
+//The games the manager set up, by appid. Commands get an appid and look the game up here
+//instead of taking whatever config the frontend sends, so only the backend decides where
+//deploying and purging write. The config on disk stays what counts, it's read again once
+//it changed, after a command saved it or the CLI did
+pub struct Registry {
+  games: Mutex<HashMap<u32, Cached>>,
+}
+
+struct Cached {
+  game: Game,
+  //the config's mtime and size when it was read
+  modified: SystemTime,
+  len: u64,
+}
+
+impl Registry {
+  pub fn new() -> Registry {
+    Registry { games: Mutex::new(HashMap::new()) }
+  }
+
+  pub fn game(&self, appid: u32) -> io::Result<Game> {
+    let path = Game::config_path(appid)?;
+    let metadata = match fs::metadata(&path) {
+      Ok(metadata) => metadata,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => {
+        self.games.lock().unwrap().remove(&appid);
+        return Err(Error::new(ErrorCode::GameNotManaged, format!("{} isn't managed", appid))
+          .param("appid", appid)
+          .into_io(io::ErrorKind::NotFound));
+      },
+      Err(e) => return Err(e),
+    };
+    let (modified, len) = (metadata.modified()?, metadata.len());
+    let mut games = self.games.lock().unwrap();
+    if let Some(cached) = games.get(&appid) {
+      if cached.modified == modified && cached.len == len {
+        return Ok(cached.game.clone());
+      }
+    }
+    let game = Game::load(&path)?;
+    validate(&game)?;
+    games.insert(appid, Cached { game: game.clone(), modified, len });
+    Ok(game)
+  }
+
+  //The game with another of its profiles in place of the active one, without switching to it
+  pub fn profile(&self, appid: u32, name: &str) -> io::Result<Game> {
+    let game = self.game(appid)?;
+    let profile_path = profiles::existing(&game, name)?;
+    Ok(Game { profile_path, ..game })
+  }
+}
+
+impl Default for Registry {
+  fn default() -> Registry {
+    Registry::new()
+  }
+}
+
+fn unsafe_path(field: &str, path: &Path) -> io::Error {
+  Error::new(ErrorCode::UnsafePath, format!("the {} '{}' isn't somewhere the manager puts it", field, path.display()))
+    .param("field", field)
+    .param("path", path)
+    .into_io(io::ErrorKind::InvalidData)
+}
+
+//Absolute and without '..', so checking where it starts is enough to know where it is
+fn plain(path: &Path) -> bool {
+  path.is_absolute() && path.components().all(|component| matches!(component, Component::RootDir | Component::Normal(_)))
+}
+
+//Other drives and what's shared between users, games and mods are kept there as much as in the home.
+//Not '/tmp', any other user can put something there first
+const MOUNT_ROOTS: &[&str] = &["/mnt", "/media", "/run/media", "/opt", "/srv"];
+
+//Where it really is, symlinks and all. One that isn't there yet is where its parent is
+fn resolved(path: &Path) -> io::Result<PathBuf> {
+  let path = if path.is_absolute() { path.to_path_buf() } else { env::current_dir()?.join(path) };
+  if let Ok(path) = fs::canonicalize(&path) {
+    return Ok(path);
+  }
+  Ok(match (path.parent().and_then(|parent| fs::canonicalize(parent).ok()), path.file_name()) {
+    (Some(parent), Some(name)) => parent.join(name),
+    _ => path,
+  })
+}
+
+//Where the stores put games and their prefixes, Steam's libraries, the itch app's install
+//locations, GOG's, Wine's, Lutris' and Bottles' own folders, and the search roots the user added
+fn scan_store_roots() -> Vec<PathBuf> {
+  let mut roots: Vec<PathBuf> = Vec::new();
+  for mut steam_dir in scan::steam_dirs() {
+    roots.extend(steam_dir.libraryfolders().paths.iter().map(|steamapps| steamapps.parent().unwrap_or(steamapps).to_path_buf()));
+    roots.push(steam_dir.path);
+  }
+  roots.extend(itch::install_locations());
+  roots.extend(scan::gog_search_roots());
+  if let Some(home) = dirs::home_dir() {
+    roots.extend([".wine", ".local/share/lutris", ".local/share/bottles", ".var/app/com.usebottles.bottles/data/bottles"].iter().map(|dir| home.join(dir)));
+  }
+  roots.extend(search::roots().unwrap_or_default());
+  roots.into_iter().map(|root| fs::canonicalize(&root).unwrap_or(root)).collect()
+}
+
+//Every path a command gets is checked, reading all of Steam's libraries each time is too much.
+//A library added in Steam shows up after a while, the search roots as soon as they're saved
+const STORE_ROOTS_FOR: Duration = Duration::from_secs(30);
+type StoreRoots = (Instant, Vec<PathBuf>);
+static STORE_ROOTS: Lazy<Mutex<Option<StoreRoots>>> = Lazy::new(|| Mutex::new(None));
+
+fn store_roots() -> Vec<PathBuf> {
+  let mut cached = STORE_ROOTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+  match cached.as_ref() {
+    Some((scanned, roots)) if scanned.elapsed() < STORE_ROOTS_FOR => roots.clone(),
+    _ => {
+      let roots = scan_store_roots();
+      *cached = Some((Instant::now(), roots.clone()));
+      roots
+    }
+  }
+}
+
+pub(crate) fn forget_store_roots() {
+  *STORE_ROOTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+//The user's own files: the home without its dotfiles, other drives and wherever the stores put
+//games. Nothing of the system's or of the manager's own, so a path the frontend sends can't
+//have the manager write to, remove or pack up anything else
+pub fn check_user_path(field: &str, path: &Path) -> io::Result<()> {
+  let real = resolved(path)?;
+  if !plain(&real) || real.starts_with(crate::config_root()?) {
+    return Err(unsafe_path(field, path));
+  }
+  let in_home = dirs::home_dir().map_or(false, |home| match real.strip_prefix(&home) {
+    Ok(rest) => rest.components().next().map_or(false, |first| !first.as_os_str().to_string_lossy().starts_with('.')),
+    Err(_) => false,
+  });
+  let on_mount = MOUNT_ROOTS.iter().any(|root| real.starts_with(root) && real != Path::new(root));
+  if !in_home && !on_mount && !store_roots().iter().any(|root| real.starts_with(root)) {
+    return Err(unsafe_path(field, path));
+  }
+  Ok(())
+}
+
+//Something only read from, like an archive to install. What the manager downloaded itself is fine too
+pub fn check_source(field: &str, path: &Path) -> io::Result<()> {
+  if resolved(path)?.starts_with(crate::config_root()?) {
+    return Ok(());
+  }
+  check_user_path(field, path)
+}
+
+//Deploying writes all over it, so it has to be a game's own folder in a store's library or in
+//a search root the user added, and never the home, a drive or a library itself
+pub fn check_install_path(path: &Path) -> io::Result<()> {
+  check_user_path("install_path", path)?;
+  let real = resolved(path)?;
+  let roots = store_roots();
+  let broad = dirs::home_dir().map_or(false, |home| home.starts_with(&real)) || roots.contains(&real);
+  if broad {
+    return Err(unsafe_path("install_path", path));
+  }
+  if !roots.iter().any(|root| real.starts_with(root)) {
+    return Err(Error::new(ErrorCode::UnsafePath, format!("'{}' isn't in a game library or a search root, add the folder it's in as one first", path.display()))
+      .param("field", "install_path")
+      .param("path", path)
+      .into_io(io::ErrorKind::InvalidData));
+  }
+  Ok(())
+}
+
+//Every path deploying, purging or cleaning up writes to or removes, checked against where the
+//manager sets them up when a config is saved and again when it's read. The profile lives in the
+//profiles dir, the work dir is the game's '.tmm_work/<appid>' and the path extension stays
+//inside the game directory
+pub fn validate(game: &Game) -> io::Result<()> {
+  check_install_path(&game.install_path)?;
+  let profiles_root = profiles::profiles_root()?;
+  let in_profiles = game.profile_path.starts_with(&profiles_root) && game.profile_path != profiles_root;
+  if !plain(&game.profile_path) || !in_profiles {
+    return Err(unsafe_path("profile_path", &game.profile_path));
+  }
+  if !plain(&game.work_path) || !game.work_path.ends_with(Path::new(".tmm_work").join(game.appid.to_string())) {
+    return Err(unsafe_path("work_path", &game.work_path));
+  }
+  if !game.path_extension.components().all(|component| matches!(component, Component::Normal(_))) {
+    return Err(unsafe_path("path_extension", &game.path_extension));
+  }
+  //both only get written to inside, 'tmm/<appid>' and the profile's own directory
+  for (field, path) in [("scratch_path", &game.scratch_path), ("sync_path", &game.sync_path)] {
+    if let Some(path) = path {
+      check_user_path(field, path)?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn refuses_tmp_and_folders_outside_the_libraries() {
+    assert!(check_user_path("dest", Path::new("/tmp/tmm-game")).is_err());
+    assert!(check_install_path(Path::new("/opt/tmm-game")).is_err());
+    assert!(check_user_path("dest", Path::new("/opt/tmm-game")).is_ok());
+  }
+}
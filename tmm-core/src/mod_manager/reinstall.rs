@@ -0,0 +1,105 @@
+use crate::mod_manager::{get_files, link, meta, reflink, with_suffix};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::pool;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//The hashes of the files as they were installed, next to the meta
+pub const INSTALL_MANIFEST_NAME: &str = ".tmm_install.json";
+
+pub fn is_manifest_file(relative: &Path) -> bool {
+  relative == Path::new(INSTALL_MANIFEST_NAME)
+}
+
+//A file the user changed or put into the mod, found when reinstalling it
+#[derive(Debug, Clone, Serialize)]
+pub struct PreservedFile {
+  path: PathBuf,
+  //false if the mod didn't come with it
+  edited: bool,
+  //put back into the reinstalled mod, the new version doesn't change it.
+  //Otherwise the new version's file is there and the user's is only in the backup
+  kept: bool,
+  backup: PathBuf,
+}
+
+fn load(mod_dir: &Path) -> Option<BTreeMap<PathBuf, String>> {
+  fs::read_to_string(mod_dir.join(INSTALL_MANIFEST_NAME)).ok()
+    .and_then(|contents| serde_json::from_str(&contents).ok())
+}
+
+//hashes are by absolute path, like pool::ingest hands them out
+pub fn record(mod_dir: &Path, hashes: &BTreeMap<PathBuf, String>) -> io::Result<()> {
+  let relative: BTreeMap<&Path, &String> = hashes.iter()
+    .filter_map(|(path, hash)| path.strip_prefix(mod_dir).ok().map(|relative| (relative, hash)))
+    .collect();
+  fs::write(mod_dir.join(INSTALL_MANIFEST_NAME), serde_json::to_string(&relative)?)
+}
+
+fn is_own_file(relative: &Path) -> bool {
+  meta::is_meta_file(relative) || is_manifest_file(relative)
+}
+
+//Copies whatever the user changed in the installed mod out of it and hands back what needs
+//to be looked at once the new version is in. Mods installed before the manifests existed
+//can't tell edits apart, nothing is preserved for them
+pub struct SetAside {
+  original: BTreeMap<PathBuf, String>,
+  //(relative, edited)
+  changed: Vec<(PathBuf, bool)>,
+  backup: PathBuf,
+}
+
+pub fn set_aside(game: &Game, mod_dir: &Path, mod_name: &str) -> io::Result<SetAside> {
+  let backup = game.profile_path.join("backups").join(mod_name).join(meta::now().unwrap_or(0).to_string());
+  let original = match load(mod_dir) {
+    Some(original) => original,
+    None => return Ok(SetAside { original: BTreeMap::new(), changed: Vec::new(), backup }),
+  };
+  let mut changed: Vec<(PathBuf, bool)> = Vec::new();
+  for file in get_files(mod_dir)? {
+    let relative = link::relative(&file, mod_dir)?.to_path_buf();
+    if is_own_file(&relative) || !fs::symlink_metadata(&file)?.is_file() {
+      continue;
+    }
+    let edited = match original.get(&relative) {
+      Some(hash) if *hash == pool::hash_file(&file)? => continue,
+      Some(_) => true,
+      None => false,
+    };
+    let copy = backup.join(&relative);
+    if let Some(parent) = copy.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    reflink::copy(&file, &copy)?;
+    changed.push((relative, edited));
+  }
+  Ok(SetAside { original, changed, backup })
+}
+
+//Edits of files the new version still has the same way and files the new version doesn't
+//have go back in, replacing the pooled file instead of writing into it
+pub fn restore(set_aside: SetAside, mod_dir: &Path, hashes: &BTreeMap<PathBuf, String>) -> io::Result<Vec<PreservedFile>> {
+  let mut preserved: Vec<PreservedFile> = Vec::new();
+  for (relative, edited) in set_aside.changed {
+    let target = mod_dir.join(&relative);
+    let backup = set_aside.backup.join(&relative);
+    let kept = if edited {
+      hashes.get(&target).is_some() && hashes.get(&target) == set_aside.original.get(&relative)
+    } else {
+      !target.exists()
+    };
+    if kept {
+      if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      let temporary = with_suffix(&target, ".tmm_restore");
+      reflink::copy(&backup, &temporary)?;
+      fs::rename(temporary, &target)?;
+    }
+    preserved.push(PreservedFile { path: relative, edited, kept, backup });
+  }
+  Ok(preserved)
+}
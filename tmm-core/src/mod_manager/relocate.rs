@@ -0,0 +1,80 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::manifest::{Deployment, DeploymentManifest};
+use crate::mod_manager::journal::{self, Journal, Operation};
+use crate::mod_manager::{root, scratch};
+use std::path::{Path, PathBuf};
+use std::io;
+
+//The work dir sits next to steamapps/common in the game's library
+pub fn work_path_for(install_path: &Path, appid: u32) -> PathBuf {
+  let components_count = install_path.components().count();
+  install_path.components().take(components_count-4).collect::<PathBuf>().join([".tmm_work/", appid.to_string().as_str()].join(""))
+}
+
+fn rebase(path: &Path, from: &Path, to: &Path) -> PathBuf {
+  match path.strip_prefix(from) {
+    Ok(relative) => to.join(relative),
+    Err(_) => path.to_path_buf(),
+  }
+}
+
+fn move_if_there(from: &Path, to: &Path) -> io::Result<()> {
+  if from == to || !from.exists() || to.exists() {
+    return Ok(());
+  }
+  scratch::move_dir(from, to)
+}
+
+//The game got moved to another library (or the drive got mounted somewhere else),
+//everything that knows the old paths gets pointed at the new ones
+pub fn relocate(game: Game, install_path: &Path, work_path: &Path) -> io::Result<Game> {
+  let old = game.clone();
+  let game = Game {
+    install_path: install_path.to_path_buf(),
+    work_path: work_path.to_path_buf(),
+    ..game
+  };
+  let old_backup = old.work_path.with_extension("backup");
+  let new_backup = game.work_path.with_extension("backup");
+  let (old_root_backup, new_root_backup) = (root::backup_path(&old), root::backup_path(&game));
+  let moved = |path: &Path| {
+    let path = rebase(&rebase(path, &old.install_path, &game.install_path), &old_backup, &new_backup);
+    rebase(&path, &old_root_backup, &new_root_backup)
+  };
+
+  //the old library might not be there anymore at all
+  for (from, to) in [(&old.work_path, &game.work_path), (&old_backup, &new_backup), (&old_root_backup, &new_root_backup)] {
+    if let Err(e) = move_if_there(from, to) {
+      tracing::warn!("Couldn't move '{}' to '{}': {}", from.display(), to.display(), e);
+    }
+  }
+
+  for deployment in [Deployment::Backend, Deployment::Root] {
+    if let Some(mut manifest) = DeploymentManifest::load_of(&game, deployment)? {
+      manifest.files = manifest.files.iter().map(|file| moved(file)).collect();
+      manifest.dirs = manifest.dirs.iter().map(|dir| moved(dir)).collect();
+      manifest.displaced = manifest.displaced.iter().map(|(original, backup)| (moved(original), moved(backup))).collect();
+      manifest.sources = manifest.sources.iter().map(|(file, source)| (moved(file), source.clone())).collect();
+      manifest.hashes = manifest.hashes.iter().map(|(file, hash)| (moved(file), hash.clone())).collect();
+      manifest.save_of(&game, deployment)?;
+    }
+    if let Some(operations) = journal::pending(&game, deployment)? {
+      //left uncommitted, it's still a deployment that never finished
+      let mut journal = Journal::begin(&game, deployment)?;
+      for operation in operations {
+        journal.record(&match operation {
+          Operation::CreateDir { path } => Operation::CreateDir { path: moved(&path) },
+          Operation::RemoveDir { path } => Operation::RemoveDir { path: moved(&path) },
+          Operation::Displace { original, backup } => Operation::Displace { original: moved(&original), backup: moved(&backup) },
+          Operation::Restore { original, backup } => Operation::Restore { original: moved(&original), backup: moved(&backup) },
+          Operation::Place { path } => Operation::Place { path: moved(&path) },
+          Operation::Remove { path } => Operation::Remove { path: moved(&path) },
+        })?;
+      }
+    }
+  }
+
+  tracing::info!("'{}' moved from '{}' to '{}'", game.public_name, old.install_path.display(), game.install_path.display());
+  game.save()?;
+  Ok(game)
+}
@@ -0,0 +1,41 @@
+use crate::mod_manager::game::Game;
+use crate::error::{Error, ErrorCode};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//Where udisks mounts SD cards and USB drives, the Deck's card ends up in '/run/media/<user>/<label>'
+const REMOVABLE_MOUNT_ROOTS: [&str; 2] = ["/run/media", "/media"];
+
+pub fn is_removable(path: &Path) -> bool {
+  REMOVABLE_MOUNT_ROOTS.iter().any(|root| path.starts_with(root))
+}
+
+//Kept in the profile, that's on the internal drive while the game's library is gone
+fn missing_marker(game: &Game) -> PathBuf {
+  game.profile_path.join(".tmm_media_missing")
+}
+
+//Deploying to a card that's not there would create the game directory on the internal
+//drive under the mount point, so that fails with a clear error instead.
+//Ok(true) when the media was missing last time, whatever was deployed on it then
+//can't be trusted anymore and has to be checked again
+pub fn ensure_present(game: &Game) -> io::Result<bool> {
+  if !game.removable {
+    return Ok(false);
+  }
+  let marker = missing_marker(game);
+  if !game.install_path.is_dir() {
+    fs::create_dir_all(&game.profile_path)?;
+    fs::write(&marker, b"")?;
+    return Err(Error::new(ErrorCode::MediaMissing, format!("'{}' is on removable storage that isn't there", game.install_path.display()))
+      .param("appid", game.appid)
+      .param("path", &game.install_path)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  if !marker.exists() {
+    return Ok(false);
+  }
+  tracing::info!("The media of '{}' is back, its deployment gets redone", game.public_name);
+  fs::remove_file(marker)?;
+  Ok(true)
+}
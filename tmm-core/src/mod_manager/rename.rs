@@ -0,0 +1,38 @@
+use crate::mod_manager::{load_mods, meta, mod_dir, modlist, overrides, validate_mod_name};
+use crate::mod_manager::game::Game;
+use crate::error::{Error, ErrorCode};
+use std::io;
+
+//Everything keeping the mod's name follows it: the load order, the override rules and the
+//dependencies other mods declare by name. What's deployed still has the old name until the next deploy
+pub fn rename(game: &Game, old_name: &str, new_name: &str) -> io::Result<()> {
+  let mods_path = game.profile_path.join("mods");
+  if old_name.is_empty() || !mods_path.join(old_name).is_dir() {
+    return Err(Error::new(ErrorCode::ModNotFound, format!("'{}' isn't installed", old_name))
+      .param("mod_name", old_name)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  validate_mod_name(game, new_name)?;
+  //with every version of it
+  meta::move_mod(&mods_path.join(old_name), &mods_path.join(new_name))?;
+
+  let mut modlist = modlist::ModList::load(game)?;
+  modlist.rename(old_name, new_name);
+  modlist.save(game)?;
+  overrides::rename_mod(game, old_name, new_name)?;
+
+  for elem in load_mods(game)? {
+    let mut changed = false;
+    let mut mod_meta = elem.meta.clone();
+    for dependency in mod_meta.dependencies.iter_mut() {
+      if dependency.name.as_deref().map_or(false, |name| name.eq_ignore_ascii_case(old_name)) {
+        dependency.name = Some(new_name.to_string());
+        changed = true;
+      }
+    }
+    if changed {
+      meta::save(&mod_dir(game, &elem)?, &mod_meta)?;
+    }
+  }
+  Ok(())
+}
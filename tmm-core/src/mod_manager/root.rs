@@ -0,0 +1,116 @@
+use crate::mod_manager::{Mod, get_files, mod_dir, reflink};
+use crate::mod_manager::game::{Game, DeployMode};
+use crate::mod_manager::deploy::{DeployBackend, DeployReport, DeployStatus, Capabilities};
+use crate::mod_manager::link::{self, relative, Placement};
+use crate::mod_manager::manifest::{Deployment, DeploymentManifest};
+use crate::mod_manager::staging;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::io;
+
+//Files in this folder of a mod go to the game's root instead of under the path extension,
+//that's where script extenders, ASI loaders and d3d DLLs have to be
+pub const ROOT_DIR_NAME: &str = "root";
+
+//Wraps every backend, root files always get copied since loaders running
+//through Wine don't all like links, and they're small anyway
+pub struct WithRootFiles {
+  pub inner: Box<dyn DeployBackend>,
+}
+
+impl DeployBackend for WithRootFiles {
+  fn deploy(&self, game: &Game, mods: &[Mod]) -> io::Result<DeployReport> {
+    purge(game)?;
+    let report = self.inner.deploy(game, mods)?;
+    deploy(game, mods)?;
+    Ok(report)
+  }
+
+  fn purge(&self, game: &Game) -> io::Result<()> {
+    purge(game)?;
+    self.inner.purge(game)
+  }
+
+  fn status(&self, game: &Game) -> io::Result<DeployStatus> {
+    self.inner.status(game)
+  }
+
+  fn capabilities(&self, game: &Game) -> Capabilities {
+    self.inner.capabilities(game)
+  }
+
+  fn incremental(&self) -> bool {
+    self.inner.incremental()
+  }
+}
+
+pub fn backup_path(game: &Game) -> PathBuf {
+  game.work_path.with_extension("root-backup")
+}
+
+//Stagings get their root files too, not the install
+fn target_root(game: &Game) -> PathBuf {
+  if game.deploy_mode == DeployMode::Staging {
+    staging::staging_root(game)
+  } else {
+    game.install_path.clone()
+  }
+}
+
+//The mod's root folder, whatever case it's spelled in
+pub fn root_dir(mod_root: &Path) -> io::Result<Option<PathBuf>> {
+  if !mod_root.is_dir() {
+    return Ok(None);
+  }
+  for entry in mod_root.read_dir()? {
+    let entry = entry?;
+    if entry.file_type()?.is_dir() && entry.file_name().to_string_lossy().eq_ignore_ascii_case(ROOT_DIR_NAME) {
+      return Ok(Some(entry.path()));
+    }
+  }
+  Ok(None)
+}
+
+//Journaled like the link backend's deployments, with a manifest of its own,
+//always from scratch since it's purged before every deployment
+fn deploy(game: &Game, mods: &[Mod]) -> io::Result<()> {
+  let mut placement = Placement::begin(game, Deployment::Root, DeploymentManifest::default())?;
+  match place(game, mods, &mut placement) {
+    Ok(()) => placement.finish().map(|_| ()),
+    Err(e) => {
+      if let Err(purge_error) = placement.abort() {
+        tracing::error!("Couldn't purge the root files of '{}' that failed to deploy: {}", game.public_name, purge_error);
+      }
+      Err(e)
+    }
+  }
+}
+
+fn place(game: &Game, mods: &[Mod], placement: &mut Placement) -> io::Result<()> {
+  let target_root = target_root(game);
+  let backup_root = backup_path(game);
+  for elem in mods {
+    let mod_root = mod_dir(game, elem)?;
+    let root = match root_dir(&mod_root)? {
+      Some(root) => root,
+      None => continue,
+    };
+    //hidden paths are relative to the mod, not its root folder
+    let hidden: HashSet<PathBuf> = elem.meta.hidden.iter().map(|hidden| mod_root.join(hidden)).collect();
+    for source in get_files(&root)? {
+      if hidden.contains(&source) {
+        continue;
+      }
+      //mods later in the list win a conflict
+      let relative = relative(&source, &root)?;
+      placement.place(&target_root.join(relative), &backup_root.join(relative), |target| reflink::copy(&source, target))?;
+    }
+  }
+  Ok(())
+}
+
+//A deployment that never finished gets its journal taken in first
+pub fn purge(game: &Game) -> io::Result<()> {
+  link::repair_of(game, Deployment::Root)?;
+  link::purge_of(game, Deployment::Root)
+}
@@ -0,0 +1,116 @@
+use crate::mod_manager::scan;
+use crate::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, io};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunnerKind {
+  //Valve's from a Steam library
+  Proton,
+  //in compatibilitytools.d, GE-Proton and the like
+  CustomProton,
+  Wine,
+}
+
+//A Proton's path is its directory with the 'proton' script, a Wine's its 'wine' binary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Runner {
+  pub name: String,
+  pub kind: RunnerKind,
+  pub path: PathBuf,
+}
+
+impl Runner {
+  pub fn is_proton(&self) -> bool {
+    self.kind != RunnerKind::Wine
+  }
+}
+
+fn protons_in(dir: &Path, kind: RunnerKind, runners: &mut Vec<Runner>) {
+  let entries = match dir.read_dir() {
+    Ok(entries) => entries,
+    Err(_) => return,
+  };
+  for entry in entries.flatten() {
+    let path = entry.path();
+    if !path.join("proton").is_file() || runners.iter().any(|runner| runner.path == path) {
+      continue;
+    }
+    runners.push(Runner { name: entry.file_name().to_string_lossy().to_string(), kind, path });
+  }
+}
+
+fn system_wine() -> Option<PathBuf> {
+  env::split_paths(&env::var_os("PATH")?).map(|dir| dir.join("wine")).find(|wine| wine.is_file())
+}
+
+//Every runner there is, sorted by kind and then name. Lutris' Wine builds count as well since
+//that's where Wine-GE and friends end up
+pub fn installed() -> Vec<Runner> {
+  let mut runners: Vec<Runner> = Vec::new();
+  for mut steam_dir in scan::steam_dirs() {
+    protons_in(&steam_dir.path.join("compatibilitytools.d"), RunnerKind::CustomProton, &mut runners);
+    for steamapps in &steam_dir.libraryfolders().paths {
+      protons_in(&steamapps.join("common"), RunnerKind::Proton, &mut runners);
+    }
+  }
+  //the system wide one, some distributions package GE-Proton there
+  protons_in(Path::new("/usr/share/steam/compatibilitytools.d"), RunnerKind::CustomProton, &mut runners);
+  if let Some(wine) = system_wine() {
+    runners.push(Runner { name: "System Wine".to_string(), kind: RunnerKind::Wine, path: wine });
+  }
+  if let Some(lutris) = dirs::data_dir().map(|data| data.join("lutris/runners/wine")) {
+    for entry in lutris.read_dir().into_iter().flatten().flatten() {
+      let wine = entry.path().join("bin/wine");
+      if wine.is_file() {
+        runners.push(Runner { name: entry.file_name().to_string_lossy().to_string(), kind: RunnerKind::Wine, path: wine });
+      }
+    }
+  }
+  runners.sort_by(|a, b| (a.kind as u8, &a.name).cmp(&(b.kind as u8, &b.name)));
+  runners
+}
+
+//Pinned runners can be gone since, e.g. after Steam removed an old Proton
+pub fn ensure_installed(runner: &Runner) -> io::Result<()> {
+  let present = if runner.is_proton() { runner.path.join("proton").is_file() } else { runner.path.is_file() };
+  if !present {
+    return Err(Error::new(ErrorCode::RunnerNotFound, format!("the runner '{}' isn't installed anymore", runner.name))
+      .param("runner", &runner.name)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  Ok(())
+}
+
+//Proton wants the directory the prefix is in, 'compatdata/<appid>' with the prefix as 'pfx' in it,
+//and the Steam installation for its runtime
+pub fn command(runner: &Runner, prefix: Option<&Path>, binary: &Path) -> io::Result<Command> {
+  ensure_installed(runner)?;
+  if !runner.is_proton() {
+    let mut command = Command::new(&runner.path);
+    command.arg(binary);
+    if let Some(prefix) = prefix {
+      command.env("WINEPREFIX", prefix);
+    }
+    return Ok(command);
+  }
+  let (prefix, compat_data) = match prefix.and_then(|prefix| Some((prefix, prefix.parent()?))) {
+    Some((prefix, compat_data)) if prefix.file_name().map_or(false, |name| name == "pfx") => (prefix, compat_data),
+    _ => {
+      return Err(Error::new(ErrorCode::InvalidRunner, format!("'{}' needs a Proton prefix, the game's isn't one", runner.name))
+        .param("runner", &runner.name)
+        .into_io(io::ErrorKind::InvalidInput));
+    }
+  };
+  let mut command = Command::new(runner.path.join("proton"));
+  command.arg("run").arg(binary);
+  command.env("STEAM_COMPAT_DATA_PATH", compat_data);
+  command.env("WINEPREFIX", prefix);
+  if let Some(steam_dir) = scan::steam_dirs().into_iter().next() {
+    command.env("STEAM_COMPAT_CLIENT_INSTALL_PATH", steam_dir.path);
+  }
+  Ok(command)
+}
@@ -0,0 +1,174 @@
+use crate::mod_manager::game::Game;
+use crate::mod_manager::scratch;
+use crate::error::{Error, ErrorCode};
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+//Where the game keeps its saves, the save_path of its definition inside the prefix's
+//Windows user, or the home for native games
+pub fn save_dir(game: &Game) -> Option<PathBuf> {
+  let save_path = game.save_path.as_ref()?;
+  let base = if game.uses_compatibility() { game.prefix_user_dir()? } else { dirs::home_dir()? };
+  Some(base.join(save_path))
+}
+
+pub fn profile_saves(profile_path: &Path) -> PathBuf {
+  profile_path.join("saves")
+}
+
+fn links_to(link: &Path, target: &Path) -> bool {
+  fs::read_link(link).map_or(false, |points_to| points_to == target)
+}
+
+//Where a save both folders had goes when they get merged, the one that lost. One folder per merge
+pub fn conflicts_dir(profile_path: &Path) -> PathBuf {
+  profile_path.join("save_conflicts")
+}
+
+//Saves are files as often as folders, both can be on another drive than the profile
+fn move_entry(from: &Path, to: &Path) -> io::Result<()> {
+  if fs::symlink_metadata(from)?.is_dir() {
+    scratch::move_dir(from, to)
+  } else {
+    scratch::move_file(from, to)
+  }
+}
+
+fn modified(path: &Path) -> io::Result<SystemTime> {
+  fs::symlink_metadata(path)?.modified()
+}
+
+//Moves what's in from into to. What's in both is kept where it was changed last, the other one
+//goes to the same path in conflicts. From only goes once nothing's left in it
+fn merge_into(from: &Path, to: &Path, conflicts: &Path) -> io::Result<usize> {
+  fs::create_dir_all(to)?;
+  let mut conflicting = 0;
+  for entry in from.read_dir()? {
+    let entry = entry?;
+    let source = entry.path();
+    let target = to.join(entry.file_name());
+    if entry.file_type()?.is_dir() && target.is_dir() {
+      conflicting += merge_into(&source, &target, &conflicts.join(entry.file_name()))?;
+    } else if fs::symlink_metadata(&target).is_err() {
+      move_entry(&source, &target)?;
+    } else {
+      fs::create_dir_all(conflicts)?;
+      let kept = conflicts.join(entry.file_name());
+      if modified(&source)? > modified(&target)? {
+        move_entry(&target, &kept)?;
+        move_entry(&source, &target)?;
+      } else {
+        move_entry(&source, &kept)?;
+      }
+      conflicting += 1;
+    }
+  }
+  fs::remove_dir(from)?;
+  Ok(conflicting)
+}
+
+//Points the game's save folder at the active profile's saves. The saves that were there before
+//go to the profile it's done for first, the one that was active when isolation got turned on
+pub fn apply(game: &Game) -> io::Result<()> {
+  if !game.isolate_saves {
+    return Ok(());
+  }
+  let save_dir = match save_dir(game) {
+    Some(save_dir) => save_dir,
+    None => return Ok(()),
+  };
+  let saves = profile_saves(&game.profile_path);
+  if links_to(&save_dir, &saves) {
+    return Ok(());
+  }
+  fs::create_dir_all(&saves)?;
+  match fs::symlink_metadata(&save_dir) {
+    Ok(metadata) if metadata.file_type().is_symlink() => fs::remove_file(&save_dir)?,
+    Ok(metadata) if metadata.is_dir() => {
+      let started = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+      let conflicts = conflicts_dir(&game.profile_path).join(started.to_string());
+      let conflicting = merge_into(&save_dir, &saves, &conflicts)?;
+      if conflicting > 0 {
+        tracing::warn!("{} saves were in both '{}' and '{}', the older copies are in '{}'", conflicting, save_dir.display(), saves.display(), conflicts.display());
+      }
+    },
+    Ok(_) => {
+      return Err(Error::new(ErrorCode::InvalidGamePath, format!("'{}' isn't a folder", save_dir.display()))
+        .param("path", &save_dir)
+        .into_io(io::ErrorKind::InvalidData));
+    },
+    Err(e) if e.kind() == io::ErrorKind::NotFound => match save_dir.parent() {
+      Some(parent) => fs::create_dir_all(parent)?,
+      None => {
+        return Err(Error::new(ErrorCode::InvalidGamePath, format!("'{}' can't be a save folder", save_dir.display()))
+          .param("path", &save_dir)
+          .into_io(io::ErrorKind::InvalidInput));
+      },
+    },
+    Err(e) => return Err(e),
+  }
+  symlink(&saves, &save_dir)
+}
+
+//The active profile's saves become the game's own again, the other profiles keep theirs
+pub fn restore(game: &Game) -> io::Result<()> {
+  let save_dir = match save_dir(game) {
+    Some(save_dir) => save_dir,
+    None => return Ok(()),
+  };
+  let saves = profile_saves(&game.profile_path);
+  if !fs::symlink_metadata(&save_dir).map_or(false, |metadata| metadata.file_type().is_symlink()) {
+    return Ok(());
+  }
+  fs::remove_file(&save_dir)?;
+  if saves.is_dir() {
+    scratch::move_dir(&saves, &save_dir)?;
+  } else {
+    fs::create_dir_all(&save_dir)?;
+  }
+  Ok(())
+}
+
+pub fn set_isolation(game: Game, enabled: bool) -> io::Result<Game> {
+  if game.save_path.is_none() {
+    return Err(Error::new(ErrorCode::NoSavePath, format!("where '{}' keeps its saves isn't known", game.public_name))
+      .param("appid", game.appid)
+      .into_io(io::ErrorKind::Unsupported));
+  }
+  let game = Game { isolate_saves: enabled, ..game };
+  if enabled {
+    apply(&game)?;
+  } else {
+    restore(&game)?;
+  }
+  game.save()?;
+  Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn merging_keeps_the_newer_and_the_loser() {
+    let dir = std::env::temp_dir().join(format!("tmm-saves-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let (from, to, conflicts) = (dir.join("from"), dir.join("to"), dir.join("conflicts"));
+    fs::create_dir_all(to.join("slot")).unwrap();
+    fs::write(to.join("slot").join("save.dat"), "older").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    fs::create_dir_all(from.join("slot")).unwrap();
+    fs::create_dir_all(from.join("empty")).unwrap();
+    fs::write(from.join("slot").join("save.dat"), "newer").unwrap();
+    fs::write(from.join("settings.ini"), "").unwrap();
+
+    assert_eq!(merge_into(&from, &to, &conflicts).unwrap(), 1);
+    assert!(!from.exists());
+    assert_eq!(fs::read_to_string(to.join("slot").join("save.dat")).unwrap(), "newer");
+    assert_eq!(fs::read_to_string(conflicts.join("slot").join("save.dat")).unwrap(), "older");
+    assert!(to.join("settings.ini").is_file() && to.join("empty").is_dir());
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
@@ -0,0 +1,554 @@
+use crate::mod_manager::{itch, lutris, make_tmm_game_directories, profiles, reflink, registry, relocate, removable, scratch};
+use crate::mod_manager::game::{Game, Executable, DeployMode, ScannedGame, ScanState, Store, VersionChange, GameConfigFile, LoadOrderFile};
+use crate::error::{Context, Error, ErrorCode};
+use crate::{schema, settings};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use steamlocate::{SteamApp, SteamDir};
+
+//How deep below a search root goggame info files get looked for, deep
+//enough for '<root>/<prefix>/drive_c/GOG Games/<game>'
+const GOG_SEARCH_DEPTH: usize = 5;
+
+#[derive(Serialize, Deserialize)]
+pub struct SupportedGame {
+  pub app_id: u32,
+  pub public_name: String,
+  pub known_binaries: Vec<Executable>,
+  pub path_extension: PathBuf,
+  //patterns new games start out excluding from deployments
+  #[serde(default)]
+  pub deploy_excludes: Vec<String>,
+  //the game's id on GOG, without one GOG installs get matched by name
+  #[serde(default)]
+  pub gog_id: Option<u32>,
+  //how the game gets deployed when it's set up, the usual default otherwise
+  #[serde(default)]
+  pub default_deploy_mode: Option<DeployMode>,
+  //relative to the prefix's Windows user, e.g. 'Documents/My Games/Skyrim Special Edition/Saves'
+  #[serde(default)]
+  pub save_path: Option<PathBuf>,
+  //the INIs each profile gets its own copy of
+  #[serde(default)]
+  pub config_files: Vec<GameConfigFile>,
+  #[serde(default)]
+  pub load_order_file: Option<LoadOrderFile>,
+}
+
+//An installed game some store knows about, before it's matched up with a config
+pub struct Found {
+  pub store: Store,
+  pub appid: u32,
+  pub name: String,
+  pub install_path: PathBuf,
+  pub work_path: PathBuf,
+  //what the store says can be started, the supported game's binaries are used otherwise
+  pub executables: Vec<Executable>,
+  pub wine_prefix: Option<PathBuf>,
+  pub store_id: Option<String>,
+  pub library: Option<PathBuf>,
+  pub version: Option<String>,
+}
+
+//A Steam library folder and how much room is left on its drive
+#[derive(Debug, Serialize)]
+pub struct SteamLibrary {
+  pub path: PathBuf,
+  //None when the drive isn't mounted right now
+  pub free_space: Option<u64>,
+  //hardlink and overlay deployments need the mods on the same filesystem
+  pub same_device_as_profiles: bool,
+  //an SD card or USB drive, its games disappear when it's pulled
+  pub removable: bool,
+  pub appids: Vec<u32>,
+}
+
+pub fn scan_games(supported_games: &[SupportedGame]) -> Result<Vec<ScannedGame>, Error> {
+  let mut found = scan_for_steam_games();
+  found.append(&mut lutris::scan_for_lutris_games());
+  found.append(&mut itch::scan_for_itch_games());
+  //GOG games Lutris installed get found by both, Lutris knows how to start them
+  for gog_game in scan_for_gog_games() {
+    if !found.iter().any(|other| other.install_path == gog_game.install_path) {
+      found.push(gog_game);
+    }
+  }
+
+  let mut scanned: Vec<ScannedGame> = Vec::new();
+  for found in found {
+    let supported = supported_games.iter().find(|supported| matches(supported, &found));
+    scanned.push(register(found, supported));
+  }
+  //games added by hand aren't found by anything, they're managed as long as their config exists
+  for game in Game::all().map_err(|e| Error::io(Context::ScanGames, e))? {
+    if game.store == Store::Custom {
+      scanned.push(ScannedGame { state: ScanState::Managed, appid: game.appid, name: game.public_name.clone(), game: Some(game), error: None, version_change: None });
+    }
+  }
+  if scanned.is_empty() && steam_dirs().is_empty() {
+    return Err(Error::new(ErrorCode::SteamNotFound, "couldn't find a Steam installation or any other game").context(Context::ScanGames));
+  }
+  Ok(scanned)
+}
+
+//What changed since the configs were last written
+#[derive(Debug, Serialize)]
+pub struct RescanReport {
+  pub added: Vec<Game>,
+  pub moved: Vec<MovedGame>,
+  //managed games nothing found anymore, their configs stay until they're removed
+  pub removed: Vec<RemovedGame>,
+  pub unchanged: usize,
+  pub scanned: Vec<ScannedGame>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MovedGame {
+  pub appid: u32,
+  pub from: PathBuf,
+  pub to: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemovedGame {
+  pub appid: u32,
+  pub name: String,
+  pub install_path: PathBuf,
+  //on an SD card that's just not plugged in, it'll be back
+  pub media_missing: bool,
+}
+
+//Scans again and compares with the stored configs, moved games get their paths
+//updated in place by the scan so there's never a second config for them
+pub fn rescan(supported_games: &[SupportedGame]) -> Result<RescanReport, Error> {
+  let stored = Game::all().map_err(|e| Error::io(Context::ScanGames, e))?;
+  let scanned = scan_games(supported_games)?;
+  let mut report = RescanReport { added: Vec::new(), moved: Vec::new(), removed: Vec::new(), unchanged: 0, scanned: Vec::new() };
+  for entry in &scanned {
+    let game = match &entry.game {
+      Some(game) => game,
+      None => continue,
+    };
+    match (entry.state, stored.iter().find(|stored| stored.appid == game.appid)) {
+      (ScanState::Added, _) | (_, None) => report.added.push(game.clone()),
+      (_, Some(before)) if before.install_path != game.install_path => report.moved.push(MovedGame {
+        appid: game.appid,
+        from: before.install_path.clone(),
+        to: game.install_path.clone(),
+      }),
+      _ => report.unchanged += 1,
+    }
+  }
+  for game in stored {
+    if scanned.iter().any(|entry| entry.appid == game.appid && entry.game.is_some()) {
+      continue;
+    }
+    report.removed.push(RemovedGame {
+      appid: game.appid,
+      name: game.public_name,
+      media_missing: game.removable && !game.install_path.is_dir(),
+      install_path: game.install_path,
+    });
+  }
+  report.scanned = scanned;
+  Ok(report)
+}
+
+//For games without a number of their own, put in the upper half where no Steam
+//or GOG id is. FNV-1a of the key, std's hasher isn't stable across releases
+pub fn made_up_appid(key: &str) -> u32 {
+  let mut hash: u32 = 0x811c_9dc5;
+  for byte in key.bytes() {
+    hash ^= byte as u32;
+    hash = hash.wrapping_mul(0x0100_0193);
+  }
+  hash | 0x8000_0000
+}
+
+//Games added by hand get an appid from their install path the first time, one that's
+//taken by a different game gets skipped for the next one. A directory some game is set up
+//for already, from a store or by hand, isn't added twice
+pub fn custom_appid(install_path: &Path) -> io::Result<u32> {
+  let games = Game::all()?;
+  if let Some(game) = games.iter().find(|game| fs::canonicalize(&game.install_path).ok().as_deref() == Some(install_path)) {
+    return Err(Error::new(ErrorCode::GameExists, format!("'{}' is already managed as '{}'", install_path.display(), game.public_name))
+      .param("appid", game.appid)
+      .param("path", install_path)
+      .into_io(io::ErrorKind::AlreadyExists));
+  }
+  let key = format!("custom:{}", install_path.display());
+  let mut appid = made_up_appid(&key);
+  let mut attempt = 1;
+  while Game::config_path(appid)?.exists() || games.iter().any(|game| game.appid == appid) {
+    appid = made_up_appid(&format!("{}#{}", key, attempt));
+    attempt += 1;
+  }
+  Ok(appid)
+}
+
+//A game the user points at themselves, installed from an archive, itch or on a share,
+//it gets set up like a scanned one would so profiles, mods and deploys all work
+pub fn add_custom_game(name: &str, install_path: &Path, executable: &Path, path_extension: &Path) -> io::Result<Game> {
+  registry::check_install_path(install_path)?;
+  if !install_path.is_dir() {
+    return Err(Error::new(ErrorCode::InvalidGamePath, format!("'{}' isn't a directory", install_path.display()))
+      .param("path", install_path)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  let install_path = fs::canonicalize(install_path)?;
+  //the executable can be given relative to the install path or as a full path inside it
+  let absolute = install_path.join(executable);
+  let relative = match fs::canonicalize(&absolute).ok().and_then(|binary| binary.strip_prefix(&install_path).ok().map(Path::to_path_buf)) {
+    Some(relative) if absolute.is_file() => relative,
+    _ => return Err(Error::new(ErrorCode::InvalidGamePath, format!("'{}' isn't a file inside '{}'", executable.display(), install_path.display()))
+      .param("path", executable)
+      .into_io(io::ErrorKind::NotFound)),
+  };
+  let appid = custom_appid(&install_path)?;
+  let found = Found {
+    store: Store::Custom,
+    appid,
+    name: name.to_string(),
+    work_path: install_path.parent().unwrap_or(Path::new("/")).join(".tmm_work").join(appid.to_string()),
+    executables: vec![Executable {
+      name: name.to_string(),
+      use_compatibility: relative.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("exe")),
+      binary_path: PathBuf::from("/").join(&relative),
+      startin_path: PathBuf::new(),
+      output_mod: "overwrite".to_string(),
+    }],
+    install_path,
+    wine_prefix: None,
+    store_id: None,
+    library: None,
+    version: None,
+  };
+  let supported = SupportedGame {
+    app_id: appid,
+    public_name: name.to_string(),
+    known_binaries: Vec::new(),
+    path_extension: path_extension.to_path_buf(),
+    deploy_excludes: Vec::new(),
+    gog_id: None,
+    default_deploy_mode: None,
+    save_path: None,
+    config_files: Vec::new(),
+    load_order_file: None,
+  };
+  add_game(found, &supported)
+}
+
+fn matches(supported: &SupportedGame, found: &Found) -> bool {
+  match found.store {
+    Store::Steam => supported.app_id == found.appid,
+    Store::Gog => match supported.gog_id {
+      Some(gog_id) => gog_id == found.appid,
+      None => supported.public_name.eq_ignore_ascii_case(&found.name),
+    },
+    Store::Lutris | Store::Itch => supported.public_name.eq_ignore_ascii_case(&found.name),
+    Store::Custom => false,
+  }
+}
+
+fn register(found: Found, supported: Option<&SupportedGame>) -> ScannedGame {
+  let appid = found.appid;
+  let name = found.name.clone();
+  let result = match (Game::config_path(appid), supported) {
+    (Ok(config_path), _) if config_path.exists() => managed_game(&found, &config_path).map(|(game, change)| (ScanState::Managed, game, change)),
+    (Err(e), _) => Err(e),
+    (Ok(_), Some(supported)) => add_game(found, supported).map(|game| (ScanState::Added, game, None)),
+    (Ok(_), None) => return ScannedGame { state: ScanState::Unsupported, appid, name, game: None, error: None, version_change: None },
+  };
+  match result {
+    Ok((state, game, version_change)) => ScannedGame { state, appid, name, game: Some(game), error: None, version_change },
+    Err(e) => {
+      tracing::warn!("Something went wrong while setting up '{}'/{}: {}", name, appid, e);
+      let error = Error::io(Context::ScanGames, e).param("appid", appid);
+      ScannedGame { state: ScanState::Error, appid, name, game: None, error: Some(error), version_change: None }
+    }
+  }
+}
+
+//Sets up the config and directories of a supported game the first time it's found
+pub fn add_game(found: Found, supported: &SupportedGame) -> io::Result<Game> {
+  let profile_path = profiles::profile_path(found.appid, profiles::DEFAULT_PROFILE)?;
+  let executables = if found.executables.is_empty() { supported.known_binaries.clone() } else { found.executables };
+  let removable = removable::is_removable(&found.install_path);
+  let defaults = settings::current();
+  let mut game = Game {
+    public_name: if found.name.is_empty() { supported.public_name.clone() } else { found.name },
+    appid: found.appid,
+    install_path: found.install_path,
+    profile_path,
+    work_path: found.work_path,
+    path_extension: supported.path_extension.clone(),
+    executables,
+    deploy_mode: supported.default_deploy_mode.or(defaults.deploy.default_backend).unwrap_or_default(),
+    storage_quota: None,
+    scratch_path: None,
+    reflink: None,
+    auto_purge: defaults.deploy.auto_purge,
+    staging: None,
+    deploy_excludes: supported.deploy_excludes.clone(),
+    store: found.store,
+    wine_prefix: found.wine_prefix,
+    store_id: found.store_id,
+    launch_through_store: found.store == Store::Lutris,
+    library: found.library,
+    version: found.version,
+    removable,
+    update_check_hours: None,
+    trash_retention_days: None,
+    save_path: supported.save_path.clone(),
+    isolate_saves: false,
+    config_files: supported.config_files.clone(),
+    load_order_file: supported.load_order_file.clone(),
+    sync_path: None,
+    tools: Vec::new(),
+    schema_version: schema::GAME.current,
+  };
+  game.reflink = Some(reflink::probe(&game));
+  fs::create_dir_all(crate::config_root()?)?;
+  game.save()?;
+  make_tmm_game_directories(game.clone())?;
+  Ok(game)
+}
+
+//An already managed game, with its paths updated if it moved and what the store says refreshed
+fn managed_game(found: &Found, config_path: &Path) -> io::Result<(Game, Option<VersionChange>)> {
+  let stored = Game::load(config_path)?;
+  let game = if stored.install_path == found.install_path {
+    stored
+  } else {
+    relocate::relocate(stored, &found.install_path, &found.work_path)?
+  };
+  //the first scan that knows the version just records it
+  let change = match (&game.version, &found.version) {
+    (Some(previous), Some(current)) if previous != current => Some(VersionChange { appid: game.appid, previous: previous.clone(), current: current.clone() }),
+    _ => None,
+  };
+  //whatever the store knows now wins, what it doesn't know stays as it was,
+  //e.g. the Proton prefix only shows up once the game ran
+  let refreshed = Game {
+    library: found.library.clone().or_else(|| game.library.clone()),
+    version: found.version.clone().or_else(|| game.version.clone()),
+    wine_prefix: found.wine_prefix.clone().or_else(|| game.wine_prefix.clone()),
+    removable: removable::is_removable(&found.install_path),
+    ..game.clone()
+  };
+  if refreshed.library != game.library || refreshed.version != game.version || refreshed.wine_prefix != game.wine_prefix || refreshed.removable != game.removable {
+    refreshed.save()?;
+  }
+  Ok((refreshed, change))
+}
+
+//Where Steam keeps its data for a native install and the Flatpak and Snap packages,
+//SteamDir::locate only ever returns the first one of these it finds
+fn steam_roots() -> Vec<PathBuf> {
+  let home = match dirs::home_dir() {
+    Some(home) => home,
+    None => return Vec::new(),
+  };
+  let flatpak = home.join(".var/app/com.valvesoftware.Steam");
+  let snap = home.join("snap/steam/common");
+  vec![
+    home.join(".steam/steam"),
+    home.join(".local/share/Steam"),
+    flatpak.join(".steam/steam"),
+    flatpak.join(".local/share/Steam"),
+    snap.join(".steam/steam"),
+    snap.join(".local/share/Steam"),
+  ]
+}
+
+//An installed Steam game, the library it's in and where Proton keeps its prefix
+pub struct SteamInstall {
+  pub app: SteamApp,
+  pub library: PathBuf,
+  pub compatdata: PathBuf,
+}
+
+//install_path is '<library>/steamapps/common/<installdir>'
+fn library_for(app: &SteamApp) -> PathBuf {
+  app.path.ancestors().nth(3).unwrap_or(&app.path).to_path_buf()
+}
+
+//Every Steam installation once, the '.steam/steam' symlinks point
+//at the same data as '.local/share/Steam' so roots get deduplicated
+pub fn steam_dirs() -> Vec<SteamDir> {
+  let mut steam_dirs: Vec<SteamDir> = SteamDir::locate().into_iter().collect();
+  for root in steam_roots() {
+    if root.join("steamapps").is_dir() {
+      let mut steam_dir = SteamDir::default();
+      steam_dir.path = root;
+      steam_dirs.push(steam_dir);
+    }
+  }
+  let mut seen_roots: Vec<PathBuf> = Vec::new();
+  steam_dirs.into_iter().filter(|steam_dir| {
+    let root = fs::canonicalize(&steam_dir.path).unwrap_or_else(|_| steam_dir.path.clone());
+    if seen_roots.contains(&root) {
+      return false;
+    }
+    seen_roots.push(root);
+    true
+  }).collect()
+}
+
+//Every Steam app in every library folder of every Steam installation
+pub fn find_steam_apps() -> Vec<SteamInstall> {
+  let mut installs: Vec<SteamInstall> = Vec::new();
+  for mut steam_dir in steam_dirs() {
+    for app in steam_dir.apps().values().flatten() {
+      //two installations sharing a library see the same games
+      if installs.iter().any(|install| install.app.appid == app.appid) {
+        continue;
+      }
+      let library = library_for(app);
+      let compatdata = library.join("steamapps").join("compatdata").join(app.appid.to_string());
+      installs.push(SteamInstall { app: app.clone(), library, compatdata });
+    }
+  }
+  installs
+}
+
+fn device_of(path: &Path) -> Option<u64> {
+  path.ancestors().find_map(|ancestor| fs::metadata(ancestor).ok()).map(|metadata| metadata.dev())
+}
+
+//The library folders libraryfolders.vdf lists, including drives that aren't mounted
+pub fn steam_libraries() -> Vec<SteamLibrary> {
+  let installs = find_steam_apps();
+  let profiles_device = profiles::profiles_root().ok().and_then(|root| device_of(&root));
+  let mut libraries: Vec<SteamLibrary> = Vec::new();
+  for mut steam_dir in steam_dirs() {
+    for steamapps in &steam_dir.libraryfolders().paths {
+      let path = steamapps.parent().unwrap_or(steamapps).to_path_buf();
+      if libraries.iter().any(|library| library.path == path) {
+        continue;
+      }
+      let mounted = steamapps.is_dir();
+      libraries.push(SteamLibrary {
+        free_space: if mounted { scratch::free_space(steamapps).ok() } else { None },
+        same_device_as_profiles: mounted && profiles_device.is_some() && device_of(steamapps) == profiles_device,
+        removable: removable::is_removable(&path),
+        appids: installs.iter().filter(|install| install.library == path).map(|install| install.app.appid).collect(),
+        path,
+      });
+    }
+  }
+  libraries
+}
+
+//Not having Steam is fine, the games might all be from somewhere else
+pub fn scan_for_steam_games() -> Vec<Found> {
+  let installs = find_steam_apps();
+  if installs.is_empty() {
+    tracing::warn!("Couldn't find any Steam games");
+  }
+  installs.into_iter().map(|install| Found {
+    store: Store::Steam,
+    appid: install.app.appid,
+    name: install.app.name.clone().unwrap_or_default(),
+    work_path: relocate::work_path_for(&install.app.path, install.app.appid),
+    install_path: install.app.path,
+    executables: Vec::new(),
+    //only games that ran through Proton once have a prefix
+    wine_prefix: Some(install.compatdata.join("pfx")).filter(|prefix| prefix.is_dir()),
+    store_id: None,
+    library: Some(install.library),
+    version: install.app.vdf.get("buildid").and_then(|entry| entry.as_str()).map(str::to_string),
+  }).collect()
+}
+
+//GOG's offline installers, Minigalaxy and Lutris all leave a goggame-<id>.info in the
+//game's directory, Windows games installed through Wine end up inside the prefix
+pub(crate) fn gog_search_roots() -> Vec<PathBuf> {
+  let home = match dirs::home_dir() {
+    Some(home) => home,
+    None => return Vec::new(),
+  };
+  vec![home.join("GOG Games"), home.join("Games")]
+}
+
+fn find_gog_info(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+  let entries = match dir.read_dir() {
+    Ok(entries) => entries,
+    Err(_) => return,
+  };
+  for entry in entries.flatten() {
+    let path = entry.path();
+    let name = entry.file_name().to_string_lossy().to_string();
+    match entry.file_type() {
+      Ok(file_type) if file_type.is_file() && name.starts_with("goggame-") && name.ends_with(".info") => found.push(path),
+      Ok(file_type) if file_type.is_dir() && depth > 0 => find_gog_info(&path, depth - 1, found),
+      _ => {}
+    }
+  }
+}
+
+//A game inside a Wine prefix, '<prefix>/drive_c/...'
+pub fn wine_prefix_of(install_path: &Path) -> Option<PathBuf> {
+  install_path.ancestors()
+    .find(|ancestor| ancestor.file_name().map_or(false, |name| name == "drive_c"))
+    .and_then(|drive_c| drive_c.parent())
+    .map(Path::to_path_buf)
+}
+
+fn gog_game(info_path: &Path) -> Option<Found> {
+  let info: Value = serde_json::from_str(&fs::read_to_string(info_path).ok()?).ok()?;
+  let appid: u32 = info["gameId"].as_str()?.parse().ok()?;
+  let install_path = info_path.parent()?.to_path_buf();
+  let executables: Vec<Executable> = info["playTasks"].as_array().map(|tasks| tasks.iter()
+    .filter(|task| task["type"] == "FileTask" && task["category"] != "document")
+    .filter_map(|task| {
+      let binary = task["path"].as_str()?.replace('\\', "/");
+      Some(Executable {
+        name: task["name"].as_str().unwrap_or(&binary).to_string(),
+        use_compatibility: binary.to_lowercase().ends_with(".exe"),
+        binary_path: PathBuf::from("/").join(&binary),
+        startin_path: task["workingDir"].as_str().map(|dir| PathBuf::from("/").join(dir.replace('\\', "/"))).unwrap_or_default(),
+        output_mod: "overwrite".to_string(),
+      })
+    }).collect()).unwrap_or_default();
+  let wine_prefix = wine_prefix_of(&install_path);
+  Some(Found {
+    store: Store::Gog,
+    appid,
+    name: info["name"].as_str().unwrap_or("").to_string(),
+    work_path: install_path.parent()?.join(".tmm_work").join(appid.to_string()),
+    install_path,
+    executables,
+    wine_prefix,
+    store_id: None,
+    library: None,
+    //only info files written by newer installers have it
+    version: info["buildId"].as_str().map(str::to_string),
+  })
+}
+
+pub fn scan_for_gog_games() -> Vec<Found> {
+  let mut info_files: Vec<PathBuf> = Vec::new();
+  for root in gog_search_roots() {
+    find_gog_info(&root, GOG_SEARCH_DEPTH, &mut info_files);
+  }
+  //some games ship one info file per DLC next to the main one
+  let mut games: HashMap<PathBuf, Found> = HashMap::new();
+  for info_path in info_files {
+    if let Some(found) = gog_game(&info_path) {
+      let is_base = info_path.file_name().map_or(false, |name| name.to_string_lossy() == format!("goggame-{}.info", found.appid));
+      let rooted = fs::read_to_string(&info_path).ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .map_or(false, |info| info["rootGameId"].as_str() == Some(&found.appid.to_string()));
+      if is_base && rooted {
+        games.insert(found.install_path.clone(), found);
+      }
+    }
+  }
+  games.into_values().collect()
+}
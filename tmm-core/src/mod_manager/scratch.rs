@@ -0,0 +1,180 @@
+use crate::mod_manager::{get_files, split};
+use crate::mod_manager::game::Game;
+use crate::error::{Error, ErrorCode};
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io, mem};
+
+//Archives usually don't say how big they are unpacked, so this is how
+//many times the archive's size gets asked for before extracting
+const EXTRACTION_RATIO: u64 = 3;
+//Left free on the drive on top of what an operation needs
+const SPARE_SPACE: u64 = 268_435_456;
+
+//Where big temporary things (extraction staging and such) happen, the user's
+//scratch dir if there is one, otherwise inside the profile. Never the system
+//tmp, that's often a small tmpfs
+pub fn scratch_root(game: &Game) -> PathBuf {
+  match &game.scratch_path {
+    Some(path) => path.join("tmm").join(game.appid.to_string()),
+    None => game.profile_path.join(".tmp"),
+  }
+}
+
+pub fn free_space(path: &Path) -> io::Result<u64> {
+  //the path itself might not exist yet
+  let mut existing = path;
+  while !existing.exists() {
+    existing = match existing.parent() {
+      Some(parent) => parent,
+      None => break,
+    };
+  }
+  let c_path = CString::new(existing.as_os_str().as_bytes())?;
+  let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+  if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+    return Err(io::Error::last_os_error());
+  }
+  Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+pub fn ensure_space(path: &Path, needed: u64) -> io::Result<()> {
+  let available = free_space(path)?;
+  if available < needed + SPARE_SPACE {
+    return Err(Error::new(ErrorCode::NotEnoughSpace, format!("'{}' has {} bytes free but {} are needed", path.display(), available, needed))
+      .param("path", path)
+      .param("available", available)
+      .param("needed", needed)
+      .into_io(io::ErrorKind::Other));
+  }
+  Ok(())
+}
+
+//A fresh directory in the scratch space, removed again once it's dropped
+pub struct ScratchDir {
+  path: PathBuf,
+}
+
+impl ScratchDir {
+  pub fn new(game: &Game, purpose: &str, needed: u64) -> io::Result<ScratchDir> {
+    let root = scratch_root(game);
+    ensure_space(&root, needed)?;
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or(0);
+    let path = root.join(format!("{}-{}", purpose, nanos));
+    fs::create_dir_all(&path)?;
+    Ok(ScratchDir { path })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+}
+
+impl Drop for ScratchDir {
+  fn drop(&mut self) {
+    if self.path.exists() {
+      if let Err(e) = fs::remove_dir_all(&self.path) {
+        tracing::warn!("Couldn't clean up the scratch dir '{}': {}", self.path.display(), e);
+      }
+    }
+  }
+}
+
+//Every part of a split archive counts
+pub fn extraction_estimate(archive: &Path) -> io::Result<u64> {
+  Ok(split::volumes(archive).size()? * EXTRACTION_RATIO)
+}
+
+//Renames if it can, the scratch dir is often on another drive so otherwise
+//it's copied over, checking first that it fits
+pub fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+  if let Some(parent) = to.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  match fs::rename(from, to) {
+    Ok(()) => return Ok(()),
+    Err(e) if e.raw_os_error() != Some(libc::EXDEV) => return Err(e),
+    Err(_) => {}
+  }
+  let mut size = 0;
+  for file in get_files(from)? {
+    size += fs::symlink_metadata(file)?.len();
+  }
+  ensure_space(to, size)?;
+  copy_tree(from, to)?;
+  fs::remove_dir_all(from)
+}
+
+//What's in from the same way under to, empty directories too
+fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+  fs::create_dir_all(to)?;
+  for entry in from.read_dir()? {
+    let entry = entry?;
+    let target = to.join(entry.file_name());
+    let file_type = entry.file_type()?;
+    if file_type.is_dir() {
+      copy_tree(&entry.path(), &target)?;
+    } else if file_type.is_symlink() {
+      std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+    } else {
+      fs::copy(entry.path(), &target)?;
+    }
+  }
+  Ok(())
+}
+
+//The same for a single file or link
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+  if let Some(parent) = to.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  match fs::rename(from, to) {
+    Ok(()) => return Ok(()),
+    Err(e) if e.raw_os_error() != Some(libc::EXDEV) => return Err(e),
+    Err(_) => {}
+  }
+  let metadata = fs::symlink_metadata(from)?;
+  if metadata.file_type().is_symlink() {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)?;
+  } else {
+    ensure_space(to.parent().unwrap_or(to), metadata.len())?;
+    fs::copy(from, to)?;
+  }
+  fs::remove_file(from)
+}
+
+//A scratch dir has to be a directory the manager can write to
+pub fn validate(path: &Path) -> io::Result<()> {
+  if !path.is_dir() {
+    return Err(Error::new(ErrorCode::InvalidScratchPath, format!("'{}' isn't a directory", path.display()))
+      .param("path", path)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  let probe = path.join(".tmm_write_test");
+  fs::write(&probe, b"")?;
+  fs::remove_file(probe)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn copies_keep_empty_folders_and_links() {
+    let dir = std::env::temp_dir().join(format!("tmm-scratch-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("from").join("empty")).unwrap();
+    fs::write(dir.join("from").join("file"), "contents").unwrap();
+    std::os::unix::fs::symlink("file", dir.join("from").join("link")).unwrap();
+    copy_tree(&dir.join("from"), &dir.join("to")).unwrap();
+    assert!(dir.join("to").join("empty").is_dir());
+    assert_eq!(fs::read_to_string(dir.join("to").join("file")).unwrap(), "contents");
+    assert_eq!(fs::read_link(dir.join("to").join("link")).unwrap(), Path::new("file"));
+
+    move_file(&dir.join("to").join("file"), &dir.join("moved").join("file")).unwrap();
+    assert!(!dir.join("to").join("file").exists() && dir.join("moved").join("file").is_file());
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
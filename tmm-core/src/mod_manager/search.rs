@@ -0,0 +1,136 @@
+use crate::mod_manager::registry;
+use crate::mod_manager::scan::{self, Found, SupportedGame};
+use crate::mod_manager::game::{Game, Store};
+use crate::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//Deep enough for '<root>/<prefix>/drive_c/Program Files/<publisher>/<game>'
+const SEARCH_DEPTH: usize = 6;
+
+//A directory that looks like a supported game, nothing gets set up until it's confirmed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposedGame {
+  pub app_id: u32,
+  pub public_name: String,
+  pub install_path: PathBuf,
+  //the known binaries that were found, more of them is a surer match
+  pub matched_binaries: Vec<PathBuf>,
+  pub wine_prefix: Option<PathBuf>,
+}
+
+fn roots_path() -> io::Result<PathBuf> {
+  Ok(crate::config_root()?.join("search_roots.json"))
+}
+
+//Where the user keeps games no store knows about, e.g. '~/Games' or a Wine prefix
+pub fn roots() -> io::Result<Vec<PathBuf>> {
+  let path = roots_path()?;
+  if !path.exists() {
+    return Ok(Vec::new());
+  }
+  Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+pub fn set_roots(roots: &[PathBuf]) -> io::Result<()> {
+  for root in roots {
+    registry::check_user_path("search_root", root)?;
+    if !root.is_dir() {
+      return Err(Error::new(ErrorCode::InvalidGamePath, format!("'{}' isn't a directory", root.display()))
+        .param("path", root)
+        .into_io(io::ErrorKind::NotFound));
+    }
+  }
+  fs::create_dir_all(crate::config_root()?)?;
+  fs::write(roots_path()?, serde_json::to_string(roots)?)?;
+  registry::forget_store_roots();
+  Ok(())
+}
+
+fn relative(binary: &Path) -> &Path {
+  binary.strip_prefix("/").unwrap_or(binary)
+}
+
+fn propose(dir: &Path, supported_games: &[SupportedGame]) -> Option<ProposedGame> {
+  supported_games.iter()
+    .filter_map(|supported| {
+      let matched: Vec<PathBuf> = supported.known_binaries.iter()
+        .map(|binary| relative(&binary.binary_path).to_path_buf())
+        .filter(|binary| dir.join(binary).is_file())
+        .collect();
+      if matched.is_empty() {
+        return None;
+      }
+      Some(ProposedGame {
+        app_id: supported.app_id,
+        public_name: supported.public_name.clone(),
+        install_path: dir.to_path_buf(),
+        matched_binaries: matched,
+        wine_prefix: scan::wine_prefix_of(dir),
+      })
+    })
+    .max_by_key(|proposed| proposed.matched_binaries.len())
+}
+
+fn walk(dir: &Path, depth: usize, supported_games: &[SupportedGame], managed: &[PathBuf], proposals: &mut Vec<ProposedGame>) {
+  if managed.iter().any(|path| path == dir) {
+    return;
+  }
+  //a game's own subdirectories won't be another game
+  if let Some(proposed) = propose(dir, supported_games) {
+    proposals.push(proposed);
+    return;
+  }
+  if depth == 0 {
+    return;
+  }
+  let entries = match dir.read_dir() {
+    Ok(entries) => entries,
+    Err(_) => return,
+  };
+  for entry in entries.flatten() {
+    //symlinks get skipped, prefixes link back up to the home directory
+    if entry.file_type().map_or(false, |file_type| file_type.is_dir()) {
+      walk(&entry.path(), depth - 1, supported_games, managed, proposals);
+    }
+  }
+}
+
+//Walks the search roots for directories holding known binaries, covers manual
+//installs, Wine prefixes and games copied over from another machine
+pub fn scan_for_other_games(supported_games: &[SupportedGame]) -> io::Result<Vec<ProposedGame>> {
+  let managed: Vec<PathBuf> = Game::all()?.into_iter().map(|game| game.install_path).collect();
+  let mut proposals: Vec<ProposedGame> = Vec::new();
+  for root in roots()? {
+    walk(&root, SEARCH_DEPTH, supported_games, &managed, &mut proposals);
+  }
+  Ok(proposals)
+}
+
+//Sets up a proposal the user confirmed, like a game they added by hand but with
+//everything the supported game knows
+pub fn confirm(proposed: &ProposedGame, supported_games: &[SupportedGame]) -> io::Result<Game> {
+  let supported = match supported_games.iter().find(|supported| supported.app_id == proposed.app_id) {
+    Some(supported) => supported,
+    None => return Err(Error::new(ErrorCode::InvalidGameDefinition, format!("{} isn't a supported game", proposed.app_id))
+      .param("appid", proposed.app_id)
+      .into_io(io::ErrorKind::NotFound)),
+  };
+  registry::check_install_path(&proposed.install_path)?;
+  let install_path = fs::canonicalize(&proposed.install_path)?;
+  let appid = scan::custom_appid(&install_path)?;
+  let found = Found {
+    store: Store::Custom,
+    appid,
+    name: supported.public_name.clone(),
+    work_path: install_path.parent().unwrap_or(Path::new("/")).join(".tmm_work").join(appid.to_string()),
+    install_path,
+    executables: Vec::new(),
+    wine_prefix: proposed.wine_prefix.clone(),
+    store_id: None,
+    library: None,
+    version: None,
+  };
+  scan::add_game(found, supported)
+}
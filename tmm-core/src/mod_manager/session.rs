@@ -0,0 +1,58 @@
+use crate::mod_manager::{Mod, deploy_mods, purge_deployed};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{deploy, launch};
+use serde::Serialize;
+use std::io;
+
+use crate::events::{BackendEvent, Events};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+  appid: u32,
+  mod_name: String,
+  //whether the mod is part of the session, the opposite of the profile
+  enabled: bool,
+}
+
+//The profile's mods with the toggled one flipped: removed if it's
+//enabled, otherwise added at the end of the load order
+pub fn toggled(mods: &[Mod], toggle: &Mod) -> (Vec<Mod>, bool) {
+  let mut session_mods: Vec<Mod> = mods.iter().filter(|elem| elem.name != toggle.name).cloned().collect();
+  let enabled = session_mods.len() == mods.len();
+  if enabled {
+    session_mods.push(toggle.clone());
+  }
+  (session_mods, enabled)
+}
+
+//Deploys the session's mods, launches the game and once it exits puts
+//the game directory back the way it was before the session
+pub fn run(game: &Game, mods: &[Mod], toggle: &Mod, events: &Events) -> io::Result<()> {
+  let (session_mods, enabled) = toggled(mods, toggle);
+  let event = SessionEvent { appid: game.appid, mod_name: toggle.name.clone(), enabled };
+  let result = run_isolated(game, mods, &session_mods, events, || events.send(BackendEvent::TestSessionStarted(event.clone())));
+  events.send(BackendEvent::TestSessionFinished(event));
+  result
+}
+
+//Plays `session_mods` once, afterwards `mods` are deployed again if they
+//were before, `on_started` gets called once the game was launched
+pub fn run_isolated<F: FnOnce()>(game: &Game, mods: &[Mod], session_mods: &[Mod], events: &Events, on_started: F) -> io::Result<()> {
+  let backend = deploy::backend(game.deploy_mode);
+  let was_deployed = backend.status(game)?.deployed;
+
+  deploy_mods(game, session_mods, events)?;
+  let result = launch::launch(game);
+  if result.is_ok() {
+    on_started();
+    launch::wait_for_exit(game);
+  }
+
+  if was_deployed {
+    deploy_mods(game, mods, events)?;
+  } else {
+    purge_deployed(game, events)?;
+  }
+  result
+}
+
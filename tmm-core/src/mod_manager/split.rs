@@ -0,0 +1,244 @@
+use crate::error::{Error, ErrorCode};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+//How an archive on disk is cut up
+pub enum Volumes {
+  Single(PathBuf),
+  //'.7z.001', '.zip.002', ... cut at arbitrary bytes, so they're one archive put back together
+  Split(Vec<PathBuf>),
+  //'.part1.rar' or '.rar' with '.r00', libarchive can only read those from files it opens itself
+  Rar(PathBuf),
+}
+
+fn numbered(path: &Path, name_of: impl Fn(usize) -> String) -> Vec<PathBuf> {
+  let mut parts: Vec<PathBuf> = Vec::new();
+  let mut number = 1;
+  loop {
+    let part = path.with_file_name(name_of(number));
+    if !part.is_file() {
+      return parts;
+    }
+    parts.push(part);
+    number += 1;
+  }
+}
+
+//Whichever part was picked, all the parts next to it are found
+pub fn volumes(path: &Path) -> Volumes {
+  let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+  let lowercase = name.to_ascii_lowercase();
+  let extension = lowercase.rsplit('.').next().unwrap_or("");
+  if extension.len() == 3 && extension.chars().all(|c| c.is_ascii_digit()) {
+    let stem = name[..name.len() - 4].to_string();
+    let parts = numbered(path, |number| format!("{}.{:03}", stem, number));
+    if parts.len() > 1 {
+      return Volumes::Split(parts);
+    }
+  }
+  if extension == "rar" {
+    if let Some(index) = lowercase.rfind(".part") {
+      let digits = &lowercase[index + 5..lowercase.len() - 4];
+      if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        let first = path.with_file_name(format!("{}.part{:0width$}.rar", &name[..index], 1, width = digits.len()));
+        return Volumes::Rar(first);
+      }
+    }
+    if path.with_extension("r00").is_file() {
+      return Volumes::Rar(path.to_path_buf());
+    }
+  }
+  Volumes::Single(path.to_path_buf())
+}
+
+impl Volumes {
+  pub fn first(&self) -> &Path {
+    match self {
+      Volumes::Split(parts) => &parts[0],
+      Volumes::Single(path) | Volumes::Rar(path) => path,
+    }
+  }
+
+  pub fn size(&self) -> io::Result<u64> {
+    match self {
+      Volumes::Split(parts) => {
+        let mut size = 0;
+        for part in parts {
+          size += fs::metadata(part)?.len();
+        }
+        Ok(size)
+      },
+      Volumes::Single(path) | Volumes::Rar(path) => Ok(fs::metadata(path)?.len()),
+    }
+  }
+}
+
+//Reads the parts of a split archive as if they were one file
+pub struct SplitReader {
+  parts: Vec<(File, u64)>,
+  position: u64,
+}
+
+impl SplitReader {
+  pub fn open(paths: &[PathBuf]) -> io::Result<SplitReader> {
+    let mut parts = Vec::new();
+    for path in paths {
+      let file = File::open(path)?;
+      let len = file.metadata()?.len();
+      parts.push((file, len));
+    }
+    Ok(SplitReader { parts, position: 0 })
+  }
+
+  fn len(&self) -> u64 {
+    self.parts.iter().map(|(_, len)| len).sum()
+  }
+}
+
+impl Read for SplitReader {
+  fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut start = 0;
+    for (file, len) in self.parts.iter_mut() {
+      if self.position < start + *len {
+        file.seek(SeekFrom::Start(self.position - start))?;
+        let max = buffer.len().min((start + *len - self.position) as usize);
+        let count = file.read(&mut buffer[..max])?;
+        self.position += count as u64;
+        return Ok(count);
+      }
+      start += *len;
+    }
+    Ok(0)
+  }
+}
+
+impl Seek for SplitReader {
+  fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+    let position = match from {
+      SeekFrom::Start(offset) => offset as i64,
+      SeekFrom::End(offset) => self.len() as i64 + offset,
+      SeekFrom::Current(offset) => self.position as i64 + offset,
+    };
+    if position < 0 {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the archive"));
+    }
+    self.position = position as u64;
+    Ok(self.position)
+  }
+}
+
+pub fn encrypted(archive: &Path) -> io::Error {
+  Error::new(ErrorCode::ArchiveEncrypted, format!("'{}' needs a password", archive.display()))
+    .param("path", archive)
+    .into_io(io::ErrorKind::PermissionDenied)
+}
+
+fn missing(e: io::Error) -> io::Error {
+  if e.kind() != io::ErrorKind::NotFound {
+    return e;
+  }
+  Error::new(ErrorCode::ExtractorMissing, "7-Zip ('7z') has to be installed for this")
+    .param("extractor", "7z")
+    .into_io(io::ErrorKind::NotFound)
+}
+
+//For what compress_tools can't do, password protected and RAR volumes as well as packing
+pub fn run_7z(command: &mut Command) -> io::Result<Output> {
+  command.output().map_err(missing)
+}
+
+//7-Zip asks for a password on stdin when it needs one, that way it never shows up in the
+//arguments for anyone to see. Without one the empty line makes it give up instead of waiting
+fn run_7z_with_password(command: &mut Command, password: Option<&str>) -> io::Result<Output> {
+  let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().map_err(missing)?;
+  if let Some(mut stdin) = child.stdin.take() {
+    //it may not ask at all and already be gone
+    let _ = writeln!(stdin, "{}", password.unwrap_or(""));
+  }
+  child.wait_with_output()
+}
+
+//Going by what 7-Zip lists about the archive rather than what its errors say. Every entry
+//that needs the password is 'Encrypted = +', with the headers encrypted as well nothing gets
+//listed but the archive's own method still names the cipher
+pub fn is_encrypted(archive: &Path) -> io::Result<bool> {
+  let output = run_7z_with_password(Command::new("7z").arg("l").arg("-slt").arg(archive), None)?;
+  Ok(listed_encrypted(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn listed_encrypted(listing: &str) -> bool {
+  listing.lines().filter_map(|line| line.split_once(" = ")).any(|(key, value)| match key.trim() {
+    "Encrypted" => value.trim() == "+",
+    "Method" => value.split(' ').any(|method| method.contains("AES") || method == "ZipCrypto"),
+    _ => false,
+  })
+}
+
+pub fn extract_with_7z(archive: &Path, target: &Path, password: Option<&str>) -> io::Result<()> {
+  let mut command = Command::new("7z");
+  command.arg("x").arg("-y").arg(format!("-o{}", target.display())).arg(archive);
+  let output = run_7z_with_password(&mut command, password)?;
+  if output.status.success() {
+    return Ok(());
+  }
+  if is_encrypted(archive)? {
+    return Err(encrypted(archive));
+  }
+  let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+  Err(io::Error::new(io::ErrorKind::InvalidData, stderr.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn scratch(name: &str, files: &[&str]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tmm-split-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for file in files {
+      fs::write(dir.join(file), file.as_bytes()).unwrap();
+    }
+    dir
+  }
+
+  #[test]
+  fn numbered_parts_are_one_archive() {
+    let dir = scratch("numbered", &["Mod.7z.001", "Mod.7z.002", "Mod.7z.003", "Mod.7z.005"]);
+    match volumes(&dir.join("Mod.7z.002")) {
+      Volumes::Split(parts) => assert_eq!(parts, [dir.join("Mod.7z.001"), dir.join("Mod.7z.002"), dir.join("Mod.7z.003")]),
+      _ => panic!("not split"),
+    }
+    let mut joined = String::new();
+    SplitReader::open(&[dir.join("Mod.7z.001"), dir.join("Mod.7z.002")]).unwrap().read_to_string(&mut joined).unwrap();
+    assert_eq!(joined, "Mod.7z.001Mod.7z.002");
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn a_lone_part_is_just_a_file() {
+    let dir = scratch("lone", &["Mod.zip.001", "Mod.zip"]);
+    assert!(matches!(volumes(&dir.join("Mod.zip.001")), Volumes::Single(_)));
+    assert!(matches!(volumes(&dir.join("Mod.zip")), Volumes::Single(_)));
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn rar_volumes_start_at_the_first() {
+    let dir = scratch("rar", &["Mod.part01.rar", "Mod.part02.rar", "Old.rar", "Old.r00", "Plain.rar"]);
+    assert!(matches!(volumes(&dir.join("Mod.part02.rar")), Volumes::Rar(first) if first == dir.join("Mod.part01.rar")));
+    assert!(matches!(volumes(&dir.join("Old.rar")), Volumes::Rar(first) if first == dir.join("Old.rar")));
+    assert!(matches!(volumes(&dir.join("Plain.rar")), Volumes::Single(_)));
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn encrypted_listings() {
+    assert!(listed_encrypted("Path = Mod.esp\nEncrypted = +\n"));
+    assert!(listed_encrypted("Path = Mod.esp\nMethod = LZMA2:24 7zAES\n"));
+    assert!(listed_encrypted("Path = Mod.esp\nMethod = ZipCrypto Deflate\n"));
+    assert!(!listed_encrypted("Path = Mod.esp\nEncrypted = -\nMethod = Deflate\n"));
+  }
+}
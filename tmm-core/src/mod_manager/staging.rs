@@ -0,0 +1,203 @@
+use crate::mod_manager::{Mod, get_files, mod_dir, overwrite, reflink, with_suffix};
+use crate::mod_manager::link::{fingerprint, relative};
+use crate::mod_manager::experiment::is_whiteout;
+use crate::mod_manager::root::ROOT_DIR_NAME;
+use crate::mod_manager::game::{Game, DeployMode};
+use crate::mod_manager::deploy::{DeployBackend, DeployReport, DeployStatus, Capabilities};
+use crate::mod_manager::case::{CaseConflict, CaseResolver};
+use crate::mod_manager::excludes::{ExcludedFile, Excludes, Filtered};
+use crate::error::{Error, ErrorCode};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+pub const DEFAULT_STAGING: &str = "default";
+
+//Builds a copy of the game with the mods on top and runs the game from there, so the install
+//itself never changes and several differently modded stagings can sit next to each other.
+//Every file is a copy of its own, whatever the game writes to one can't reach the install or
+//the mods, they're reflinks where the filesystem can share the blocks
+pub struct StagingBackend;
+
+impl DeployBackend for StagingBackend {
+  fn deploy(&self, game: &Game, mods: &[Mod]) -> io::Result<DeployReport> {
+    let root = staging_root(game);
+    //building it fresh is simpler than working out what changed
+    overwrite::capture(game, &root)?;
+    remove_staging(&root)?;
+    fs::create_dir_all(&root)?;
+    let mut report = DeployReport::default();
+    match build(game, mods, &root, &mut report) {
+      Ok(()) => Ok(report),
+      Err(e) => {
+        remove_staging(&root)?;
+        Err(e)
+      }
+    }
+  }
+
+  fn purge(&self, game: &Game) -> io::Result<()> {
+    let root = staging_root(game);
+    overwrite::capture(game, &root)?;
+    remove_staging(&root)
+  }
+
+  fn status(&self, game: &Game) -> io::Result<DeployStatus> {
+    let root = staging_root(game);
+    let deployed = root.exists();
+    Ok(DeployStatus {
+      backend: DeployMode::Staging,
+      deployed,
+      files: if deployed { Some(get_files(&root)?.len()) } else { None },
+    })
+  }
+
+  fn capabilities(&self, game: &Game) -> Capabilities {
+    let available = !game.executables.is_empty();
+    Capabilities {
+      backend: DeployMode::Staging,
+      available,
+      reason: if available { None } else { Some("the game has no known executable to start from the staging".to_string()) },
+      privileged: false,
+      uses_space: true,
+    }
+  }
+}
+
+//Next to the work directory, so it's on the game's filesystem and vanilla files can be reflinked
+pub fn stagings_path(game: &Game) -> PathBuf {
+  game.work_path.with_extension("staging")
+}
+
+pub fn staging_root(game: &Game) -> PathBuf {
+  stagings_path(game).join(game.staging.as_deref().unwrap_or(DEFAULT_STAGING))
+}
+
+pub fn list(game: &Game) -> io::Result<Vec<String>> {
+  let mut stagings: Vec<String> = Vec::new();
+  let path = stagings_path(game);
+  if path.exists() {
+    for entry in path.read_dir()? {
+      let entry = entry?;
+      if entry.file_type()?.is_dir() {
+        stagings.push(entry.file_name().to_string_lossy().to_string());
+      }
+    }
+  }
+  stagings.sort();
+  Ok(stagings)
+}
+
+pub fn validate_name(name: &str) -> io::Result<()> {
+  if name.is_empty() || name.contains('/') || name.starts_with('.') {
+    return Err(Error::new(ErrorCode::InvalidStagingName, format!("'{}' can't be used as the name of a staging", name))
+      .param("name", name)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  Ok(())
+}
+
+pub fn remove(game: &Game, name: &str) -> io::Result<()> {
+  validate_name(name)?;
+  let root = stagings_path(game).join(name);
+  overwrite::capture(game, &root)?;
+  remove_staging(&root)
+}
+
+fn remove_staging(root: &Path) -> io::Result<()> {
+  for result in [fs::remove_dir_all(root), fs::remove_file(built_path(root))] {
+    match result {
+      Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+      _ => {},
+    }
+  }
+  Ok(())
+}
+
+//Next to the staging, anything inside it would be seen by the game
+fn built_path(root: &Path) -> PathBuf {
+  with_suffix(root, ".built.json")
+}
+
+//Every file of the staging by a fingerprint of it right after it was built, the ones whose
+//fingerprint changed since got written by the game. None for stagings built before it was kept
+pub fn built(root: &Path) -> io::Result<Option<BTreeMap<PathBuf, u64>>> {
+  let path = built_path(root);
+  if !path.exists() {
+    return Ok(None);
+  }
+  Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+}
+
+//The file that was at the target, the vanilla one or an earlier mod's, gets replaced
+fn place(source: &Path, target: &Path, root: &Path, built: &mut BTreeMap<PathBuf, u64>) -> io::Result<()> {
+  if let Some(parent) = target.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  if fs::symlink_metadata(target).is_ok() {
+    fs::remove_file(target)?;
+  }
+  reflink::copy(source, target)?;
+  built.insert(relative(target, root)?.to_path_buf(), fingerprint(target)?);
+  Ok(())
+}
+
+fn build(game: &Game, mods: &[Mod], root: &Path, report: &mut DeployReport) -> io::Result<()> {
+  let mut built: BTreeMap<PathBuf, u64> = BTreeMap::new();
+  for source in get_files(&game.install_path)? {
+    let target = root.join(relative(&source, &game.install_path)?);
+    place(&source, &target, root, &mut built)?;
+  }
+
+  let target_root = root.join(&game.path_extension);
+  let mut resolver = if game.uses_compatibility() { Some(CaseResolver::new(target_root.clone())) } else { None };
+  let excludes = Excludes::for_game(game)?;
+  for elem in mods {
+    let mod_root = mod_dir(game, elem)?;
+    for source in get_files(&mod_root)? {
+      let mut relative = relative(&source, &mod_root)?.to_path_buf();
+      match excludes.filter(elem, &relative) {
+        Filtered::Deployed => {},
+        Filtered::Excluded => {
+          report.excluded.push(ExcludedFile { mod_name: elem.name.clone(), path: relative });
+          continue;
+        },
+        _ => continue,
+      }
+      if let Some(ref mut resolver) = resolver {
+        match resolver.resolve(&relative)? {
+          Ok(resolved) => {
+            resolver.added(&resolved)?;
+            relative = resolved;
+          },
+          Err(candidates) => {
+            report.case_conflicts.push(CaseConflict { mod_name: elem.name.clone(), path: relative, candidates });
+            continue;
+          }
+        }
+      }
+      place(&source, &target_root.join(&relative), root, &mut built)?;
+    }
+  }
+
+  //what the game wrote last time goes on top of everything, deletions the overlay recorded apply too
+  let overwrite = overwrite::overwrite_path(game);
+  if overwrite.is_dir() {
+    for source in get_files(&overwrite)? {
+      let written = relative(&source, &overwrite)?;
+      let target = match written.strip_prefix(ROOT_DIR_NAME) {
+        Ok(relative) => root.join(relative),
+        Err(_) => target_root.join(written),
+      };
+      if is_whiteout(&source)? {
+        if fs::symlink_metadata(&target).is_ok() {
+          fs::remove_file(&target)?;
+        }
+        built.remove(relative(&target, root)?);
+        continue;
+      }
+      place(&source, &target, root, &mut built)?;
+    }
+  }
+  fs::write(built_path(root), serde_json::to_string(&built)?)
+}
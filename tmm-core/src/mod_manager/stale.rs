@@ -0,0 +1,177 @@
+use crate::mod_manager::game::{DeployMode, Game};
+use crate::mod_manager::{launch, ofs, profiles};
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//Mounts left behind by a run of the manager that didn't get to take them down,
+//e.g. because it crashed in the middle of an experiment
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleMount {
+  appid: u32,
+  path: PathBuf,
+  fs_type: String,
+  reason: StaleReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleReason {
+  //experiments only stay mounted while their tool runs
+  Experiment,
+  //the game doesn't deploy through an overlay (anymore)
+  WrongBackend,
+  //overlays stacked on top of the deployment
+  Stacked,
+  //one of the layers doesn't exist anymore
+  MissingLayer,
+  //something mounted somewhere inside the game directory
+  Nested,
+}
+
+fn layers(options: &str) -> Vec<PathBuf> {
+  let mut layers: Vec<PathBuf> = Vec::new();
+  for option in options.split(',') {
+    if let Some(lower) = option.strip_prefix("lowerdir=") {
+      layers.extend(lower.split(':').map(PathBuf::from));
+    } else if let Some(lower) = option.strip_prefix("lowerdir+=") {
+      layers.push(PathBuf::from(lower));
+    } else if let Some(dir) = option.strip_prefix("upperdir=").or_else(|| option.strip_prefix("workdir=")) {
+      layers.push(PathBuf::from(dir));
+    }
+  }
+  layers
+}
+
+//The layers the files come from and go to, the work dir says nothing about whose mount it is
+fn content_layers(options: &str) -> Vec<PathBuf> {
+  let mut layers: Vec<PathBuf> = Vec::new();
+  for option in options.split(',') {
+    if let Some(lower) = option.strip_prefix("lowerdir=") {
+      layers.extend(lower.split(':').map(PathBuf::from));
+    } else if let Some(lower) = option.strip_prefix("lowerdir+=") {
+      layers.push(PathBuf::from(lower));
+    } else if let Some(upper) = option.strip_prefix("upperdir=") {
+      layers.push(PathBuf::from(upper));
+    }
+  }
+  layers
+}
+
+//Any of the game's profiles, a mount can be from before the active one was switched
+fn in_profiles(game: &Game, layer: &Path) -> bool {
+  if layer.starts_with(&game.profile_path) || layer.starts_with(&game.work_path) {
+    return true;
+  }
+  let root = match profiles::profiles_root() {
+    Ok(root) => root,
+    Err(_) => return false,
+  };
+  let profile = match layer.strip_prefix(&root).ok().and_then(|rest| rest.iter().next()) {
+    Some(profile) => profile.to_string_lossy().to_string(),
+    None => return false,
+  };
+  profile == game.appid.to_string() || profile.starts_with(&format!("{}.", game.appid))
+}
+
+fn classify(game: &Game, entries: &[&ofs::MountEntry], index: usize) -> Option<StaleReason> {
+  let entry = entries[index];
+  let mount = ofs::mount_path(game);
+  if entry.path != mount {
+    return Some(StaleReason::Nested);
+  }
+  let experiments = game.profile_path.join("experiments");
+  if content_layers(&entry.options).iter().any(|layer| layer.starts_with(&experiments)) {
+    return Some(StaleReason::Experiment);
+  }
+  if game.deploy_mode != DeployMode::Overlay || entry.fs_type != "overlay" {
+    return Some(StaleReason::WrongBackend);
+  }
+  //only the first overlay on the mount path is the deployment
+  if entries[..index].iter().any(|earlier| earlier.path == mount) {
+    return Some(StaleReason::Stacked);
+  }
+  if layers(&entry.options).iter().any(|layer| !layer.exists()) {
+    return Some(StaleReason::MissingLayer);
+  }
+  None
+}
+
+//Only overlays the manager could have mounted, with a layer in one of the game's profiles.
+//Bind mounts, other fuse filesystems and whatever the user mounted in the game directory
+//aren't ours to take down. fuse-overlayfs doesn't list its layers, so those never match, and
+//neither do the mount helper's on kernels before 6.7, they only list the fds it mounted from
+fn inside(game: &Game, entry: &ofs::MountEntry) -> bool {
+  (entry.fs_type == "overlay" || entry.fs_type == "fuse.fuse-overlayfs")
+    && content_layers(&entry.options).iter().any(|layer| in_profiles(game, layer))
+}
+
+//Mounts are listed topmost first, which is the order they have to come off in
+pub fn find(games: &[Game]) -> io::Result<Vec<StaleMount>> {
+  let mounts = ofs::mounts()?;
+  let mut stale: Vec<StaleMount> = Vec::new();
+  for game in games {
+    let entries: Vec<&ofs::MountEntry> = mounts.iter().filter(|entry| inside(game, entry)).collect();
+    for index in 0..entries.len() {
+      if let Some(reason) = classify(game, &entries, index) {
+        stale.push(StaleMount { appid: game.appid, path: entries[index].path.clone(), fs_type: entries[index].fs_type.clone(), reason });
+      }
+    }
+  }
+  stale.reverse();
+  Ok(stale)
+}
+
+//Checks what's stale again right before unmounting so nothing that changed since it was
+//reported gets taken down, games that are running get left alone
+pub fn cleanup(games: &[Game]) -> io::Result<Vec<StaleMount>> {
+  let mut cleaned: Vec<StaleMount> = Vec::new();
+  for mount in find(games)? {
+    let game = match games.iter().find(|game| game.appid == mount.appid) {
+      Some(game) => game,
+      None => continue,
+    };
+    if launch::is_running(game) {
+      continue;
+    }
+    ofs::unmount(game, &mount.path)?;
+    cleaned.push(mount);
+  }
+  Ok(cleaned)
+}
+
+//Logs what's left over from the last run, the frontend asks for it itself once it's up
+pub fn report_on_startup() {
+  let games = match Game::all() {
+    Ok(games) => games,
+    Err(e) => {
+      tracing::warn!("Couldn't read the game configs to look for stale mounts: {}", e);
+      return;
+    }
+  };
+  match find(&games) {
+    Ok(stale) if !stale.is_empty() => {
+      for mount in &stale {
+        tracing::warn!("Stale mount left behind for {}: '{}' ({:?})", mount.appid, mount.path.display(), mount.reason);
+      }
+      crate::status::publish("stale-mounts", &stale);
+    }
+    Ok(_) => {}
+    Err(e) => tracing::warn!("Couldn't look for stale mounts: {}", e),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn layers_listed_either_way() {
+    let listed = "rw,relatime,lowerdir=/p/mods/A:/g/Data,upperdir=/p/overwrite,workdir=/p/overwrite.work";
+    let by_fds = "rw,nosuid,nodev,lowerdir+=/p/mods/A,lowerdir+=/g/Data,upperdir=/p/overwrite,workdir=/p/overwrite.work,uuid=on";
+    for options in [listed, by_fds] {
+      assert_eq!(content_layers(options), [Path::new("/p/mods/A"), Path::new("/g/Data"), Path::new("/p/overwrite")]);
+      assert_eq!(layers(options).len(), 4);
+    }
+  }
+}
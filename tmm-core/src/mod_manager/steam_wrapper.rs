@@ -0,0 +1,113 @@
+use crate::mod_manager::{hooks, launch, launch_options, localconfig, monitor, profiles};
+use crate::mod_manager::game::Game;
+use crate::events::Events;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+use std::{env, io};
+
+//The CLI's binary, what the README has Steam run
+const CLI_NAME: &str = "tmm";
+
+//What Steam gets told to run, the CLI installed next to the app or on the PATH. Inside an
+//AppImage the binaries are in a mount that's gone once it exits, the AppImage itself takes
+//'launch' like the CLI does, and so does the app's binary when there's no CLI anywhere
+pub fn path() -> io::Result<PathBuf> {
+  let current = env::current_exe()?;
+  let appimage = env::var_os("APPIMAGE");
+  let next_to = current.with_file_name(CLI_NAME);
+  if appimage.is_none() && next_to.is_file() {
+    return Ok(next_to);
+  }
+  let on_path = env::var_os("PATH").and_then(|paths| {
+    env::split_paths(&paths).map(|dir| dir.join(CLI_NAME)).find(|cli| cli.is_file())
+  });
+  Ok(on_path.or_else(|| appimage.map(PathBuf::from)).unwrap_or(current))
+}
+
+fn marker() -> io::Result<String> {
+  Ok(format!("{} launch %command%", launch_options::quote(&path()?.to_string_lossy())))
+}
+
+//The profile's launch options with the game going through '<manager> launch'
+pub fn steam_string(game: &Game) -> io::Result<String> {
+  let options = launch_options::load(game)?;
+  Ok(launch_options::steam_string(&options).replacen("%command%", &marker()?, 1))
+}
+
+//Whether starting it from Steam goes through the manager, for the account Steam logs in with
+pub fn routed(game: &Game) -> bool {
+  match (localconfig::launch_options(game.appid), marker()) {
+    (Ok(Some(options)), Ok(marker)) => options.contains(&marker),
+    _ => false,
+  }
+}
+
+//Steam gives every game SteamAppId, Proton's command has it as 'AppId=' as well
+fn appid_of(command: &[OsString]) -> Option<u32> {
+  if let Some(appid) = env::var("SteamAppId").ok().and_then(|appid| appid.parse().ok()) {
+    return Some(appid);
+  }
+  command.iter()
+    .filter_map(|arg| arg.to_str()?.strip_prefix("AppId=")?.parse().ok())
+    .next()
+}
+
+fn managed_game(appid: u32) -> Option<Game> {
+  match Game::all() {
+    Ok(games) => games.into_iter().find(|game| game.appid == appid),
+    Err(e) => {
+      tracing::warn!("Couldn't read the game configs: {}", e);
+      None
+    }
+  }
+}
+
+//'<manager> launch %command%', what Steam starts when the game should go through the manager.
+//Deploys the active profile like launch_game does, runs the command Steam gave and waits for it,
+//then does what comes after the game exited. Games the manager doesn't know start as they are.
+//Returns the exit code to exit with
+pub fn run(command: Vec<OsString>) -> i32 {
+  let (program, args) = match command.split_first() {
+    Some(split) => split,
+    None => {
+      tracing::error!("'launch' needs the command to run, '%command%' in the launch options");
+      return 2;
+    }
+  };
+  let game = appid_of(&command).and_then(managed_game);
+  if let Some(game) = &game {
+    //Steam has the game as running while this is what it started for it
+    monitor::wrapping(game.appid);
+    if let Err(e) = hooks::run(game, hooks::HookStage::PreLaunch, None) {
+      tracing::error!("Not starting '{}', a pre-launch hook failed: {}", game.public_name, e);
+      return 1;
+    }
+    //starting it unmodded instead would play on with the saves of a modded profile
+    if let Err(e) = crate::mod_manager::deploy_for_launch(game, &Events::none()) {
+      tracing::error!("Not starting '{}', deploying '{}' failed: {}", game.public_name, profiles::active_name(game), e);
+      return 1;
+    }
+  }
+  let mut child = match Command::new(program).args(args).spawn() {
+    Ok(child) => child,
+    Err(e) => {
+      tracing::error!("Couldn't start {:?}: {}", program, e);
+      return 1;
+    }
+  };
+  let status = match &game {
+    Some(game) => monitor::wait(game.appid, child),
+    None => child.wait(),
+  };
+  if let Some(game) = &game {
+    launch::after_exit(game, None);
+  }
+  match status {
+    Ok(status) => status.code().unwrap_or(1),
+    Err(e) => {
+      tracing::error!("Couldn't wait for {:?}: {}", program, e);
+      1
+    }
+  }
+}
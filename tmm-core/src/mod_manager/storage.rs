@@ -0,0 +1,218 @@
+use crate::mod_manager::{get_directories, get_files, load_mods};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::pool;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::MetadataExt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::time::SystemTime;
+
+use crate::events::{BackendEvent, Events};
+use crate::settings;
+
+//Smaller files aren't worth the time it takes to hash them
+const MIN_DEDUPE_SIZE: u64 = 1_048_576;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageReport {
+  downloads_size: u64,
+  mods_size: u64,
+  quota: Option<u64>,
+  warning: bool,
+  exceeded: bool,
+  suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Suggestion {
+  //an archive that no installed mod came from
+  OrphanDownload { path: PathBuf, size: u64 },
+  //an installed mod that looks like an older version of another one
+  OldVersion { mod_name: String, newer: String, size: u64 },
+  //files with identical contents, all but one could go
+  Duplicate { paths: Vec<PathBuf>, size: u64 },
+}
+
+pub fn report(game: &Game) -> io::Result<StorageReport> {
+  let downloads_size = dir_size(&game.profile_path.join("downloads"))?;
+  let mods_size = dir_size(&game.profile_path.join("mods"))?;
+  let used = downloads_size + mods_size;
+  let (warning, exceeded) = match game.storage_quota {
+    Some(quota) => (used as f64 >= quota as f64 * settings::current().storage.warning_ratio, used > quota),
+    None => (false, false),
+  };
+  //finding suggestions means walking and hashing everything,
+  //so it's only done once they're actually needed
+  let suggestions = if warning { suggestions(game)? } else { Vec::new() };
+  Ok(StorageReport { downloads_size, mods_size, quota: game.storage_quota, warning, exceeded, suggestions })
+}
+
+//The quota is soft: nothing gets blocked, the frontend just gets told
+//about it after something added to the storage
+pub fn check_quota(game: &Game, events: &Events) {
+  if game.storage_quota.is_none() {
+    return;
+  }
+  match report(game) {
+    Ok(report) => {
+      if report.warning {
+        events.send(BackendEvent::StorageQuotaWarning(report));
+      }
+    },
+    Err(e) => {
+      tracing::warn!("Couldn't check the storage quota of '{}': {}", game.public_name, e);
+    }
+  }
+}
+
+fn suggestions(game: &Game) -> io::Result<Vec<Suggestion>> {
+  let mods_path = game.profile_path.join("mods");
+  let downloads_path = game.profile_path.join("downloads");
+  let mods = get_directories(&mods_path)?;
+  let mod_names: Vec<String> = mods.iter().map(|path| file_name(path)).collect();
+  let mut suggestions: Vec<Suggestion> = Vec::new();
+
+  if downloads_path.exists() {
+    for entry in downloads_path.read_dir()? {
+      let path = entry?.path();
+      let name = file_name(&path);
+      //unfinished downloads still have their state file next to them
+      if name.ends_with(".st") || path.with_file_name(format!("{}.st", name)).exists() {
+        continue;
+      }
+      let stem = name.split('.').next().unwrap_or("");
+      if path.is_file() && !mod_names.iter().any(|mod_name| mod_name == stem) {
+        suggestions.push(Suggestion::OrphanDownload { size: fs::metadata(&path)?.len(), path });
+      }
+    }
+  }
+
+  let mut versions: HashMap<&str, Vec<(SystemTime, &PathBuf)>> = HashMap::new();
+  for (path, name) in mods.iter().zip(&mod_names) {
+    let modified = fs::metadata(path)?.modified()?;
+    versions.entry(version_base(name)).or_default().push((modified, path));
+  }
+  for (_, mut group) in versions {
+    group.sort();
+    if let Some((_, newest)) = group.pop() {
+      for (_, path) in group {
+        suggestions.push(Suggestion::OldVersion { mod_name: file_name(path), newer: file_name(newest), size: dir_size(path)? });
+      }
+    }
+  }
+
+  let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+  for path in get_files(&mods_path)?.into_iter().chain(get_files(&downloads_path)?) {
+    let size = fs::symlink_metadata(&path)?.len();
+    if size >= MIN_DEDUPE_SIZE {
+      by_size.entry(size).or_default().push(path);
+    }
+  }
+  for (size, paths) in by_size.into_iter().filter(|(_, paths)| paths.len() > 1) {
+    let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+      by_hash.entry(pool::hash_file(&path)?).or_default().push(path);
+    }
+    for (_, paths) in by_hash.into_iter().filter(|(_, paths)| paths.len() > 1) {
+      suggestions.push(Suggestion::Duplicate { paths, size });
+    }
+  }
+
+  Ok(suggestions)
+}
+
+//Mod folders are named after their archive, which usually looks like
+//'Name-1234-1-2', everything from the first dash followed by a digit is version
+fn version_base(name: &str) -> &str {
+  let bytes = name.as_bytes();
+  for index in 0..bytes.len().saturating_sub(1) {
+    if (bytes[index] == b'-' || bytes[index] == b'_') && bytes[index + 1].is_ascii_digit() {
+      return &name[..index];
+    }
+  }
+  name
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModStats {
+  mod_name: String,
+  //files the pool shares with other mods count for each of them
+  size: u64,
+  files: usize,
+  //of the mod's directories' mtimes and its files' sizes and mtimes
+  signature: String,
+}
+
+fn stats_cache_path(game: &Game) -> PathBuf {
+  game.profile_path.join("mod_stats.json")
+}
+
+fn nanos(time: SystemTime) -> u64 {
+  time.duration_since(SystemTime::UNIX_EPOCH).map(|since| since.as_nanos() as u64).unwrap_or(0)
+}
+
+//Adding, removing or renaming a file changes the mtime of its directory, a file changed in
+//place changes its own size or mtime. Only metadata, nothing gets read
+fn add_signature(path: &Path, hasher: &mut blake3::Hasher) -> io::Result<()> {
+  hasher.update(&nanos(fs::metadata(path)?.modified()?).to_le_bytes());
+  let mut entries: Vec<fs::DirEntry> = path.read_dir()?.collect::<io::Result<Vec<fs::DirEntry>>>()?;
+  entries.sort_by_key(|entry| entry.file_name());
+  for entry in entries {
+    let metadata = entry.metadata()?;
+    if metadata.is_dir() {
+      add_signature(&entry.path(), hasher)?;
+    } else {
+      hasher.update(entry.file_name().to_string_lossy().as_bytes());
+      hasher.update(&metadata.len().to_le_bytes());
+      hasher.update(&nanos(metadata.modified()?).to_le_bytes());
+    }
+  }
+  Ok(())
+}
+
+fn signature(path: &Path) -> io::Result<String> {
+  let mut hasher = blake3::Hasher::new();
+  add_signature(path, &mut hasher)?;
+  Ok(hasher.finalize().to_hex().to_string())
+}
+
+//Only the mods that changed since they were last counted get walked again
+pub fn mod_stats(game: &Game) -> io::Result<Vec<ModStats>> {
+  let cache_path = stats_cache_path(game);
+  let cached: Vec<ModStats> = fs::read_to_string(&cache_path).ok()
+    .and_then(|contents| serde_json::from_str(&contents).ok())
+    .unwrap_or_default();
+  let mut stats: Vec<ModStats> = Vec::new();
+  for elem in load_mods(game)? {
+    let path = game.profile_path.join("mods").join(&elem.name);
+    let signature = signature(&path)?;
+    match cached.iter().find(|cached| cached.mod_name == elem.name && cached.signature == signature) {
+      Some(cached) => stats.push(cached.clone()),
+      None => stats.push(ModStats { size: dir_size(&path)?, files: get_files(&path)?.len(), mod_name: elem.name, signature }),
+    }
+  }
+  let temporary = cache_path.with_extension("json.tmp");
+  fs::write(&temporary, serde_json::to_string(&stats)?)?;
+  fs::rename(temporary, cache_path)?;
+  Ok(stats)
+}
+
+//Files shared through the pool only count once
+fn dir_size(path: &Path) -> io::Result<u64> {
+  let mut size = 0;
+  let mut seen: HashSet<(u64, u64)> = HashSet::new();
+  for file in get_files(path)? {
+    let metadata = fs::symlink_metadata(file)?;
+    if seen.insert((metadata.dev(), metadata.ino())) {
+      size += metadata.len();
+    }
+  }
+  Ok(size)
+}
+
+fn file_name(path: &Path) -> String {
+  path.file_name().unwrap().to_string_lossy().to_string()
+}
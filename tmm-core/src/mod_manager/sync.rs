@@ -0,0 +1,379 @@
+use crate::mod_manager::{check_mod_name, link, load_mods, meta, modlist, overrides, plugins, portable, profiles, versions};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::overrides::OverrideRule;
+use crate::error::{Context, Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{fs, io};
+
+use crate::events::Events;
+
+//Only what the profile is made of gets synced, never the mods' files. Each machine downloads
+//the mods again from their sources, the same way importing an exported profile does
+const FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncSection {
+  //the mods in load order, whether they're enabled and what the user set on them
+  Mods,
+  Overrides,
+  Plugins,
+}
+
+//Which side wins a section both machines changed since they last synced
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncSide {
+  Local,
+  Remote,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SyncedMod {
+  name: String,
+  enabled: bool,
+  //without what's only about this machine's copy, like when it got installed
+  meta: meta::ModMeta,
+}
+
+//'<sync_path>/<appid>/<profile>.json'
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncedProfile {
+  format: u32,
+  synced_by: String,
+  synced_at: Option<u64>,
+  mods: Vec<SyncedMod>,
+  overrides: Vec<OverrideRule>,
+  plugins: plugins::PluginOrder,
+}
+
+//The hashes of a section on both sides as of the last sync, a side that still has its hash
+//didn't change it since
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SectionBase {
+  local: String,
+  remote: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncBase {
+  #[serde(default)]
+  mods: Option<SectionBase>,
+  #[serde(default)]
+  overrides: Option<SectionBase>,
+  #[serde(default)]
+  plugins: Option<SectionBase>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+  appid: u32,
+  profile: String,
+  pushed: Vec<SyncSection>,
+  pulled: Vec<SyncSection>,
+  //changed on both sides and no side was preferred, both are left as they are
+  conflicts: Vec<SyncSection>,
+  //the machine that synced the remote side last
+  remote_synced_by: Option<String>,
+  downloaded: Vec<String>,
+  //on the remote side but not installed here and not from a known source
+  missing: Vec<String>,
+  failed: Vec<Error>,
+}
+
+fn base_path(profile_path: &Path) -> PathBuf {
+  profile_path.join("sync.json")
+}
+
+fn remote_path(sync_path: &Path, game: &Game, profile: &str) -> PathBuf {
+  sync_path.join(game.appid.to_string()).join(format!("{}.json", profile))
+}
+
+fn hash<T: Serialize>(section: &T) -> io::Result<String> {
+  Ok(blake3::hash(&serde_json::to_vec(section)?).to_hex().to_string())
+}
+
+//What the kernel has for it, HOSTNAME otherwise and nothing when neither is there
+fn machine_name() -> String {
+  let mut name = [0u8; 256];
+  if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } == 0 {
+    let length = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+    let name = String::from_utf8_lossy(&name[..length]).trim().to_string();
+    if !name.is_empty() {
+      return name;
+    }
+  }
+  std::env::var("HOSTNAME").map(|name| name.trim().to_string()).unwrap_or_default()
+}
+
+fn synced_meta(meta: meta::ModMeta) -> meta::ModMeta {
+  meta::ModMeta { installed_at: None, archive_hash: None, ..meta }
+}
+
+fn local(game: &Game) -> io::Result<SyncedProfile> {
+  let list = modlist::ModList::load(game)?;
+  let mods = list.sorted(&load_mods(game)?).into_iter()
+    .map(|elem| SyncedMod { name: elem.name, enabled: elem.enabled, meta: synced_meta(elem.meta) })
+    .collect();
+  Ok(SyncedProfile {
+    format: FORMAT,
+    synced_by: machine_name(),
+    synced_at: meta::now(),
+    mods,
+    overrides: overrides::load(game)?,
+    plugins: plugins::order(game)?,
+  })
+}
+
+fn git(sync_path: &Path, args: &[&str]) -> io::Result<String> {
+  let output = match Command::new("git").arg("-C").arg(sync_path).args(args).output() {
+    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+      return Err(Error::new(ErrorCode::ExtractorMissing, "git has to be installed to sync through a repository")
+        .param("extractor", "git")
+        .into_io(io::ErrorKind::NotFound));
+    },
+    result => result?,
+  };
+  if !output.status.success() {
+    return Err(Error::new(ErrorCode::SyncFailed, String::from_utf8_lossy(&output.stderr).trim().to_string())
+      .param("path", sync_path)
+      .into_io(io::ErrorKind::Other));
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+//A sync directory that's a git repository gets pulled before and pushed after,
+//without a remote it's only committed to
+fn is_repository(sync_path: &Path) -> bool {
+  sync_path.join(".git").exists()
+}
+
+fn has_remote(sync_path: &Path) -> io::Result<bool> {
+  Ok(!git(sync_path, &["remote"])?.trim().is_empty())
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+  Same,
+  Push,
+  Pull,
+  Conflict,
+}
+
+fn compare(local: &str, remote: &str, base: Option<&SectionBase>, prefer: Option<SyncSide>) -> Outcome {
+  if local == remote {
+    return Outcome::Same;
+  }
+  let local_changed = base.map_or(true, |base| base.local != local);
+  let remote_changed = base.map_or(true, |base| base.remote != remote);
+  match (local_changed, remote_changed, prefer) {
+    //what the last pull couldn't take in, e.g. mods that failed to download, gets pulled again
+    (false, false, _) => Outcome::Pull,
+    (true, false, _) => Outcome::Push,
+    (false, true, _) => Outcome::Pull,
+    (true, true, Some(SyncSide::Local)) => Outcome::Push,
+    (true, true, Some(SyncSide::Remote)) => Outcome::Pull,
+    (true, true, None) => Outcome::Conflict,
+  }
+}
+
+//Where a section goes after comparing both sides, the new base of it. Pulling takes in the
+//remote side and says what the local side's hash is afterwards
+fn sync_section<T: Serialize>(
+  section: SyncSection,
+  local_hash: String,
+  remote: Option<&T>,
+  base: Option<&SectionBase>,
+  prefer: Option<SyncSide>,
+  report: &mut SyncReport,
+  pull: impl FnOnce(&T, &mut SyncReport) -> io::Result<String>,
+) -> io::Result<Option<SectionBase>> {
+  let (remote, remote_hash) = match remote {
+    Some(remote) => (remote, hash(remote)?),
+    //nothing synced yet, or not from this profile
+    None => {
+      report.pushed.push(section);
+      return Ok(Some(SectionBase { local: local_hash.clone(), remote: local_hash }));
+    }
+  };
+  Ok(match compare(&local_hash, &remote_hash, base, prefer) {
+    Outcome::Same => Some(SectionBase { local: local_hash.clone(), remote: local_hash }),
+    Outcome::Push => {
+      report.pushed.push(section);
+      Some(SectionBase { local: local_hash.clone(), remote: local_hash })
+    },
+    Outcome::Pull => {
+      let local_hash = pull(remote, report)?;
+      report.pulled.push(section);
+      Some(SectionBase { local: local_hash, remote: remote_hash })
+    },
+    Outcome::Conflict => {
+      report.conflicts.push(section);
+      base.cloned()
+    },
+  })
+}
+
+//Mods that are installed get the remote side's order, enabled and user fields,
+//the others get downloaded if their source is known. Mods only installed here stay after the rest
+fn pull_mods(game: &Game, mods: &[SyncedMod], events: &Events, report: &mut SyncReport) -> io::Result<()> {
+  //the names become folders, nothing of the remote side is taken in if one can't be
+  for synced in mods {
+    check_mod_name(&synced.name)?;
+  }
+  let installed: Vec<String> = load_mods(game)?.into_iter().map(|elem| elem.name).collect();
+  for synced in mods {
+    if installed.contains(&synced.name) {
+      let mod_dir = versions::active(game, &synced.name)?;
+      meta::save(&mod_dir, &portable::with_user_fields(meta::load(&mod_dir), &synced.meta))?;
+      continue;
+    }
+    let url = match synced.meta.source_url.as_deref() {
+      Some(url) if synced.meta.source.is_some() => url,
+      _ => {
+        report.missing.push(synced.name.clone());
+        continue;
+      }
+    };
+    match portable::download(game, &synced.name, &synced.meta, url, events) {
+      Ok(_) => report.downloaded.push(synced.name.clone()),
+      Err(e) => report.failed.push(Error::io(Context::Sync, e).param("mod_name", &synced.name)),
+    }
+  }
+  let mut list = modlist::ModList::load(game)?;
+  list.set_order(&mods.iter().map(|synced| synced.name.clone()).collect::<Vec<String>>());
+  for synced in mods {
+    list.set_enabled(&synced.name, synced.enabled);
+  }
+  list.save(game)
+}
+
+pub fn sync(game: &Game, profile: &str, prefer: Option<SyncSide>, events: &Events) -> io::Result<SyncReport> {
+  let sync_path = match &game.sync_path {
+    Some(sync_path) => sync_path,
+    None => {
+      return Err(Error::new(ErrorCode::NoSyncPath, format!("'{}' has no sync directory set", game.public_name))
+        .param("appid", game.appid)
+        .into_io(io::ErrorKind::NotFound));
+    }
+  };
+  let profile_game = Game { profile_path: profiles::existing(game, profile)?, ..game.clone() };
+  let repository = is_repository(sync_path);
+  let remote_repository = repository && has_remote(sync_path)?;
+  if remote_repository {
+    git(sync_path, &["pull", "--rebase", "--autostash"])?;
+  }
+
+  let remote_file = remote_path(sync_path, game, profile);
+  let remote: Option<SyncedProfile> = match fs::read_to_string(&remote_file) {
+    Ok(contents) => Some(serde_json::from_str(&contents)?),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+    Err(e) => return Err(e),
+  };
+  let base: SyncBase = fs::read_to_string(base_path(&profile_game.profile_path)).ok()
+    .and_then(|contents| serde_json::from_str(&contents).ok())
+    .unwrap_or_default();
+  let mut merged = local(&profile_game)?;
+  let mut report = SyncReport {
+    appid: game.appid,
+    profile: profile.to_string(),
+    remote_synced_by: remote.as_ref().map(|remote| remote.synced_by.clone()),
+    ..SyncReport::default()
+  };
+  let mut new_base = SyncBase::default();
+
+  //a pulled section can still differ here afterwards, e.g. mods that couldn't be downloaded,
+  //as long as neither side changes it the next sync pulls again instead of pushing that
+  let remote_mods = remote.as_ref().map(|remote| &remote.mods);
+  new_base.mods = sync_section(SyncSection::Mods, hash(&merged.mods)?, remote_mods, base.mods.as_ref(), prefer, &mut report, |mods, report| {
+    pull_mods(&profile_game, mods, events, report)?;
+    merged.mods = mods.clone();
+    hash(&local(&profile_game)?.mods)
+  })?;
+  let remote_overrides = remote.as_ref().map(|remote| &remote.overrides);
+  new_base.overrides = sync_section(SyncSection::Overrides, hash(&merged.overrides)?, remote_overrides, base.overrides.as_ref(), prefer, &mut report, |overrides, _| {
+    overrides::save(&profile_game, overrides)?;
+    merged.overrides = overrides.clone();
+    hash(overrides)
+  })?;
+  let remote_plugins = remote.as_ref().map(|remote| &remote.plugins);
+  new_base.plugins = sync_section(SyncSection::Plugins, hash(&merged.plugins)?, remote_plugins, base.plugins.as_ref(), prefer, &mut report, |plugins, _| {
+    plugins::set_order(&profile_game, plugins.clone())?;
+    merged.plugins = plugins.clone();
+    hash(plugins)
+  })?;
+
+  //what's in conflict stays as the remote side has it until it's resolved
+  if let Some(remote) = remote {
+    if report.conflicts.contains(&SyncSection::Mods) {
+      merged.mods = remote.mods;
+    }
+    if report.conflicts.contains(&SyncSection::Overrides) {
+      merged.overrides = remote.overrides;
+    }
+    if report.conflicts.contains(&SyncSection::Plugins) {
+      merged.plugins = remote.plugins;
+    }
+  }
+  if !report.pushed.is_empty() {
+    if let Some(parent) = remote_file.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    let temporary = remote_file.with_extension("json.tmp");
+    fs::write(&temporary, serde_json::to_string_pretty(&merged)?)?;
+    fs::rename(&temporary, &remote_file)?;
+    if repository {
+      let relative = link::relative(&remote_file, sync_path)?.to_string_lossy().to_string();
+      git(sync_path, &["add", &relative])?;
+      git(sync_path, &["commit", "-m", &format!("Sync {} '{}' from {}", game.public_name, profile, merged.synced_by)])?;
+      if remote_repository {
+        git(sync_path, &["push"])?;
+      }
+    }
+  }
+  fs::write(base_path(&profile_game.profile_path), serde_json::to_string(&new_base)?)?;
+  Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn base(local: &str, remote: &str) -> SectionBase {
+    SectionBase { local: local.to_string(), remote: remote.to_string() }
+  }
+
+  #[test]
+  fn the_side_that_changed_wins() {
+    assert_eq!(compare("a", "a", None, None), Outcome::Same);
+    assert_eq!(compare("b", "a", Some(&base("a", "a")), None), Outcome::Push);
+    assert_eq!(compare("a", "b", Some(&base("a", "a")), None), Outcome::Pull);
+    assert_eq!(compare("partial", "full", Some(&base("partial", "full")), None), Outcome::Pull);
+    assert_eq!(compare("partial", "full", Some(&base("partial", "full")), Some(SyncSide::Local)), Outcome::Pull);
+    assert_eq!(compare("b", "c", Some(&base("a", "a")), None), Outcome::Conflict);
+    assert_eq!(compare("b", "c", Some(&base("a", "a")), Some(SyncSide::Local)), Outcome::Push);
+    assert_eq!(compare("b", "c", Some(&base("a", "a")), Some(SyncSide::Remote)), Outcome::Pull);
+    //never synced, both sides count as changed
+    assert_eq!(compare("b", "c", None, None), Outcome::Conflict);
+  }
+
+  #[test]
+  fn a_conflict_keeps_the_base() {
+    let mut report = SyncReport::default();
+    let old = base("a", "a");
+    let remote = vec!["remote".to_string()];
+    let new = sync_section(SyncSection::Mods, "local".to_string(), Some(&remote), Some(&old), None, &mut report, |_, _| unreachable!()).unwrap();
+    assert_eq!(report.conflicts, [SyncSection::Mods]);
+    assert!(matches!(new, Some(SectionBase { local, .. }) if local == "a"));
+
+    let new = sync_section(SyncSection::Plugins, "local".to_string(), Some(&remote), Some(&old), Some(SyncSide::Remote), &mut report, |_, _| Ok("pulled".to_string())).unwrap();
+    assert_eq!(report.pulled, [SyncSection::Plugins]);
+    assert!(matches!(new, Some(SectionBase { local, remote }) if local == "pulled" && remote == hash(&vec!["remote".to_string()]).unwrap()));
+
+    let new = sync_section::<Vec<String>>(SyncSection::Overrides, "local".to_string(), None, None, None, &mut report, |_, _| unreachable!()).unwrap();
+    assert_eq!(report.pushed, [SyncSection::Overrides]);
+    assert!(matches!(new, Some(SectionBase { local, remote }) if local == "local" && remote == "local"));
+  }
+}
@@ -0,0 +1,75 @@
+use crate::mod_manager::launch;
+use crate::mod_manager::game::{Game, Tool};
+use crate::error::{Error, ErrorCode};
+use std::io;
+use std::process::Child;
+
+fn not_found(game: &Game, name: &str) -> io::Error {
+  Error::new(ErrorCode::ToolNotFound, format!("'{}' has no tool '{}'", game.public_name, name))
+    .param("tool", name)
+    .into_io(io::ErrorKind::NotFound)
+}
+
+fn validate(tool: &Tool) -> io::Result<()> {
+  if tool.name.trim().is_empty() || tool.path.as_os_str().is_empty() {
+    return Err(Error::new(ErrorCode::InvalidTool, "a tool needs a name and a path")
+      .param("tool", &tool.name)
+      .into_io(io::ErrorKind::InvalidInput));
+  }
+  Ok(())
+}
+
+pub fn add(game: Game, tool: Tool) -> io::Result<Game> {
+  validate(&tool)?;
+  if game.tools.iter().any(|known| known.name == tool.name) {
+    return Err(Error::new(ErrorCode::ToolExists, format!("there already is a tool called '{}'", tool.name))
+      .param("tool", &tool.name)
+      .into_io(io::ErrorKind::AlreadyExists));
+  }
+  let mut tools = game.tools.clone();
+  tools.push(tool);
+  let game = Game { tools, ..game };
+  game.save()?;
+  Ok(game)
+}
+
+//Renaming it is fine as long as the new name isn't another tool's
+pub fn update(game: Game, name: &str, tool: Tool) -> io::Result<Game> {
+  validate(&tool)?;
+  if tool.name != name && game.tools.iter().any(|known| known.name == tool.name) {
+    return Err(Error::new(ErrorCode::ToolExists, format!("there already is a tool called '{}'", tool.name))
+      .param("tool", &tool.name)
+      .into_io(io::ErrorKind::AlreadyExists));
+  }
+  let mut tools = game.tools.clone();
+  match tools.iter_mut().find(|known| known.name == name) {
+    Some(known) => *known = tool,
+    None => return Err(not_found(&game, name)),
+  }
+  let game = Game { tools, ..game };
+  game.save()?;
+  Ok(game)
+}
+
+pub fn remove(game: Game, name: &str) -> io::Result<Game> {
+  if !game.tools.iter().any(|known| known.name == name) {
+    return Err(not_found(&game, name));
+  }
+  let tools = game.tools.iter().filter(|known| known.name != name).cloned().collect();
+  let game = Game { tools, ..game };
+  game.save()?;
+  Ok(game)
+}
+
+//A relative path is inside the game directory as it's deployed, like the game's own
+//executables, xEdit and such are often put next to the game
+pub fn run(game: &Game, name: &str) -> io::Result<Child> {
+  let tool = game.tools.iter().find(|known| known.name == name).ok_or_else(|| not_found(game, name))?;
+  let binary = if tool.path.is_absolute() { tool.path.clone() } else { launch::game_root(game).join(&tool.path) };
+  let working_dir = match &tool.working_dir {
+    Some(working_dir) if working_dir.is_absolute() => working_dir.clone(),
+    Some(working_dir) => launch::game_root(game).join(working_dir),
+    None => binary.parent().map(|parent| parent.to_path_buf()).unwrap_or_else(|| launch::game_root(game)),
+  };
+  launch::spawn(game, &binary, tool.use_compatibility, &tool.arguments, &working_dir)
+}
@@ -0,0 +1,164 @@
+use crate::mod_manager::{check_mod_name, meta, modlist, monitor, overrides, validate_mod_name, versions};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::overrides::OverrideRule;
+use crate::error::{Error, ErrorCode};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::{fs, io};
+
+const ENTRY_FILE_NAME: &str = "entry.json";
+
+//A removed mod, with what's needed to put it back where it was
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+  id: String,
+  mod_name: String,
+  //seconds since the epoch
+  removed_at: u64,
+  enabled: bool,
+  //where it was in the load order
+  position: Option<usize>,
+  rules: Vec<OverrideRule>,
+  //the one of its versions that was in use
+  #[serde(default)]
+  version: Option<String>,
+}
+
+fn trash_path(game: &Game) -> PathBuf {
+  game.profile_path.join("trash")
+}
+
+fn not_in_trash(id: &str) -> io::Error {
+  Error::new(ErrorCode::NotInTrash, format!("there is no '{}' in the trash", id))
+    .param("id", id)
+    .into_io(io::ErrorKind::NotFound)
+}
+
+//Moved next to the mods in the same profile, so it's a rename and nothing gets copied.
+//Its load order entry and override rules go with it
+pub fn remove(game: &Game, mod_name: &str) -> io::Result<TrashEntry> {
+  monitor::ensure_not_running(game)?;
+  check_mod_name(mod_name)?;
+  let mod_dir = versions::mod_root(game, mod_name);
+  if mod_name.is_empty() || fs::symlink_metadata(&mod_dir).is_err() {
+    return Err(Error::new(ErrorCode::ModNotFound, format!("'{}' isn't installed", mod_name))
+      .param("mod_name", mod_name)
+      .into_io(io::ErrorKind::NotFound));
+  }
+  let removed_at = meta::now().unwrap_or(0);
+  let mut modlist = modlist::ModList::load(game)?;
+  let rules = overrides::load(game)?;
+  let entry = TrashEntry {
+    id: format!("{}-{}", removed_at, mod_name),
+    mod_name: mod_name.to_string(),
+    removed_at,
+    enabled: modlist.is_enabled(mod_name),
+    position: modlist.mods.iter().position(|entry| entry.name == mod_name),
+    rules: rules.iter().filter(|rule| rule.mod_name == mod_name).cloned().collect(),
+    version: versions::active(game, mod_name)?.strip_prefix(&mod_dir).ok().map(|version| version.to_string_lossy().to_string()).filter(|version| !version.is_empty()),
+  };
+  let entry_path = trash_path(game).join(&entry.id);
+  fs::create_dir_all(&entry_path)?;
+  fs::write(entry_path.join(ENTRY_FILE_NAME), serde_json::to_string(&entry)?)?;
+  meta::move_mod(&mod_dir, &entry_path.join("mod"))?;
+
+  modlist.remove(mod_name);
+  modlist.save(game)?;
+  let kept: Vec<OverrideRule> = rules.into_iter().filter(|rule| rule.mod_name != mod_name).collect();
+  overrides::save(game, &kept)?;
+  expire(game)?;
+  Ok(entry)
+}
+
+//Newest first
+pub fn list(game: &Game) -> io::Result<Vec<TrashEntry>> {
+  let mut entries: Vec<TrashEntry> = Vec::new();
+  let path = trash_path(game);
+  if !path.is_dir() {
+    return Ok(entries);
+  }
+  for dir in path.read_dir()? {
+    let dir = dir?;
+    let entry: Option<TrashEntry> = fs::read_to_string(dir.path().join(ENTRY_FILE_NAME)).ok().and_then(|contents| serde_json::from_str(&contents).ok());
+    match entry {
+      //the id is what gets joined onto the trash path later, it has to be the directory it's in
+      Some(entry) if dir.file_name() == entry.id.as_str() => entries.push(entry),
+      _ => tracing::warn!("'{}' in the trash isn't a removed mod", dir.path().display()),
+    }
+  }
+  entries.sort_by_key(|entry| std::cmp::Reverse(entry.removed_at));
+  Ok(entries)
+}
+
+//Back under its old name unless it's given another one, e.g. because a mod took the name since
+pub fn restore(game: &Game, id: &str, new_name: Option<&str>) -> io::Result<String> {
+  let entry = list(game)?.into_iter().find(|entry| entry.id == id).ok_or_else(|| not_in_trash(id))?;
+  let name = new_name.unwrap_or(&entry.mod_name).to_string();
+  validate_mod_name(game, &name)?;
+  let entry_path = trash_path(game).join(&entry.id);
+  //with all of its versions
+  meta::move_mod(&entry_path.join("mod"), &versions::mod_root(game, &name))?;
+
+  let mut modlist = modlist::ModList::load(game)?;
+  let restored = modlist::ModEntry { name: name.clone(), enabled: entry.enabled, version: entry.version, usage: modlist::Usage::default() };
+  match entry.position {
+    Some(position) => modlist.mods.insert(position.min(modlist.mods.len()), restored),
+    None => modlist.mods.push(restored),
+  }
+  modlist.save(game)?;
+  let mut rules = overrides::load(game)?;
+  for rule in entry.rules {
+    if !rules.iter().any(|existing| existing.path == rule.path) {
+      rules.push(OverrideRule { mod_name: name.clone(), ..rule });
+    }
+  }
+  overrides::save(game, &rules)?;
+  fs::remove_dir_all(entry_path)?;
+  Ok(name)
+}
+
+fn delete(game: &Game, entry: &TrashEntry) -> io::Result<()> {
+  fs::remove_dir_all(trash_path(game).join(&entry.id))
+}
+
+//Everything with no id, otherwise only that one. Only what's listed can go, an id is never a path
+pub fn empty(game: &Game, id: Option<&str>) -> io::Result<()> {
+  match id {
+    Some(id) => {
+      let entry = list(game)?.into_iter().find(|entry| entry.id == id).ok_or_else(|| not_in_trash(id))?;
+      delete(game, &entry)
+    },
+    None => {
+      for entry in list(game)? {
+        delete(game, &entry)?;
+      }
+      Ok(())
+    }
+  }
+}
+
+//Mods that were put in the trash before they had their versions in their folders
+pub(crate) fn nest(game: &Game) -> io::Result<()> {
+  for mut entry in list(game)?.into_iter().filter(|entry| entry.version.is_none()) {
+    let entry_path = trash_path(game).join(&entry.id);
+    if fs::symlink_metadata(entry_path.join("mod")).is_ok() {
+      entry.version = Some(versions::nest(&entry_path.join("mod"))?);
+      fs::write(entry_path.join(ENTRY_FILE_NAME), serde_json::to_string(&entry)?)?;
+    }
+  }
+  Ok(())
+}
+
+//Deletes what's been in the trash longer than the game keeps it
+pub fn expire(game: &Game) -> io::Result<()> {
+  let days = game.trash_retention_days.unwrap_or_else(|| settings::current().trash.retention_days);
+  if days == 0 {
+    return Ok(());
+  }
+  let cutoff = meta::now().unwrap_or(0).saturating_sub(days * 86_400);
+  for entry in list(game)?.into_iter().filter(|entry| entry.removed_at < cutoff) {
+    delete(game, &entry)?;
+  }
+  Ok(())
+}
@@ -0,0 +1,131 @@
+use crate::mod_manager::game::{Game, Store, VersionChange};
+use crate::mod_manager::{deploy, purge_deployed};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{fs, thread};
+
+use crate::events::{BackendEvent, Events};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+//Steam's StateFlags bits for everything between "an update is needed" and
+//"the update is written", any of them means the game directory is about to change
+const UPDATE_FLAGS: u64 = 2 //UpdateRequired
+  | 256 //UpdateRunning
+  | 1024 //UpdateStarted
+  | 131_072 //Validating
+  | 1_048_576 //Downloading
+  | 2_097_152 //Staging
+  | 4_194_304; //Committing
+
+static WATCHER: OnceCell<()> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct ManifestState {
+  state_flags: u64,
+  buildid: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateEvent {
+  appid: u32,
+  state_flags: u64,
+  deployed: bool,
+  //games with auto purge on get their deployment taken down before Steam touches them
+  purged: bool,
+}
+
+fn manifest_path(game: &Game) -> Option<PathBuf> {
+  //install_path is '<library>/steamapps/common/<installdir>'
+  let library = game.library.clone().or_else(|| game.install_path.ancestors().nth(3).map(PathBuf::from))?;
+  Some(library.join("steamapps").join(format!("appmanifest_{}.acf", game.appid)))
+}
+
+//Just the two top level values, they're always '"key"  "value"' on their own line
+fn read_state(game: &Game) -> Option<ManifestState> {
+  let contents = fs::read_to_string(manifest_path(game)?).ok()?;
+  let mut state = ManifestState::default();
+  for line in contents.lines() {
+    let parts: Vec<&str> = line.split('"').filter(|part| !part.trim().is_empty()).collect();
+    match parts.as_slice() {
+      ["StateFlags", value] => state.state_flags = value.parse().ok()?,
+      ["buildid", value] => state.buildid = value.parse().ok(),
+      _ => {}
+    }
+  }
+  Some(state)
+}
+
+fn updating(state: &ManifestState) -> bool {
+  state.state_flags & UPDATE_FLAGS != 0
+}
+
+fn on_update_started(game: &Game, state: &ManifestState, events: &Events) {
+  let backend = deploy::backend(game.deploy_mode);
+  let deployed = backend.status(game).map(|status| status.deployed).unwrap_or(false);
+  //Steam would patch or "verify" mod files into its depot cache otherwise
+  let purged = deployed && game.auto_purge && match purge_deployed(game, events) {
+    Ok(()) => true,
+    Err(e) => {
+      tracing::warn!("Couldn't purge '{}' before its update: {}", game.public_name, e);
+      false
+    }
+  };
+  events.send(BackendEvent::GameUpdateStarted(UpdateEvent { appid: game.appid, state_flags: state.state_flags, deployed, purged }));
+}
+
+fn on_build_changed(game: &Game, buildid: u64, events: &Events) {
+  let current = buildid.to_string();
+  let updated = Game { version: Some(current.clone()), ..game.clone() };
+  if let Err(e) = updated.save() {
+    tracing::warn!("Couldn't record the new build of '{}': {}", game.public_name, e);
+  }
+  if let Some(previous) = &game.version {
+    events.send(BackendEvent::GameVersionChanged(VersionChange { appid: game.appid, previous: previous.clone(), current }));
+  }
+}
+
+fn poll(states: &mut HashMap<u32, ManifestState>, events: &Events) {
+  let games = match Game::all() {
+    Ok(games) => games,
+    Err(e) => {
+      tracing::warn!("Couldn't load the games to watch for updates: {}", e);
+      return;
+    }
+  };
+  for game in games.into_iter().filter(|game| game.store == Store::Steam) {
+    let state = match read_state(&game) {
+      Some(state) => state,
+      None => continue,
+    };
+    //the first look at a manifest only sets what later ones get compared to
+    if let Some(previous) = states.insert(game.appid, state) {
+      if updating(&state) && !updating(&previous) {
+        on_update_started(&game, &state, events);
+      }
+      if let Some(buildid) = state.buildid {
+        if previous.buildid != Some(buildid) && game.version.as_deref() != Some(buildid.to_string().as_str()) {
+          on_build_changed(&game, buildid, events);
+        }
+      }
+    }
+  }
+}
+
+//Keeps an eye on the appmanifests of all managed Steam games, polling since
+//Steam rewrites them in place. Only ever starts once
+pub fn watch(events: Events) {
+  if WATCHER.set(()).is_err() {
+    return;
+  }
+  thread::spawn(move || {
+    let mut states: HashMap<u32, ManifestState> = HashMap::new();
+    loop {
+      poll(&mut states, &events);
+      thread::sleep(POLL_INTERVAL);
+    }
+  });
+}
@@ -0,0 +1,248 @@
+use std::iter::Peekable;
+use std::path::Path;
+use std::{fs, io};
+
+//Steam's KeyValues text format, enough of it to change one value and write the rest back as it was.
+//Steam reads it back with any whitespace, so only the order and the values have to survive
+#[derive(Debug, Clone)]
+pub enum Vdf {
+  Value(String),
+  Block(Vec<(String, Vdf)>),
+  //one with a conditional like [$WIN32] after it, only other platforms care what it says but it's written back
+  Conditional(Box<Vdf>, String),
+}
+
+impl Vdf {
+  //What it is without the conditional
+  pub fn inner(&self) -> &Vdf {
+    match self {
+      Vdf::Conditional(inner, _) => inner.inner(),
+      vdf => vdf,
+    }
+  }
+
+  fn inner_mut(&mut self) -> &mut Vdf {
+    match self {
+      Vdf::Conditional(inner, _) => inner.inner_mut(),
+      vdf => vdf,
+    }
+  }
+}
+
+enum Token {
+  Text(String),
+  Open,
+  Close,
+  Condition(String),
+}
+
+fn tokenize(contents: &str) -> io::Result<Vec<Token>> {
+  let mut tokens: Vec<Token> = Vec::new();
+  let mut chars = contents.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      c if c.is_whitespace() => {},
+      '{' => tokens.push(Token::Open),
+      '}' => tokens.push(Token::Close),
+      '/' if chars.peek() == Some(&'/') => {
+        while chars.peek().map_or(false, |c| *c != '\n') {
+          chars.next();
+        }
+      },
+      '[' => {
+        let mut condition = String::new();
+        loop {
+          match chars.next() {
+            Some(']') => break,
+            Some(c) => condition.push(c),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "unterminated conditional in the vdf")),
+          }
+        }
+        tokens.push(Token::Condition(condition));
+      },
+      '"' => {
+        let mut text = String::new();
+        loop {
+          match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+              Some('n') => text.push('\n'),
+              Some('t') => text.push('\t'),
+              Some(escaped) => text.push(escaped),
+              None => break,
+            },
+            Some(c) => text.push(c),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "unterminated string in the vdf")),
+          }
+        }
+        tokens.push(Token::Text(text));
+      },
+      c => {
+        let mut text = c.to_string();
+        while chars.peek().map_or(false, |c| !c.is_whitespace() && !matches!(c, '{' | '}' | '"' | '[')) {
+          text.push(chars.next().unwrap());
+        }
+        tokens.push(Token::Text(text));
+      },
+    }
+  }
+  Ok(tokens)
+}
+
+fn condition(tokens: &mut Peekable<std::vec::IntoIter<Token>>) -> Option<String> {
+  match tokens.peek() {
+    Some(Token::Condition(_)) => match tokens.next() {
+      Some(Token::Condition(condition)) => Some(condition),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn parse_block(tokens: &mut Peekable<std::vec::IntoIter<Token>>, nested: bool) -> io::Result<Vec<(String, Vdf)>> {
+  let mut entries: Vec<(String, Vdf)> = Vec::new();
+  loop {
+    let key = match tokens.next() {
+      Some(Token::Text(key)) => key,
+      Some(Token::Close) if nested => return Ok(entries),
+      None if !nested => return Ok(entries),
+      _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "expected a key in the vdf")),
+    };
+    //after the key of a block or after the value
+    let before = condition(tokens);
+    let value = match tokens.next() {
+      Some(Token::Text(value)) => Vdf::Value(value),
+      Some(Token::Open) => Vdf::Block(parse_block(tokens, true)?),
+      _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("'{}' has no value in the vdf", key))),
+    };
+    let value = match before.or_else(|| condition(tokens)) {
+      Some(condition) => Vdf::Conditional(Box::new(value), condition),
+      None => value,
+    };
+    entries.push((key, value));
+  }
+}
+
+//Back to what tokenize reads, launch options can have newlines and tabs in them
+fn escape(text: &str) -> String {
+  text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\t', "\\t")
+}
+
+pub fn render(entries: &[(String, Vdf)], depth: usize, output: &mut String) {
+  let indent = "\t".repeat(depth);
+  for (key, value) in entries {
+    let condition = match value {
+      Vdf::Conditional(_, condition) => format!(" [{}]", condition),
+      _ => String::new(),
+    };
+    match value.inner() {
+      Vdf::Value(value) => output.push_str(&format!("{}\"{}\"\t\t\"{}\"{}\n", indent, escape(key), escape(value), condition)),
+      Vdf::Block(children) => {
+        output.push_str(&format!("{}\"{}\"{}\n{}{{\n", indent, escape(key), condition, indent));
+        render(children, depth + 1, output);
+        output.push_str(&format!("{}}}\n", indent));
+      },
+      Vdf::Conditional(..) => unreachable!(),
+    }
+  }
+}
+
+//Keys are case insensitive to Steam, it writes 'apps' and 'Apps' depending on the version
+pub fn child<'a>(entries: &'a [(String, Vdf)], key: &str) -> Option<&'a Vdf> {
+  entries.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)).map(|(_, value)| value.inner())
+}
+
+pub fn block_mut<'a>(entries: &'a mut Vec<(String, Vdf)>, key: &str) -> &'a mut Vec<(String, Vdf)> {
+  let index = match entries.iter().position(|(name, value)| name.eq_ignore_ascii_case(key) && matches!(value.inner(), Vdf::Block(_))) {
+    Some(index) => index,
+    None => {
+      entries.push((key.to_string(), Vdf::Block(Vec::new())));
+      entries.len() - 1
+    }
+  };
+  match entries[index].1.inner_mut() {
+    Vdf::Block(children) => children,
+    _ => unreachable!(),
+  }
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<(String, Vdf)>> {
+  let mut tokens = tokenize(&fs::read_to_string(path)?)?.into_iter().peekable();
+  parse_block(&mut tokens, false)
+}
+
+//The value at the end of the keys, None if any of them isn't there
+pub fn lookup<'a>(entries: &'a [(String, Vdf)], keys: &[&str]) -> Option<&'a Vdf> {
+  let (last, blocks) = keys.split_last()?;
+  let mut current = entries;
+  for key in blocks {
+    current = match child(current, key)? {
+      Vdf::Block(children) => children,
+      _ => return None,
+    };
+  }
+  child(current, last)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(contents: &str) -> io::Result<Vec<(String, Vdf)>> {
+    parse_block(&mut tokenize(contents)?.into_iter().peekable(), false)
+  }
+
+  const LOCALCONFIG: &str = r#"
+"UserLocalConfigStore"
+{
+  //written by Steam
+  "Software" { "Valve" { "Steam" { "Apps" { "489830" { "LaunchOptions" "-skipintro \"%command%\"" } } } } }
+  "Overlay" [$WIN32]
+  {
+    "Enabled"  1
+  }
+  "Path" "C:\\Games" [$WINDOWS]
+}
+"#;
+
+  #[test]
+  fn reads_nested_blocks_case_insensitive() {
+    let entries = parse(LOCALCONFIG).unwrap();
+    let keys = ["userlocalconfigstore", "software", "valve", "steam", "apps", "489830", "launchoptions"];
+    assert!(matches!(lookup(&entries, &keys), Some(Vdf::Value(value)) if value == "-skipintro \"%command%\""));
+    assert!(matches!(lookup(&entries, &["UserLocalConfigStore", "Overlay", "Enabled"]), Some(Vdf::Value(value)) if value == "1"));
+    assert!(matches!(lookup(&entries, &["UserLocalConfigStore", "Path"]), Some(Vdf::Value(value)) if value == "C:\\Games"));
+    assert!(lookup(&entries, &["UserLocalConfigStore", "Missing", "Enabled"]).is_none());
+  }
+
+  #[test]
+  fn renders_what_it_reads() {
+    let mut entries = parse(LOCALCONFIG).unwrap();
+    let mut current = &mut entries;
+    for key in ["UserLocalConfigStore", "Software", "Valve", "Steam", "apps", "72850"] {
+      current = block_mut(current, key);
+    }
+    current.push(("LaunchOptions".to_string(), Vdf::Value("a\tb\nc \\ \"d\"".to_string())));
+    let mut contents = String::new();
+    render(&entries, 0, &mut contents);
+    assert!(contents.contains("\"Overlay\" [$WIN32]\n"));
+    assert!(contents.contains("\"C:\\\\Games\" [$WINDOWS]\n"));
+
+    let again = parse(&contents).unwrap();
+    let mut rendered = String::new();
+    render(&again, 0, &mut rendered);
+    assert_eq!(rendered, contents);
+    let keys = ["UserLocalConfigStore", "Software", "Valve", "Steam", "Apps", "72850", "LaunchOptions"];
+    assert!(matches!(lookup(&again, &keys), Some(Vdf::Value(value)) if value == "a\tb\nc \\ \"d\""));
+    //the apps block was found, not made again next to the one there
+    assert!(matches!(lookup(&again, &keys[..5]), Some(Vdf::Block(apps)) if apps.len() == 2));
+  }
+
+  #[test]
+  fn unterminated_is_invalid() {
+    assert_eq!(parse("\"key\" \"value").unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(parse("\"key\" \"value\" [$WIN32").unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(parse("\"block\" { \"key\" \"value\"").unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(parse("\"key\"").unwrap_err().kind(), io::ErrorKind::InvalidData);
+  }
+}
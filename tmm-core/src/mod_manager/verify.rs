@@ -0,0 +1,86 @@
+use crate::mod_manager::{Mod, get_files, link, merge, mod_dir, ofs, overrides, pool};
+use crate::mod_manager::game::Game;
+use crate::mod_manager::{baseline, case, conflicts};
+use crate::tasks;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+  //files that matched the mod they came from
+  verified: usize,
+  //files a mod should have put there that aren't there
+  missing: Vec<PathBuf>,
+  //files that are there but don't match the winning mod anymore
+  modified: Vec<ModifiedFile>,
+  //files that are neither from a mod nor from the game, only
+  //known once a baseline was taken
+  orphaned: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModifiedFile {
+  path: PathBuf,
+  mod_name: String,
+}
+
+//Checks what's in the game directory against what the last deployment of `mods`
+//should have put there. The generated mods are taken as they are on disk instead
+//of being refreshed, that's what got deployed
+pub fn verify(game: &Game, mods: &[Mod]) -> io::Result<VerifyReport> {
+  let mut mods = mods.to_vec();
+  for generated in [merge::MERGED_MOD_NAME, overrides::OVERRIDES_MOD_NAME] {
+    if game.profile_path.join("mods").join(generated).exists() {
+      mods.push(Mod::generated(generated));
+    }
+  }
+
+  let mount = ofs::mount_path(game);
+  let mut resolver = case::CaseResolver::new(mount.clone());
+  let mut report = VerifyReport::default();
+  let mut expected: BTreeSet<PathBuf> = BTreeSet::new();
+  let index = conflicts::index(game, &mods)?;
+  let mut dirs: HashMap<&str, PathBuf> = HashMap::new();
+  for elem in &mods {
+    dirs.insert(&elem.name, mod_dir(game, elem)?);
+  }
+  let total = index.len() as u64;
+  for (done, (relative, providers)) in index.into_iter().enumerate() {
+    tasks::checkpoint()?;
+    tasks::progress(done as u64, Some(total));
+    let winner = providers.last().unwrap();
+    //Proton games might have the file under another spelling
+    let target = if game.uses_compatibility() {
+      match resolver.resolve(&relative)? {
+        Ok(resolved) => resolved,
+        Err(_) => relative.clone(),
+      }
+    } else {
+      relative.clone()
+    };
+    expected.insert(mount.join(&target));
+    let deployed = mount.join(&target);
+    if !deployed.exists() {
+      report.missing.push(relative);
+      continue;
+    }
+    let source = dirs[winner.as_str()].join(&relative);
+    if pool::hash_file(&source)? == pool::hash_file(&deployed)? {
+      report.verified += 1;
+    } else {
+      report.modified.push(ModifiedFile { path: relative, mod_name: winner.clone() });
+    }
+  }
+
+  if let Some(vanilla) = baseline::vanilla_files(game)? {
+    let vanilla: BTreeSet<PathBuf> = vanilla.into_iter().map(|file| game.install_path.join(file)).collect();
+    for file in get_files(&game.install_path)? {
+      if !expected.contains(&file) && !vanilla.contains(&file) {
+        report.orphaned.push(link::relative(&file, &game.install_path)?.to_path_buf());
+      }
+    }
+  }
+  Ok(report)
+}
@@ -0,0 +1,151 @@
+use crate::mod_manager::{meta, modlist, GENERATED_MOD_PREFIX};
+use crate::mod_manager::game::Game;
+use crate::error::{Error, ErrorCode};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//Every version of a mod is in 'mods/<mod>/<version>', the load order's entry says which one is the
+//mod. Switching is only changing that, nothing gets moved, downloaded or extracted again, and the
+//mod's versions go wherever the mod goes
+pub fn mod_root(game: &Game, mod_name: &str) -> PathBuf {
+  game.profile_path.join("mods").join(mod_name)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModVersion {
+  version: String,
+  active: bool,
+  meta: meta::ModMeta,
+}
+
+//What the version's folder is called, the same version installed twice gets told apart by its install time
+pub fn version_key(mod_meta: &meta::ModMeta) -> String {
+  let version = mod_meta.custom_version.clone()
+    .or_else(|| mod_meta.version.clone())
+    .or_else(|| mod_meta.installed_at.map(|installed_at| installed_at.to_string()))
+    .unwrap_or_else(|| "unknown".to_string());
+  let key: String = version.trim().chars().map(|c| if c == '/' || c == '\0' { '_' } else { c }).collect();
+  //the manager's own folders in there start with a dot
+  match key.trim_start_matches('.') {
+    "" => "unknown".to_string(),
+    key => key.to_string(),
+  }
+}
+
+fn is_version_name(name: &str) -> bool {
+  !name.is_empty() && !name.contains('/') && !name.starts_with('.')
+}
+
+//The versions there are by name, a linked folder is one too
+pub fn kept(root: &Path) -> io::Result<Vec<String>> {
+  let mut versions: Vec<String> = Vec::new();
+  if !root.is_dir() {
+    return Ok(versions);
+  }
+  for entry in root.read_dir()? {
+    let entry = entry?;
+    let name = entry.file_name().to_string_lossy().to_string();
+    if is_version_name(&name) && entry.path().is_dir() {
+      versions.push(name);
+    }
+  }
+  versions.sort();
+  Ok(versions)
+}
+
+//The one picked if it's there, otherwise the one installed last. A mod that has none
+//at all is just its root, nothing is in there
+pub fn active_in(root: &Path, picked: Option<&str>) -> io::Result<PathBuf> {
+  if let Some(picked) = picked.filter(|picked| is_version_name(picked) && root.join(picked).is_dir()) {
+    return Ok(root.join(picked));
+  }
+  let newest = kept(root)?.into_iter().max_by_key(|version| meta::load(&root.join(version)).installed_at);
+  Ok(match newest {
+    Some(version) => root.join(version),
+    None => root.to_path_buf(),
+  })
+}
+
+//The manager's own mods only ever have the one
+pub fn active(game: &Game, mod_name: &str) -> io::Result<PathBuf> {
+  let root = mod_root(game, mod_name);
+  if mod_name.starts_with(GENERATED_MOD_PREFIX) {
+    return Ok(root);
+  }
+  let modlist = modlist::ModList::load(game)?;
+  active_in(&root, modlist.version(mod_name))
+}
+
+//A folder for a version that isn't taken, the same version twice gets numbered
+pub fn free(game: &Game, mod_name: &str, mod_meta: &meta::ModMeta) -> PathBuf {
+  let root = mod_root(game, mod_name);
+  let key = version_key(mod_meta);
+  let mut version = key.clone();
+  let mut number = 2;
+  while fs::symlink_metadata(root.join(&version)).is_ok() {
+    version = format!("{} ({})", key, number);
+    number += 1;
+  }
+  root.join(version)
+}
+
+//A mod folder from before there were versions in it becomes its only version, returns which.
+//It's moved out of the way first, under a name get_mods doesn't list
+pub fn nest(root: &Path) -> io::Result<String> {
+  let version = version_key(&meta::load(root));
+  let name = root.file_name().unwrap_or_default().to_string_lossy().to_string();
+  let nesting = root.with_file_name(format!("{}nesting_{}", GENERATED_MOD_PREFIX, name));
+  meta::move_mod(root, &nesting)?;
+  fs::create_dir_all(root)?;
+  meta::move_mod(&nesting, &root.join(&version))?;
+  Ok(version)
+}
+
+//The load order's entry says which version is the one deployed
+pub fn set_active(game: &Game, mod_name: &str, version: &str) -> io::Result<()> {
+  let mut modlist = modlist::ModList::load(game)?;
+  modlist.set_version(mod_name, Some(version.to_string()));
+  modlist.save(game)
+}
+
+fn not_found(mod_name: &str, version: &str) -> io::Error {
+  Error::new(ErrorCode::VersionNotFound, format!("'{}' has no version '{}' kept", mod_name, version))
+    .param("mod_name", mod_name)
+    .param("version", version)
+    .into_io(io::ErrorKind::NotFound)
+}
+
+//Kept next to the active one, which can't be switched to or deleted
+fn is_kept(game: &Game, mod_name: &str, version: &str) -> io::Result<bool> {
+  let root = mod_root(game, mod_name);
+  Ok(is_version_name(version) && root.join(version).is_dir() && active(game, mod_name)? != root.join(version))
+}
+
+//The active version first, the kept ones by name
+pub fn list(game: &Game, mod_name: &str) -> io::Result<Vec<ModVersion>> {
+  let root = mod_root(game, mod_name);
+  let active = active(game, mod_name)?;
+  let mut versions: Vec<ModVersion> = Vec::new();
+  for version in kept(&root)? {
+    let path = root.join(&version);
+    versions.push(ModVersion { active: path == active, meta: meta::load(&path), version });
+  }
+  versions.sort_by_key(|version| !version.active);
+  Ok(versions)
+}
+
+pub fn switch(game: &Game, mod_name: &str, version: &str) -> io::Result<()> {
+  if !is_kept(game, mod_name, version)? {
+    return Err(not_found(mod_name, version));
+  }
+  set_active(game, mod_name, version)
+}
+
+pub fn delete(game: &Game, mod_name: &str, version: &str) -> io::Result<()> {
+  if !is_kept(game, mod_name, version)? {
+    return Err(not_found(mod_name, version));
+  }
+  //the pool's blobs of the version go with the next garbage collection
+  meta::remove_mod(&mod_root(game, mod_name).join(version))
+}
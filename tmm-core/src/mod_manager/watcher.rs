@@ -0,0 +1,215 @@
+use crate::mod_manager::game::Game;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{fs, io, thread};
+
+use crate::events::{BackendEvent, Events};
+use crate::settings;
+
+//Changes come in bursts, a mod folder being copied in is hundreds of them,
+//they go out once nothing changed for this long
+const QUIET: Duration = Duration::from_millis(500);
+static WATCHER: OnceCell<()> = OnceCell::new();
+
+//What the manager is writing itself, with how many are writing there and when the last one
+//was done. What changed there isn't news, the frontend knows from the command that did it
+type Writers = HashMap<PathBuf, (usize, Option<Instant>)>;
+
+static OWN: Lazy<Mutex<Writers>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+//Changes below the path aren't sent while it's held and for a moment after, until the
+//last of their events came in
+pub struct Writing(PathBuf);
+
+pub fn writing(path: &Path) -> Writing {
+  let mut own = OWN.lock().unwrap();
+  let (writers, done_at) = own.entry(path.to_path_buf()).or_insert((0, None));
+  *writers += 1;
+  *done_at = None;
+  Writing(path.to_path_buf())
+}
+
+impl Drop for Writing {
+  fn drop(&mut self) {
+    if let Some((writers, done_at)) = OWN.lock().unwrap().get_mut(&self.0) {
+      *writers -= 1;
+      if *writers == 0 {
+        *done_at = Some(Instant::now());
+      }
+    }
+  }
+}
+
+fn own(path: &Path) -> bool {
+  let mut own = OWN.lock().unwrap();
+  own.retain(|_, (writers, done_at)| *writers > 0 || done_at.map_or(true, |done_at| done_at.elapsed() < QUIET * 2));
+  own.keys().any(|writing| path.starts_with(writing))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Folder {
+  Mods,
+  Downloads,
+}
+
+impl Folder {
+  fn event(self, changed: FolderChanged) -> BackendEvent {
+    match self {
+      Folder::Mods => BackendEvent::ModFolderChanged(changed),
+      Folder::Downloads => BackendEvent::DownloadFolderChanged(changed),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderChanged {
+  appid: u32,
+  //what was added, changed or removed right in the folder, e.g. a mod's directory
+  paths: Vec<PathBuf>,
+}
+
+fn watch_error(e: notify::Error) -> io::Error {
+  io::Error::new(io::ErrorKind::Other, e)
+}
+
+//The mods and downloads of every game's active profile, only what's right in them. A mod's
+//own files changing doesn't change the list and watching all of them would run out of watches
+fn wanted() -> HashMap<PathBuf, (u32, Folder)> {
+  let games = match Game::all() {
+    Ok(games) => games,
+    Err(e) => {
+      tracing::warn!("Couldn't read the game configs to watch their mods: {}", e);
+      return HashMap::new();
+    }
+  };
+  let mut wanted = HashMap::new();
+  for game in games {
+    wanted.insert(game.profile_path.join("mods"), (game.appid, Folder::Mods));
+    wanted.insert(game.profile_path.join("downloads"), (game.appid, Folder::Downloads));
+  }
+  wanted
+}
+
+fn resync(watcher: &mut RecommendedWatcher, watched: &mut HashMap<PathBuf, (u32, Folder)>) {
+  let wanted = wanted();
+  for path in watched.keys().filter(|path| !wanted.contains_key(*path)).cloned().collect::<Vec<PathBuf>>() {
+    //gone with the directory already if it was removed
+    let _ = watcher.unwatch(&path);
+    watched.remove(&path);
+  }
+  for (path, folder) in wanted {
+    if watched.contains_key(&path) || !path.is_dir() {
+      continue;
+    }
+    match watcher.watch(&path, RecursiveMode::NonRecursive) {
+      Ok(()) => {
+        watched.insert(path, folder);
+      },
+      Err(e) => tracing::warn!("Couldn't watch '{}': {}", path.display(), e),
+    }
+  }
+}
+
+//Installs being staged are hidden. The downloader writes '<name>' with its chunks in '<name>.st',
+//the file is only done once that's gone
+fn hidden(path: &Path) -> bool {
+  let name = match path.file_name() {
+    Some(name) => name.to_string_lossy().to_string(),
+    None => return false,
+  };
+  name.starts_with('.') || name.ends_with(".st") || fs::symlink_metadata(path.with_file_name(format!("{}.st", name))).is_ok()
+}
+
+fn record(event: Event, watched: &HashMap<PathBuf, (u32, Folder)>, pending: &mut HashMap<(u32, Folder), (Instant, BTreeSet<PathBuf>)>) {
+  if matches!(event.kind, EventKind::Access(_)) {
+    return;
+  }
+  for path in event.paths.into_iter().filter(|path| !hidden(path) && !own(path)) {
+    //the folder itself being removed comes with its own path
+    let folder = path.parent().and_then(|parent| watched.get(parent)).or_else(|| watched.get(&path));
+    if let Some(folder) = folder {
+      let (changed_at, paths) = pending.entry(*folder).or_insert_with(|| (Instant::now(), BTreeSet::new()));
+      *changed_at = Instant::now();
+      paths.insert(path);
+    }
+  }
+}
+
+//The game configs and the settings, both get written next to themselves and moved over so
+//it's their directory that's watched
+fn watch_configs(watcher: &mut RecommendedWatcher) -> io::Result<PathBuf> {
+  let config_root = crate::config_root()?;
+  fs::create_dir_all(&config_root)?;
+  //what changes get compared to
+  settings::current();
+  watcher.watch(&config_root, RecursiveMode::NonRecursive).map_err(watch_error)?;
+  Ok(config_root)
+}
+
+//A game added, forgotten or switched to another profile, what's watched has to follow
+fn is_game_config(path: &Path) -> bool {
+  path.extension().map_or(false, |extension| extension == "json")
+    && path.file_stem().and_then(|stem| stem.to_str()).map_or(false, |stem| stem.parse::<u32>().is_ok())
+}
+
+fn run(events: &Events) -> io::Result<()> {
+  let (sender, receiver) = mpsc::channel();
+  let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+  let mut watched: HashMap<PathBuf, (u32, Folder)> = HashMap::new();
+  let mut pending: HashMap<(u32, Folder), (Instant, BTreeSet<PathBuf>)> = HashMap::new();
+  let config_root = watch_configs(&mut watcher).map_err(|e| tracing::warn!("Couldn't watch the configs: {}", e)).ok();
+  let settings_path = settings::path().ok();
+  let mut settings_changed: Option<Instant> = None;
+  let mut configs_changed: Option<Instant> = None;
+  resync(&mut watcher, &mut watched);
+  loop {
+    if configs_changed.map_or(false, |changed_at| changed_at.elapsed() >= QUIET) {
+      configs_changed = None;
+      resync(&mut watcher, &mut watched);
+    }
+    match receiver.recv_timeout(QUIET) {
+      Ok(Ok(event)) => {
+        if event.paths.iter().any(|path| Some(path) == settings_path.as_ref()) {
+          settings_changed = Some(Instant::now());
+        }
+        let configs = event.paths.iter().any(|path| path.parent() == config_root.as_deref() && is_game_config(path));
+        if configs && !matches!(event.kind, EventKind::Access(_)) {
+          configs_changed = Some(Instant::now());
+        }
+        record(event, &watched, &mut pending)
+      },
+      Ok(Err(e)) => tracing::warn!("Watching the mod folders failed: {}", e),
+      Err(RecvTimeoutError::Timeout) => {},
+      Err(RecvTimeoutError::Disconnected) => return Ok(()),
+    }
+    let settled: Vec<(u32, Folder)> = pending.iter().filter(|(_, (changed_at, _))| changed_at.elapsed() >= QUIET).map(|(key, _)| *key).collect();
+    for (appid, folder) in settled {
+      let (_, paths) = pending.remove(&(appid, folder)).unwrap();
+      events.send(folder.event(FolderChanged { appid, paths: paths.into_iter().collect() }));
+    }
+    if settings_changed.map_or(false, |changed_at| changed_at.elapsed() >= QUIET) {
+      settings_changed = None;
+      settings::refresh(events);
+    }
+  }
+}
+
+//Lets the frontend know when mods or downloads change outside of it, e.g. a mod folder copied
+//in or deleted by hand, with 'mod-folder-changed' and 'download-folder-changed', and with
+//'settings-changed' when settings.toml was edited. Only ever starts once
+pub fn watch(events: Events) {
+  if WATCHER.set(()).is_err() {
+    return;
+  }
+  thread::spawn(move || {
+    if let Err(e) = run(&events) {
+      tracing::warn!("Couldn't watch the mod folders: {}", e);
+    }
+  });
+}
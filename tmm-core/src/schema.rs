@@ -0,0 +1,154 @@
+use crate::error::{Error, ErrorCode};
+use crate::mod_manager::migrate;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+//Changes a config from one version to the next, on the JSON before it's deserialized so
+//fields can be renamed, moved or split up without the structs having to know the old ones
+type Migration = fn(&mut Map<String, Value>) -> io::Result<()>;
+
+//How a kind of config is versioned. Each config has its 'schema_version', bumped whenever
+//its struct changes in a way serde defaults don't cover, with a migration for that version
+pub struct Schema {
+  pub name: &'static str,
+  //what configs get written with
+  pub current: u32,
+  //what a config without 'schema_version' is at
+  pub unversioned: u32,
+  //by the version they upgrade from, a version without one only gets its number bumped
+  migrations: &'static [(u32, Migration)],
+}
+
+//The game configs, '<appid>.json'. 0 is everything from before they were versioned,
+//fields added until then all have defaults. 2 has the profiles laid out like they are now,
+//'profile_layout' is gone
+pub const GAME: Schema = Schema { name: "game", current: 2, unversioned: 0, migrations: &[(1, migrate::profiles)] };
+
+//settings.toml came with the versioning, one without a version is the first
+pub const SETTINGS: Schema = Schema { name: "settings", current: 1, unversioned: 1, migrations: &[] };
+
+fn backup_dir() -> io::Result<PathBuf> {
+  Ok(crate::config_root()?.join("backups").join("schema-migration"))
+}
+
+//The file as it was before the upgrade, one that's there already is from an earlier try and the older one
+fn back_up(path: &Path, version: u32) -> io::Result<()> {
+  let backup = backup_dir()?.join(format!("{}.v{}", path.file_name().unwrap().to_string_lossy(), version));
+  if backup.exists() {
+    return Ok(());
+  }
+  fs::create_dir_all(backup_dir()?)?;
+  fs::copy(path, backup).map(|_| ())
+}
+
+fn version(schema: &Schema, path: &Path, config: &Map<String, Value>) -> io::Result<u32> {
+  let version = match config.get("schema_version") {
+    None => return Ok(schema.unversioned),
+    Some(version) => version.as_u64().and_then(|version| u32::try_from(version).ok()),
+  };
+  let version = version.ok_or_else(|| Error::new(ErrorCode::ConfigParseFailed, format!("'{}' has a schema_version that isn't a number", path.display()))
+    .param("path", path)
+    .into_io(io::ErrorKind::InvalidData))?;
+  if version > schema.current {
+    return Err(Error::new(ErrorCode::ConfigTooNew, format!("'{}' is from a newer version of the manager, {} schema {} where this one knows {}", path.display(), schema.name, version, schema.current))
+      .param("path", path)
+      .param("schema_version", version)
+      .into_io(io::ErrorKind::InvalidData));
+  }
+  Ok(version)
+}
+
+//Brings a config read from 'path' up to the current version, backing up the file first.
+//True if it was upgraded and should be written back
+pub fn upgrade(schema: &Schema, path: &Path, value: &mut Value) -> io::Result<bool> {
+  let config = value.as_object_mut().ok_or_else(|| Error::new(ErrorCode::ConfigParseFailed, format!("'{}' isn't a table of settings", path.display()))
+    .param("path", path)
+    .into_io(io::ErrorKind::InvalidData))?;
+  let from = version(schema, path, config)?;
+  if from == schema.current {
+    return Ok(false);
+  }
+  back_up(path, from)?;
+  migrate(schema, from, config)?;
+  tracing::info!("Upgraded '{}' from {} schema {} to {}", path.display(), schema.name, from, schema.current);
+  Ok(true)
+}
+
+//Every step from 'from' on in order, then it's at the current version
+fn migrate(schema: &Schema, from: u32, config: &mut Map<String, Value>) -> io::Result<()> {
+  for version in from..schema.current {
+    for (_, migration) in schema.migrations.iter().filter(|(step, _)| *step == version) {
+      migration(config)?;
+    }
+  }
+  config.insert("schema_version".to_string(), Value::from(schema.current));
+  Ok(())
+}
+
+//Writes the upgraded config next to the old one and moves it over, so a crash leaves one of the two
+pub fn replace(path: &Path, contents: &str) -> io::Result<()> {
+  let temp = path.with_extension("upgrade.tmp");
+  fs::write(&temp, contents)?;
+  fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn rename_name(config: &mut Map<String, Value>) -> io::Result<()> {
+    if let Some(name) = config.remove("name") {
+      config.insert("title".to_string(), name);
+    }
+    Ok(())
+  }
+
+  fn split_title(config: &mut Map<String, Value>) -> io::Result<()> {
+    let title = config.get("title").and_then(|title| title.as_str()).unwrap_or_default().to_string();
+    config.insert("words".to_string(), Value::from(title.split(' ').count()));
+    Ok(())
+  }
+
+  //0 -> 1 renames, 1 -> 2 only bumps, 2 -> 3 needs what the first one did
+  const TEST: Schema = Schema { name: "test", current: 3, unversioned: 0, migrations: &[(2, split_title), (0, rename_name)] };
+
+  fn config(value: Value) -> Map<String, Value> {
+    value.as_object().unwrap().clone()
+  }
+
+  #[test]
+  fn steps_run_in_order_from_the_version() {
+    let mut old = config(json!({"name": "Skyrim Special Edition"}));
+    let from = version(&TEST, Path::new("old.json"), &old).unwrap();
+    assert_eq!(from, 0);
+    migrate(&TEST, from, &mut old).unwrap();
+    assert_eq!(Value::Object(old), json!({"title": "Skyrim Special Edition", "words": 3, "schema_version": 3}));
+
+    let mut newer = config(json!({"title": "Fallout 4", "schema_version": 2}));
+    migrate(&TEST, version(&TEST, Path::new("newer.json"), &newer).unwrap(), &mut newer).unwrap();
+    assert_eq!(Value::Object(newer), json!({"title": "Fallout 4", "words": 2, "schema_version": 3}));
+  }
+
+  #[test]
+  fn versions_it_cant_upgrade() {
+    assert!(version(&TEST, Path::new("new.json"), &config(json!({"schema_version": 4}))).is_err());
+    assert!(version(&TEST, Path::new("odd.json"), &config(json!({"schema_version": "3"}))).is_err());
+    assert!(upgrade(&TEST, Path::new("list.json"), &mut json!([1, 2])).is_err());
+  }
+
+  #[test]
+  fn current_ones_are_left_alone() {
+    let mut value = json!({"title": "Starfield", "schema_version": 3});
+    assert!(!upgrade(&TEST, Path::new("current.json"), &mut value).unwrap());
+    assert_eq!(value, json!({"title": "Starfield", "schema_version": 3}));
+  }
+
+  #[test]
+  fn laid_out_profiles_only_lose_the_field() {
+    let mut game = config(json!({"appid": 489830, "profile_layout": 2, "schema_version": 1}));
+    migrate(&GAME, 1, &mut game).unwrap();
+    assert_eq!(Value::Object(game), json!({"appid": 489830, "schema_version": 2}));
+  }
+}
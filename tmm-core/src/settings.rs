@@ -0,0 +1,284 @@
+use crate::error::{Error, ErrorCode};
+use crate::events::{BackendEvent, Events};
+use crate::mod_manager::game::DeployMode;
+use crate::schema;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::{fs, io};
+
+//What the manager does when neither the game's config nor the command says otherwise,
+//kept in 'settings.toml' next to the game configs. Everything has a default, the file
+//only needs what the user changed and a missing file is all defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+  //which version of the file this is, older ones get upgraded when they're read
+  pub schema_version: u32,
+  pub downloads: Downloads,
+  pub deploy: Deploy,
+  pub launch: Launch,
+  pub trash: Trash,
+  pub storage: Storage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Downloads {
+  //threads fetching chunks of one download at once
+  pub workers: usize,
+  //bytes per chunk
+  pub chunk_size: u64,
+  pub timeout_seconds: u64,
+  //chunks that failed get fetched again this many times
+  pub max_retries: i32,
+  //in chunks when the server allows ranges, otherwise in one go
+  pub concurrent: bool,
+  //bytes per second for all downloads together, None is no limit
+  pub bandwidth_limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Deploy {
+  //for games set up from now on whose definition doesn't ask for one, None is the overlay
+  pub default_backend: Option<DeployMode>,
+  //if games set up from now on purge after the game exits
+  pub auto_purge: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Launch {
+  //how long Steam gets to actually start the game after being asked to
+  pub start_timeout_seconds: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Trash {
+  //for games that don't set their own, 0 keeps removed mods until the trash is emptied
+  pub retention_days: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Storage {
+  //once usage passes this share of the quota the user gets warned
+  pub warning_ratio: f64,
+}
+
+impl Default for Settings {
+  fn default() -> Settings {
+    Settings {
+      schema_version: schema::SETTINGS.current,
+      downloads: Downloads::default(),
+      deploy: Deploy::default(),
+      launch: Launch::default(),
+      trash: Trash::default(),
+      storage: Storage::default(),
+    }
+  }
+}
+
+impl Default for Downloads {
+  fn default() -> Downloads {
+    Downloads { workers: 8, chunk_size: 512_000, timeout_seconds: 30, max_retries: 100, concurrent: true, bandwidth_limit: None }
+  }
+}
+
+impl Default for Launch {
+  fn default() -> Launch {
+    Launch { start_timeout_seconds: 120 }
+  }
+}
+
+impl Default for Trash {
+  fn default() -> Trash {
+    Trash { retention_days: 30 }
+  }
+}
+
+impl Default for Storage {
+  fn default() -> Storage {
+    Storage { warning_ratio: 0.9 }
+  }
+}
+
+//The settings with the mtime of the file they were read from, None when there was no file
+type Cached = Option<(Option<SystemTime>, Settings)>;
+
+static CURRENT: Lazy<Mutex<Cached>> = Lazy::new(|| Mutex::new(None));
+
+pub fn path() -> io::Result<PathBuf> {
+  Ok(crate::config_root()?.join("settings.toml"))
+}
+
+fn parse_error(path: &Path, detail: String) -> io::Error {
+  Error::new(ErrorCode::ConfigParseFailed, format!("'{}': {}", path.display(), detail))
+    .param("path", path)
+    .into_io(io::ErrorKind::InvalidData)
+}
+
+//Read from the file, upgraded if it's from an older version and checked, the defaults if there's none
+pub fn load() -> io::Result<Settings> {
+  let path = path()?;
+  let text = match fs::read_to_string(&path) {
+    Ok(text) => text,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+    Err(e) => return Err(e),
+  };
+  let mut value: serde_json::Value = toml::from_str(&text).map_err(|e| parse_error(&path, e.to_string()))?;
+  let upgraded = schema::upgrade(&schema::SETTINGS, &path, &mut value)?;
+  let settings: Settings = serde_json::from_value(value).map_err(|e| parse_error(&path, e.to_string()))?;
+  validate(&settings)?;
+  if upgraded {
+    let text = toml::to_string(&settings).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    schema::replace(&path, &text)?;
+  }
+  Ok(settings)
+}
+
+//What everything else goes by. Read again once the file changed, the CLI or an editor
+//could have written it. If it doesn't parse the defaults are used until it's fixed
+pub fn current() -> Settings {
+  reread().0
+}
+
+//The settings, and if they're not the ones from before anymore
+fn reread() -> (Settings, bool) {
+  let modified = path().and_then(fs::metadata).and_then(|metadata| metadata.modified()).ok();
+  let mut cached = CURRENT.lock().unwrap();
+  if let Some((stamp, settings)) = cached.as_ref() {
+    if *stamp == modified {
+      return (settings.clone(), false);
+    }
+  }
+  let settings = load().unwrap_or_else(|e| {
+    tracing::warn!("Couldn't read the settings, using the defaults: {}", e);
+    Settings::default()
+  });
+  let changed = cached.as_ref().map_or(false, |(_, previous)| *previous != settings);
+  *cached = Some((modified, settings.clone()));
+  (settings, changed)
+}
+
+//For when the file was written outside the app, 'settings-changed' goes out if what's in it
+//is different now. What the app saved itself is already known and doesn't go out twice
+pub fn refresh(events: &Events) {
+  let (settings, changed) = reread();
+  if changed {
+    events.send(BackendEvent::SettingsChanged(settings));
+  }
+}
+
+fn invalid(setting: &str, message: &str) -> io::Error {
+  Error::new(ErrorCode::InvalidSettings, format!("'{}' {}", setting, message))
+    .param("setting", setting)
+    .into_io(io::ErrorKind::InvalidInput)
+}
+
+pub fn validate(settings: &Settings) -> io::Result<()> {
+  let downloads = &settings.downloads;
+  if downloads.workers == 0 || downloads.workers > 64 {
+    return Err(invalid("downloads.workers", "has to be between 1 and 64"));
+  }
+  if downloads.chunk_size < 1024 {
+    return Err(invalid("downloads.chunk_size", "has to be at least 1024 bytes"));
+  }
+  if downloads.timeout_seconds == 0 {
+    return Err(invalid("downloads.timeout_seconds", "can't be 0"));
+  }
+  if downloads.max_retries < 0 {
+    return Err(invalid("downloads.max_retries", "can't be negative"));
+  }
+  if downloads.bandwidth_limit == Some(0) {
+    return Err(invalid("downloads.bandwidth_limit", "can't be 0, leave it out for no limit"));
+  }
+  if settings.launch.start_timeout_seconds == 0 {
+    return Err(invalid("launch.start_timeout_seconds", "can't be 0"));
+  }
+  let ratio = settings.storage.warning_ratio;
+  if !(ratio > 0.0 && ratio <= 1.0) {
+    return Err(invalid("storage.warning_ratio", "has to be above 0 and at most 1"));
+  }
+  Ok(())
+}
+
+//Checked, written next to the file and moved over it so nothing ever reads half of it
+pub fn save(settings: &Settings) -> io::Result<()> {
+  validate(settings)?;
+  let settings = &Settings { schema_version: schema::SETTINGS.current, ..settings.clone() };
+  let text = toml::to_string(settings).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+  let path = path()?;
+  fs::create_dir_all(crate::config_root()?)?;
+  let temp = path.with_extension("toml.tmp");
+  fs::write(&temp, text)?;
+  fs::rename(&temp, &path)?;
+  *CURRENT.lock().unwrap() = Some((fs::metadata(&path).and_then(|metadata| metadata.modified()).ok(), settings.clone()));
+  Ok(())
+}
+
+//Saves them and lets the frontend know, 'settings-changed' carries the new settings
+pub fn update(settings: &Settings, events: &Events) -> io::Result<()> {
+  save(settings)?;
+  events.send(BackendEvent::SettingsChanged(settings.clone()));
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rejected(settings: &Settings) -> String {
+    validate(settings).unwrap_err().to_string()
+  }
+
+  #[test]
+  fn defaults_are_valid() {
+    assert!(validate(&Settings::default()).is_ok());
+  }
+
+  #[test]
+  fn download_limits() {
+    let mut settings = Settings::default();
+    settings.downloads.workers = 0;
+    assert!(rejected(&settings).contains("'downloads.workers'"));
+    settings.downloads.workers = 65;
+    assert!(rejected(&settings).contains("'downloads.workers'"));
+    settings.downloads.workers = 64;
+    settings.downloads.chunk_size = 1023;
+    assert!(rejected(&settings).contains("'downloads.chunk_size'"));
+    settings.downloads.chunk_size = 1024;
+    settings.downloads.max_retries = -1;
+    assert!(rejected(&settings).contains("'downloads.max_retries'"));
+    settings.downloads.max_retries = 0;
+    settings.downloads.bandwidth_limit = Some(0);
+    assert!(rejected(&settings).contains("'downloads.bandwidth_limit'"));
+    settings.downloads.bandwidth_limit = Some(1);
+    assert!(validate(&settings).is_ok());
+  }
+
+  #[test]
+  fn timeouts_cant_be_zero() {
+    let mut settings = Settings::default();
+    settings.downloads.timeout_seconds = 0;
+    assert!(rejected(&settings).contains("'downloads.timeout_seconds'"));
+    let mut settings = Settings::default();
+    settings.launch.start_timeout_seconds = 0;
+    assert!(rejected(&settings).contains("'launch.start_timeout_seconds'"));
+  }
+
+  #[test]
+  fn warning_ratio_is_a_share() {
+    let mut settings = Settings::default();
+    for ratio in [0.0, -0.5, 1.5, f64::NAN] {
+      settings.storage.warning_ratio = ratio;
+      assert!(rejected(&settings).contains("'storage.warning_ratio'"));
+    }
+    settings.storage.warning_ratio = 1.0;
+    assert!(validate(&settings).is_ok());
+  }
+}
@@ -0,0 +1,94 @@
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, thread};
+
+//Every event the frontend gets is also written here as one json object per line so
+//scripts, status bar widgets and screen reader friendly frontends can follow along
+//without the UI. Turned on with `--status <target>` or TMM_STATUS=<target> where
+//the target is 'stdout' or the path of a unix socket to listen on
+static SINK: OnceCell<Sink> = OnceCell::new();
+
+enum Sink {
+  Stdout,
+  //clients that disconnect get dropped on the next write
+  Socket(Mutex<Vec<UnixStream>>),
+}
+
+#[derive(Serialize)]
+struct StatusLine<'a> {
+  event: &'a str,
+  //milliseconds since the epoch
+  time: u128,
+  payload: Value,
+}
+
+fn target() -> Option<String> {
+  let mut args = std::env::args();
+  while let Some(arg) = args.next() {
+    if arg == "--status" {
+      return args.next();
+    }
+    if let Some(target) = arg.strip_prefix("--status=") {
+      return Some(target.to_string());
+    }
+  }
+  std::env::var("TMM_STATUS").ok().filter(|target| !target.is_empty())
+}
+
+pub fn init() -> io::Result<()> {
+  let sink = match target() {
+    None => return Ok(()),
+    Some(target) if target == "stdout" || target == "-" => Sink::Stdout,
+    Some(target) => {
+      let path = PathBuf::from(target);
+      //a socket left behind by an earlier run
+      if path.exists() && UnixStream::connect(&path).is_err() {
+        fs::remove_file(&path)?;
+      }
+      let listener = UnixListener::bind(&path)?;
+      thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+          if let Some(Sink::Socket(clients)) = SINK.get() {
+            clients.lock().unwrap().push(stream);
+          }
+        }
+      });
+      Sink::Socket(Mutex::new(Vec::new()))
+    }
+  };
+  let _ = SINK.set(sink);
+  Ok(())
+}
+
+pub fn publish<S: Serialize>(event: &str, payload: &S) {
+  let sink = match SINK.get() {
+    Some(sink) => sink,
+    None => return,
+  };
+  let line = StatusLine {
+    event,
+    time: SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis()).unwrap_or(0),
+    payload: serde_json::to_value(payload).unwrap_or_else(|e| json!({ "error": e.to_string() })),
+  };
+  let mut line = match serde_json::to_string(&line) {
+    Ok(line) => line,
+    Err(_) => return,
+  };
+  line.push('\n');
+  match sink {
+    Sink::Stdout => {
+      let stdout = io::stdout();
+      let mut stdout = stdout.lock();
+      let _ = stdout.write_all(line.as_bytes()).and_then(|()| stdout.flush());
+    }
+    Sink::Socket(clients) => {
+      clients.lock().unwrap().retain(|mut client| client.write_all(line.as_bytes()).is_ok());
+    }
+  }
+}
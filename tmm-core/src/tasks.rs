@@ -0,0 +1,315 @@
+use crate::error::{Error, ErrorCode};
+use crate::events::{BackendEvent, Events};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::io;
+
+use threadpool::ThreadPool;
+
+//How many tasks run at once, the others wait in line
+const WORKERS: usize = 4;
+//Finished tasks stay listed so the frontend can show how they went, only this many
+const KEPT_FINISHED: usize = 50;
+//Progress doesn't have to go out more often than the frontend redraws
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+  Download,
+  Install,
+  //verifying deployments and deduplicating mods, both hash every file
+  Hash,
+  Deploy,
+  Purge,
+  UpdateCheck,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+  Queued,
+  Running,
+  Finished,
+  Failed,
+  Cancelled,
+}
+
+//What the task events carry, one goes out whenever the state or the progress changes
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+  pub id: u64,
+  pub kind: TaskKind,
+  pub appid: Option<u32>,
+  //what it works on, e.g. the url or the archive
+  pub label: String,
+  pub state: TaskState,
+  //how far it got in whatever it counts, bytes, files or mods, the total once it's known
+  pub done: u64,
+  pub total: Option<u64>,
+  pub error: Option<Error>,
+}
+
+struct Entry {
+  info: Mutex<TaskInfo>,
+  cancelled: AtomicBool,
+  events: Events,
+  last_progress: Mutex<Option<Instant>>,
+}
+
+impl Entry {
+  fn update(&self, change: impl FnOnce(&mut TaskInfo)) {
+    let info = {
+      let mut info = self.info.lock().unwrap();
+      change(&mut info);
+      info.clone()
+    };
+    self.events.send(event(info));
+  }
+
+  fn finished(&self) -> bool {
+    matches!(self.info.lock().unwrap().state, TaskState::Finished | TaskState::Failed | TaskState::Cancelled)
+  }
+}
+
+fn event(info: TaskInfo) -> BackendEvent {
+  match info.state {
+    TaskState::Queued | TaskState::Running => BackendEvent::TaskProgress(info),
+    TaskState::Finished => BackendEvent::TaskFinished(info),
+    TaskState::Failed => BackendEvent::TaskFailed(info),
+    TaskState::Cancelled => BackendEvent::TaskCancelled(info),
+  }
+}
+
+thread_local! {
+  //The task the worker is running, so code deep down can report progress and
+  //notice it got cancelled without every function taking the task
+  static CURRENT: RefCell<Option<Arc<Entry>>> = const { RefCell::new(None) };
+}
+
+//Runs the long operations, downloads, installs, hashing, deployments and update checks,
+//on a pool of its own. Every task gets an id to cancel it by and reports through the task
+//events. Cancelling is cooperative, a task stops at its next checkpoint
+pub struct TaskManager {
+  pool: Mutex<ThreadPool>,
+  //oldest first
+  tasks: Mutex<Vec<Arc<Entry>>>,
+  next_id: AtomicU64,
+}
+
+//A task that was spawned, its id and a way to wait for what it returns
+pub struct Pending<T> {
+  pub id: u64,
+  result: mpsc::Receiver<Result<T, Error>>,
+}
+
+impl<T> Pending<T> {
+  //Blocks until the task is done
+  pub fn wait(self) -> Result<T, Error> {
+    self.result.recv().unwrap_or_else(|_| Err(Error::new(ErrorCode::TaskCrashed, format!("task {} crashed", self.id))))
+  }
+}
+
+fn cancelled_error(id: u64) -> Error {
+  Error::new(ErrorCode::Cancelled, format!("task {} was cancelled", id)).param("task", id)
+}
+
+impl TaskManager {
+  pub fn new() -> TaskManager {
+    TaskManager { pool: Mutex::new(ThreadPool::new(WORKERS)), tasks: Mutex::new(Vec::new()), next_id: AtomicU64::new(1) }
+  }
+
+  pub fn spawn<T, F>(&self, kind: TaskKind, appid: Option<u32>, label: impl Into<String>, events: &Events, job: F) -> Pending<T>
+  where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+  {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    let info = TaskInfo { id, kind, appid, label: label.into(), state: TaskState::Queued, done: 0, total: None, error: None };
+    let entry = Arc::new(Entry { info: Mutex::new(info.clone()), cancelled: AtomicBool::new(false), events: events.clone(), last_progress: Mutex::new(None) });
+    {
+      let mut tasks = self.tasks.lock().unwrap();
+      tasks.push(entry.clone());
+      let finished = tasks.iter().filter(|entry| entry.finished()).count();
+      if finished > KEPT_FINISHED {
+        let mut dropping = finished - KEPT_FINISHED;
+        tasks.retain(|entry| {
+          let drop = dropping > 0 && entry.finished();
+          if drop {
+            dropping -= 1;
+          }
+          !drop
+        });
+      }
+    }
+    events.send(event(info));
+    let (sender, receiver) = mpsc::channel();
+    self.pool.lock().unwrap().execute(move || {
+      //cancelled while it was waiting, it's marked as that already
+      if entry.cancelled.load(Ordering::SeqCst) {
+        let _ = sender.send(Err(cancelled_error(id)));
+        return;
+      }
+      entry.update(|info| info.state = TaskState::Running);
+      tracing::info!(task = id, kind = ?kind, "task started");
+      CURRENT.with(|current| *current.borrow_mut() = Some(entry.clone()));
+      let result = panic::catch_unwind(AssertUnwindSafe(job))
+        .unwrap_or_else(|_| Err(Error::new(ErrorCode::TaskCrashed, format!("task {} crashed", id))));
+      CURRENT.with(|current| *current.borrow_mut() = None);
+      //whatever went wrong after it was cancelled is from stopping
+      let result = match result {
+        Err(_) if entry.cancelled.load(Ordering::SeqCst) => Err(cancelled_error(id)),
+        result => result,
+      };
+      entry.update(|info| match &result {
+        Ok(_) => info.state = TaskState::Finished,
+        Err(_) if entry.cancelled.load(Ordering::SeqCst) => info.state = TaskState::Cancelled,
+        Err(e) => {
+          info.state = TaskState::Failed;
+          info.error = Some(e.clone());
+        }
+      });
+      if let Err(e) = &result {
+        tracing::warn!(task = id, kind = ?kind, "task didn't finish: {}", e);
+      }
+      let _ = sender.send(result);
+    });
+    Pending { id, result: receiver }
+  }
+
+  //The tasks that are queued or running and the last ones that finished, oldest first
+  pub fn list(&self) -> Vec<TaskInfo> {
+    self.tasks.lock().unwrap().iter().map(|entry| entry.info.lock().unwrap().clone()).collect()
+  }
+
+  //A queued task never starts, a running one stops at its next checkpoint.
+  //Ones that are done already are left as they are
+  pub fn cancel(&self, id: u64) -> io::Result<()> {
+    let entry = self.tasks.lock().unwrap().iter().find(|entry| entry.info.lock().unwrap().id == id).cloned();
+    let entry = entry.ok_or_else(|| Error::new(ErrorCode::TaskNotFound, format!("there's no task {}", id))
+      .param("task", id)
+      .into_io(io::ErrorKind::NotFound))?;
+    if entry.finished() {
+      return Ok(());
+    }
+    entry.cancelled.store(true, Ordering::SeqCst);
+    let queued = entry.info.lock().unwrap().state == TaskState::Queued;
+    if queued {
+      entry.update(|info| info.state = TaskState::Cancelled);
+    }
+    Ok(())
+  }
+}
+
+impl Default for TaskManager {
+  fn default() -> TaskManager {
+    TaskManager::new()
+  }
+}
+
+fn current() -> Option<Arc<Entry>> {
+  CURRENT.with(|current| current.borrow().clone())
+}
+
+//If the task the thread runs got cancelled, false outside of tasks
+pub fn cancelled() -> bool {
+  current().map_or(false, |entry| entry.cancelled.load(Ordering::SeqCst))
+}
+
+//Where a task can stop, the error goes up like any other and the task ends up cancelled
+pub fn checkpoint() -> io::Result<()> {
+  match current() {
+    Some(entry) if entry.cancelled.load(Ordering::SeqCst) => {
+      let id = entry.info.lock().unwrap().id;
+      Err(cancelled_error(id).into_io(io::ErrorKind::Interrupted))
+    },
+    _ => Ok(()),
+  }
+}
+
+//How far the task the thread runs got, nothing happens outside of tasks
+pub fn progress(done: u64, total: Option<u64>) {
+  let entry = match current() {
+    Some(entry) => entry,
+    None => return,
+  };
+  {
+    let mut last = entry.last_progress.lock().unwrap();
+    let due = last.map_or(true, |last| last.elapsed() >= PROGRESS_INTERVAL) || Some(done) == total;
+    if !due {
+      let mut info = entry.info.lock().unwrap();
+      info.done = done;
+      info.total = total;
+      return;
+    }
+    *last = Some(Instant::now());
+  }
+  entry.update(|info| {
+    info.done = done;
+    info.total = total;
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn state(tasks: &TaskManager, id: u64) -> TaskState {
+    tasks.list().into_iter().find(|info| info.id == id).unwrap().state
+  }
+
+  #[test]
+  fn outside_of_tasks_nothing_is_cancelled() {
+    assert!(!cancelled());
+    assert!(checkpoint().is_ok());
+    progress(1, Some(2));
+  }
+
+  #[test]
+  fn a_running_task_stops_at_its_checkpoint() {
+    let tasks = TaskManager::new();
+    let (started, running) = mpsc::channel();
+    let pending = tasks.spawn::<(), _>(TaskKind::Hash, None, "hashing", &Events::none(), move || {
+      started.send(()).unwrap();
+      loop {
+        checkpoint().map_err(|e| Error::io(crate::error::Context::VerifyDeployment, e))?;
+        progress(1, None);
+        std::thread::sleep(Duration::from_millis(5));
+      }
+    });
+    running.recv().unwrap();
+    assert_eq!(state(&tasks, pending.id), TaskState::Running);
+    let id = pending.id;
+    tasks.cancel(id).unwrap();
+    assert!(pending.wait().is_err());
+    assert_eq!(state(&tasks, id), TaskState::Cancelled);
+    //done already, cancelling again changes nothing
+    tasks.cancel(id).unwrap();
+    assert_eq!(tasks.cancel(id + 100).unwrap_err().kind(), io::ErrorKind::NotFound);
+  }
+
+  #[test]
+  fn finished_failed_and_crashed() {
+    let tasks = TaskManager::new();
+    let finished = tasks.spawn(TaskKind::Install, Some(489830), "finishing", &Events::none(), || Ok(1));
+    let finished_id = finished.id;
+    assert_eq!(finished.wait().unwrap(), 1);
+    assert_eq!(state(&tasks, finished_id), TaskState::Finished);
+
+    let failed = tasks.spawn::<(), _>(TaskKind::Install, None, "failing", &Events::none(), || Err(Error::new(ErrorCode::TaskNotFound, "failed")));
+    let failed_id = failed.id;
+    assert!(failed.wait().is_err());
+    let info = tasks.list().into_iter().find(|info| info.id == failed_id).unwrap();
+    assert_eq!(info.state, TaskState::Failed);
+    assert!(info.error.is_some());
+
+    let crashed = tasks.spawn::<(), _>(TaskKind::Install, None, "crashing", &Events::none(), || panic!("crashed"));
+    let crashed_id = crashed.id;
+    assert!(crashed.wait().is_err());
+    assert_eq!(state(&tasks, crashed_id), TaskState::Failed);
+  }
+}